@@ -0,0 +1,1010 @@
+use avro_schema::schema;
+use avro_schema::schema::*;
+use quote::ToTokens;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use syn::visit::Visit;
+use syn::{
+    visit, Attribute, Fields, GenericArgument, ItemEnum, ItemStruct, ItemType, Lit, Meta,
+    NestedMeta, PathArguments, Type,
+};
+use topological_sort::TopologicalSort;
+
+#[cfg(test)]
+mod lib_test;
+
+#[derive(Debug, Clone)]
+pub struct GenError {
+    // type name, or the file name if it could not be parsed
+    pub entity: String,
+    pub message: String,
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.entity, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeTransformVisitor {
+    entities: Vec<(String, BoxEntity)>,
+    pub entities_index: HashMap<String, BoxEntity>,
+    whitelist: Option<Vec<String>>,
+    blacklist: Option<Vec<String>>,
+    pub errors: Vec<GenError>,
+}
+
+impl TypeTransformVisitor {
+    pub fn new(whitelist: Option<Vec<String>>, blacklist: Option<Vec<String>>) -> Self {
+        TypeTransformVisitor {
+            entities: vec![],
+            entities_index: Default::default(),
+            whitelist,
+            blacklist,
+            errors: vec![],
+        }
+    }
+
+    fn error(&mut self, entity: &String, message: String) {
+        eprintln!("Failed {}: {}", entity, message);
+        self.errors.push(GenError {
+            entity: entity.clone(),
+            message,
+        });
+    }
+
+    fn add(&mut self, key: String, ent: BoxEntity) {
+        self.entities_index.insert(key.clone(), ent.clone());
+        self.entities.push((key, ent));
+    }
+
+    fn grab_fields_get_deps_enum_variant(
+        &self,
+        en_var: &syn::Variant,
+        collected_vars: &mut Vec<BoxRecord>,
+        into_deps: &mut Vec<String>,
+        skip_none: bool,
+    ) -> Result<(), String> {
+        let mut record = BoxRecord {
+            deps: vec![],
+            name: en_var.ident.to_string(),
+            namespace: None,
+            fields: vec![],
+        };
+        let mut side_effect_types = vec![];
+        self.grab_fields_get_deps_any(
+            &mut record.fields,
+            into_deps,
+            &mut side_effect_types,
+            &en_var.fields,
+            skip_none,
+        )
+        .map_err(|e| format!("variant {}: {}", record.name, e))?;
+        collected_vars.push(record);
+        Ok(())
+    }
+
+    pub fn top_sorted_records(&self) -> Vec<Vec<(String, &BoxEntity)>> {
+        let mut unmapped_types = vec![];
+        let mut ts = TopologicalSort::<String>::new();
+        for (key, value) in self.entities.iter() {
+            ts.insert(key);
+            for dep in value.get_deps().iter() {
+                ts.add_dependency(dep.clone(), key.clone());
+            }
+        }
+        let mut sorted = vec![];
+        loop {
+            let mut layer = ts.pop_all();
+            layer.sort();
+            if layer.is_empty() {
+                break;
+            }
+            sorted.push(layer)
+        }
+        eprintln!("sorted len={} {:?}", self.entities.len(), sorted);
+        let res: Vec<Vec<(String, &BoxEntity)>> = sorted
+            .into_iter()
+            .map(|layer| {
+                layer
+                    .into_iter()
+                    .filter_map(|name| {
+                        let rec = self.entities_index.get(name.as_str());
+                        if rec.is_none() {
+                            unmapped_types.push(name.clone());
+                        }
+                        rec.map(|rec| (name.clone(), rec))
+                    })
+                    .collect()
+            })
+            .collect();
+        if !unmapped_types.is_empty() {
+            eprintln!(
+                "\n========================\nUnmapped types: {:?}",
+                unmapped_types
+            );
+        }
+        res
+    }
+
+    fn check_analyze(
+        &mut self,
+        enum_name: &String,
+        attributes: &[Attribute],
+        ignore_attrs: bool,
+    ) -> bool {
+        let blacklisted = if let Some(blacklist) = &self.blacklist {
+            blacklist.iter().any(|filter| enum_name == filter.as_str())
+        } else {
+            false
+        };
+
+        let whitelisted = if let Some(filter) = &self.whitelist {
+            filter
+                .iter()
+                .any(|filter| enum_name.contains(filter.as_str()))
+        } else {
+            false
+        };
+
+        let has_typescript_definition_attribute = attributes.iter().any(|attr| {
+            let id = attr.path.segments[0].ident.to_string();
+            if id != "derive" {
+                return false;
+            }
+            attr.tokens.to_string().contains("TypescriptDefinition")
+        });
+        (!blacklisted || whitelisted) && (has_typescript_definition_attribute || ignore_attrs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoxRecord {
+    pub deps: Vec<String>,
+    pub name: String,
+    pub namespace: Option<String>,
+    pub fields: Vec<BoxField>,
+}
+
+// the same representations as serde has, see https://serde.rs/enum-representations.html
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumTagging {
+    External,
+    Internal { tag: String },
+    Adjacent { tag: String, content: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct BoxEnum {
+    pub deps: Vec<String>,
+    pub enum_name: String,
+    pub enum_variants: Vec<BoxRecord>,
+    pub tagging: EnumTagging,
+}
+
+impl BoxEnum {
+    fn is_unit_only(&self) -> bool {
+        self.enum_variants.iter().all(|v| v.fields.is_empty())
+    }
+
+    // what serde puts into the content of a variant - nothing, a single value, an array or an object
+    fn variant_content(&self, variant: &BoxRecord) -> Option<Schema> {
+        if variant.fields.is_empty() {
+            return None;
+        }
+        let record_name = format!("{}{}", self.enum_name, variant.name);
+        if is_positional(&variant.fields) {
+            if variant.fields.len() == 1 {
+                return Some(variant.fields[0].0.schema.clone());
+            }
+            let fields = variant
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| {
+                    let mut field = f.0.clone();
+                    field.name = i.to_string();
+                    field
+                })
+                .collect();
+            return Some(Schema::Record(schema::Record::new(record_name, fields)));
+        }
+        Some(Schema::Record(schema::Record::new(
+            record_name,
+            variant.fields.iter().map(|f| f.0.clone()).collect(),
+        )))
+    }
+
+    fn unit_symbols(&self) -> Vec<String> {
+        self.enum_variants
+            .iter()
+            .filter(|v| v.fields.is_empty())
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    fn to_schema(&self) -> Schema {
+        match &self.tagging {
+            EnumTagging::External => {
+                if self.is_unit_only() {
+                    return Schema::Enum(schema::Enum::new(
+                        self.enum_name.clone(),
+                        self.unit_symbols(),
+                    ));
+                }
+                // {"Variant": content} for the data variants, "Variant" for the unit ones
+                let data_fields = self
+                    .enum_variants
+                    .iter()
+                    .filter_map(|v| {
+                        self.variant_content(v)
+                            .map(|content| optional_field(v.name.clone(), content))
+                    })
+                    .collect();
+                let unit_symbols = self.unit_symbols();
+                if unit_symbols.is_empty() {
+                    return Schema::Record(schema::Record::new(
+                        self.enum_name.clone(),
+                        data_fields,
+                    ));
+                }
+                Schema::Union(vec![
+                    Schema::Enum(schema::Enum::new(
+                        format!("{}Unit", self.enum_name),
+                        unit_symbols,
+                    )),
+                    Schema::Record(schema::Record::new(
+                        format!("{}Data", self.enum_name),
+                        data_fields,
+                    )),
+                ])
+            }
+            EnumTagging::Internal { tag } => {
+                // every variant is an object with the tag, so all fields get merged together
+                let mut fields = vec![required_field(tag.clone(), Schema::String(None))];
+                for variant in self.enum_variants.iter() {
+                    for field in variant.fields.iter() {
+                        match fields.iter_mut().find(|f| f.name == field.0.name) {
+                            Some(existing) => {
+                                existing.schema =
+                                    union_of(vec![existing.schema.clone(), field.0.schema.clone()])
+                            }
+                            None => fields
+                                .push(optional_field(field.0.name.clone(), field.0.schema.clone())),
+                        }
+                    }
+                }
+                Schema::Record(schema::Record::new(self.enum_name.clone(), fields))
+            }
+            EnumTagging::Adjacent { tag, content } => {
+                let mut fields = vec![required_field(tag.clone(), Schema::String(None))];
+                let contents = self
+                    .enum_variants
+                    .iter()
+                    .filter_map(|v| self.variant_content(v))
+                    .collect::<Vec<_>>();
+                if !contents.is_empty() {
+                    fields.push(optional_field(content.clone(), union_of(contents)));
+                }
+                Schema::Record(schema::Record::new(self.enum_name.clone(), fields))
+            }
+        }
+    }
+}
+
+fn is_positional(fields: &[BoxField]) -> bool {
+    fields
+        .iter()
+        .all(|f| f.0.name.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn required_field(name: String, schema: Schema) -> schema::Field {
+    schema::Field {
+        name,
+        doc: None,
+        schema,
+        default: None,
+        order: None,
+        aliases: vec![],
+    }
+}
+
+// the field can be absent in json, e.g. because of skip_serializing_none
+fn optional_field(name: String, schema: Schema) -> schema::Field {
+    schema::Field {
+        name,
+        doc: None,
+        schema: union_of(vec![schema]),
+        default: Some(Schema::Null),
+        order: None,
+        aliases: vec![],
+    }
+}
+
+fn push_union_branch(into: &mut Vec<Schema>, schema: Schema) {
+    match schema {
+        // avro does not allow unions inside unions
+        Schema::Union(branches) => {
+            for branch in branches {
+                push_union_branch(into, branch);
+            }
+        }
+        other => {
+            if !into.contains(&other) {
+                into.push(other)
+            }
+        }
+    }
+}
+
+// nullable union, with null going first, since it's the default value
+fn union_of(schemas: Vec<Schema>) -> Schema {
+    let mut branches = vec![Schema::Null];
+    for schema in schemas {
+        push_union_branch(&mut branches, schema);
+    }
+    Schema::Union(branches)
+}
+
+// avro-schema keeps the default values as Schema, so the null default gets serialized as "null" string
+fn fix_null_defaults(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(obj) => {
+            if obj.get("default") == Some(&serde_json::Value::from("null")) {
+                obj.insert("default".to_string(), serde_json::Value::Null);
+            }
+            for (_, v) in obj.iter_mut() {
+                fix_null_defaults(v);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items.iter_mut() {
+                fix_null_defaults(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+// all #[serde(...)] arguments, e.g. ("tag", Some("tag")) or ("default", None)
+fn serde_args(attrs: &[Attribute]) -> Vec<(String, Option<String>)> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter().collect::<Vec<_>>()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) => nv.path.get_ident().map(|id| {
+                let value = match nv.lit {
+                    Lit::Str(str) => Some(str.value()),
+                    _ => None,
+                };
+                (id.to_string(), value)
+            }),
+            NestedMeta::Meta(Meta::Path(path)) => path.get_ident().map(|id| (id.to_string(), None)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn serde_arg(attrs: &[Attribute], name: &str) -> Option<String> {
+    serde_args(attrs)
+        .into_iter()
+        .find(|(arg, _)| arg == name)
+        .and_then(|(_, value)| value)
+}
+
+// e.g. #[skip_serializing_none] or #[serde_with::skip_serializing_none]
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name)
+    })
+}
+
+fn enum_tagging(attrs: &[Attribute]) -> EnumTagging {
+    match (serde_arg(attrs, "tag"), serde_arg(attrs, "content")) {
+        (Some(tag), Some(content)) => EnumTagging::Adjacent { tag, content },
+        (Some(tag), None) => EnumTagging::Internal { tag },
+        _ => EnumTagging::External,
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(tp) => tp
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn type_to_string(ty: &Type) -> String {
+    ty.to_token_stream().to_string()
+}
+
+// FNV-1a, unlike the std hashers it is guaranteed to stay the same between the compiler versions
+fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct BoxSchema {
+    pub deps: Vec<String>,
+    pub name: String,
+    pub schema: Schema,
+}
+#[derive(Debug, Clone)]
+pub enum BoxEntity {
+    Record(BoxRecord),
+    Enum(BoxEnum),
+    RawType(BoxSchema),
+}
+
+impl BoxEntity {
+    fn get_deps(&self) -> &Vec<String> {
+        match self {
+            BoxEntity::Record(v) => &v.deps,
+            BoxEntity::Enum(v) => &v.deps,
+            BoxEntity::RawType(v) => &v.deps,
+        }
+    }
+    fn get_name(&self) -> String {
+        match self {
+            BoxEntity::Record(r) => r.name.clone(),
+            BoxEntity::Enum(e) => e.enum_name.clone(),
+            BoxEntity::RawType(rt) => rt.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoxField(schema::Field);
+
+#[derive(Debug, Clone)]
+pub enum SchemaOrRef {
+    Schema(Schema),
+    Ref(String),
+}
+
+impl Serialize for SchemaOrRef {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            SchemaOrRef::Schema(schema) => schema.serialize(serializer),
+            SchemaOrRef::Ref(str) => serializer.serialize_str(str.as_str()),
+        }
+    }
+}
+
+impl Serialize for BoxField {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        let schema_clone = self.0.schema.clone();
+        let schema_fixed: SchemaOrRef = match &self.0.schema {
+            Schema::Fixed(Fixed { size, name, .. }) => {
+                if *size == 0 {
+                    SchemaOrRef::Ref(name.clone())
+                } else {
+                    SchemaOrRef::Schema(schema_clone)
+                }
+            }
+            _ => SchemaOrRef::Schema(schema_clone),
+        };
+        map.serialize_entry("type", &schema_fixed)?;
+        map.serialize_entry("name", &self.0.name)?;
+        if self.0.default.is_some() {
+            map.serialize_entry("default", &self.0.default)?;
+        }
+        if self.0.doc.is_some() {
+            map.serialize_entry("doc", &self.0.doc)?;
+        }
+        if !self.0.aliases.is_empty() {
+            map.serialize_entry("aliases", &self.0.aliases)?;
+        }
+        // order is ignored here
+        map.end()
+    }
+}
+
+impl Serialize for BoxRecord {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", "record")?;
+        map.serialize_entry("name", &self.name)?;
+        map.serialize_entry("fields", &self.fields)?;
+        map.end()
+    }
+}
+
+impl Serialize for BoxEntity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            BoxEntity::Record(record) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "record")?;
+                map.serialize_entry("name", &record.name)?;
+                map.serialize_entry("fields", &record.fields)?;
+                map.end()
+            }
+            BoxEntity::Enum(en) => match en.to_schema() {
+                // records keep plain name references in the fields, same as the structs
+                Schema::Record(record) => BoxRecord {
+                    deps: vec![],
+                    name: record.name,
+                    namespace: None,
+                    fields: record.fields.into_iter().map(BoxField).collect(),
+                }
+                .serialize(serializer),
+                // avro has no named unions, so it's the same as a type alias
+                Schema::Union(branches) => {
+                    let mut map = serializer.serialize_map(None)?;
+                    map.serialize_entry("name", &en.enum_name)?;
+                    map.serialize_entry("type", &branches)?;
+                    map.end()
+                }
+                other => other.serialize(serializer),
+            },
+            BoxEntity::RawType(rt) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("name", &rt.name)?;
+                map.serialize_entry("type", &rt.schema)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl TypeTransformVisitor {
+    fn grab_fields_get_deps_record(
+        &self,
+        into_fields: &mut Vec<BoxField>,
+        into_deps: &mut Vec<String>,
+        node: &ItemStruct,
+        side_effect_types: &mut Vec<BoxEntity>,
+    ) -> Result<(), String> {
+        self.grab_fields_get_deps_any(
+            into_fields,
+            into_deps,
+            side_effect_types,
+            &node.fields,
+            has_attr(&node.attrs, "skip_serializing_none"),
+        )
+    }
+
+    fn grab_fields_get_deps_any(
+        &self,
+        into_fields: &mut Vec<BoxField>,
+        into_deps: &mut Vec<String>,
+        side_effect_types: &mut Vec<BoxEntity>,
+        fields: &Fields,
+        skip_none: bool,
+    ) -> Result<(), String> {
+        let mut unnamed_index = 0;
+        for field in fields.iter() {
+            let field_name = field.ident.as_ref().map_or_else(
+                || {
+                    unnamed_index += 1;
+                    unnamed_index.to_string()
+                },
+                |id| id.to_string(),
+            );
+            let mapped = if serde_arg(&field.attrs, "with").as_deref() == Some("any_key_map") {
+                self.map_any_key_map(&field.ty, side_effect_types)
+            } else {
+                self.map_type(&field.ty, side_effect_types)
+            };
+            let (schema, dep_name) = mapped.map_err(|e| format!("field {}: {}", field_name, e))?;
+            for dep_name in dep_name {
+                into_deps.push(dep_name);
+            }
+            let skipped_when_none = skip_none
+                || serde_arg(&field.attrs, "skip_serializing_if").as_deref()
+                    == Some("Option::is_none");
+            into_fields.push(BoxField(schema::Field {
+                name: field_name,
+                doc: None,
+                schema,
+                default: if skipped_when_none && is_option(&field.ty) {
+                    Some(Schema::Null)
+                } else {
+                    None
+                },
+                order: None,
+                aliases: vec![],
+            }))
+        }
+        Ok(())
+    }
+
+    // any_key_map writes the keys as json strings, so any key type will work
+    fn map_any_key_map(
+        &self,
+        ty: &Type,
+        side_effect_types: &mut Vec<BoxEntity>,
+    ) -> Result<(Schema, Vec<String>), String> {
+        match ty {
+            Type::Path(tp) if tp.path.segments[0].ident == "HashMap" => {
+                let value_arg = get_nth_type_arg_type_path(&tp.path.segments[0].arguments, 1)?;
+                let (value_schema, deps) = self.map_type(&value_arg, side_effect_types)?;
+                Ok((Schema::Map(Box::new(value_schema)), deps))
+            }
+            _ => Err(format!(
+                "any_key_map is only supported for HashMap, got {}",
+                type_to_string(ty)
+            )),
+        }
+    }
+
+    fn map_primitive(&self, prim: &str) -> Option<(Schema, Vec<String>)> {
+        match prim {
+            "f64" => Some((Schema::Double, vec![])),
+            "f32" => Some((Schema::Float, vec![])),
+            "u32" => Some((Schema::Int(None), vec![])),
+            "i32" => Some((Schema::Int(None), vec![])),
+            "usize" => Some((Schema::Int(None), vec![])),
+            "bool" => Some((Schema::Boolean, vec![])),
+            "i64" => Some((Schema::Long(None), vec![])),
+            "u64" => Some((Schema::Long(None), vec![])),
+            "String" => Some((Schema::String(None), vec![])),
+            "Uuid" => Some((Schema::String(Some(StringLogical::Uuid)), vec![])), // while not primitive, it's string-based
+            _ => None,
+        }
+    }
+
+    fn map_built_in_complex(
+        &self,
+        built_in: &Type,
+        side_effect_types: &mut Vec<BoxEntity>,
+    ) -> Result<Option<(Schema, Vec<String>)>, String> {
+        match built_in {
+            Type::Path(tp) => {
+                let first_segment = &tp.path.segments[0];
+                let first_segment_name = first_segment.ident.to_string();
+                if first_segment_name == "Option" {
+                    let first_arg = get_nth_type_arg_type_path(&first_segment.arguments, 0)?;
+                    let map_target_result = self.map_type(&first_arg, side_effect_types)?;
+                    return Ok(Some((
+                        Schema::Union(vec![Schema::Null, map_target_result.0]),
+                        map_target_result.1,
+                    )));
+                } else if first_segment_name == "Box" {
+                    // boxing does not change the serialized form
+                    let first_arg = get_nth_type_arg_type_path(&first_segment.arguments, 0)?;
+                    return Ok(Some(self.map_type(&first_arg, side_effect_types)?));
+                } else if first_segment_name == "Vec"
+                    || first_segment_name == "VecDeque"
+                    || first_segment_name == "HashSet"
+                {
+                    let first_arg = get_nth_type_arg_type_path(&first_segment.arguments, 0)?;
+                    let map_target_result = self.map_type(&first_arg, side_effect_types)?;
+                    return Ok(Some((
+                        Schema::Array(Box::from(map_target_result.0)),
+                        map_target_result.1,
+                    )));
+                } else if first_segment_name == "HashMap" {
+                    let first_arg = get_nth_type_arg_type_path(&first_segment.arguments, 0)?;
+                    // strictly speaking, any key that is not serialized as a string is a problem, but this requires
+                    // analysing aliases and their mappings, so only the obvious cases are checked
+                    if let Type::Tuple(_) = first_arg {
+                        return Err(format!(
+                            "map key {} cannot be a json object key, use #[serde(with = \"any_key_map\")]",
+                            type_to_string(&first_arg)
+                        ));
+                    }
+                    let mut map_first = self.map_type(&first_arg, side_effect_types)?;
+                    let second_arg = get_nth_type_arg_type_path(&first_segment.arguments, 1)?;
+                    let mut map_second = self.map_type(&second_arg, side_effect_types)?;
+                    let mut deps = vec![];
+                    deps.append(&mut map_first.1);
+                    deps.append(&mut map_second.1);
+                    let schema = Schema::Map(Box::new(map_second.0));
+                    return Ok(Some((schema, deps)));
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn map_type(
+        &self,
+        ty: &Type,
+        side_effect_types: &mut Vec<BoxEntity>,
+    ) -> Result<(Schema, Vec<String>), String> {
+        match ty {
+            // Type::Array(_) => {}
+            // Type::BareFn(_) => {}
+            // Type::Group(_) => {}
+            // Type::ImplTrait(_) => {}
+            // Type::Infer(_) => {}
+            // Type::Macro(_) => {}
+            // Type::Never(_) => {}
+            // Type::Paren(_) => {}
+            Type::Path(tp) => {
+                let first_segment_name = tp.path.segments[0].ident.to_string();
+                if let Some(map_primitive) = self.map_primitive(first_segment_name.as_str()) {
+                    return Ok(map_primitive);
+                }
+                if let Some(map_build_in_complex) =
+                    self.map_built_in_complex(ty, side_effect_types)?
+                {
+                    return Ok(map_build_in_complex);
+                }
+                let map_reference = self.map_reference(first_segment_name.as_str());
+                Ok(map_reference)
+            }
+            // Type::Ptr(_) => {}
+            // Type::Reference(_) => {}
+            // Type::Slice(_) => {}
+            // Type::TraitObject(_) => {}
+            // unit is serialized as null
+            Type::Tuple(tup) if tup.elems.is_empty() => Ok((Schema::Null, vec![])),
+            Type::Tuple(tup) => {
+                // tuple has to emit an anonymous type along the way,
+                let mut collected_deps = vec![];
+                let mut fields = vec![];
+                for (counter, e) in tup.elems.iter().enumerate() {
+                    let mapped_type = self
+                        .map_type(e, side_effect_types)
+                        .map_err(|err| format!("tuple element {}: {}", counter, err))?;
+                    collected_deps.extend(mapped_type.1);
+                    fields.push(schema::Field {
+                        name: counter.to_string(),
+                        doc: None,
+                        schema: mapped_type.0,
+                        default: None,
+                        order: None,
+                        aliases: vec![],
+                    });
+                }
+                let schema_record = Schema::Record(schema::Record {
+                    // derived from the type, so regenerating the schemas does not change them
+                    name: format!("tuple_{:016x}", stable_hash(&type_to_string(ty))),
+                    namespace: None,
+                    doc: None,
+                    aliases: vec![],
+                    fields,
+                });
+                Ok((schema_record, collected_deps))
+            }
+            // Type::Verbatim(_) => {}
+            // _ => Schema::Null,
+            _ => Err(format!("unsupported type {}", type_to_string(ty))),
+        }
+    }
+    fn map_reference(&self, reference: &str) -> (Schema, Vec<String>) {
+        // I am abusing Fixed here because there is no schema-reference ability in avro-schema (Schema::Ref variant)
+        // This field will not be a true avro Fixed, but rather 0-size name-reference to another schema
+        let ref_name = reference.to_string();
+        (
+            Schema::Fixed(Fixed {
+                name: ref_name.clone(),
+                namespace: None,
+                doc: None,
+                aliases: vec![],
+                size: 0,
+                logical: None,
+            }),
+            vec![ref_name],
+        )
+    }
+}
+
+fn get_nth_type_arg_type_path(args: &PathArguments, idx: usize) -> Result<Type, String> {
+    match args {
+        PathArguments::AngleBracketed(angle_args) => match angle_args.args.iter().nth(idx) {
+            Some(GenericArgument::Type(ty)) => Ok(ty.clone()),
+            Some(other) => Err(format!(
+                "unsupported generic argument {} in {}",
+                other.to_token_stream(),
+                args.to_token_stream()
+            )),
+            None => Err(format!(
+                "expected at least {} generic arguments in {}",
+                idx + 1,
+                args.to_token_stream()
+            )),
+        },
+        _ => Err(format!(
+            "expected generic arguments, got '{}'",
+            args.to_token_stream()
+        )),
+    }
+}
+
+impl<'ast> Visit<'ast> for TypeTransformVisitor {
+    fn visit_item_type(&mut self, node: &'ast ItemType) {
+        let type_name = node.ident.to_string();
+        eprintln!("Started type alias {}", type_name);
+        let analyze = self.check_analyze(&type_name, &node.attrs, true);
+        if analyze {
+            eprintln!("Grabbed type alias {}", type_name);
+            let mut se = vec![];
+            match self.map_type(&node.ty, &mut se) {
+                Ok(mapped) => self.add(
+                    type_name.clone(),
+                    BoxEntity::RawType(BoxSchema {
+                        deps: mapped.1,
+                        name: type_name,
+                        schema: mapped.0,
+                    }),
+                ),
+                Err(e) => self.error(&type_name, e),
+            }
+        } else {
+            eprintln!("Skipped type alias {}", type_name);
+        }
+        visit::visit_item_type(self, node);
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast ItemEnum) {
+        let enum_name = node.ident.to_string();
+        eprintln!("Started enum {}", enum_name);
+
+        let analyze = self.check_analyze(&enum_name, &node.attrs, false);
+
+        if analyze {
+            let mut union: Vec<BoxRecord> = vec![];
+            let mut deps: Vec<String> = vec![];
+            let tagging = enum_tagging(&node.attrs);
+            let skip_none = has_attr(&node.attrs, "skip_serializing_none");
+            let grabbed = node.variants.iter().try_for_each(|item| {
+                self.grab_fields_get_deps_enum_variant(item, &mut union, &mut deps, skip_none)?;
+                let variant = union.last().unwrap();
+                match tagging {
+                    // serde merges the newtype variant fields into the tagged object, which cannot be expressed
+                    // without the inner type definition, and it does not support other tuple variants at all
+                    EnumTagging::Internal { .. }
+                        if !variant.fields.is_empty() && is_positional(&variant.fields) =>
+                    {
+                        Err(format!(
+                            "tuple variant {} of internally tagged enum is not supported",
+                            variant.name
+                        ))
+                    }
+                    _ => Ok(()),
+                }
+            });
+            match grabbed {
+                Ok(_) => {
+                    self.add(
+                        enum_name.clone(),
+                        BoxEntity::Enum(BoxEnum {
+                            enum_name: enum_name.clone(),
+                            deps,
+                            enum_variants: union,
+                            tagging,
+                        }),
+                    );
+                    eprintln!("Grabbed enum {}", enum_name);
+                }
+                Err(e) => self.error(&enum_name, e),
+            }
+        } else {
+            eprintln!("Skipped enum {}", enum_name);
+        }
+        visit::visit_item_enum(self, node);
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast ItemStruct) {
+        let struct_name = node.ident.to_string();
+        eprintln!("Started struct {}", struct_name);
+        let analyze = self.check_analyze(&struct_name, &node.attrs, false);
+        if analyze {
+            let mut record = BoxRecord {
+                deps: vec![],
+                name: struct_name.clone(),
+                namespace: None,
+                fields: vec![],
+            };
+
+            let mut side_effect_types = vec![];
+            let grabbed = self.grab_fields_get_deps_record(
+                &mut record.fields,
+                &mut record.deps,
+                node,
+                &mut side_effect_types,
+            );
+            if let Err(e) = grabbed {
+                self.error(&struct_name, e);
+            } else {
+                self.add(struct_name.clone(), BoxEntity::Record(record));
+                for side_effect_type in side_effect_types.into_iter() {
+                    self.add(side_effect_type.get_name(), side_effect_type);
+                }
+                eprintln!("Grabbed struct {}", struct_name);
+            }
+        } else {
+            eprintln!("Skipped struct {}", struct_name);
+        }
+        visit::visit_item_struct(self, node);
+    }
+}
+
+pub struct GeneratedSchema {
+    pub file_name: String,
+    pub name: String,
+    pub schema: serde_json::Value,
+}
+
+// schemas are returned in the dependency order, so every one of them only references the previous ones
+pub fn generate_schemas(
+    from: &Vec<PathBuf>,
+    whitelist: Option<Vec<String>>,
+    blacklist: Option<Vec<String>>,
+) -> Result<Vec<GeneratedSchema>, Vec<GenError>> {
+    let mut sources = vec![];
+    for file in from {
+        let src = fs::read_to_string(file.clone()).map_err(|e| {
+            vec![GenError {
+                entity: format!("{file:?}"),
+                message: format!("cannot read: {e}"),
+            }]
+        })?;
+        sources.push((format!("{file:?}"), src));
+    }
+    generate_schemas_from_sources(&sources, whitelist, blacklist)
+}
+
+// sources are pairs of (file name, contents)
+pub fn generate_schemas_from_sources(
+    sources: &Vec<(String, String)>,
+    whitelist: Option<Vec<String>>,
+    blacklist: Option<Vec<String>>,
+) -> Result<Vec<GeneratedSchema>, Vec<GenError>> {
+    let mut visitor = TypeTransformVisitor::new(whitelist, blacklist);
+    for (file_name, src) in sources {
+        match syn::parse_file(src.as_str()) {
+            Ok(file) => visitor.visit_file(&file),
+            Err(e) => visitor.error(file_name, format!("Unable to create AST from file: {e}")),
+        }
+    }
+    if !visitor.errors.is_empty() {
+        return Err(visitor.errors);
+    }
+    let mut res = vec![];
+    let mut layer_counter = 0;
+    for layer in visitor.top_sorted_records() {
+        let mut item_counter = 0;
+        layer_counter += 1;
+        for (key, value) in layer {
+            item_counter += 1;
+            let mut schema = serde_json::to_value(value)
+                .unwrap_or_else(|_| panic!("could not serialize record named {key}"));
+            fix_null_defaults(&mut schema);
+            res.push(GeneratedSchema {
+                file_name: format!("{layer_counter:0>2}-{item_counter:0>2}-{key}.json"),
+                name: key,
+                schema,
+            });
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,149 @@
+use crate::generate_schemas_from_sources;
+use serde_json::{json, Value};
+
+fn gen(src: &str) -> Vec<(String, Value)> {
+    generate_schemas_from_sources(&vec![("test.rs".to_string(), src.to_string())], None, None)
+        .unwrap_or_else(|errors| panic!("{:?}", errors))
+        .into_iter()
+        .map(|g| (g.name, g.schema))
+        .collect()
+}
+
+fn gen_one(src: &str, name: &str) -> Value {
+    gen(src)
+        .into_iter()
+        .find(|(n, _)| n == name)
+        .unwrap_or_else(|| panic!("no schema for {}", name))
+        .1
+}
+
+fn gen_errors(src: &str) -> Vec<String> {
+    match generate_schemas_from_sources(&vec![("test.rs".to_string(), src.to_string())], None, None)
+    {
+        Ok(_) => panic!("expected errors"),
+        Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+#[test]
+pub fn unit_enum_is_avro_enum() {
+    let schema = gen_one(
+        "#[derive(TypescriptDefinition)] pub enum Mode { A, B }",
+        "Mode",
+    );
+    assert_eq!(
+        schema,
+        json!({"type": "enum", "name": "Mode", "symbols": ["A", "B"]})
+    );
+}
+
+#[test]
+pub fn internally_tagged_enum_merges_variant_fields() {
+    let schema = gen_one(
+        r#"
+        #[derive(TypescriptDefinition)]
+        #[serde(tag = "kind")]
+        pub enum Act { Stop, Go { id: u32, target: String }, Dock { target: Uuid } }
+        "#,
+        "Act",
+    );
+    assert_eq!(
+        schema,
+        json!({"type": "record", "name": "Act", "fields": [
+            {"name": "kind", "type": "string"},
+            {"name": "id", "type": ["null", "int"], "default": null},
+            {"name": "target", "type": ["null", "string", {"type": "string", "logicalType": "uuid"}], "default": null},
+        ]})
+    );
+}
+
+#[test]
+pub fn adjacently_tagged_enum_has_content_union() {
+    let schema = gen_one(
+        r#"
+        #[derive(TypescriptDefinition)]
+        #[serde(tag = "tag", content = "fields")]
+        pub enum Prop { Flag, Amount(u32), Pair(u32, String) }
+        "#,
+        "Prop",
+    );
+    assert_eq!(
+        schema,
+        json!({"type": "record", "name": "Prop", "fields": [
+            {"name": "tag", "type": "string"},
+            {"name": "fields", "default": null, "type": ["null", "int", {
+                "type": "record",
+                "name": "PropPair",
+                "fields": [{"name": "0", "type": "int"}, {"name": "1", "type": "string"}]
+            }]},
+        ]})
+    );
+}
+
+#[test]
+pub fn externally_tagged_enum_with_unit_variants_is_union() {
+    let schema = gen_one(
+        "#[derive(TypescriptDefinition)] pub enum Effect { Nothing, Switch(String) }",
+        "Effect",
+    );
+    assert_eq!(
+        schema,
+        json!({"name": "Effect", "type": [
+            {"type": "enum", "name": "EffectUnit", "symbols": ["Nothing"]},
+            {"type": "record", "name": "EffectData", "fields": [
+                {"name": "Switch", "type": ["null", "string"], "default": null}
+            ]},
+        ]})
+    );
+}
+
+#[test]
+pub fn skip_serializing_none_makes_null_defaults() {
+    let schema = gen_one(
+        r#"
+        #[skip_serializing_none]
+        #[derive(TypescriptDefinition)]
+        pub struct Item { pub id: u32, pub quest_id: Option<Uuid> }
+        "#,
+        "Item",
+    );
+    assert_eq!(
+        schema["fields"][1],
+        json!({"name": "quest_id", "type": ["null", {"type": "string", "logicalType": "uuid"}], "default": null})
+    );
+    assert_eq!(schema["fields"][0].get("default"), None);
+}
+
+#[test]
+pub fn any_key_map_allows_any_keys() {
+    let schema = gen_one(
+        r#"
+        #[derive(TypescriptDefinition)]
+        pub struct Script {
+            #[serde(with = "any_key_map")]
+            pub transitions: HashMap<(Uuid, Uuid), Vec<String>>,
+        }
+        "#,
+        "Script",
+    );
+    assert_eq!(
+        schema["fields"][0]["type"],
+        json!({"type": "map", "values": {"type": "array", "items": "string"}})
+    );
+}
+
+#[test]
+pub fn unsupported_shapes_are_errors() {
+    let errors = gen_errors(
+        r#"
+        #[derive(TypescriptDefinition)] pub struct A { pub m: HashMap<(String, u32), u32> }
+        #[derive(TypescriptDefinition)] pub struct B { pub r: &'static str }
+        #[derive(TypescriptDefinition)] #[serde(tag = "tag")] pub enum C { X(u32) }
+        "#,
+    );
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].starts_with("A: field m: map key"));
+    assert!(errors[0].contains("any_key_map"));
+    assert!(errors[1].starts_with("B: field r: unsupported type"));
+    assert!(errors[2].starts_with("C: tuple variant X"));
+}
@@ -33,6 +33,11 @@ const HpDisplay = ({ myShip }: { myShip: Ship }) => {
         <div className="prefix-text">Ship integrity:&nbsp;</div>
         <div className="text">
           {Math.floor(myShip.health.current)}/{Math.floor(myShip.health.max)}
+          {myShip.health.shield && (
+            <span className="shield-text">
+              &nbsp;+{Math.floor(myShip.health.shield.current)}
+            </span>
+          )}
         </div>
         <div
           className="filler"
@@ -58,7 +63,10 @@ function shouldUpdateByHealth(prev: GameState, next: GameState) {
     return false;
   }
   if (myShipOld && myShipNew) {
-    return myShipOld.health.current !== myShipNew.health.current;
+    return (
+      myShipOld.health.current !== myShipNew.health.current ||
+      myShipOld.health.shield?.current !== myShipNew.health.shield?.current
+    );
   }
   return false;
 }
@@ -13,8 +13,9 @@ import { OreSvg } from './svg/OreSvg';
 import { FoodSvg } from './svg/FoodSvg';
 import MedicineSvg from './svg/MedicineSvg';
 import { WeaponSvg } from './svg/WeaponSvg';
+import { ModuleSvg } from './svg/ModuleSvg';
 
-const getDisplayName = (iit: InventoryItemType): string => {
+export const getDisplayName = (iit: InventoryItemType): string => {
   switch (iit) {
     case InventoryItemType.Food:
       return 'Food';
@@ -32,6 +33,14 @@ const getDisplayName = (iit: InventoryItemType): string => {
       return 'Rare mineral';
     case InventoryItemType.QuestCargo:
       return 'Quest cargo';
+    case InventoryItemType.TurretModule:
+      return 'Turret module';
+    case InventoryItemType.HullPlatingModule:
+      return 'Hull plating';
+    case InventoryItemType.EngineModule:
+      return 'Engine upgrade';
+    case InventoryItemType.TractorModule:
+      return 'Tractor amplifier';
     default:
       throw new UnreachableCaseError(iit);
   }
@@ -55,6 +64,14 @@ const getItemColor = (iit: InventoryItemType): string => {
       return rare;
     case InventoryItemType.QuestCargo:
       return rare;
+    case InventoryItemType.TurretModule:
+      return rare;
+    case InventoryItemType.HullPlatingModule:
+      return common;
+    case InventoryItemType.EngineModule:
+      return rare;
+    case InventoryItemType.TractorModule:
+      return uncommon;
     default:
       throw new UnreachableCaseError(iit);
   }
@@ -124,6 +141,18 @@ const renderItem = (item: InventoryItem) => {
           stroke="#111"
         />
       );
+    case InventoryItemType.TurretModule:
+    case InventoryItemType.HullPlatingModule:
+    case InventoryItemType.EngineModule:
+    case InventoryItemType.TractorModule:
+      return (
+        <ModuleSvg
+          width={50}
+          height={50}
+          fill={getItemColor(item.item_type)}
+          stroke="#111"
+        />
+      );
     default:
       throw new UnreachableCaseError(item.item_type);
   }
@@ -29,11 +29,13 @@ export const LeaderboardWindow: React.FC = () => {
       JSON.stringify(prevState.leaderboard) !==
         JSON.stringify(nextState.leaderboard) ||
       JSON.stringify(prevState.game_over) !==
-        JSON.stringify(nextState.game_over)
+        JSON.stringify(nextState.game_over) ||
+      JSON.stringify(prevState.pirate_waves) !==
+        JSON.stringify(nextState.pirate_waves)
     );
   });
 
-  const { leaderboard, paused, my_id, game_over } = ns.state;
+  const { leaderboard, paused, my_id, game_over, pirate_waves } = ns.state;
   if (!leaderboard) {
     return null;
   }
@@ -71,7 +73,9 @@ export const LeaderboardWindow: React.FC = () => {
       </div>
       {paused && game_over && (
         <div className="game-over">
-          <div className="game-over-text">Game over:</div>
+          <div className="game-over-text">
+            {game_over.victory ? 'Victory:' : 'Game over:'}
+          </div>
           <div className="game-over-text game-over-reason">
             {game_over.reason}
           </div>
@@ -80,6 +84,20 @@ export const LeaderboardWindow: React.FC = () => {
       {paused && !game_over && (
         <div className="winner">Winner:{leaderboard.winner}</div>
       )}
+      {!paused && pirate_waves && (
+        <div className="countdown">
+          {pirate_waves.in_progress
+            ? `Wave ${pirate_waves.current + 1} of ${pirate_waves.total}`
+            : `Next wave ${pirate_waves.current + 1} of ${
+                pirate_waves.total
+              } in ${Math.max(
+                0,
+                Math.ceil(
+                  (pirate_waves.next_wave_at_ticks - ns.state.ticks) / 1e6
+                )
+              )}s`}
+        </div>
+      )}
       <div className="header">Leaderboard:</div>
       {leaderboard.rating.map(
         ([id, score, name]: [string, number, string], i: number) => (
@@ -19,6 +19,8 @@ const getActionName = (a: LongAction): string | undefined => {
       return undefined;
     case 'TransSystemJump':
       return 'Jumping...';
+    case 'AutoJump':
+      return undefined;
     case 'Shoot':
       return undefined;
     case 'Launch':
@@ -86,7 +88,8 @@ export const LongActionsDisplay = () => {
         {myShip &&
           myShip.long_actions.map((a) => {
             const name = getActionName(a);
-            if (a.tag === 'Unknown' || !name) {
+            // auto-jump is not timed, but every jump it makes is displayed on its own
+            if (a.tag === 'Unknown' || a.tag === 'AutoJump' || !name) {
               return null;
             }
             return (
@@ -1,9 +1,10 @@
 import React from 'react';
 import { Button } from './ui/Button';
 import { GameMode } from '../../../world/pkg/world.extra';
+import { BotDifficulty } from '../../../world/pkg/world';
 
 export const PlayMenu: React.FC<{
-  start: (gameMode: GameMode) => void;
+  start: (gameMode: GameMode, botDifficulty?: BotDifficulty) => void;
   hide: () => void;
 }> = ({ start, hide }) => (
   <div className="play-menu">
@@ -24,6 +25,13 @@ export const PlayMenu: React.FC<{
       hotkey="c"
       text="CARGO RUSH"
     />
+    <div>Same, but the bots also trade and mine on the side:</div>
+    <Button
+      className="play"
+      onClick={() => start(GameMode.CargoRush, 'Hard')}
+      hotkey="h"
+      text="CARGO RUSH, HARD BOTS"
+    />
     <div>Defend a planet against hordes of invader ships:</div>
     <Button
       className="play"
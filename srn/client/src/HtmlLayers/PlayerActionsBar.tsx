@@ -4,7 +4,7 @@ import { ActionBarAction, ActionsBar } from './ActionsBar';
 import { ObjectSpecifier, Player, Ship } from '../world';
 // eslint-disable-next-line import/named
 import { Ability } from '../../../world/pkg/world';
-import { FaBullseye } from 'react-icons/fa';
+import { FaBullseye, FaCrosshairs } from 'react-icons/fa';
 import { BiRocket } from 'react-icons/bi';
 import { UnreachableCaseError } from 'ts-essentials';
 import { useActiveInteractors } from '../store';
@@ -80,6 +80,13 @@ const mapShipAbility = (interactorIds: InteractorIds) => (
         cooldownNormalized: ability.cooldown_normalized,
         icon: <BiRocket />,
       };
+    case 'PointDefence':
+      return {
+        // fires on its own, the slot only shows the cooldown
+        action: () => {},
+        cooldownNormalized: ability.cooldown_normalized,
+        icon: <FaCrosshairs />,
+      };
     default:
       throw new UnreachableCaseError(ability);
   }
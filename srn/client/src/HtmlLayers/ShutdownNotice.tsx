@@ -0,0 +1,44 @@
+import React, { useEffect, useState } from 'react';
+import NetState from '../NetState';
+import './ShutdownNotice.scss';
+
+const COUNTDOWN_UPDATE_MS = 250;
+
+export const ShutdownNotice: React.FC = () => {
+  const ns = NetState.get();
+  if (!ns) return null;
+
+  // the local time when the server is expected to stop
+  const [shutdownAt, setShutdownAt] = useState<number | null>(null);
+  const [now, setNow] = useState(Date.now());
+  useEffect(() => {
+    const onShutdown = (ev: any) => {
+      if (ev.tag === 'ServerShutdown') {
+        setShutdownAt(Date.now() + ev.shutdown_in_ms);
+      }
+    };
+    ns.on('gameEvent', onShutdown);
+    return () => {
+      ns.off('gameEvent', onShutdown);
+    };
+  }, [ns, ns.id]);
+  useEffect(() => {
+    if (shutdownAt === null) {
+      return () => {};
+    }
+    const interval = setInterval(() => setNow(Date.now()), COUNTDOWN_UPDATE_MS);
+    return () => clearInterval(interval);
+  }, [shutdownAt]);
+
+  if (shutdownAt === null) {
+    return null;
+  }
+  const secondsLeft = Math.max(0, Math.ceil((shutdownAt - now) / 1000));
+  return (
+    <div className="shutdown-notice">
+      {secondsLeft > 0
+        ? `The server is shutting down in ${secondsLeft}s`
+        : 'The server is shutting down'}
+    </div>
+  );
+};
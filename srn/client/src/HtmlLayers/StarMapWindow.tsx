@@ -65,8 +65,9 @@ export const StarMapWindow: React.FC = () => {
               links={links}
               onSystemClick={(id) => {
                 ns.startLongAction(
-                  LongActionStartBuilder.LongActionStartTransSystemJump({
+                  LongActionStartBuilder.LongActionStartAutoJump({
                     to: id,
+                    metric: 'Jumps',
                   })
                 );
                 setMapWindow(WindowState.Hidden);
@@ -2,17 +2,20 @@ import React, { useEffect, useState } from 'react';
 import { Window } from './ui/Window';
 import { cellsToPixels, ItemGrid, ItemMoveKind, MoveEvent } from './ItemGrid';
 import './TradeWindow.scss';
-import { GameState, Market } from '../world';
+import { GameState, InventoryItemType, Market } from '../world';
 import { useStore, WindowState } from '../store';
 import _ from 'lodash';
 import {
   ActionBuilder,
+  FittingActionBuilder,
   InventoryActionBuilder,
+  ShipModule,
 } from '../../../world/pkg/world.extra';
 import styleVars from './TradeWindow.vars.module.scss';
 import { pxToNumber } from '../utils/pxToNumber';
 import { findMyShip } from '../ClientStateIndexing';
 import { useNSForceChange } from '../NetStateHooks';
+import { getDisplayName } from './InventoryItem';
 
 const BOTTOM_BAR_HEIGHT = Number(pxToNumber(styleVars.bottomBarHeight));
 const TOP_BAR_HEIGHT = Number(pxToNumber(styleVars.topBarHeight));
@@ -42,6 +45,16 @@ const selectWares = (market: Market | undefined | null, planetId: string) => {
   }));
 };
 
+const MODULE_ITEM_TYPES: Record<ShipModule, InventoryItemType> = {
+  [ShipModule.ExtraTurret]: InventoryItemType.TurretModule,
+  [ShipModule.HullPlating]: InventoryItemType.HullPlatingModule,
+  [ShipModule.EngineUpgrade]: InventoryItemType.EngineModule,
+  [ShipModule.TractorAmplifier]: InventoryItemType.TractorModule,
+};
+
+const isModuleItem = (iit: InventoryItemType) =>
+  Object.values(MODULE_ITEM_TYPES).includes(iit);
+
 const selectPlayerItems = (state: GameState) => {
   const myShip = findMyShip(state);
   if (!myShip) return [];
@@ -59,6 +72,8 @@ export const TradeWindow = () => {
       return (
         JSON.stringify(oldIndexes?.myShip?.trading_with) !==
           JSON.stringify(newIndexes?.myShip?.trading_with) ||
+        JSON.stringify(oldIndexes?.myShip?.modules) !==
+          JSON.stringify(newIndexes?.myShip?.modules) ||
         JSON.stringify(oldState.market) !== JSON.stringify(newState.market)
       );
     }
@@ -151,6 +166,12 @@ export const TradeWindow = () => {
 
   if (!planetId) return null;
 
+  const myShip = ns.indexes.myShip;
+  const fittedModules = myShip?.modules || [];
+  const fittableItems = (myShip?.inventory || []).filter((it) =>
+    isModuleItem(it.item_type)
+  );
+
   return (
     <Window
       height={WINDOW_HEIGHT}
@@ -193,7 +214,41 @@ export const TradeWindow = () => {
               onMove={onMove}
             />
           </div>
-          <div className="bottom-bar">Shift+click to split stacks</div>
+          <div className="bottom-bar">
+            <div>Shift+click to split stacks</div>
+            <div className="fitting">
+              {fittedModules.map((module, i) => (
+                <span
+                  key={`${module}-${i}`}
+                  className="fitting-link"
+                  title="Click to unfit"
+                  onClick={() =>
+                    ns.sendFittingAction(
+                      FittingActionBuilder.FittingActionUnfit({ module })
+                    )
+                  }
+                >
+                  -{getDisplayName(MODULE_ITEM_TYPES[module])}
+                </span>
+              ))}
+              {fittableItems.map((item) => (
+                <span
+                  key={item.id}
+                  className="fitting-link"
+                  title="Click to fit"
+                  onClick={() =>
+                    ns.sendFittingAction(
+                      FittingActionBuilder.FittingActionFit({
+                        item_id: item.id,
+                      })
+                    )
+                  }
+                >
+                  +{getDisplayName(item.item_type)}
+                </span>
+              ))}
+            </div>
+          </div>
         </div>
       </div>
     </Window>
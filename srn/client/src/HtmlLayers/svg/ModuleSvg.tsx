@@ -0,0 +1,23 @@
+import * as React from 'react';
+
+export const ModuleSvg = ({
+  fill,
+  stroke,
+  ...props
+}: React.SVGProps<SVGSVGElement>) => (
+  <svg
+    xmlns="http://www.w3.org/2000/svg"
+    width={128}
+    height={128}
+    viewBox="0 0 128 128"
+    {...props}
+  >
+    <g fill={fill} stroke={stroke} strokeLinecap="round" strokeLinejoin="round">
+      <path d="M64 14l43.3 25v50L64 114 20.7 89V39z" strokeWidth={2} />
+      <path
+        d="M64 40a24 24 0 100 48 24 24 0 000-48zm0 12a12 12 0 110 24 12 12 0 010-24z"
+        strokeWidth={1.5}
+      />
+    </g>
+  </svg>
+);
@@ -1,6 +1,8 @@
 import {
   AABB,
+  decodeWireFrame,
   DEFAULT_STATE,
+  encodeWireFrame,
   ensureDialogueTableLoaded,
   GameMode,
   GameState,
@@ -15,6 +17,7 @@ import {
   updateWorld,
   updateWorldIncremental,
   waitForWasmLoad,
+  WIRE_BINARY_PROTOCOL,
 } from './world';
 import EventEmitter from 'events';
 import * as uuid from 'uuid';
@@ -27,7 +30,9 @@ import _ from 'lodash';
 import { UnreachableCaseError } from 'ts-essentials';
 import {
   Action,
+  BotDifficulty,
   Breadcrumb,
+  FittingAction,
   InventoryAction,
   LongActionStart,
   NotificationActionR,
@@ -178,6 +183,8 @@ export default class NetState extends EventEmitter {
 
   private mode!: GameMode;
 
+  private botDifficulty?: BotDifficulty;
+
   private switchingRooms = false;
 
   public replay: any;
@@ -295,10 +302,14 @@ export default class NetState extends EventEmitter {
     NetState.instance = undefined;
   };
 
-  init = async (mode: GameMode): Promise<void> => {
+  init = async (
+    mode: GameMode,
+    botDifficulty?: BotDifficulty
+  ): Promise<void> => {
     await waitForWasmLoad();
     await ensureDialogueTableLoaded();
     this.mode = mode;
+    this.botDifficulty = botDifficulty;
     normalLog(`initializing NS ${this.id}`);
     this.connecting = true;
     Perf.start();
@@ -548,7 +559,12 @@ export default class NetState extends EventEmitter {
         return;
       }
       normalLog(`connecting NS ${this.id}`);
-      this.socket = new WebSocket(api.getWebSocketUrl(), 'rust-websocket');
+      // older servers only know the text protocol
+      this.socket = new WebSocket(api.getWebSocketUrl(), [
+        WIRE_BINARY_PROTOCOL,
+        'rust-websocket',
+      ]);
+      this.socket.binaryType = 'arraybuffer';
       this.socket.onmessage = (event) => {
         Perf.markEvent(Measure.SocketFrameEvent);
         Perf.usingMeasure(Measure.SocketFrameTime, () => {
@@ -569,7 +585,10 @@ export default class NetState extends EventEmitter {
         const switchRoomTag = uuid.v4();
         this.switchingRooms = true;
         (async () => {
-          const roomId = await api.getRoomToJoin(this.mode);
+          const roomId = await api.getRoomToJoin(
+            this.mode,
+            this.botDifficulty
+          );
           this.send({
             code: ClientOpCode.SwitchRoom,
             value: { room_id: roomId, client_name: this.playerName },
@@ -672,11 +691,29 @@ export default class NetState extends EventEmitter {
     );
   };
 
-  private handleMessage(rawData: string) {
-    try {
+  private decodeMessage(
+    rawData: string | ArrayBuffer
+  ): { messageCode: number; size: number; parse: () => any } {
+    if (typeof rawData === 'string') {
       const [messageCodeStr, data] = rawData.split('_%_');
+      return {
+        messageCode: Number(messageCodeStr),
+        size: data.length,
+        parse: () => JSON.parse(data),
+      };
+    }
+    const frame = decodeWireFrame(new Uint8Array(rawData));
+    return {
+      messageCode: frame.opcode,
+      size: rawData.byteLength,
+      parse: () =>
+        frame.payload.kind === 'Empty' ? null : frame.payload.value,
+    };
+  }
 
-      const messageCode = Number(messageCodeStr);
+  private handleMessage(rawData: string | ArrayBuffer) {
+    try {
+      const { messageCode, size, parse } = this.decodeMessage(rawData);
 
       if (this.switchingRooms) {
         this.resetState(); // force to have initial state
@@ -687,10 +724,10 @@ export default class NetState extends EventEmitter {
         this.switchingRooms = false;
         this.send({
           code: ClientOpCode.Name,
-          value: JSON.stringify({
+          value: {
             name: this.playerName,
             portrait_name: this.portraitName,
-          }),
+          },
         });
         return;
       }
@@ -701,9 +738,9 @@ export default class NetState extends EventEmitter {
           ServerToClientMessageCode.ObsoleteStateChangeExclusive ||
         messageCode === ServerToClientMessageCode.XCastGameState
       ) {
-        Perf.markEvent(Measure.ServerStateSize, data.length);
-        // console.log(`state size=${(data.length / 1024).toFixed(2)}KiB`);
-        const parsed = JSON.parse(data);
+        Perf.markEvent(Measure.ServerStateSize, size);
+        // console.log(`state size=${(size / 1024).toFixed(2)}KiB`);
+        const parsed = parse();
         this.sync({
           tag: 'server state',
           state: parsed,
@@ -715,7 +752,7 @@ export default class NetState extends EventEmitter {
       ) {
         normalLog('Received obsolete multicast partial ships update message');
       } else if (messageCode === ServerToClientMessageCode.XCastGameEvent) {
-        const event = JSON.parse(data).value;
+        const event = parse().value;
         this.emit('gameEvent', event);
         const chat = ChatState.get();
         if (chat && event.text_representation) {
@@ -731,7 +768,7 @@ export default class NetState extends EventEmitter {
         normalLog('Received disconnect request from server');
         this.disconnectAndDestroy();
       } else if (messageCode === ServerToClientMessageCode.TagConfirm) {
-        this.syncer.handleServerConfirmedPacket(JSON.parse(data).tag);
+        this.syncer.handleServerConfirmedPacket(parse().tag);
       } else if (messageCode === ServerToClientMessageCode.XcastStateDiff) {
         normalWarn('diffs are not supported anymore');
       } else if (messageCode === ServerToClientMessageCode.Pong) {
@@ -767,25 +804,15 @@ export default class NetState extends EventEmitter {
           normalWarn(`Unknown opcode ${cmd.code}`);
           break;
         }
-        case ClientOpCode.Name: {
-          this.socket.send(`${cmd.code}_%_${cmd.value}`);
+        case ClientOpCode.Name:
+        case ClientOpCode.Ping: {
+          this.sendFrame(this.socket, cmd.code, cmd.value);
           break;
         }
         case ClientOpCode.SwitchRoom:
-        case ClientOpCode.SchedulePlayerAction: {
-          this.socket.send(
-            `${cmd.code}_%_${JSON.stringify(cmd.value)}_%_${cmd.tag}`
-          );
-          break;
-        }
+        case ClientOpCode.SchedulePlayerAction:
         case ClientOpCode.SchedulePlayerActionBatch: {
-          this.socket.send(
-            `${cmd.code}_%_${JSON.stringify(cmd.value)}_%_${cmd.tag}`
-          );
-          break;
-        }
-        case ClientOpCode.Ping: {
-          this.socket.send(`${cmd.code}_%_${JSON.stringify(cmd.value)}`);
+          this.sendFrame(this.socket, cmd.code, cmd.value, cmd.tag);
           break;
         }
         default:
@@ -794,6 +821,27 @@ export default class NetState extends EventEmitter {
     }
   }
 
+  private sendFrame(
+    socket: WebSocket,
+    code: ClientOpCode,
+    value: any,
+    tag?: string
+  ) {
+    if (socket.protocol === WIRE_BINARY_PROTOCOL) {
+      socket.send(
+        encodeWireFrame({
+          opcode: code,
+          tag: tag || null,
+          payload: { kind: 'Value', value },
+        })
+      );
+    } else if (tag) {
+      socket.send(`${code}_%_${JSON.stringify(value)}_%_${tag}`);
+    } else {
+      socket.send(`${code}_%_${JSON.stringify(value)}`);
+    }
+  }
+
   public sendDialogueOption(dialogueId: string, optionId: string) {
     this.sendSchedulePlayerAction(
       ActionBuilder.ActionSelectDialogueOption({
@@ -869,6 +917,15 @@ export default class NetState extends EventEmitter {
     );
   }
 
+  public sendFittingAction(fitAct: FittingAction) {
+    this.sendSchedulePlayerAction(
+      ActionBuilder.ActionFitting({
+        player_id: this.state.my_id,
+        action: fitAct,
+      })
+    );
+  }
+
   public sendInventoryAction(invAct: InventoryAction) {
     this.sendSchedulePlayerAction(
       ActionBuilder.ActionInventory({
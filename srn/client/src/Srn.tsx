@@ -36,11 +36,13 @@ import { ChatWindow } from './HtmlLayers/ChatWindow';
 import { InventoryWindow } from './HtmlLayers/InventoryWindow';
 import { DialogueWindow } from './HtmlLayers/DialogueWindow';
 import { ensureDialogueTableLoaded, GameMode } from './world';
+import { BotDifficulty } from '../../world/pkg/world';
 import { SandboxQuickMenu } from './HtmlLayers/SandboxQuickMenu';
 import { TradeWindow } from './HtmlLayers/TradeWindow';
 import { PromptWindow } from './HtmlLayers/PromptWindow';
 import { StarMapWindow } from './HtmlLayers/StarMapWindow';
 import { LongActionsDisplay } from './HtmlLayers/LongActionsDisplay';
+import { ShutdownNotice } from './HtmlLayers/ShutdownNotice';
 import { StartMenuBackground } from './StartMenuBackground';
 import { NetStateToStorePusher } from './NetStateToStorePusher';
 import {
@@ -88,6 +90,7 @@ const renderPlayingElements = (mode: GameMode) => (
       <SandboxQuickMenu />
       <CameraCoordinatesBox />
       <StatsWindow />
+      <ShutdownNotice />
     </>
   </>
 );
@@ -176,7 +179,7 @@ const Srn = () => {
 
   const [mode, setMode] = useState(GameMode.CargoRush);
 
-  const start = async (mode: GameMode) => {
+  const start = async (mode: GameMode, botDifficulty?: BotDifficulty) => {
     if (!NetState.get()) {
       NetState.make();
     }
@@ -193,7 +196,7 @@ const Srn = () => {
     ns.portraitName = portrait; // portrait files are 1-based
     ns.disconnecting = false;
     setMode(mode);
-    await ns.init(mode);
+    await ns.init(mode, botDifficulty);
     ns.on('disconnect', () => {
       setMainUiState(MainUiState.Idle);
       setMenu(true);
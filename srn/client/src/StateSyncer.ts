@@ -905,6 +905,7 @@ export class StateSyncer extends EventEmitter {
       'market',
       'interval_data',
       'game_over',
+      'pirate_waves',
       'events',
       'processed_events',
       'player_actions',
@@ -7,6 +7,7 @@ import { ObjectPropertyKey } from '../../../world/pkg/world.extra';
 import { ObjectPropertyDecays } from '../../../world/pkg/world';
 import { ThreeExplosionNodeV2 } from './blocks/ThreeExplosionNodeV2';
 import { ThreeRocket } from './ThreeRocket';
+import { babyBlue, crimson } from '../utils/palette';
 
 export type ThreeProjectilesLayerParams = {
   visMap: Record<string, boolean>;
@@ -53,23 +54,48 @@ export const ThreeProjectilesLayer: React.FC<ThreeProjectilesLayerParams> = ({
             projectile.fields.properties,
             ObjectPropertyKey.Decays
           );
-          return (
-            <ThreeRocket
-              position={projectile.fields.spatial.position}
-              rotation={projectile.fields.spatial.rotation_rad}
-              velocity={projectile.fields.spatial.velocity}
-              fadeOver={decayProp ? decayProp.fields.max_ticks : undefined}
-              radius={projectile.fields.spatial.radius}
-              markers={projectile.fields.markers}
-              key={projectile.fields.id}
-              brake={
-                projectile.fields.brake > 0.0 ||
-                projectile.fields.gas < 0.0 ||
-                Math.abs(projectile.fields.turn) > 1e-3
-              }
-              gas={projectile.fields.gas > 0.0}
-            />
-          );
+          switch (projectile.tag) {
+            case 'Rocket':
+            case 'Mine':
+              return (
+                <ThreeRocket
+                  position={projectile.fields.spatial.position}
+                  rotation={projectile.fields.spatial.rotation_rad}
+                  velocity={projectile.fields.spatial.velocity}
+                  fadeOver={decayProp ? decayProp.fields.max_ticks : undefined}
+                  radius={projectile.fields.spatial.radius}
+                  markers={projectile.fields.markers}
+                  key={projectile.fields.id}
+                  brake={
+                    projectile.fields.brake > 0.0 ||
+                    projectile.fields.gas < 0.0 ||
+                    Math.abs(projectile.fields.turn) > 1e-3
+                  }
+                  gas={projectile.fields.gas > 0.0}
+                />
+              );
+            case 'Slug':
+            case 'Emp':
+              return (
+                <mesh
+                  key={projectile.fields.id}
+                  position={posToThreePos(
+                    projectile.fields.spatial.position.x,
+                    projectile.fields.spatial.position.y,
+                    1
+                  )}
+                >
+                  <circleBufferGeometry
+                    args={[projectile.fields.spatial.radius, 16]}
+                  />
+                  <meshBasicMaterial
+                    color={projectile.tag === 'Emp' ? babyBlue : crimson}
+                  />
+                </mesh>
+              );
+            default:
+              return null;
+          }
         })}
       </group>
     </group>
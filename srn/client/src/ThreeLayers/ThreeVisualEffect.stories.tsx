@@ -2,7 +2,10 @@ import React, { useEffect, useState } from 'react';
 import { Story, Meta } from '@storybook/react';
 import { ThreeVisualEffect, ThreeVisualEffectProps } from './ThreeVisualEffect';
 import { StoryCanvas } from '../TestUI/StoryCanvas';
-import { LocalEffectDmgDone } from '../../../world/pkg/world';
+import {
+  LocalEffectDmgDone,
+  LocalEffectShieldHit,
+} from '../../../world/pkg/world';
 import { VectorFzero } from '../utils/Vector';
 
 const effects: Record<string, any> = {
@@ -13,6 +16,13 @@ const effects: Record<string, any> = {
     last_tick: 0,
     position: VectorFzero,
   } as LocalEffectDmgDone,
+  ShieldHit: {
+    tag: 'ShieldHit',
+    hp: 100,
+    key: '2',
+    last_tick: 0,
+    position: VectorFzero,
+  } as LocalEffectShieldHit,
 };
 export default {
   title: 'Three/ThreeVisualEffect',
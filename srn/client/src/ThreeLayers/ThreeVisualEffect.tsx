@@ -1,6 +1,6 @@
 import React, { useRef } from 'react';
 import { Text } from '@react-three/drei';
-import { crimson, darkGreen, teal } from '../utils/palette';
+import { babyBlue, crimson, darkGreen, teal } from '../utils/palette';
 import { LocalEffect } from '../../../world/pkg/world';
 import { useFrame } from '@react-three/fiber';
 import { Group } from 'three';
@@ -55,6 +55,7 @@ const effectColor: Record<LocalEffect['tag'], string> = {
   DmgDone: crimson,
   Heal: darkGreen,
   PickUp: teal,
+  ShieldHit: babyBlue,
 };
 
 export const ThreeVisualEffect: React.FC<ThreeVisualEffectProps> = ({
@@ -1,7 +1,13 @@
 import { GameMode } from '../../../world/pkg/world.extra';
 import useSWR, { mutate } from 'swr';
 // eslint-disable-next-line import/named
-import { PerfStats, Room, RoomIdResponse } from '../../../world/pkg/world';
+import {
+  BotDifficulty,
+  PerfStats,
+  Room,
+  RoomIdResponse,
+  ShipClass,
+} from '../../../world/pkg/world';
 import pWaitFor from 'p-wait-for';
 
 const patchParams = (url: string, params: Record<string, string>) => {
@@ -29,6 +35,8 @@ export const api = {
     const resp = await fetch(`${apiUrl}/resources/dialogue_scripts`);
     return await resp.json();
   },
+  useShipClasses: (): ShipClass[] =>
+    useSWR(`${apiUrl}/resources/ship_classes`).data || [],
   useSavedStates: () =>
     useSWR(`${api.getSandboxApiUrl()}/saved_states`).data || [],
   useSavedReplays: () => useSWR(`${api.getReplaysApiUrl()}`).data || [],
@@ -102,22 +110,33 @@ export const api = {
     return rawResponse as Room[];
   },
 
-  createRoom: async (mode: string): Promise<string> => {
-    const res = await fetch(
-      patchParams(`${api.getRoomsApiUrl()}/create/<mode>`, {
-        mode,
-      }),
-      { method: 'POST' }
-    );
+  createRoom: async (
+    mode: string,
+    botDifficulty?: BotDifficulty
+  ): Promise<string> => {
+    let url = patchParams(`${api.getRoomsApiUrl()}/create/<mode>`, {
+      mode,
+    });
+    if (botDifficulty) {
+      url += `?bot_difficulty=${botDifficulty}`;
+    }
+    const res = await fetch(url, { method: 'POST' });
     const rawResponse = await res.json();
     const { room_id: roomId } = rawResponse as RoomIdResponse;
     await api.waitUntilRoomExists(roomId);
     return roomId;
   },
 
-  getRoomToJoin: async (mode: GameMode): Promise<string> => {
-    if (mode !== GameMode.CargoRush && mode !== GameMode.PirateDefence) {
-      return api.createRoom(mode);
+  getRoomToJoin: async (
+    mode: GameMode,
+    botDifficulty?: BotDifficulty
+  ): Promise<string> => {
+    // the room list does not tell the bots difficulty, so a chosen one always gets a new room
+    if (
+      (mode !== GameMode.CargoRush && mode !== GameMode.PirateDefence) ||
+      botDifficulty
+    ) {
+      return api.createRoom(mode, botDifficulty);
     }
     const rooms = await api.getRoomsList(mode);
     if (rooms.length <= 0) {
@@ -351,6 +351,27 @@ export const loadReplayIntoWasm = (replay: any) => {
   wasmFunctions.load_replay(replay);
 };
 
+export const WIRE_BINARY_PROTOCOL = 'srn-binary-v1';
+
+export type WirePayload =
+  | { kind: 'Empty' }
+  | { kind: 'Value'; value: any }
+  | { kind: 'Diffs'; value: any[] };
+
+export type WireFrame = {
+  opcode: number;
+  tag: string | null;
+  payload: WirePayload;
+};
+
+export const decodeWireFrame = (bytes: Uint8Array): WireFrame => {
+  return wasmFunctions.decode_binary_wire_frame(bytes);
+};
+
+export const encodeWireFrame = (frame: WireFrame): Uint8Array => {
+  return wasmFunctions.encode_binary_wire_frame(frame);
+};
+
 export const buildDialogueFromState = (
   dialogueId: string,
   currentStateId: string,
@@ -447,6 +468,8 @@ export const DEFAULT_STATE = {
     prices: {},
     wares: {},
     time_before_next_shake: 0,
+    time_before_next_economy_update: 0,
+    price_modifiers: {},
   },
   mode: GameMode.Unknown,
   seed: '',
@@ -491,6 +514,8 @@ export const DEFAULT_STATE = {
   dialogue_states: {},
   breadcrumbs: [],
   projectile_templates: [],
+  combat_log: null,
+  pirate_waves: null,
 };
 
 const periodPrimes = [7, 11, 13, 17, 19, 23];
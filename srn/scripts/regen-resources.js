@@ -12,11 +12,16 @@ const fs = require('fs-extra');
     'NextState',
     'PrevState',
   ].join(',');
-  const files = fs
-    .readdirSync('./server/src')
-    .filter((file) => !blacklistFiles.has(file))
-    .map((f) => `../server/src/${f}`)
-    .join(',');
+  // keep in sync with server/src/avro_test.rs
+  const sourceFiles = (dir) =>
+    fs
+      .readdirSync(`./server/${dir}`)
+      .filter((file) => file.endsWith('.rs') && !blacklistFiles.has(file))
+      .map((f) => `../server/${dir}/${f}`);
+  const files = [
+    ...sourceFiles('src'),
+    ...sourceFiles('src/properties'),
+  ].join(',');
 
   await spawnWatched(
     `cargo run generate --from="${files}" --blacklist=${blacklistEntities} --to ../server/resources/avro_schemas`,
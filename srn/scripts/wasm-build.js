@@ -133,7 +133,7 @@ async function buildForWeb({
     const extraExtraImports = `
 
     import { Vec2f64, ObjectSpecifier, ManualMoveUpdate, LongActionStart,
-    InventoryAction, NotificationActionR, SandboxCommand, TradeAction, NotificationText,
+    InventoryAction, NotificationActionR, SandboxCommand, TradeAction, FittingAction, NotificationText,
     SBAddPlanet, SBTeleport, SBSetupState, SBAddAsteroid, Ship, PlanetV2, MoneyOnKillProps, ObjectIndexSpecifier,
     SBSetFofOverrides, ProcessProps, Movement
     } from "./world"
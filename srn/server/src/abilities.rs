@@ -1,3 +1,4 @@
+use crate::combat::is_emp_disabled;
 use crate::spatial_movement::Movement;
 use crate::world::{GameState, Ship, ShipIdx};
 use core::mem;
@@ -12,6 +13,8 @@ use wasm_bindgen::prelude::*;
 pub const SHOOT_COOLDOWN_TICKS: i32 = 500 * 1000;
 pub const SHOOT_ABILITY_DURATION: i32 = 25 * 1000;
 pub const SHOOT_DEFAULT_DISTANCE: f64 = 50.0;
+pub const POINT_DEFENCE_COOLDOWN_TICKS: i32 = 750 * 1000;
+pub const POINT_DEFENCE_DISTANCE: f64 = 30.0;
 
 #[derive(
     Serialize,
@@ -45,6 +48,12 @@ pub enum Ability {
         movements: Vec<Movement>,
         current_idx: usize,
     },
+    // fires automatically at hostile projectiles, see combat::update_point_defence
+    PointDefence {
+        cooldown_ticks_remaining: i32,
+        cooldown_normalized: f64,
+        cooldown_ticks_max: i32,
+    },
 }
 
 impl Ability {
@@ -87,6 +96,7 @@ impl Ability {
             Ability::ShootAll => 0,
             Ability::ToggleMovement { .. } => 0,
             Ability::Launch { .. } => SHOOT_COOLDOWN_TICKS,
+            Ability::PointDefence { .. } => POINT_DEFENCE_COOLDOWN_TICKS,
         }
     }
 
@@ -98,6 +108,7 @@ impl Ability {
             Ability::ShootAll => 0.0,
             Ability::ToggleMovement { .. } => 0.0,
             Ability::Launch { .. } => SHOOT_DEFAULT_DISTANCE,
+            Ability::PointDefence { .. } => POINT_DEFENCE_DISTANCE,
         }
     }
 
@@ -115,6 +126,10 @@ impl Ability {
                 cooldown_ticks_remaining,
                 ..
             } => *cooldown_ticks_remaining,
+            Ability::PointDefence {
+                cooldown_ticks_remaining,
+                ..
+            } => *cooldown_ticks_remaining,
         };
     }
 
@@ -136,6 +151,12 @@ impl Ability {
             } => {
                 *cooldown_ticks_remaining = val;
             }
+            Ability::PointDefence {
+                cooldown_ticks_remaining,
+                ..
+            } => {
+                *cooldown_ticks_remaining = val;
+            }
         };
     }
 
@@ -171,6 +192,18 @@ impl Ability {
                     .max(0.0)
                     .min(1.0);
             }
+            Ability::PointDefence {
+                cooldown_ticks_remaining,
+                cooldown_normalized,
+                cooldown_ticks_max,
+            } => {
+                *cooldown_ticks_remaining =
+                    (*cooldown_ticks_remaining - ticks_elapsed as i32).max(0);
+                *cooldown_normalized = (*cooldown_ticks_remaining as f64
+                    / *cooldown_ticks_max as f64)
+                    .max(0.0)
+                    .min(1.0);
+            }
         };
     }
 }
@@ -213,6 +246,9 @@ pub fn try_invoke(
     {
         return false;
     }
+    if is_emp_disabled(&state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx]) {
+        return false;
+    }
     let ability_read = state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx].abilities
         [ability_idx]
         .clone();
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
+use crate::behaviour_tree::BtMemory;
 use crate::indexing::GameStateCaches;
 use serde_derive::{Deserialize, Serialize};
 use typescript_definitions::{TypeScriptify, TypescriptDefinition};
@@ -17,6 +18,22 @@ pub enum AiTrait {
     ImmediatePlanetLand,
     PirateDefencePlanetDefender,
     CargoRushHauler,
+    // see pirate_defence::pirate_npc_act
+    PirateAttacker,
+    PirateEvasive,
+    PirateEscort,
+    // see economy::npc_trader_act
+    NpcTrader,
+}
+
+// chosen on room creation, see cargo_rush::on_create_room
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TypeScriptify, TypescriptDefinition,
+)]
+pub enum BotDifficulty {
+    Easy,
+    // the bots trade and collect minerals on top of the quests, see behaviour_tree::CARGO_RUSH_TRADER_TREE
+    Hard,
 }
 
 #[skip_serializing_none]
@@ -25,6 +42,10 @@ pub struct Bot {
     pub id: Uuid,
     pub traits: Vec<AiTrait>,
     pub timer: Option<i64>,
+    // id of a behaviour tree from the resources, see behaviour_tree::get_bot_tree for the defaults
+    pub behaviour: Option<String>,
+    #[serde(default)]
+    pub memory: BtMemory,
 }
 
 pub fn new_bot(traits: Option<Vec<AiTrait>>, id: Uuid) -> Bot {
@@ -32,9 +53,17 @@ pub fn new_bot(traits: Option<Vec<AiTrait>>, id: Uuid) -> Bot {
         id,
         traits: traits.unwrap_or(vec![]),
         timer: Some(0),
+        behaviour: None,
+        memory: BtMemory::new(),
     }
 }
 
+pub fn new_tree_bot(behaviour: &str, id: Uuid) -> Bot {
+    let mut bot = new_bot(None, id);
+    bot.behaviour = Some(behaviour.to_string());
+    bot
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, TypescriptDefinition, TypeScriptify)]
 pub struct ClientMarker {
     pub name: String,
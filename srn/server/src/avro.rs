@@ -0,0 +1,1005 @@
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Avro binary encoding of the server types, driven by the schemas that avro-genschema writes into
+// resources/avro_schemas (yarn regen-resources), and which are committed so a generator change shows up in
+// their diff. Generic avro libraries cannot be used here, since the generated
+// schemas reference each other by name across files, and also use type aliases. The schemas follow the serde
+// representation of the types, so the values are mapped from and to their json form, with the fields that have
+// null default being absent from json when they are null, like with skip_serializing_none.
+pub const AVRO_SCHEMAS_DIR: &str = "resources/avro_schemas";
+
+#[derive(Debug, Clone)]
+pub enum AvroError {
+    BadSchema(String),
+    UnknownType(String),
+    Mismatch { path: String, message: String },
+    UnexpectedEnd,
+    Custom(String),
+}
+
+impl AvroError {
+    fn mismatch(message: String) -> Self {
+        AvroError::Mismatch {
+            path: "".to_string(),
+            message,
+        }
+    }
+
+    fn at(self, segment: &str) -> Self {
+        match self {
+            AvroError::Mismatch { path, message } => AvroError::Mismatch {
+                path: if path.is_empty() {
+                    segment.to_string()
+                } else {
+                    format!("{}.{}", segment, path)
+                },
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for AvroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AvroError::BadSchema(msg) => write!(f, "bad schema: {}", msg),
+            AvroError::UnknownType(name) => write!(f, "unknown type {}", name),
+            AvroError::Mismatch { path, message } => write!(f, "at '{}': {}", path, message),
+            AvroError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            AvroError::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AvroError {}
+
+impl de::Error for AvroError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        AvroError::Custom(msg.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AvroType {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record {
+        name: String,
+        fields: Vec<AvroField>,
+    },
+    Enum {
+        name: String,
+        symbols: Vec<String>,
+    },
+    Array(Box<AvroType>),
+    Map(Box<AvroType>),
+    Union(Vec<AvroType>),
+    Fixed {
+        name: String,
+        size: usize,
+    },
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroField {
+    pub name: String,
+    pub ty: AvroType,
+    pub default_null: bool,
+}
+
+impl AvroType {
+    pub fn named(name: &str) -> Self {
+        AvroType::Named(name.to_string())
+    }
+
+    pub fn array(items: AvroType) -> Self {
+        AvroType::Array(Box::new(items))
+    }
+
+    pub fn optional(inner: AvroType) -> Self {
+        AvroType::Union(vec![AvroType::Null, inner])
+    }
+
+    fn primitive(name: &str) -> Option<AvroType> {
+        match name {
+            "null" => Some(AvroType::Null),
+            "boolean" => Some(AvroType::Boolean),
+            "int" => Some(AvroType::Int),
+            "long" => Some(AvroType::Long),
+            "float" => Some(AvroType::Float),
+            "double" => Some(AvroType::Double),
+            "bytes" => Some(AvroType::Bytes),
+            "string" => Some(AvroType::String),
+            _ => None,
+        }
+    }
+}
+
+// tuples and tuple structs get their fields named by position
+fn is_positional_record(fields: &Vec<AvroField>) -> bool {
+    !fields.is_empty()
+        && fields
+            .iter()
+            .all(|f| f.name.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AvroSchemas {
+    named: HashMap<String, AvroType>,
+}
+
+impl AvroSchemas {
+    // the schemas have to go in the dependency order, like the generated files are named
+    pub fn from_schemas(schemas: Vec<Value>) -> Result<AvroSchemas, AvroError> {
+        let mut res = AvroSchemas::default();
+        for schema in schemas.iter() {
+            let obj = schema
+                .as_object()
+                .ok_or(AvroError::BadSchema(format!("not an object: {}", schema)))?;
+            let is_named_type = obj.get("type").map_or(false, |t| {
+                t == "record" || t == "enum" || (t == "fixed" && obj.get("size") != Some(&0.into()))
+            });
+            if is_named_type {
+                res.parse_type(schema)?;
+            } else {
+                // type alias
+                let name = obj
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .ok_or(AvroError::BadSchema(format!("no name: {}", schema)))?;
+                let inner = obj
+                    .get("type")
+                    .ok_or(AvroError::BadSchema(format!("no type: {}", schema)))?;
+                let parsed = res.parse_type(inner)?;
+                res.named.insert(name.to_string(), parsed);
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn load_dir(dir: &Path) -> Result<AvroSchemas, AvroError> {
+        let mut paths = fs::read_dir(dir)
+            .map_err(|e| AvroError::BadSchema(format!("cannot read {:?}: {}", dir, e)))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        let mut schemas = vec![];
+        for path in paths {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| AvroError::BadSchema(format!("cannot read {:?}: {}", path, e)))?;
+            schemas.push(
+                serde_json::from_str(contents.as_str())
+                    .map_err(|e| AvroError::BadSchema(format!("bad json in {:?}: {}", path, e)))?,
+            );
+        }
+        AvroSchemas::from_schemas(schemas)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.named.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    // names that are referenced, but not defined by any schema
+    pub fn unresolved_names(&self) -> Vec<String> {
+        let mut res = vec![];
+        for ty in self.named.values() {
+            self.collect_unresolved(ty, &mut res);
+        }
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    fn collect_unresolved(&self, ty: &AvroType, into: &mut Vec<String>) {
+        match ty {
+            AvroType::Named(name) => {
+                if !self.named.contains_key(name) {
+                    into.push(name.clone());
+                }
+            }
+            AvroType::Record { fields, .. } => {
+                for field in fields.iter() {
+                    self.collect_unresolved(&field.ty, into);
+                }
+            }
+            AvroType::Array(inner) | AvroType::Map(inner) => self.collect_unresolved(inner, into),
+            AvroType::Union(branches) => {
+                for branch in branches.iter() {
+                    self.collect_unresolved(branch, into);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn parse_type(&mut self, schema: &Value) -> Result<AvroType, AvroError> {
+        match schema {
+            // either primitive or a reference to the type defined earlier
+            Value::String(name) => {
+                Ok(AvroType::primitive(name.as_str()).unwrap_or(AvroType::Named(name.clone())))
+            }
+            Value::Array(branches) => Ok(AvroType::Union(
+                branches
+                    .iter()
+                    .map(|b| self.parse_type(b))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            Value::Object(obj) => {
+                let type_name = match obj.get("type") {
+                    Some(Value::String(type_name)) => type_name.as_str(),
+                    Some(other) => return self.parse_type(other),
+                    None => return Err(AvroError::BadSchema(format!("no type: {}", schema))),
+                };
+                let name = obj
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .map(|n| n.to_string());
+                let get_name = || {
+                    name.clone()
+                        .ok_or(AvroError::BadSchema(format!("no name: {}", schema)))
+                };
+                match type_name {
+                    "record" => {
+                        let name = get_name()?;
+                        let mut fields = vec![];
+                        for field in obj
+                            .get("fields")
+                            .and_then(|f| f.as_array())
+                            .ok_or(AvroError::BadSchema(format!("no fields: {}", schema)))?
+                        {
+                            let field_name = field
+                                .get("name")
+                                .and_then(|n| n.as_str())
+                                .ok_or(AvroError::BadSchema(format!("no field name: {}", field)))?;
+                            let field_type = field
+                                .get("type")
+                                .ok_or(AvroError::BadSchema(format!("no field type: {}", field)))?;
+                            fields.push(AvroField {
+                                name: field_name.to_string(),
+                                ty: self.parse_type(field_type)?,
+                                default_null: field.get("default") == Some(&Value::Null),
+                            });
+                        }
+                        let record = AvroType::Record {
+                            name: name.clone(),
+                            fields,
+                        };
+                        self.named.insert(name, record.clone());
+                        Ok(record)
+                    }
+                    "enum" => {
+                        let name = get_name()?;
+                        let symbols = obj
+                            .get("symbols")
+                            .and_then(|s| s.as_array())
+                            .ok_or(AvroError::BadSchema(format!("no symbols: {}", schema)))?
+                            .iter()
+                            .map(|s| s.as_str().unwrap_or_default().to_string())
+                            .collect();
+                        let en = AvroType::Enum {
+                            name: name.clone(),
+                            symbols,
+                        };
+                        self.named.insert(name, en.clone());
+                        Ok(en)
+                    }
+                    "array" => Ok(AvroType::Array(Box::new(
+                        self.parse_type(
+                            obj.get("items")
+                                .ok_or(AvroError::BadSchema(format!("no items: {}", schema)))?,
+                        )?,
+                    ))),
+                    "map" => Ok(AvroType::Map(Box::new(
+                        self.parse_type(
+                            obj.get("values")
+                                .ok_or(AvroError::BadSchema(format!("no values: {}", schema)))?,
+                        )?,
+                    ))),
+                    "fixed" => {
+                        let name = get_name()?;
+                        let size = obj.get("size").and_then(|s| s.as_u64()).unwrap_or(0) as usize;
+                        // avro-genschema uses 0-sized fixed as a reference to another schema
+                        if size == 0 {
+                            return Ok(AvroType::Named(name));
+                        }
+                        let fixed = AvroType::Fixed {
+                            name: name.clone(),
+                            size,
+                        };
+                        self.named.insert(name, fixed.clone());
+                        Ok(fixed)
+                    }
+                    // primitive with a logical type, or a reference
+                    other => Ok(
+                        AvroType::primitive(other).unwrap_or(AvroType::Named(other.to_string()))
+                    ),
+                }
+            }
+            _ => Err(AvroError::BadSchema(format!(
+                "unexpected schema {}",
+                schema
+            ))),
+        }
+    }
+
+    fn resolve<'a>(&'a self, ty: &'a AvroType) -> Result<&'a AvroType, AvroError> {
+        let mut current = ty;
+        while let AvroType::Named(name) = current {
+            current = self
+                .named
+                .get(name)
+                .ok_or(AvroError::UnknownType(name.clone()))?;
+        }
+        Ok(current)
+    }
+
+    pub fn encode(&self, ty: &AvroType, value: &Value) -> Result<Vec<u8>, AvroError> {
+        let mut out = vec![];
+        self.write(ty, value, &mut out, &mut None)?;
+        Ok(out)
+    }
+
+    pub fn encode_typed<T: Serialize>(
+        &self,
+        ty: &AvroType,
+        value: &T,
+    ) -> Result<Vec<u8>, AvroError> {
+        let value = serde_json::to_value(value).map_err(|e| AvroError::Custom(e.to_string()))?;
+        self.encode(ty, &value)
+    }
+
+    // decodes into the canonical json form, where every enum is internally tagged
+    pub fn decode(&self, ty: &AvroType, bytes: &[u8]) -> Result<Value, AvroError> {
+        Ok(self.read_all(ty, bytes)?.into_json())
+    }
+
+    pub fn decode_typed<T: DeserializeOwned>(
+        &self,
+        ty: &AvroType,
+        bytes: &[u8],
+    ) -> Result<T, AvroError> {
+        T::deserialize(self.read_all(ty, bytes)?)
+    }
+
+    // every value of a named type that is met during the encoding, mostly for checking the schemas
+    pub fn named_instances(
+        &self,
+        ty: &AvroType,
+        value: &Value,
+    ) -> Result<HashMap<String, Vec<Value>>, AvroError> {
+        let mut seen = Some(vec![]);
+        self.write(ty, value, &mut vec![], &mut seen)?;
+        let mut res: HashMap<String, Vec<Value>> = HashMap::new();
+        for (name, instance) in seen.unwrap_or_default() {
+            res.entry(name).or_default().push(instance);
+        }
+        Ok(res)
+    }
+
+    fn read_all(&self, ty: &AvroType, bytes: &[u8]) -> Result<AvroValue, AvroError> {
+        let mut reader = AvroReader { bytes, pos: 0 };
+        let value = self.read(ty, &mut reader)?;
+        if reader.pos != bytes.len() {
+            return Err(AvroError::Custom(format!(
+                "{} trailing bytes",
+                bytes.len() - reader.pos
+            )));
+        }
+        Ok(value)
+    }
+
+    fn write(
+        &self,
+        ty: &AvroType,
+        value: &Value,
+        out: &mut Vec<u8>,
+        seen: &mut Option<Vec<(String, Value)>>,
+    ) -> Result<(), AvroError> {
+        let resolved = self.resolve(ty)?;
+        let mismatch = |expected: &str| {
+            AvroError::mismatch(format!("expected {}, got {}", expected, describe(value)))
+        };
+        match resolved {
+            AvroType::Null => {
+                if !value.is_null() {
+                    return Err(mismatch("null"));
+                }
+            }
+            AvroType::Boolean => out.push(value.as_bool().ok_or_else(|| mismatch("bool"))? as u8),
+            AvroType::Int | AvroType::Long => {
+                write_long(value.as_i64().ok_or_else(|| mismatch("integer"))?, out)
+            }
+            AvroType::Float => {
+                out.extend((value.as_f64().ok_or_else(|| mismatch("float"))? as f32).to_le_bytes())
+            }
+            AvroType::Double => out.extend(
+                value
+                    .as_f64()
+                    .ok_or_else(|| mismatch("double"))?
+                    .to_le_bytes(),
+            ),
+            AvroType::Bytes | AvroType::Fixed { .. } => {
+                let bytes = value
+                    .as_array()
+                    .ok_or_else(|| mismatch("bytes"))?
+                    .iter()
+                    .map(|b| b.as_u64().filter(|b| *b <= 255).map(|b| b as u8))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| mismatch("bytes"))?;
+                match resolved {
+                    AvroType::Fixed { size, .. } => {
+                        if bytes.len() != *size {
+                            return Err(mismatch(format!("{} bytes", size).as_str()));
+                        }
+                    }
+                    _ => write_long(bytes.len() as i64, out),
+                }
+                out.extend(bytes);
+            }
+            AvroType::String => write_str(value.as_str().ok_or_else(|| mismatch("string"))?, out),
+            AvroType::Enum { symbols, .. } => {
+                let symbol = value.as_str().ok_or_else(|| mismatch("enum symbol"))?;
+                let idx = symbols
+                    .iter()
+                    .position(|s| s == symbol)
+                    .ok_or_else(|| mismatch("known enum symbol"))?;
+                write_long(idx as i64, out);
+            }
+            AvroType::Array(items) => {
+                let values = value.as_array().ok_or_else(|| mismatch("array"))?;
+                if !values.is_empty() {
+                    write_long(values.len() as i64, out);
+                    for (i, item) in values.iter().enumerate() {
+                        self.write(items, item, out, seen)
+                            .map_err(|e| e.at(i.to_string().as_str()))?;
+                    }
+                }
+                write_long(0, out);
+            }
+            AvroType::Map(values) => {
+                let entries = value.as_object().ok_or_else(|| mismatch("map"))?;
+                if !entries.is_empty() {
+                    write_long(entries.len() as i64, out);
+                    for (key, item) in entries.iter() {
+                        write_str(key, out);
+                        self.write(values, item, out, seen)
+                            .map_err(|e| e.at(key.as_str()))?;
+                    }
+                }
+                write_long(0, out);
+            }
+            AvroType::Union(branches) => {
+                let mut last_err = mismatch("any of the union branches");
+                let mut found = false;
+                for (idx, branch) in branches.iter().enumerate() {
+                    match self.try_write(branch, value, seen) {
+                        Ok(bytes) => {
+                            write_long(idx as i64, out);
+                            out.extend(bytes);
+                            found = true;
+                            break;
+                        }
+                        // null branch errors are the least informative ones
+                        Err(e) => {
+                            if *branch != AvroType::Null {
+                                last_err = e
+                            }
+                        }
+                    }
+                }
+                if !found {
+                    return Err(last_err);
+                }
+            }
+            AvroType::Record { fields, .. } => self.write_record(fields, value, out, seen)?,
+            AvroType::Named(_) => unreachable!("resolved above"),
+        }
+        if let (AvroType::Named(name), Some(seen)) = (ty, seen.as_mut()) {
+            seen.push((name.clone(), value.clone()));
+        }
+        Ok(())
+    }
+
+    fn try_write(
+        &self,
+        ty: &AvroType,
+        value: &Value,
+        seen: &mut Option<Vec<(String, Value)>>,
+    ) -> Result<Vec<u8>, AvroError> {
+        let mut bytes = vec![];
+        let mut branch_seen = seen.as_ref().map(|_| vec![]);
+        self.write(ty, value, &mut bytes, &mut branch_seen)?;
+        if let (Some(seen), Some(branch_seen)) = (seen.as_mut(), branch_seen) {
+            seen.extend(branch_seen);
+        }
+        Ok(bytes)
+    }
+
+    fn write_record(
+        &self,
+        fields: &Vec<AvroField>,
+        value: &Value,
+        out: &mut Vec<u8>,
+        seen: &mut Option<Vec<(String, Value)>>,
+    ) -> Result<(), AvroError> {
+        match value {
+            Value::Object(obj) => {
+                for key in obj.keys() {
+                    if !fields.iter().any(|f| f.name == *key) {
+                        return Err(AvroError::mismatch(format!(
+                            "field {} is not in the schema",
+                            key
+                        )));
+                    }
+                }
+                for field in fields.iter() {
+                    self.write(
+                        &field.ty,
+                        obj.get(&field.name).unwrap_or(&Value::Null),
+                        out,
+                        seen,
+                    )
+                    .map_err(|e| e.at(field.name.as_str()))?;
+                }
+            }
+            // tuples
+            Value::Array(items) if items.len() == fields.len() && is_positional_record(fields) => {
+                for (field, item) in fields.iter().zip(items.iter()) {
+                    self.write(&field.ty, item, out, seen)
+                        .map_err(|e| e.at(field.name.as_str()))?;
+                }
+            }
+            // newtype structs are serialized as their only field
+            _ if fields.len() == 1 && is_positional_record(fields) => {
+                self.write(&fields[0].ty, value, out, seen)
+                    .map_err(|e| e.at(fields[0].name.as_str()))?;
+            }
+            _ => {
+                return Err(AvroError::mismatch(format!(
+                    "expected record, got {}",
+                    describe(value)
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&self, ty: &AvroType, reader: &mut AvroReader) -> Result<AvroValue, AvroError> {
+        Ok(match self.resolve(ty)? {
+            AvroType::Null => AvroValue::Null,
+            AvroType::Boolean => AvroValue::Boolean(reader.byte()? != 0),
+            AvroType::Int | AvroType::Long => AvroValue::Long(reader.long()?),
+            AvroType::Float => {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(reader.take(4)?);
+                AvroValue::Double(f32::from_le_bytes(buf) as f64)
+            }
+            AvroType::Double => {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(reader.take(8)?);
+                AvroValue::Double(f64::from_le_bytes(buf))
+            }
+            AvroType::Bytes => {
+                let len = reader.len()?;
+                AvroValue::Bytes(reader.take(len)?.to_vec())
+            }
+            AvroType::Fixed { size, .. } => AvroValue::Bytes(reader.take(*size)?.to_vec()),
+            AvroType::String => AvroValue::String(reader.string()?),
+            AvroType::Enum { symbols, .. } => {
+                let idx = reader.long()?;
+                AvroValue::String(
+                    symbols
+                        .get(idx as usize)
+                        .ok_or(AvroError::Custom(format!("bad enum index {}", idx)))?
+                        .clone(),
+                )
+            }
+            AvroType::Array(items) => {
+                let mut res = vec![];
+                loop {
+                    let count = reader.block_items()?;
+                    if count == 0 {
+                        break;
+                    }
+                    for _ in 0..count {
+                        res.push(self.read(items, reader)?);
+                    }
+                }
+                AvroValue::Array(res)
+            }
+            AvroType::Map(values) => {
+                let mut res = vec![];
+                loop {
+                    let count = reader.block_items()?;
+                    if count == 0 {
+                        break;
+                    }
+                    for _ in 0..count {
+                        let key = reader.string()?;
+                        res.push((key, self.read(values, reader)?));
+                    }
+                }
+                AvroValue::Map(res)
+            }
+            AvroType::Union(branches) => {
+                let idx = reader.long()?;
+                let branch = branches
+                    .get(idx as usize)
+                    .ok_or(AvroError::Custom(format!("bad union index {}", idx)))?;
+                self.read(branch, reader)?
+            }
+            AvroType::Record { fields, .. } => {
+                let mut values = vec![];
+                for field in fields.iter() {
+                    let value = self.read(&field.ty, reader)?;
+                    if !(field.default_null && value == AvroValue::Null) {
+                        values.push((field.name.clone(), value));
+                    }
+                }
+                if is_positional_record(fields) {
+                    if values.len() == 1 {
+                        values.pop().unwrap().1
+                    } else {
+                        AvroValue::Array(values.into_iter().map(|(_, v)| v).collect())
+                    }
+                } else {
+                    AvroValue::Record(values)
+                }
+            }
+            AvroType::Named(_) => unreachable!("resolved above"),
+        })
+    }
+}
+
+fn write_long(v: i64, out: &mut Vec<u8>) {
+    let mut z = ((v << 1) ^ (v >> 63)) as u64;
+    loop {
+        if z <= 0x7F {
+            out.push(z as u8);
+            break;
+        }
+        out.push((0x80 | (z & 0x7F)) as u8);
+        z >>= 7;
+    }
+}
+
+fn write_str(v: &str, out: &mut Vec<u8>) {
+    write_long(v.len() as i64, out);
+    out.extend(v.as_bytes());
+}
+
+struct AvroReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AvroReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], AvroError> {
+        if self.pos + count > self.bytes.len() {
+            return Err(AvroError::UnexpectedEnd);
+        }
+        let res = &self.bytes[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(res)
+    }
+
+    fn byte(&mut self) -> Result<u8, AvroError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn long(&mut self) -> Result<i64, AvroError> {
+        let mut z: u64 = 0;
+        let mut shift = 0;
+        loop {
+            if shift > 63 {
+                return Err(AvroError::Custom("varint is too long".to_string()));
+            }
+            let byte = self.byte()?;
+            z |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        Ok((z >> 1) as i64 ^ -((z & 1) as i64))
+    }
+
+    fn len(&mut self) -> Result<usize, AvroError> {
+        let len = self.long()?;
+        if len < 0 {
+            return Err(AvroError::Custom(format!("negative length {}", len)));
+        }
+        Ok(len as usize)
+    }
+
+    fn string(&mut self) -> Result<String, AvroError> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| AvroError::Custom(format!("bad string: {}", e)))
+    }
+
+    // arrays and maps are written in blocks, the one with 0 items ends them
+    fn block_items(&mut self) -> Result<usize, AvroError> {
+        let count = self.long()?;
+        if count < 0 {
+            // negative count is followed by the block size in bytes
+            self.long()?;
+        }
+        Ok(count.unsigned_abs() as usize)
+    }
+}
+
+// intermediate form of the decoded data, which is deserialized directly instead of going through json
+#[derive(Debug, Clone, PartialEq)]
+enum AvroValue {
+    Null,
+    Boolean(bool),
+    Long(i64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<AvroValue>),
+    Map(Vec<(String, AvroValue)>),
+    Record(Vec<(String, AvroValue)>),
+}
+
+impl AvroValue {
+    fn into_json(self) -> Value {
+        match self {
+            AvroValue::Null => Value::Null,
+            AvroValue::Boolean(v) => Value::Bool(v),
+            AvroValue::Long(v) => Value::Number(v.into()),
+            AvroValue::Double(v) => Number::from_f64(v).map_or(Value::Null, Value::Number),
+            AvroValue::String(v) => Value::String(v),
+            AvroValue::Bytes(v) => Value::Array(v.into_iter().map(|b| Value::from(b)).collect()),
+            AvroValue::Array(items) => {
+                Value::Array(items.into_iter().map(|i| i.into_json()).collect())
+            }
+            AvroValue::Map(entries) | AvroValue::Record(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, v.into_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn visit_entries<'de, V: Visitor<'de>>(
+    entries: Vec<(String, AvroValue)>,
+    visitor: V,
+) -> Result<V::Value, AvroError> {
+    let mut access =
+        de::value::MapDeserializer::new(entries.into_iter().map(|(k, v)| (MapKey(k), v)));
+    let res = visitor.visit_map(&mut access)?;
+    access.end()?;
+    Ok(res)
+}
+
+fn visit_items<'de, V: Visitor<'de>>(
+    items: Vec<AvroValue>,
+    visitor: V,
+) -> Result<V::Value, AvroError> {
+    let mut access = de::value::SeqDeserializer::new(items.into_iter());
+    let res = visitor.visit_seq(&mut access)?;
+    access.end()?;
+    Ok(res)
+}
+
+impl<'de> IntoDeserializer<'de, AvroError> for AvroValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for AvroValue {
+    type Error = AvroError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            AvroValue::Null => visitor.visit_unit(),
+            AvroValue::Boolean(v) => visitor.visit_bool(v),
+            AvroValue::Long(v) => visitor.visit_i64(v),
+            AvroValue::Double(v) => visitor.visit_f64(v),
+            AvroValue::String(v) => visitor.visit_string(v),
+            AvroValue::Bytes(v) => visitor.visit_byte_buf(v),
+            AvroValue::Array(items) => visit_items(items, visitor),
+            AvroValue::Map(entries) | AvroValue::Record(entries) => visit_entries(entries, visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            AvroValue::Null => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            // unit variant
+            AvroValue::String(tag) => visitor.visit_enum(VariantAccessor { tag, content: None }),
+            // externally tagged variant with data is a record with the only non-null field
+            AvroValue::Record(mut entries) | AvroValue::Map(mut entries) if entries.len() == 1 => {
+                let (tag, content) = entries.pop().unwrap();
+                visitor.visit_enum(VariantAccessor {
+                    tag,
+                    content: Some(content),
+                })
+            }
+            other => Err(AvroError::Custom(format!("expected enum, got {:?}", other))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct VariantAccessor {
+    tag: String,
+    content: Option<AvroValue>,
+}
+
+impl<'de> EnumAccess<'de> for VariantAccessor {
+    type Error = AvroError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let tag: de::value::StringDeserializer<AvroError> = self.tag.clone().into_deserializer();
+        Ok((seed.deserialize(tag)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantAccessor {
+    type Error = AvroError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.content.unwrap_or(AvroValue::Null))
+    }
+
+    // content is an array for tuple variants and a record for struct ones
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_any(self.content.unwrap_or(AvroValue::Null), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_any(self.content.unwrap_or(AvroValue::Null), visitor)
+    }
+}
+
+// map keys are always strings in avro, same as in json
+struct MapKey(String);
+
+impl<'de> IntoDeserializer<'de, AvroError> for MapKey {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed_key {
+    ($method:ident, $visit:ident) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.parse() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => visitor.visit_string(self.0),
+            }
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey {
+    type Error = AvroError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_parsed_key!(deserialize_bool, visit_bool);
+    deserialize_parsed_key!(deserialize_i8, visit_i8);
+    deserialize_parsed_key!(deserialize_i16, visit_i16);
+    deserialize_parsed_key!(deserialize_i32, visit_i32);
+    deserialize_parsed_key!(deserialize_i64, visit_i64);
+    deserialize_parsed_key!(deserialize_u8, visit_u8);
+    deserialize_parsed_key!(deserialize_u16, visit_u16);
+    deserialize_parsed_key!(deserialize_u32, visit_u32);
+    deserialize_parsed_key!(deserialize_u64, visit_u64);
+    deserialize_parsed_key!(deserialize_f32, visit_f32);
+    deserialize_parsed_key!(deserialize_f64, visit_f64);
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(VariantAccessor {
+            tag: self.0,
+            content: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,261 @@
+#[cfg(all(test, feature = "avro"))]
+mod avro_test {
+    use crate::avro::{AvroSchemas, AvroType, AVRO_SCHEMAS_DIR};
+    use crate::indexing::GameStateCaches;
+    use crate::market::Price;
+    use crate::net::{ServerOpCode, ServerToClientMessage, TagConfirm};
+    use crate::new_id;
+    use crate::system_gen::seed_state;
+    use crate::vec2::Vec2f64;
+    use crate::world::{GameMode, GameState};
+    use crate::world_actions::Action;
+    use crate::xcast::XCast;
+    use avro_genschema::{generate_schemas, GeneratedSchema};
+    use itertools::Itertools;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // the same set of files and entities as in scripts/regen-resources.js
+    const SKIPPED_FILES: [&str; 3] = ["main.rs", "main_ws_server.rs", "chat.rs"];
+    const SKIPPED_ENTITIES: [&str; 8] = [
+        "ClientErr",
+        "Room",
+        "ClientMarker",
+        "RoomIdResponse",
+        "RoomsState",
+        "CurrState",
+        "NextState",
+        "PrevState",
+    ];
+    const MODES: [GameMode; 4] = [
+        GameMode::CargoRush,
+        GameMode::Tutorial,
+        GameMode::Sandbox,
+        GameMode::PirateDefence,
+    ];
+
+    fn source_files(dir: PathBuf) -> Vec<PathBuf> {
+        let mut res = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| {
+                let name = p.file_name().unwrap().to_str().unwrap();
+                name.ends_with(".rs") && !SKIPPED_FILES.contains(&name)
+            })
+            .collect::<Vec<_>>();
+        res.sort();
+        res
+    }
+
+    fn generated() -> Vec<GeneratedSchema> {
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = source_files(src.clone());
+        files.extend(source_files(src.join("properties")));
+        generate_schemas(
+            &files,
+            None,
+            Some(SKIPPED_ENTITIES.iter().map(|e| e.to_string()).collect()),
+        )
+        .unwrap_or_else(|errors| {
+            panic!(
+                "could not generate schemas: {}",
+                errors.iter().map(|e| e.to_string()).join(", ")
+            )
+        })
+    }
+
+    fn schemas() -> AvroSchemas {
+        AvroSchemas::from_schemas(generated().into_iter().map(|g| g.schema).collect()).unwrap()
+    }
+
+    fn committed_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(AVRO_SCHEMAS_DIR)
+    }
+
+    fn seeded(mode: &GameMode) -> GameState {
+        seed_state(mode, "avro".to_string(), None, &mut GameStateCaches::new())
+    }
+
+    #[test]
+    pub fn can_parse_generated_schemas() {
+        let schemas = schemas();
+        for name in ["GameState", "Action", "Ship", "Dialogue", "GameEvent"] {
+            assert!(
+                schemas.names().contains(&name.to_string()),
+                "no schema for {}",
+                name
+            );
+        }
+    }
+
+    // the committed schemas are the ones the server loads, so a generator change has to show up in their diff
+    #[test]
+    pub fn committed_schemas_are_up_to_date() {
+        let generated = generated()
+            .into_iter()
+            .map(|g| (g.file_name, g.schema))
+            .collect::<BTreeMap<_, _>>();
+        let committed = fs::read_dir(committed_dir())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .map(|p| {
+                let json = fs::read_to_string(&p).unwrap();
+                (
+                    p.file_name().unwrap().to_string_lossy().to_string(),
+                    serde_json::from_str::<Value>(json.as_str()).unwrap(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            committed.keys().collect::<Vec<_>>(),
+            generated.keys().collect::<Vec<_>>(),
+            "schema files differ, run yarn regen-resources"
+        );
+        for (file_name, schema) in generated.iter() {
+            assert_eq!(
+                &committed[file_name], schema,
+                "{} differs, run yarn regen-resources",
+                file_name
+            );
+        }
+    }
+
+    // the bytes are written by hand following the avro specification, so the codec is not checked against itself
+    #[test]
+    pub fn encodes_as_the_avro_specification() {
+        let schemas = AvroSchemas::load_dir(&committed_dir()).unwrap();
+        // doubles are 8 bytes little-endian
+        let point = Vec2f64 { x: 1.5, y: -20.0 };
+        let point_bytes = vec![
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x3F, //
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0xC0,
+        ];
+        // ints are zig-zag varints, 110 -> 220 and 90 -> 180
+        let price = Price { sell: 110, buy: 90 };
+        let price_bytes = vec![0xDC, 0x01, 0xB4, 0x01];
+        // strings are the zig-zag length, then the utf-8 bytes
+        let tag = "some-tag".to_string();
+        let mut tag_bytes = vec![0x10];
+        tag_bytes.extend(tag.as_bytes());
+
+        let ty = AvroType::named("Vec2f64");
+        assert_eq!(schemas.encode_typed(&ty, &point).unwrap(), point_bytes);
+        let back: Vec2f64 = schemas.decode_typed(&ty, &point_bytes).unwrap();
+        assert_eq!(back, point);
+        let ty = AvroType::named("Price");
+        assert_eq!(schemas.encode_typed(&ty, &price).unwrap(), price_bytes);
+        let back: Price = schemas.decode_typed(&ty, &price_bytes).unwrap();
+        assert_eq!((back.sell, back.buy), (110, 90));
+        assert_eq!(
+            schemas.encode_typed(&AvroType::String, &tag).unwrap(),
+            tag_bytes
+        );
+    }
+
+    #[test]
+    pub fn can_round_trip_game_state() {
+        let schemas = schemas();
+        let ty = AvroType::named("GameState");
+        for mode in MODES.iter() {
+            let state = seeded(mode);
+            let bytes = schemas.encode_typed(&ty, &state).unwrap();
+            let back: GameState = schemas.decode_typed(&ty, &bytes).unwrap();
+            assert_eq!(
+                serde_json::to_value(&back).unwrap(),
+                serde_json::to_value(&state).unwrap(),
+                "state of {} differs after the round trip",
+                mode
+            );
+        }
+    }
+
+    // schemas follow the serde representation, so decoding gives back exactly the same json
+    #[test]
+    pub fn every_schema_of_seeded_state_is_stable() {
+        let schemas = schemas();
+        for mode in MODES.iter() {
+            let state = serde_json::to_value(seeded(mode)).unwrap();
+            let instances = schemas
+                .named_instances(&AvroType::named("GameState"), &state)
+                .unwrap();
+            for (name, values) in instances.iter() {
+                let ty = AvroType::named(name);
+                for value in values.iter() {
+                    let bytes = schemas.encode(&ty, value).unwrap();
+                    let decoded = schemas.decode(&ty, &bytes).unwrap();
+                    assert_eq!(
+                        &decoded, value,
+                        "{} of {} differs after decoding",
+                        name, mode
+                    );
+                    let reencoded = schemas
+                        .encode(&ty, &decoded)
+                        .unwrap_or_else(|e| panic!("cannot re-encode {} of {}: {}", name, mode, e));
+                    assert_eq!(bytes, reencoded, "{} of {} is not stable", name, mode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn can_round_trip_actions() {
+        let schemas = schemas();
+        let ty = AvroType::named("Action");
+        let ship_id = new_id();
+        let actions = vec![
+            Action::Unknown,
+            Action::Gas { ship_id },
+            Action::MoveAxis {
+                brake: true,
+                ship_id,
+            },
+            Action::Navigate {
+                ship_id,
+                target: Vec2f64 { x: 1.5, y: -20.0 },
+            },
+            Action::DockNavigate {
+                ship_id,
+                target: new_id(),
+            },
+        ];
+        for action in actions.iter() {
+            let bytes = schemas.encode_typed(&ty, action).unwrap();
+            let back: Action = schemas.decode_typed(&ty, &bytes).unwrap();
+            assert_eq!(
+                serde_json::to_value(&back).unwrap(),
+                serde_json::to_value(action).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    pub fn can_decode_serialized_messages() {
+        let schemas = schemas();
+        let state = seeded(&GameMode::CargoRush);
+        let bytes =
+            ServerToClientMessage::XCastStateChange(state.clone(), XCast::Broadcast(state.id))
+                .serialize_avro(&schemas)
+                .unwrap();
+        assert_eq!(bytes[0], ServerOpCode::XCastStateChange as u8);
+        let back: GameState = schemas
+            .decode_typed(&AvroType::named("GameState"), &bytes[1..])
+            .unwrap();
+        assert_eq!(back.id, state.id);
+
+        let bytes = ServerToClientMessage::TagConfirm(
+            TagConfirm {
+                tag: "some-tag".to_string(),
+            },
+            new_id(),
+        )
+        .serialize_avro(&schemas)
+        .unwrap();
+        assert_eq!(bytes[0], ServerOpCode::TagConfirm as u8);
+        let tag: String = schemas
+            .decode_typed(&AvroType::String, &bytes[1..])
+            .unwrap();
+        assert_eq!(tag, "some-tag");
+    }
+}
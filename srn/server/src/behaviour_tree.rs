@@ -0,0 +1,966 @@
+use std::collections::{HashMap, HashSet};
+
+use include_dir::{include_dir, Dir};
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use typescript_definitions::{TypeScriptify, TypescriptDefinition};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+use crate::abilities::{Ability, SHOOT_DEFAULT_DISTANCE};
+use crate::api_struct::{AiTrait, Bot};
+use crate::bots::{make_dialogue_act, travel_to_planet, BotAct};
+use crate::cargo_rush::CargoDeliveryQuestState;
+use crate::dialogue::{check_trigger_conditions, DialogueTable, TriggerCondition};
+use crate::fof;
+use crate::indexing::{find_my_player, find_planet, ObjectSpecifier};
+use crate::inventory::{count_items_of_types, InventoryItemType, MINERAL_TYPES};
+use crate::long_actions::LongActionStart;
+use crate::market::{Market, TradeAction};
+use crate::tractoring::get_tractor_range;
+use crate::vec2::Vec2f64;
+use crate::world::{GameState, PlanetV2, Player, Ship, ShipIdx, SpatialIndexes};
+use crate::world_actions::Action;
+
+// every json file there is a behaviour tree with the id equal to the file name, same as ship_classes
+static BEHAVIOUR_TREES_DIR: Dir<'_> =
+    include_dir!("$CARGO_MANIFEST_DIR/../server/resources/behaviour_trees");
+
+pub const CARGO_RUSH_HAULER_TREE: &str = "cargo_rush_hauler";
+pub const PLANET_DEFENDER_TREE: &str = "planet_defender";
+// the hard CargoRush bots, see cargo_rush::on_create_room
+pub const CARGO_RUSH_TRADER_TREE: &str = "cargo_rush_trader";
+const REQUIRED_TREES: [&str; 3] = [
+    CARGO_RUSH_HAULER_TREE,
+    PLANET_DEFENDER_TREE,
+    CARGO_RUSH_TRADER_TREE,
+];
+
+// closer than that to the navigation target means arrived
+pub const BT_ARRIVAL_DISTANCE: f64 = 3.0;
+const BT_TALK_JITTER_MS: i64 = 500;
+// patrolling ships return when they are farther than that many target radiuses
+const BT_PATROL_DISTANCE_COEFF: f64 = 1.5;
+// there is no cargo limit, so a rich bot would otherwise buy out the whole planet
+pub const BT_TRADE_MAX_QUANTITY: i32 = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BtStatus {
+    Success,
+    Failure,
+    Running,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BtTarget {
+    QuestPickup,
+    QuestDropoff,
+    // the first planet of the first location, like the defended one in PirateDefence
+    HomePlanet,
+    ClosestPlanet,
+    // within SHOOT_DEFAULT_DISTANCE * 2, see fof::find_closest_foe
+    ClosestFoe,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "tag")]
+pub enum BtCondition {
+    InDialogue,
+    Trigger { condition: TriggerCondition },
+    QuestState { state: CargoDeliveryQuestState },
+    Docked,
+    // share of the max hull
+    HealthBelow { share: f64 },
+    FoeInRange { radius: f64 },
+    MoneyAbove { amount: i32 },
+    // a trade is already in progress, or the best one pays more than the current quest
+    TradeBeatsQuest { min_profit: i32 },
+}
+
+// The tree is re-evaluated from the root on every bot tick, so nodes that start something
+// (like Navigate) check what the ship is already doing instead of remembering it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "tag")]
+pub enum BtNode {
+    // ticks the children in order while they succeed
+    Sequence {
+        children: Vec<BtNode>,
+    },
+    // ticks the children in order until one of them does not fail
+    Selector {
+        children: Vec<BtNode>,
+    },
+    // ticks all the children, runs while any of them runs and fails if any of them fails
+    Parallel {
+        children: Vec<BtNode>,
+    },
+    Not {
+        child: Box<BtNode>,
+    },
+    // never fails, for the optional side jobs in a Parallel
+    Succeed {
+        child: Box<BtNode>,
+    },
+    Check {
+        condition: BtCondition,
+    },
+    Wait {
+        ms: i64,
+    },
+    Navigate {
+        target: BtTarget,
+    },
+    // may auto-jump to another star system first
+    Dock {
+        target: BtTarget,
+    },
+    // keeps the ship around the target, moving to random points near it
+    Patrol {
+        target: BtTarget,
+    },
+    // answers all the bot's dialogues via DialogueScript.bot_path, fails when there are none
+    Talk {
+        delay_ms: i64,
+    },
+    // all the items of these types, to the planet the ship is docked at
+    Sell {
+        items: Vec<InventoryItemType>,
+    },
+    // until the ship has that quantity
+    Buy {
+        item: InventoryItemType,
+        quantity: i32,
+    },
+    // shoots with every ready weapon, fails when there is nobody to shoot at
+    Attack {
+        radius: f64,
+        chase: bool,
+    },
+    // succeeds when there are no foes within the radius
+    Flee {
+        radius: f64,
+    },
+    // buys low at one planet of the ship's location and sells high at another, see BtTradePlan
+    Trade {
+        min_profit: i32,
+    },
+    // picks up the closest mineral within the radius, fails when there is none
+    Tractor {
+        radius: f64,
+        chase: bool,
+    },
+}
+
+impl BtNode {
+    pub fn get_children(&self) -> Vec<&BtNode> {
+        match self {
+            BtNode::Sequence { children }
+            | BtNode::Selector { children }
+            | BtNode::Parallel { children } => children.iter().collect(),
+            BtNode::Not { child } | BtNode::Succeed { child } => vec![child.as_ref()],
+            _ => vec![],
+        }
+    }
+
+    // nodes are identified by their pre-order index, so every node in the tree has its own memory
+    pub fn size(&self) -> usize {
+        1 + self.get_children().iter().map(|c| c.size()).sum::<usize>()
+    }
+
+    pub fn validate(&self, id: &str) -> Vec<String> {
+        let mut errors = vec![];
+        match self {
+            BtNode::Sequence { children }
+            | BtNode::Selector { children }
+            | BtNode::Parallel { children } => {
+                if children.len() == 0 {
+                    errors.push(format!("{}: composite nodes must have children", id));
+                }
+            }
+            BtNode::Wait { ms } | BtNode::Talk { delay_ms: ms } => {
+                if *ms < 0 {
+                    errors.push(format!("{}: delays cannot be negative", id));
+                }
+            }
+            BtNode::Check {
+                condition:
+                    BtCondition::HealthBelow { share: value }
+                    | BtCondition::FoeInRange { radius: value },
+            }
+            | BtNode::Attack { radius: value, .. }
+            | BtNode::Flee { radius: value }
+            | BtNode::Tractor { radius: value, .. } => {
+                if !(*value > 0.0) {
+                    errors.push(format!("{}: radiuses and shares must be positive", id));
+                }
+            }
+            BtNode::Buy { quantity, .. } => {
+                if *quantity <= 0 {
+                    errors.push(format!("{}: buy quantity must be positive", id));
+                }
+            }
+            _ => {}
+        }
+        for child in self.get_children() {
+            errors.append(&mut child.validate(id));
+        }
+        errors
+    }
+}
+
+// per-bot state of the tree, lives in the Bot so it is deterministic and survives snapshots
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TypescriptDefinition, TypeScriptify)]
+pub struct BtMemory {
+    // by node index, only kept for the nodes that were ticked the last time
+    pub timers: HashMap<usize, i64>,
+    // unlike the timers, kept until the Trade node finishes it, so the bought goods get sold
+    pub trade: Option<BtTradePlan>,
+}
+
+impl BtMemory {
+    pub fn new() -> Self {
+        Self {
+            timers: HashMap::new(),
+            trade: None,
+        }
+    }
+}
+
+// only between the planets of one location, so the bot never has to jump
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct BtTradePlan {
+    pub from_id: Uuid,
+    pub to_id: Uuid,
+    pub item: InventoryItemType,
+    pub quantity: i32,
+    pub profit: i32,
+    pub bought: bool,
+}
+
+pub fn parse_behaviour_trees(
+    files: Vec<(String, &str)>,
+) -> Result<HashMap<String, BtNode>, Vec<String>> {
+    let mut res = HashMap::new();
+    let mut errors = vec![];
+    for (id, json) in files {
+        match serde_json::from_str::<BtNode>(json) {
+            Ok(tree) => {
+                errors.append(&mut tree.validate(id.as_str()));
+                res.insert(id, tree);
+            }
+            Err(e) => errors.push(format!("{}: {}", id, e)),
+        }
+    }
+    for id in REQUIRED_TREES.iter() {
+        if !res.contains_key(*id) {
+            errors.push(format!("{}: tree is required but not defined", id));
+        }
+    }
+    if errors.len() > 0 {
+        return Err(errors);
+    }
+    Ok(res)
+}
+
+fn read_embedded_behaviour_trees() -> Vec<(String, &'static str)> {
+    BEHAVIOUR_TREES_DIR
+        .files()
+        .filter(|f| f.path().extension().map_or(false, |ext| ext == "json"))
+        .filter_map(|f| {
+            let id = f.path().file_stem()?.to_string_lossy().to_string();
+            f.contents_utf8().map(|json| (id, json))
+        })
+        .collect()
+}
+
+lazy_static! {
+    pub static ref BEHAVIOUR_TREES: HashMap<String, BtNode> =
+        parse_behaviour_trees(read_embedded_behaviour_trees())
+            .unwrap_or_else(|errors| panic!("Invalid behaviour trees: {}", errors.join(", ")));
+}
+
+pub fn validate_behaviour_trees() {
+    lazy_static::initialize(&BEHAVIOUR_TREES);
+    log!(format!("Loaded {} behaviour trees", BEHAVIOUR_TREES.len()));
+}
+
+pub fn get_behaviour_tree(id: &str) -> Option<&'static BtNode> {
+    BEHAVIOUR_TREES.get(id)
+}
+
+// the traits of the older bots are mapped to the trees that replaced them
+pub fn get_bot_tree(bot: &Bot) -> Option<&'static BtNode> {
+    if let Some(behaviour) = &bot.behaviour {
+        let tree = get_behaviour_tree(behaviour.as_str());
+        if tree.is_none() {
+            warn!(format!(
+                "bot {} has unknown behaviour {}",
+                bot.id, behaviour
+            ));
+        }
+        return tree;
+    }
+    bot.traits.iter().find_map(|t| match t {
+        AiTrait::CargoRushHauler => get_behaviour_tree(CARGO_RUSH_HAULER_TREE),
+        AiTrait::PirateDefencePlanetDefender => get_behaviour_tree(PLANET_DEFENDER_TREE),
+        _ => None,
+    })
+}
+
+pub struct BtContext<'a> {
+    pub state: &'a GameState,
+    pub d_table: &'a DialogueTable,
+    pub spatial_indexes: &'a SpatialIndexes,
+    pub elapsed_micro: i64,
+    // only for the player bots, the npc ships don't have it
+    pub player_id: Option<Uuid>,
+    pub ship_idx: Option<ShipIdx>,
+}
+
+impl<'a> BtContext<'a> {
+    fn ship(&self) -> Option<&'a Ship> {
+        let idx = self.ship_idx.as_ref()?;
+        self.state
+            .locations
+            .get(idx.location_idx)?
+            .ships
+            .get(idx.ship_idx)
+    }
+
+    fn player(&self) -> Option<&'a Player> {
+        self.player_id.and_then(|id| find_my_player(self.state, id))
+    }
+}
+
+struct BtRun<'b> {
+    memory: &'b mut BtMemory,
+    visited: HashSet<usize>,
+    acts: Vec<BotAct>,
+    prng: &'b mut Pcg64Mcg,
+}
+
+pub fn bot_tree_act(
+    mut bot: Bot,
+    tree: &BtNode,
+    ctx: &BtContext,
+    prng: &mut Pcg64Mcg,
+) -> (Bot, Vec<BotAct>) {
+    let mut run = BtRun {
+        memory: &mut bot.memory,
+        visited: HashSet::new(),
+        acts: vec![],
+        prng,
+    };
+    tick_node(tree, 0, ctx, &mut run);
+    let BtRun { visited, acts, .. } = run;
+    bot.memory.timers.retain(|idx, _| visited.contains(idx));
+    (bot, acts)
+}
+
+fn tick_node(node: &BtNode, idx: usize, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    run.visited.insert(idx);
+    match node {
+        BtNode::Sequence { children } => {
+            let mut child_idx = idx + 1;
+            for child in children.iter() {
+                let status = tick_node(child, child_idx, ctx, run);
+                if status != BtStatus::Success {
+                    return status;
+                }
+                child_idx += child.size();
+            }
+            BtStatus::Success
+        }
+        BtNode::Selector { children } => {
+            let mut child_idx = idx + 1;
+            for child in children.iter() {
+                let status = tick_node(child, child_idx, ctx, run);
+                if status != BtStatus::Failure {
+                    return status;
+                }
+                child_idx += child.size();
+            }
+            BtStatus::Failure
+        }
+        BtNode::Parallel { children } => {
+            let mut child_idx = idx + 1;
+            let mut statuses = vec![];
+            for child in children.iter() {
+                statuses.push(tick_node(child, child_idx, ctx, run));
+                child_idx += child.size();
+            }
+            if statuses.contains(&BtStatus::Failure) {
+                BtStatus::Failure
+            } else if statuses.contains(&BtStatus::Running) {
+                BtStatus::Running
+            } else {
+                BtStatus::Success
+            }
+        }
+        BtNode::Not { child } => match tick_node(child, idx + 1, ctx, run) {
+            BtStatus::Success => BtStatus::Failure,
+            BtStatus::Failure => BtStatus::Success,
+            BtStatus::Running => BtStatus::Running,
+        },
+        BtNode::Succeed { child } => match tick_node(child, idx + 1, ctx, run) {
+            BtStatus::Running => BtStatus::Running,
+            _ => BtStatus::Success,
+        },
+        BtNode::Check { condition } => {
+            if check_condition(condition, ctx, run.memory) {
+                BtStatus::Success
+            } else {
+                BtStatus::Failure
+            }
+        }
+        BtNode::Wait { ms } => {
+            if tick_timer(idx, *ms * 1000, ctx, run) {
+                BtStatus::Success
+            } else {
+                BtStatus::Running
+            }
+        }
+        BtNode::Navigate { target } => tick_navigate(target, ctx, run),
+        BtNode::Dock { target } => tick_dock(target, ctx, run),
+        BtNode::Patrol { target } => tick_patrol(target, ctx, run),
+        BtNode::Talk { delay_ms } => tick_talk(idx, *delay_ms, ctx, run),
+        BtNode::Sell { items } => tick_sell(items, ctx, run),
+        BtNode::Buy { item, quantity } => tick_buy(item, *quantity, ctx, run),
+        BtNode::Attack { radius, chase } => tick_attack(*radius, *chase, ctx, run),
+        BtNode::Flee { radius } => tick_flee(*radius, ctx, run),
+        BtNode::Trade { min_profit } => tick_trade(*min_profit, ctx, run),
+        BtNode::Tractor { radius, chase } => tick_tractor(*radius, *chase, ctx, run),
+    }
+}
+
+// true when the timer has fired, then it starts from the beginning on the next tick
+fn tick_timer(idx: usize, duration_micro: i64, ctx: &BtContext, run: &mut BtRun) -> bool {
+    let left = run.memory.timers.get(&idx).map_or(duration_micro, |t| *t) - ctx.elapsed_micro;
+    if left <= 0 {
+        run.memory.timers.remove(&idx);
+        return true;
+    }
+    run.memory.timers.insert(idx, left);
+    false
+}
+
+fn check_condition(condition: &BtCondition, ctx: &BtContext, memory: &BtMemory) -> bool {
+    let ship = ctx.ship();
+    let player = ctx.player();
+    match condition {
+        BtCondition::InDialogue => has_open_dialogues(ctx),
+        BtCondition::Trigger { condition } => ctx.player_id.map_or(false, |id| {
+            check_trigger_conditions(ctx.state, id).contains(condition)
+        }),
+        BtCondition::QuestState { state } => player
+            .and_then(|p| p.quest.as_ref())
+            .map_or(false, |q| q.state == *state),
+        BtCondition::Docked => ship.map_or(false, |s| s.docked_at.is_some()),
+        BtCondition::HealthBelow { share } => {
+            ship.map_or(false, |s| s.health.current < s.health.max * share)
+        }
+        BtCondition::FoeInRange { radius } => find_foe(ctx, *radius).is_some(),
+        BtCondition::MoneyAbove { amount } => player.map_or(false, |p| p.money > *amount),
+        BtCondition::TradeBeatsQuest { min_profit } => {
+            memory.trade.is_some()
+                || find_trade_plan(ctx, *min_profit).map_or(false, |plan| {
+                    player
+                        .and_then(|p| p.quest.as_ref())
+                        .map_or(true, |q| plan.profit > q.reward)
+                })
+        }
+    }
+}
+
+fn has_open_dialogues(ctx: &BtContext) -> bool {
+    match (ctx.player_id, ctx.state.dialogue_states.as_ref()) {
+        (Some(player_id), Some(d_states)) => {
+            DialogueTable::get_player_d_states_read(d_states, player_id)
+                .map_or(false, |states| states.values().any(|v| v.is_some()))
+        }
+        _ => false,
+    }
+}
+
+fn find_foe<'a>(ctx: &BtContext<'a>, radius: f64) -> Option<&'a Ship> {
+    ctx.ship_idx
+        .as_ref()
+        .and_then(|idx| fof::find_closest_foe(ctx.state, idx, ctx.spatial_indexes, radius))
+}
+
+fn target_planet_id(target: &BtTarget, ctx: &BtContext) -> Option<Uuid> {
+    match target {
+        BtTarget::QuestPickup => ctx
+            .player()
+            .and_then(|p| p.quest.as_ref())
+            .map(|q| q.from_id),
+        BtTarget::QuestDropoff => ctx.player().and_then(|p| p.quest.as_ref()).map(|q| q.to_id),
+        BtTarget::HomePlanet => ctx
+            .state
+            .locations
+            .get(0)
+            .and_then(|l| l.planets.get(0))
+            .map(|p| p.id),
+        BtTarget::ClosestPlanet => {
+            let (ship, ship_idx) = (ctx.ship()?, ctx.ship_idx.as_ref()?);
+            ctx.state.locations[ship_idx.location_idx]
+                .planets
+                .iter()
+                .min_by(|a, b| {
+                    let dist_a = a.spatial.position.euclidean_distance(&ship.as_vec());
+                    let dist_b = b.spatial.position.euclidean_distance(&ship.as_vec());
+                    dist_a
+                        .partial_cmp(&dist_b)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|p| p.id)
+        }
+        BtTarget::ClosestFoe => None,
+    }
+}
+
+// position and radius, only in the ship's location
+fn target_position(target: &BtTarget, ctx: &BtContext) -> Option<(Vec2f64, f64)> {
+    let ship_idx = ctx.ship_idx.as_ref()?;
+    let loc = &ctx.state.locations[ship_idx.location_idx];
+    if *target == BtTarget::ClosestFoe {
+        return find_foe(ctx, SHOOT_DEFAULT_DISTANCE * 2.0)
+            .map(|foe| (foe.as_vec(), foe.spatial.radius));
+    }
+    let planet_id = target_planet_id(target, ctx)?;
+    loc.planets
+        .iter()
+        .find(|p| p.id == planet_id)
+        .map(|p| (p.spatial.position.clone(), p.spatial.radius))
+}
+
+// None when the ship is already there or flies there, as navigation resets the trajectory.
+// shared with the hand-written npc logic, e.g. pirate_defence::pirate_npc_act
+pub fn navigate_act(ship: &Ship, target: Vec2f64, tolerance: f64) -> Option<Action> {
+    let close_enough = |pos: &Vec2f64| pos.euclidean_distance(&target) < tolerance;
+    if close_enough(&ship.as_vec()) || ship.navigate_target.as_ref().map_or(false, close_enough) {
+        return None;
+    }
+    Some(Action::Navigate {
+        ship_id: ship.id,
+        target,
+    })
+}
+
+fn navigate(ship: &Ship, target: Vec2f64, run: &mut BtRun) {
+    if let Some(act) = navigate_act(ship, target, BT_ARRIVAL_DISTANCE) {
+        run.acts.push(BotAct::Act(act));
+    }
+}
+
+fn tick_navigate(target: &BtTarget, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (ship, (pos, _radius)) = match (ctx.ship(), target_position(target, ctx)) {
+        (Some(ship), Some(target)) => (ship, target),
+        _ => return BtStatus::Failure,
+    };
+    if ship.as_vec().euclidean_distance(&pos) < BT_ARRIVAL_DISTANCE {
+        return BtStatus::Success;
+    }
+    navigate(ship, pos, run);
+    BtStatus::Running
+}
+
+fn tick_dock(target: &BtTarget, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (ship, planet_id) = match (ctx.ship(), target_planet_id(target, ctx)) {
+        (Some(ship), Some(planet_id)) => (ship, planet_id),
+        _ => return BtStatus::Failure,
+    };
+    if ship.docked_at == Some(planet_id) {
+        return BtStatus::Success;
+    }
+    if find_planet(ctx.state, &planet_id).is_none() {
+        return BtStatus::Failure;
+    }
+    run.acts.append(&mut travel_to_planet(
+        ctx.state,
+        ship,
+        ctx.player_id,
+        planet_id,
+    ));
+    BtStatus::Running
+}
+
+fn tick_patrol(target: &BtTarget, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (ship, (pos, radius)) = match (ctx.ship(), target_position(target, ctx)) {
+        (Some(ship), Some(target)) => (ship, target),
+        _ => return BtStatus::Failure,
+    };
+    if ship.as_vec().euclidean_distance(&pos) <= radius * BT_PATROL_DISTANCE_COEFF {
+        return BtStatus::Success;
+    }
+    if ship.trajectory.len() == 0 {
+        let shift = Vec2f64 {
+            x: run.prng.gen_range(-radius, radius),
+            y: run.prng.gen_range(-radius, radius),
+        };
+        run.acts.push(BotAct::Act(Action::Navigate {
+            ship_id: ship.id,
+            target: pos.add(&shift),
+        }));
+    }
+    BtStatus::Running
+}
+
+fn tick_talk(idx: usize, delay_ms: i64, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    if !has_open_dialogues(ctx) {
+        return BtStatus::Failure;
+    }
+    let player_id = ctx.player_id.unwrap();
+    // nobody answers instantly, and the bots should not answer all at once either
+    let delay = if run.memory.timers.contains_key(&idx) {
+        0
+    } else {
+        (delay_ms + run.prng.gen_range(-BT_TALK_JITTER_MS, BT_TALK_JITTER_MS)).max(0) * 1000
+    };
+    if !tick_timer(idx, delay, ctx, run) {
+        return BtStatus::Running;
+    }
+    let d_states = DialogueTable::get_player_d_states_read(
+        ctx.state.dialogue_states.as_ref().unwrap(),
+        player_id,
+    )
+    .unwrap();
+    for (dialogue_id, d_state) in d_states.iter() {
+        if d_state.is_none() {
+            continue;
+        }
+        if let Some(act) =
+            make_dialogue_act(player_id, ctx.d_table, d_states, *dialogue_id, ctx.state)
+        {
+            run.acts.push(act);
+        }
+    }
+    BtStatus::Running
+}
+
+// the planet the player ship is docked at
+fn trade_context<'a>(ctx: &BtContext<'a>) -> Option<(Uuid, &'a Ship, Uuid)> {
+    let player_id = ctx.player_id?;
+    let ship = ctx.ship()?;
+    ship.docked_at.map(|planet_id| (player_id, ship, planet_id))
+}
+
+// everything the ship has of these types, None when there is nothing to sell
+fn make_sell_act(
+    player_id: Uuid,
+    ship: &Ship,
+    planet_id: Uuid,
+    items: &Vec<InventoryItemType>,
+) -> Option<BotAct> {
+    let sells_to_planet = items
+        .iter()
+        .map(|it| {
+            (
+                it.clone(),
+                count_items_of_types(&ship.inventory, &vec![it.clone()]),
+            )
+        })
+        .filter(|(_, quantity)| *quantity > 0)
+        .collect::<Vec<_>>();
+    if sells_to_planet.len() == 0 {
+        return None;
+    }
+    Some(BotAct::Act(Action::Trade {
+        player_id,
+        action: TradeAction {
+            planet_id,
+            sells_to_planet,
+            buys_from_planet: vec![],
+        },
+    }))
+}
+
+fn make_buy_act(
+    player_id: Uuid,
+    planet_id: Uuid,
+    item: &InventoryItemType,
+    quantity: i32,
+) -> BotAct {
+    BotAct::Act(Action::Trade {
+        player_id,
+        action: TradeAction {
+            planet_id,
+            sells_to_planet: vec![],
+            buys_from_planet: vec![(item.clone(), quantity)],
+        },
+    })
+}
+
+// limited by both the planet wares and the player money
+fn get_affordable_quantity(ctx: &BtContext, planet_id: Uuid, item: &InventoryItemType) -> i32 {
+    let market = match ctx.state.market.as_ref() {
+        Some(market) => market,
+        None => return 0,
+    };
+    let price = market
+        .prices
+        .get(&planet_id)
+        .and_then(|prices| prices.get(item))
+        .map_or(0, |p| p.sell);
+    let available = market
+        .wares
+        .get(&planet_id)
+        .map_or(0, |wares| count_items_of_types(wares, &vec![item.clone()]));
+    let money = ctx.player().map_or(0, |p| p.money);
+    let affordable = if price > 0 { money / price } else { 0 };
+    available.min(affordable)
+}
+
+fn tick_sell(items: &Vec<InventoryItemType>, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (player_id, ship, planet_id) = match trade_context(ctx) {
+        Some(res) => res,
+        None => return BtStatus::Failure,
+    };
+    match make_sell_act(player_id, ship, planet_id, items) {
+        Some(act) => {
+            run.acts.push(act);
+            BtStatus::Running
+        }
+        None => BtStatus::Success,
+    }
+}
+
+fn tick_buy(item: &InventoryItemType, quantity: i32, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (player_id, ship, planet_id) = match trade_context(ctx) {
+        Some(res) => res,
+        None => return BtStatus::Failure,
+    };
+    let has = count_items_of_types(&ship.inventory, &vec![item.clone()]);
+    if has >= quantity {
+        return BtStatus::Success;
+    }
+    let amount = (quantity - has).min(get_affordable_quantity(ctx, planet_id, item));
+    if amount <= 0 {
+        return BtStatus::Failure;
+    }
+    run.acts
+        .push(make_buy_act(player_id, planet_id, item, amount));
+    BtStatus::Running
+}
+
+fn tick_attack(radius: f64, chase: bool, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (ship, foe) = match (ctx.ship(), find_foe(ctx, radius)) {
+        (Some(ship), Some(foe)) => (ship, foe),
+        _ => return BtStatus::Failure,
+    };
+    run.acts.extend(
+        attack_acts(ship, foe, ctx.player_id)
+            .into_iter()
+            .map(BotAct::Act),
+    );
+    if chase && foe.as_vec().euclidean_distance(&ship.as_vec()) > SHOOT_DEFAULT_DISTANCE {
+        navigate(ship, foe.as_vec(), run);
+    }
+    BtStatus::Running
+}
+
+// every weapon that is ready and reaches the foe, shared with the hand-written npc logic
+pub fn attack_acts(ship: &Ship, foe: &Ship, player_id: Option<Uuid>) -> Vec<Action> {
+    let dist = foe.as_vec().euclidean_distance(&ship.as_vec());
+    let mut acts = vec![];
+    for ability in ship.abilities.iter() {
+        if ability.get_current_cooldown() > 0 || dist > ability.get_distance() {
+            continue;
+        }
+        let long_action_start = match ability {
+            Ability::Shoot { turret_id, .. } => LongActionStart::Shoot {
+                target: ObjectSpecifier::Ship { id: foe.id },
+                turret_id: *turret_id,
+            },
+            // the launched projectiles find the target themselves
+            Ability::Launch { turret_id, .. } => LongActionStart::Launch {
+                turret_id: *turret_id,
+            },
+            _ => continue,
+        };
+        acts.push(Action::LongActionStart {
+            long_action_start,
+            player_id,
+            ship_id: ship.id,
+        });
+    }
+    acts
+}
+
+// the point straight away from the foe, None if they are at the same spot
+pub fn flee_point(ship: &Ship, foe: &Ship, distance: f64) -> Option<Vec2f64> {
+    let away = ship.as_vec().subtract(&foe.as_vec()).normalize()?;
+    Some(ship.as_vec().add(&away.scalar_mul(distance)))
+}
+
+fn tick_flee(radius: f64, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let ship = match ctx.ship() {
+        Some(ship) => ship,
+        None => return BtStatus::Failure,
+    };
+    let foe = match find_foe(ctx, radius) {
+        Some(foe) => foe,
+        None => return BtStatus::Success,
+    };
+    if let Some(target) = flee_point(ship, foe, radius) {
+        navigate(ship, target, run);
+    }
+    BtStatus::Running
+}
+
+// the most profitable one for the bot money, by comparing the planet prices of the market
+pub fn find_trade_plan(ctx: &BtContext, min_profit: i32) -> Option<BtTradePlan> {
+    let ship_idx = ctx.ship_idx.as_ref()?;
+    let market = ctx.state.market.as_ref()?;
+    let money = ctx.player()?.money;
+    let planets = &ctx.state.locations[ship_idx.location_idx].planets;
+    find_best_trade(
+        market,
+        planets,
+        |_item, buy_price, stock| stock.min(money / buy_price).min(BT_TRADE_MAX_QUANTITY),
+        |_margin, profit| profit >= min_profit,
+    )
+}
+
+// the most profitable trade between the planets, shared by the trading bots and the npc freighters.
+// max_quantity gets the item, its buy price and the stock of it, while accept gets the margin per unit
+// and the profit of the whole plan
+pub fn find_best_trade(
+    market: &Market,
+    planets: &[PlanetV2],
+    max_quantity: impl Fn(&InventoryItemType, i32, i32) -> i32,
+    accept: impl Fn(i32, i32) -> bool,
+) -> Option<BtTradePlan> {
+    let mut best: Option<BtTradePlan> = None;
+    for from in planets.iter() {
+        let (from_prices, from_wares) =
+            match (market.prices.get(&from.id), market.wares.get(&from.id)) {
+                (Some(prices), Some(wares)) => (prices, wares),
+                _ => continue,
+            };
+        for item in InventoryItemType::iter() {
+            let buy_price = match from_prices.get(&item) {
+                Some(price) if price.sell > 0 => price.sell,
+                _ => continue,
+            };
+            let stock = count_items_of_types(from_wares, &vec![item.clone()]);
+            let quantity = max_quantity(&item, buy_price, stock);
+            if quantity <= 0 {
+                continue;
+            }
+            for to in planets.iter().filter(|p| p.id != from.id) {
+                let sell_price = market
+                    .prices
+                    .get(&to.id)
+                    .and_then(|prices| prices.get(&item))
+                    .map_or(0, |price| price.buy);
+                let margin = sell_price - buy_price;
+                let profit = margin * quantity;
+                if accept(margin, profit) && best.as_ref().map_or(true, |b| profit > b.profit) {
+                    best = Some(BtTradePlan {
+                        from_id: from.id,
+                        to_id: to.id,
+                        item: item.clone(),
+                        quantity,
+                        profit,
+                        bought: false,
+                    });
+                }
+            }
+        }
+    }
+    best
+}
+
+fn tick_trade(min_profit: i32, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (player_id, ship) = match (ctx.player_id, ctx.ship()) {
+        (Some(player_id), Some(ship)) => (player_id, ship),
+        _ => return BtStatus::Failure,
+    };
+    let mut plan = match run
+        .memory
+        .trade
+        .take()
+        .or_else(|| find_trade_plan(ctx, min_profit))
+    {
+        Some(plan) => plan,
+        None => return BtStatus::Failure,
+    };
+    let planet_id = if plan.bought {
+        plan.to_id
+    } else {
+        plan.from_id
+    };
+    if find_planet(ctx.state, &planet_id).is_none() {
+        return BtStatus::Failure;
+    }
+    if ship.docked_at != Some(planet_id) {
+        run.acts.append(&mut travel_to_planet(
+            ctx.state,
+            ship,
+            ctx.player_id,
+            planet_id,
+        ));
+        run.memory.trade = Some(plan);
+        return BtStatus::Running;
+    }
+    if plan.bought {
+        // whatever was tractored on the way goes there too
+        let mut items = vec![plan.item.clone()];
+        items.extend(MINERAL_TYPES.iter().filter(|m| **m != plan.item).cloned());
+        if let Some(act) = make_sell_act(player_id, ship, planet_id, &items) {
+            run.acts.push(act);
+        }
+        return BtStatus::Success;
+    }
+    // the prices or the wares could have changed on the way
+    let amount = plan
+        .quantity
+        .min(get_affordable_quantity(ctx, planet_id, &plan.item));
+    if amount <= 0 {
+        return BtStatus::Failure;
+    }
+    run.acts
+        .push(make_buy_act(player_id, planet_id, &plan.item, amount));
+    plan.bought = true;
+    run.memory.trade = Some(plan);
+    BtStatus::Running
+}
+
+fn tick_tractor(radius: f64, chase: bool, ctx: &BtContext, run: &mut BtRun) -> BtStatus {
+    let (ship, ship_idx) = match (ctx.ship(), ctx.ship_idx.as_ref()) {
+        (Some(ship), Some(ship_idx)) => (ship, ship_idx),
+        _ => return BtStatus::Failure,
+    };
+    if ship.tractor_target.is_some() {
+        return BtStatus::Running;
+    }
+    let closest = ctx.state.locations[ship_idx.location_idx]
+        .minerals
+        .iter()
+        .map(|m| {
+            let pos = Vec2f64 { x: m.x, y: m.y };
+            let dist = pos.euclidean_distance(&ship.as_vec());
+            (m.id, pos, dist)
+        })
+        .filter(|(_, _, dist)| *dist <= radius)
+        .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let (mineral_id, pos, dist) = match closest {
+        Some(closest) => closest,
+        None => return BtStatus::Failure,
+    };
+    if dist <= get_tractor_range(ship) {
+        run.acts.push(BotAct::Act(Action::Tractor {
+            ship_id: ship.id,
+            target: mineral_id,
+        }));
+    } else if chase {
+        navigate(ship, pos, run);
+    } else {
+        return BtStatus::Failure;
+    }
+    BtStatus::Running
+}
@@ -0,0 +1,310 @@
+#[cfg(test)]
+mod behaviour_tree_test {
+    use crate::api_struct::{new_bot, new_tree_bot, AiTrait, Bot, BotDifficulty};
+    use crate::behaviour_tree::{
+        bot_tree_act, get_behaviour_tree, get_bot_tree, parse_behaviour_trees, BtContext, BtNode,
+        BT_TRADE_MAX_QUANTITY, CARGO_RUSH_HAULER_TREE, CARGO_RUSH_TRADER_TREE,
+        PLANET_DEFENDER_TREE,
+    };
+    use crate::bots::{bot_act, BotAct};
+    use crate::cargo_rush::{CargoDeliveryQuestState, Quest, CARGO_RUSH_BOT_COUNT};
+    use crate::dialogue::DialogueTable;
+    use crate::indexing::{
+        build_full_spatial_indexes, find_my_ship_mut, find_player_and_ship_mut,
+        find_player_ship_index, ObjectSpecifier,
+    };
+    use crate::inventory::{add_item, InventoryItem, InventoryItemType};
+    use crate::long_actions::LongActionStart;
+    use crate::market::Price;
+    use crate::test_helpers::{add_player_ship, new_state};
+    use crate::vec2::Vec2f64;
+    use crate::world::{
+        make_room, spawn_mineral, spawn_ship, GameMode, GameState, Rarity, ShipTemplate,
+    };
+    use crate::world_actions::Action;
+    use crate::{new_id, seed_prng};
+    use uuid::Uuid;
+
+    const AT: Vec2f64 = Vec2f64 { x: 321.5, y: 123.5 };
+    const WAIT_THEN_DOCKED: &str = r#"{
+        "tag": "Sequence",
+        "children": [
+            { "tag": "Wait", "ms": 1000 },
+            { "tag": "Check", "condition": { "tag": "Docked" } }
+        ]
+    }"#;
+
+    fn act(state: &GameState, bot: Bot, elapsed_micro: i64) -> (Bot, Vec<Action>) {
+        let mut prng = seed_prng("behaviour tree act".to_string());
+        let (bot, bot_acts) = bot_act(
+            bot,
+            state,
+            elapsed_micro,
+            &DialogueTable::new(),
+            &build_full_spatial_indexes(state),
+            &mut prng,
+        );
+        let acts = bot_acts
+            .into_iter()
+            .filter_map(|a| match a {
+                BotAct::Act(a) => Some(a),
+                BotAct::Speak(_) => None,
+            })
+            .collect();
+        (bot, acts)
+    }
+
+    #[test]
+    pub fn embedded_trees_are_valid_and_broken_ones_are_reported() {
+        assert!(get_behaviour_tree(CARGO_RUSH_HAULER_TREE).is_some());
+        assert!(get_behaviour_tree(PLANET_DEFENDER_TREE).is_some());
+        assert!(get_bot_tree(&new_bot(Some(vec![AiTrait::CargoRushHauler]), new_id())).is_some());
+        assert!(get_bot_tree(&new_tree_bot("no such tree", new_id())).is_none());
+        let (_, room) = make_room(
+            &GameMode::CargoRush,
+            new_id(),
+            &mut seed_prng("behaviour tree room".to_string()),
+            None,
+            Some(BotDifficulty::Hard),
+            None,
+            None,
+        );
+        assert_eq!(room.bots.len(), CARGO_RUSH_BOT_COUNT);
+        assert!(room.bots.iter().all(|b| get_bot_tree(b).is_some()
+            && b.behaviour == Some(CARGO_RUSH_TRADER_TREE.to_string())));
+
+        let errors = parse_behaviour_trees(vec![
+            (
+                "empty".to_string(),
+                r#"{ "tag": "Selector", "children": [] }"#,
+            ),
+            ("broken".to_string(), r#"{ "tag": "Fly" }"#),
+        ])
+        .unwrap_err();
+        assert!(errors.iter().any(|e| e.starts_with("empty:")));
+        assert!(errors.iter().any(|e| e.starts_with("broken:")));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with(&format!("{}:", CARGO_RUSH_HAULER_TREE))));
+    }
+
+    #[test]
+    pub fn node_timers_are_kept_in_the_bot_memory() {
+        let state = new_state(&GameMode::CargoRush, "behaviour tree");
+        let tree: BtNode = serde_json::from_str(WAIT_THEN_DOCKED).unwrap();
+        let mut prng = seed_prng("behaviour tree wait".to_string());
+        let d_table = DialogueTable::new();
+        let spatial_indexes = build_full_spatial_indexes(&state);
+        let ctx = BtContext {
+            state: &state,
+            d_table: &d_table,
+            spatial_indexes: &spatial_indexes,
+            elapsed_micro: 600 * 1000,
+            player_id: None,
+            ship_idx: None,
+        };
+        let bot = new_bot(None, new_id());
+        let (bot, _) = bot_tree_act(bot, &tree, &ctx, &mut prng);
+        // the wait node is the second one in the pre-order
+        assert_eq!(bot.memory.timers.get(&1), Some(&(400 * 1000)));
+        let (bot, _) = bot_tree_act(bot, &tree, &ctx, &mut prng);
+        assert!(bot.memory.timers.is_empty());
+    }
+
+    #[test]
+    pub fn hauler_tree_flies_to_the_quest_pickup() {
+        let mut prng = seed_prng("behaviour tree hauler".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "behaviour tree");
+        let bot_id = add_player_ship(&mut state, true, None, Some(AT), &mut prng).0;
+        let (from_id, to_id) = {
+            let planets = &state.locations[0].planets;
+            (planets[0].id, planets[1].id)
+        };
+        state
+            .players
+            .iter_mut()
+            .find(|p| p.id == bot_id)
+            .unwrap()
+            .quest = Some(Quest {
+            id: new_id(),
+            from_id,
+            to_id,
+            state: CargoDeliveryQuestState::Started,
+            reward: 500,
+        });
+
+        let (_, acts) = act(
+            &state,
+            new_bot(Some(vec![AiTrait::CargoRushHauler]), bot_id),
+            0,
+        );
+        match acts.as_slice() {
+            [Action::DockNavigate { target, .. }] => assert_eq!(*target, from_id),
+            other => panic!("expected to fly to the pickup, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn defender_tree_shoots_pirates() {
+        let mut prng = seed_prng("behaviour tree defender".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "behaviour tree");
+        let bot_id = add_player_ship(&mut state, true, None, Some(AT), &mut prng).0;
+        let pirate_id = spawn_ship(
+            &mut state,
+            None,
+            ShipTemplate::pirate(Some(AT.add(&Vec2f64 { x: 10.0, y: 0.0 }))),
+            &mut prng,
+        )
+        .id;
+
+        let (_, acts) = act(
+            &state,
+            new_bot(Some(vec![AiTrait::PirateDefencePlanetDefender]), bot_id),
+            0,
+        );
+        assert!(acts.iter().any(|a| match a {
+            Action::LongActionStart {
+                long_action_start:
+                    LongActionStart::Shoot {
+                        target: ObjectSpecifier::Ship { id },
+                        ..
+                    },
+                player_id,
+                ..
+            } => *id == pirate_id && *player_id == Some(bot_id),
+            _ => false,
+        }));
+        // far from the home planet, so it also goes back there
+        assert!(acts.iter().any(|a| matches!(a, Action::Navigate { .. })));
+    }
+
+    fn trades(
+        acts: &Vec<Action>,
+    ) -> Vec<(
+        Uuid,
+        Vec<(InventoryItemType, i32)>,
+        Vec<(InventoryItemType, i32)>,
+    )> {
+        acts.iter()
+            .filter_map(|a| match a {
+                Action::Trade { action, .. } => Some((
+                    action.planet_id,
+                    action.sells_to_planet.clone(),
+                    action.buys_from_planet.clone(),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn trader_tree_buys_low_and_sells_high() {
+        let mut prng = seed_prng("behaviour tree trader".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "behaviour tree");
+        let bot_id = add_player_ship(&mut state, true, None, Some(AT), &mut prng).0;
+        let (from_id, to_id) = {
+            let planets = &state.locations[0].planets;
+            (planets[0].id, planets[1].id)
+        };
+        let item = InventoryItemType::CommonMineral;
+        {
+            let market = state.market.as_mut().unwrap();
+            // nothing else is worth trading
+            for prices in market.prices.values_mut() {
+                for price in prices.values_mut() {
+                    *price = Price { sell: 1000, buy: 1 };
+                }
+            }
+            market
+                .prices
+                .get_mut(&from_id)
+                .unwrap()
+                .get_mut(&item)
+                .unwrap()
+                .sell = 10;
+            market
+                .prices
+                .get_mut(&to_id)
+                .unwrap()
+                .get_mut(&item)
+                .unwrap()
+                .buy = 200;
+            market.wares.insert(
+                from_id,
+                vec![InventoryItem::new(item.clone(), 100, new_id())],
+            );
+        }
+        let (player, ship) = find_player_and_ship_mut(&mut state, bot_id);
+        player.unwrap().money = 1000;
+        ship.unwrap().docked_at = Some(from_id);
+
+        let (bot, acts) = act(&state, new_tree_bot(CARGO_RUSH_TRADER_TREE, bot_id), 0);
+        assert_eq!(
+            trades(&acts),
+            vec![(from_id, vec![], vec![(item.clone(), BT_TRADE_MAX_QUANTITY)])]
+        );
+        let plan = bot.memory.trade.clone().unwrap();
+        assert!(plan.bought);
+        assert_eq!(plan.profit, 190 * BT_TRADE_MAX_QUANTITY);
+
+        let ship = find_my_ship_mut(&mut state, bot_id).unwrap();
+        add_item(
+            &mut ship.inventory,
+            InventoryItem::new(item.clone(), BT_TRADE_MAX_QUANTITY, new_id()),
+        );
+        ship.docked_at = None;
+        let (bot, acts) = act(&state, bot, 0);
+        assert!(acts
+            .iter()
+            .any(|a| matches!(a, Action::DockNavigate { target, .. } if *target == to_id)));
+
+        find_my_ship_mut(&mut state, bot_id).unwrap().docked_at = Some(to_id);
+        let (bot, acts) = act(&state, bot, 0);
+        assert_eq!(
+            trades(&acts),
+            vec![(to_id, vec![(item, BT_TRADE_MAX_QUANTITY)], vec![])]
+        );
+        assert!(bot.memory.trade.is_none());
+    }
+
+    #[test]
+    pub fn tractor_node_picks_up_close_minerals_only() {
+        let mut prng = seed_prng("behaviour tree tractor".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "behaviour tree");
+        let bot_id = add_player_ship(&mut state, true, None, Some(AT), &mut prng).0;
+        state.locations[0].minerals.clear();
+        spawn_mineral(
+            &mut state.locations[0],
+            Rarity::Common,
+            AT.add(&Vec2f64 { x: 20.0, y: 0.0 }),
+            &mut prng,
+        );
+        let mineral_id = state.locations[0].minerals[0].id;
+        let tree: BtNode =
+            serde_json::from_str(r#"{ "tag": "Tractor", "radius": 100.0, "chase": false }"#)
+                .unwrap();
+        let d_table = DialogueTable::new();
+        let spatial_indexes = build_full_spatial_indexes(&state);
+        let tick = |state: &GameState| {
+            let ctx = BtContext {
+                state,
+                d_table: &d_table,
+                spatial_indexes: &spatial_indexes,
+                elapsed_micro: 0,
+                player_id: Some(bot_id),
+                ship_idx: find_player_ship_index(state, bot_id),
+            };
+            let mut prng = seed_prng("behaviour tree tractor tick".to_string());
+            bot_tree_act(new_bot(None, bot_id), &tree, &ctx, &mut prng).1
+        };
+        match tick(&state).as_slice() {
+            [BotAct::Act(Action::Tractor { target, .. })] => assert_eq!(*target, mineral_id),
+            [_] => panic!("expected to tractor the mineral"),
+            _ => panic!("expected a single action"),
+        }
+
+        // out of the tractor range, and the node does not chase
+        state.locations[0].minerals[0].x = AT.x + 60.0;
+        assert!(tick(&state).is_empty());
+    }
+}
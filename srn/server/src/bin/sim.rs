@@ -0,0 +1,469 @@
+#![feature(exclusive_range_pattern)]
+#![feature(path_file_prefix)]
+#[macro_use]
+extern crate serde_derive;
+
+// headless simulation of the world, without rocket, websockets and the rest of the server infrastructure.
+// only the modules that the world wasm crate shares with the server are included here, so everything
+// that is simulated is exactly what both the server and the client run
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::Timelike;
+use clap::command;
+use lazy_static::lazy_static;
+use rand::{RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use regex::Regex;
+use strum::IntoEnumIterator;
+use uuid::*;
+
+use crate::api_struct::Room;
+use crate::determinism::{
+    check_determinism, collect_action_log, continuous_state_prng, extract_replay_frames,
+    ResimulationOptions,
+};
+use crate::dialogue::DialogueTable;
+use crate::indexing::GameStateCaches;
+use crate::perf::{Sampler, SamplerMarks};
+use crate::replay::ReplayDiffed;
+use crate::replay_binary::{write_replay_binary, CompactReplay, REPLAY_BINARY_EXTENSION};
+use crate::system_gen::{seed_state, GenStateOpts};
+use crate::vec2::Vec2f64;
+use crate::world::{GameMode, GameState, ShipTemplate, UpdateOptions, AABB};
+use crate::world_actions::Action;
+
+#[macro_use]
+#[path = "../log_macros.rs"]
+mod log_macros;
+
+#[macro_use]
+#[path = "../macros.rs"]
+mod macros;
+
+// the server uses the parts of the world that the simulation does not
+#[allow(dead_code, unused_imports)]
+#[path = "../world_modules.rs"]
+mod world_modules;
+
+#[path = "../sim_test.rs"]
+mod sim_test;
+
+use world_modules::*;
+
+pub const DEFAULT_STEP_MS: u64 = 15;
+pub const DEFAULT_REPLAY_INTERVAL_MS: u64 = 250;
+// same as clap uses for the arguments it rejects itself
+const BAD_ARGS_EXIT_CODE: i32 = 2;
+
+pub const DEBUG_PHYSICS: bool = false;
+
+lazy_static! {
+    pub static ref ENABLE_PERF: bool = false;
+}
+
+lazy_static! {
+    pub static ref SUB_RE: Regex = Regex::new(r"s_\w+").unwrap();
+}
+
+lazy_static! {
+    // every id and every 'random' prng is drawn from here, so the same seed always produces the same ids
+    static ref ID_PRNG: Mutex<Pcg64Mcg> = Mutex::new(Pcg64Mcg::seed_from_u64(0));
+}
+
+pub fn get_now_nano() -> u64 {
+    let now = chrono::Local::now();
+    now.second() as u64 * 1_000_000_000 + now.nanosecond() as u64
+}
+
+pub fn get_prng() -> Pcg64Mcg {
+    let mut rng = ID_PRNG.lock().unwrap();
+    Pcg64Mcg::seed_from_u64(rng.next_u64())
+}
+
+pub fn seed_prng(seed: String) -> Pcg64Mcg {
+    return Pcg64Mcg::seed_from_u64(system_gen::str_to_hash(seed));
+}
+
+pub fn prng_id(rng: &mut Pcg64Mcg) -> Uuid {
+    let mut bytes = [0u8; 16];
+    rng.fill_bytes(&mut bytes);
+
+    crate::Builder::from_bytes(bytes)
+        .set_variant(Variant::RFC4122)
+        .set_version(Version::Random)
+        .build()
+}
+
+pub fn new_id() -> Uuid {
+    prng_id(&mut ID_PRNG.lock().unwrap())
+}
+
+// the simulation can be repeated from any recorded state, so the ids have to be derived from it too
+fn reset_id_prng(state: &GameState) {
+    *ID_PRNG.lock().unwrap() = seed_prng(format!("{}-{}", state.seed, state.ticks));
+}
+
+pub fn fire_event(_ev: world_events::GameEvent) {
+    // server-only events are about notifying clients, the world ones go through state.events anyway
+}
+
+pub struct SimulationSetup {
+    pub mode: GameMode,
+    pub seed: String,
+    pub gen_state_opts: Option<GenStateOpts>,
+    pub players: Vec<Uuid>,
+}
+
+pub struct SimulationOptions {
+    pub duration_ticks: u64,
+    pub step_ticks: u64,
+    // (state ticks at which the action happens, action), sorted by ticks
+    pub actions: Vec<(u64, Action)>,
+    pub replay_interval_ticks: Option<u64>,
+}
+
+pub struct SimulationResult {
+    pub state: GameState,
+    pub replay: Option<ReplayDiffed>,
+}
+
+fn make_sampler() -> Sampler {
+    let mut marks_holder = vec![];
+    for mark in SamplerMarks::iter() {
+        marks_holder.push(mark.to_string());
+    }
+    Sampler::new(marks_holder)
+}
+
+pub fn make_d_table() -> DialogueTable {
+    let mut d_table = DialogueTable::new();
+    for script in dialogue::gen_scripts() {
+        d_table.scripts.insert(script.id, script);
+    }
+    d_table
+}
+
+pub fn setup_state(setup: &SimulationSetup, caches: &mut GameStateCaches) -> GameState {
+    *ID_PRNG.lock().unwrap() = seed_prng(setup.seed.clone());
+    let mut state = seed_state(
+        &setup.mode,
+        setup.seed.clone(),
+        setup.gen_state_opts.clone(),
+        caches,
+    );
+    let mut prng = seed_prng(setup.seed.clone());
+    for player_id in setup.players.iter() {
+        world::add_player(&mut state, *player_id, false, None, &mut prng);
+        world::spawn_ship(
+            &mut state,
+            Some(*player_id),
+            ShipTemplate::player(None),
+            &mut prng,
+        );
+    }
+    state
+}
+
+pub fn simulate(
+    mut state: GameState,
+    opts: &SimulationOptions,
+    d_table: &DialogueTable,
+    caches: &mut GameStateCaches,
+) -> SimulationResult {
+    let mut sampler = make_sampler();
+    let mut replay = opts.replay_interval_ticks.map(|_| {
+        let mut replay = ReplayDiffed::new(new_id());
+        replay.name = format!("simulation {:?} {}", state.mode, state.seed);
+        replay
+    });
+    reset_id_prng(&state);
+    let mut next_action_idx = 0;
+    let mut last_recorded_ticks: Option<u64> = None;
+    let end_ticks = state.ticks + opts.duration_ticks;
+    loop {
+        if let (Some(replay), Some(interval)) = (replay.as_mut(), opts.replay_interval_ticks) {
+            if last_recorded_ticks.map_or(true, |last| state.ticks - last >= interval) {
+                let mut frame = state.clone();
+                // server-only, same as in the recorded replays
+                frame.combat_log = None;
+                if let Err(e) = replay.add(frame) {
+                    warn!(format!("failed to record replay frame, err {:?}", e));
+                }
+                last_recorded_ticks = Some(state.ticks);
+            }
+        }
+        if state.ticks >= end_ticks {
+            break;
+        }
+        while next_action_idx < opts.actions.len() && opts.actions[next_action_idx].0 <= state.ticks
+        {
+            let (ticks, action) = opts.actions[next_action_idx].clone();
+            state.player_actions.push_back((action, None, Some(ticks)));
+            next_action_idx += 1;
+        }
+        let mut prng = continuous_state_prng(&mut state);
+        let (new_state, new_sampler, _indexes) = world::update_world(
+            state,
+            opts.step_ticks as i64,
+            false,
+            sampler,
+            UpdateOptions {
+                limit_area: AABB::maxed(),
+                force_non_determinism: None,
+            },
+            &mut prng,
+            d_table,
+            caches,
+        );
+        state = new_state;
+        sampler = new_sampler;
+    }
+    SimulationResult { state, replay }
+}
+
+// the errors are for the user of the tool, so they are plain messages
+fn parse_mode(value: &String) -> Result<GameMode, String> {
+    serde_json::from_str::<GameMode>(format!("\"{}\"", value).as_str())
+        .map_err(|_| format!("unknown game mode {}", value))
+}
+
+fn parse_gen_state_opts(value: &String) -> Result<GenStateOpts, String> {
+    // either an inline json, or a path to the json file
+    let json = if value.trim_start().starts_with("{") {
+        value.clone()
+    } else {
+        fs::read_to_string(value)
+            .map_err(|e| format!("could not read gen opts file {}, err {}", value, e))?
+    };
+    serde_json::from_str::<GenStateOpts>(json.as_str())
+        .map_err(|e| format!("invalid gen opts, err {}", e))
+}
+
+fn parse_players(value: &String) -> Result<Vec<Uuid>, String> {
+    value
+        .split(",")
+        .map(|id| Uuid::parse_str(id).map_err(|_| format!("bad player id {}", id)))
+        .collect()
+}
+
+fn read_actions(path: &PathBuf) -> Result<Vec<(u64, Action)>, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("could not read actions file {:?}, err {}", path, e))?;
+    let mut actions = serde_json::from_str::<Vec<(u64, Action)>>(json.as_str())
+        .map_err(|e| format!("invalid actions file {:?}, err {}", path, e))?;
+    actions.sort_by_key(|(ticks, _)| *ticks);
+    Ok(actions)
+}
+
+fn read_replay(path: &PathBuf) -> Result<ReplayDiffed, String> {
+    let bytes =
+        fs::read(path).map_err(|e| format!("could not read replay file {:?}, err {}", path, e))?;
+    let is_binary = path
+        .extension()
+        .map_or(false, |ext| ext == REPLAY_BINARY_EXTENSION);
+    if is_binary {
+        CompactReplay::parse(&bytes)
+            .and_then(|r| r.to_replay_diffed())
+            .map_err(|e| format!("invalid replay file {:?}, err {:?}", path, e))
+    } else {
+        serde_json::from_slice::<ReplayDiffed>(&bytes)
+            .map_err(|e| format!("invalid replay file {:?}, err {}", path, e))
+    }
+}
+
+fn write_state(state: &GameState, path: &PathBuf) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("could not serialize state, err {}", e))?;
+    fs::write(path, json).map_err(|e| format!("could not write state to {:?}, err {}", path, e))
+}
+
+fn write_replay(replay: &ReplayDiffed, path: &PathBuf) -> Result<(), String> {
+    let is_binary = path
+        .extension()
+        .map_or(false, |ext| ext == REPLAY_BINARY_EXTENSION);
+    let bytes = if is_binary {
+        write_replay_binary(replay)
+            .map_err(|e| format!("could not serialize replay, err {:?}", e))?
+    } else {
+        serde_json::to_vec(replay).map_err(|e| format!("could not serialize replay, err {}", e))?
+    };
+    fs::write(path, bytes).map_err(|e| format!("could not write replay to {:?}, err {}", path, e))
+}
+
+fn main() {
+    let cmd = clap::Command::new("srn-sim")
+        .bin_name("srn-sim")
+        .subcommand_required(true)
+        .subcommand(
+            command!("simulate")
+                .arg(clap::arg!(--"mode" <MODE>).required(true))
+                .arg(clap::arg!(--"seed" <SEED>).required(true))
+                .arg(clap::arg!(--"gen-opts" <JSON_OR_PATH>))
+                .arg(
+                    clap::arg!(--"duration-ms" <MS>)
+                        .required(true)
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(clap::arg!(--"step-ms" <MS>).value_parser(clap::value_parser!(u64)))
+                .arg(clap::arg!(--"players" <UUIDS>))
+                .arg(
+                    clap::arg!(--"actions" <PATH>)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    clap::arg!(--"out-state" <PATH>)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    clap::arg!(--"out-replay" <PATH>)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    clap::arg!(--"replay-interval-ms" <MS>).value_parser(clap::value_parser!(u64)),
+                ),
+        )
+        .subcommand(
+            command!("check-determinism")
+                .arg(
+                    clap::arg!(--"replay" <PATH>)
+                        .required(true)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(clap::arg!(--"step-ms" <MS>).value_parser(clap::value_parser!(u64)))
+                .arg(clap::arg!(--"client").action(clap::ArgAction::SetTrue))
+                .arg(
+                    clap::arg!(--"out-report" <PATH>)
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                ),
+        );
+
+    let matches = cmd.get_matches();
+    let res = match matches.subcommand() {
+        Some(("simulate", matches)) => run_simulate(matches),
+        Some(("check-determinism", matches)) => run_check_determinism(matches),
+        _ => unreachable!("clap should ensure we don't get here"),
+    };
+    if let Err(e) = res {
+        err!(e);
+        std::process::exit(BAD_ARGS_EXIT_CODE);
+    }
+}
+
+fn run_simulate(matches: &clap::ArgMatches) -> Result<(), String> {
+    let setup = SimulationSetup {
+        mode: parse_mode(matches.get_one::<String>("mode").unwrap())?,
+        seed: matches.get_one::<String>("seed").unwrap().clone(),
+        gen_state_opts: matches
+            .get_one::<String>("gen-opts")
+            .map(|v| parse_gen_state_opts(v))
+            .transpose()?,
+        players: matches
+            .get_one::<String>("players")
+            .map(|v| parse_players(v))
+            .transpose()?
+            .unwrap_or(vec![]),
+    };
+    let out_state = matches.get_one::<PathBuf>("out-state");
+    let out_replay = matches.get_one::<PathBuf>("out-replay");
+    let opts = SimulationOptions {
+        duration_ticks: *matches.get_one::<u64>("duration-ms").unwrap() * 1000,
+        step_ticks: *matches
+            .get_one::<u64>("step-ms")
+            .unwrap_or(&DEFAULT_STEP_MS)
+            * 1000,
+        actions: matches
+            .get_one::<PathBuf>("actions")
+            .map(|p| read_actions(p))
+            .transpose()?
+            .unwrap_or(vec![]),
+        replay_interval_ticks: out_replay.map(|_| {
+            *matches
+                .get_one::<u64>("replay-interval-ms")
+                .unwrap_or(&DEFAULT_REPLAY_INTERVAL_MS)
+                * 1000
+        }),
+    };
+    if opts.step_ticks == 0 {
+        return Err("--step-ms must be positive".to_string());
+    }
+    if out_state.is_none() && out_replay.is_none() {
+        return Err("at least one of --out-state and --out-replay is required".to_string());
+    }
+
+    let d_table = make_d_table();
+    let mut caches = GameStateCaches::new();
+    let state = setup_state(&setup, &mut caches);
+    let result = simulate(state, &opts, &d_table, &mut caches);
+
+    if let Some(path) = out_state {
+        write_state(&result.state, path)?;
+    }
+    if let (Some(path), Some(replay)) = (out_replay, result.replay.as_ref()) {
+        write_replay(replay, path)?;
+    }
+    log!(format!(
+        "simulated {:?} seed '{}' up to {} ticks, {} actions, {} processed events",
+        setup.mode,
+        setup.seed,
+        result.state.ticks,
+        opts.actions.len(),
+        result.state.processed_events.len()
+    ));
+    Ok(())
+}
+
+fn run_check_determinism(matches: &clap::ArgMatches) -> Result<(), String> {
+    let replay_path = matches.get_one::<PathBuf>("replay").unwrap();
+    let replay = read_replay(replay_path)?;
+    let frames = extract_replay_frames(&replay)
+        .map_err(|e| format!("could not restore replay {:?}, err {:?}", replay_path, e))?;
+    let log = collect_action_log(frames.iter());
+    let opts = ResimulationOptions {
+        step_ticks: *matches
+            .get_one::<u64>("step-ms")
+            .unwrap_or(&DEFAULT_STEP_MS)
+            * 1000,
+        client: matches.get_flag("client"),
+    };
+    if let Some(first) = frames.first() {
+        reset_id_prng(first);
+    }
+    let d_table = make_d_table();
+    let mut caches = GameStateCaches::new();
+    let report = check_determinism(&frames, &log, &opts, &d_table, &mut caches);
+
+    if let Some(path) = matches.get_one::<PathBuf>("out-report") {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("could not serialize report, err {}", e))?;
+        fs::write(path, json)
+            .map_err(|e| format!("could not write report to {:?}, err {}", path, e))?;
+    }
+    match report.divergence {
+        None => {
+            log!(format!(
+                "no divergence in {} frames up to {} ticks, {} actions replayed",
+                report.checked_frames,
+                report.last_checked_ticks,
+                log.len()
+            ));
+        }
+        Some(divergence) => {
+            err!(format!(
+                "diverged at {} ticks (frame {}), expected hash {}, got {}, {} diffs",
+                divergence.ticks,
+                report.checked_frames,
+                divergence.expected_hash,
+                divergence.actual_hash,
+                divergence.diffs.len()
+            ));
+            for diff in divergence.diffs.iter() {
+                err!(format!("{:?}", diff));
+            }
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
@@ -11,27 +11,29 @@ use uuid::Uuid;
 
 use crate::api_struct::{new_bot, AiTrait, Bot, Room};
 use crate::autofocus::{object_index_into_object_id, object_index_into_object_pos};
+use crate::behaviour_tree;
+use crate::behaviour_tree::BtContext;
 use crate::cargo_rush::CargoDeliveryQuestState;
 use crate::dialogue::{
     check_trigger_conditions, execute_dialog_option, DialogueId, DialogueScript, DialogueStates,
     DialogueStatesForPlayer, DialogueTable, DialogueUpdate, StateId, TriggerCondition,
 };
 use crate::indexing::{
-    find_my_player, find_my_ship, find_planet, GameStateIndexes, ObjectIndexSpecifier,
-    ObjectSpecifier,
+    find_my_player, find_my_ship, find_planet, find_player_ship_index, GameStateIndexes,
+    ObjectIndexSpecifier, ObjectSpecifier,
 };
-use crate::long_actions::LongAction;
+use crate::locations::RouteMetric;
+use crate::long_actions::{LongAction, LongActionStart};
 use crate::random_stuff::gen_bot_name;
 use crate::world;
 use crate::world::{GameState, Ship, ShipIdx, ShipTemplate, SpatialIndexes};
 use crate::world_actions::Action;
 use crate::world_events::GameEvent;
-use crate::{fire_event, pirate_defence};
+use crate::{economy, fire_event, pirate_defence};
 use crate::{indexing, world_actions};
 use std::iter::FromIterator;
 
 const BOT_SLEEP_MS: u64 = 200;
-const BOT_QUEST_ACT_DELAY_MC: i64 = 2 * 1000 * 1000;
 
 pub enum BotAct {
     Speak(DialogueUpdate),
@@ -46,113 +48,68 @@ pub fn bot_act(
     spatial_indexes: &SpatialIndexes,
     prng: &mut Pcg64Mcg,
 ) -> (Bot, Vec<BotAct>) {
-    if bot
-        .traits
-        .iter()
-        .any(|t| matches!(t, AiTrait::CargoRushHauler { .. }))
-    {
-        return bot_cargo_rush_hauler_act(bot, &state, bot_elapsed_micro, d_table, prng);
-    }
-    if bot
-        .traits
-        .iter()
-        .any(|t| matches!(t, AiTrait::PirateDefencePlanetDefender { .. }))
-    {
-        return pirate_defence::bot_planet_defender_act(
-            bot,
-            &state,
-            bot_elapsed_micro,
+    if let Some(tree) = behaviour_tree::get_bot_tree(&bot) {
+        let ctx = BtContext {
+            state,
             d_table,
             spatial_indexes,
-            prng,
-        );
+            elapsed_micro: bot_elapsed_micro,
+            player_id: Some(bot.id),
+            ship_idx: find_player_ship_index(state, bot.id),
+        };
+        return behaviour_tree::bot_tree_act(bot, tree, &ctx, prng);
     }
     return (bot, vec![]);
 }
 
-fn bot_cargo_rush_hauler_act(
-    mut bot: Bot,
-    state: &&GameState,
-    bot_elapsed_micro: i64,
-    d_table: &DialogueTable,
-    prng: &mut Pcg64Mcg,
-) -> (Bot, Vec<BotAct>) {
-    let player = find_my_player(&state, bot.id);
-    let conditions = check_trigger_conditions(state, bot.id);
-    if player.is_none() {
-        warn!(format!("{} no player", bot.id));
-        return (bot, vec![]);
-    }
-    let ship = find_my_ship(&state, bot.id);
-    if ship.is_none() {
-        return (bot, vec![]);
-    }
-    let ship = ship.unwrap();
-    let player = player.unwrap();
-    let quest = player.quest.clone();
-    if quest.is_none() {
-        return (bot, vec![]);
-    }
-    let quest = quest.unwrap();
-
-    let mut result_actions = vec![];
-
-    let empty = HashMap::new();
-    if let Some(dialogue_states) = state.dialogue_states.as_ref() {
-        let bot_d_states =
-            DialogueTable::get_player_d_states_read(dialogue_states, bot.id).unwrap_or(&empty);
-        let not_empty_d_states = bot_d_states
-            .iter()
-            .filter(|(_, v)| v.is_some())
-            .collect::<Vec<_>>();
-
-        if not_empty_d_states.len() > 0 {
-            // log!(format!("bot {} is talking since there are some dialogues {:?}", bot.id, bot_d_states));
-            // stop all other actions when talking
-            if bot.timer.is_none() {
-                bot.timer = Some(BOT_QUEST_ACT_DELAY_MC + prng.gen_range(-500, 500) * 1000);
-            } else {
-                bot.timer = Some(bot.timer.unwrap() - bot_elapsed_micro);
-                if bot.timer.unwrap() <= 0 {
-                    bot.timer = Some(BOT_QUEST_ACT_DELAY_MC);
-                    // time to act on all the dialogues
-                    for (dialogue_id, _) in not_empty_d_states.iter() {
-                        let act =
-                            make_dialogue_act(&bot, d_table, bot_d_states, **dialogue_id, state);
-                        if let Some(act) = act {
-                            result_actions.push(act);
-                        }
-                    }
-                } else {
-                    // still waiting
-                }
-            }
+// the planet may be in another star system, then the bot has to auto-jump there first
+pub fn travel_to_planet(
+    state: &GameState,
+    ship: &Ship,
+    player_id: Option<Uuid>,
+    planet_id: Uuid,
+) -> Vec<BotAct> {
+    let ship_loc = state
+        .locations
+        .iter()
+        .find(|l| l.ships.iter().any(|s| s.id == ship.id && !s.to_clean));
+    let planet_loc = state
+        .locations
+        .iter()
+        .find(|l| l.planets.iter().any(|p| p.id == planet_id));
+    let (ship_loc, planet_loc) = match (ship_loc, planet_loc) {
+        (Some(ship_loc), Some(planet_loc)) => (ship_loc, planet_loc),
+        _ => return vec![],
+    };
+    if ship_loc.id == planet_loc.id {
+        return if not_already_there(ship, planet_id) {
+            vec![BotAct::Act(Action::DockNavigate {
+                ship_id: ship.id,
+                target: planet_id,
+            })]
         } else {
-            if quest.state == CargoDeliveryQuestState::Started
-                && !conditions.contains(&TriggerCondition::CurrentPlanetIsPickup)
-            {
-                let desired_target = quest.from_id;
-                if not_already_there(ship, desired_target) {
-                    result_actions.push(BotAct::Act(Action::DockNavigate {
-                        ship_id: ship.id,
-                        target: desired_target,
-                    }));
-                }
-            } else if quest.state == CargoDeliveryQuestState::Picked
-                && !conditions.contains(&TriggerCondition::CurrentPlanetIsDropoff)
-            {
-                let desired_target = quest.to_id;
-                if not_already_there(ship, desired_target) {
-                    result_actions.push(BotAct::Act(Action::DockNavigate {
-                        ship_id: ship.id,
-                        target: desired_target,
-                    }));
-                }
-            }
-        }
+            vec![]
+        };
     }
-
-    return (bot, result_actions);
+    // only the player ships can jump
+    if player_id.is_none()
+        || ship.long_actions.iter().any(|la| {
+            matches!(
+                la,
+                LongAction::AutoJump { .. } | LongAction::TransSystemJump { .. }
+            )
+        })
+    {
+        return vec![];
+    }
+    vec![BotAct::Act(Action::LongActionStart {
+        long_action_start: LongActionStart::AutoJump {
+            to: planet_loc.id,
+            metric: RouteMetric::Jumps,
+        },
+        player_id,
+        ship_id: ship.id,
+    })]
 }
 
 fn not_already_there(ship: &Ship, desired_target: Uuid) -> bool {
@@ -160,8 +117,8 @@ fn not_already_there(ship: &Ship, desired_target: Uuid) -> bool {
         && !ship.docked_at.map_or(false, |id| id == desired_target)
 }
 
-fn make_dialogue_act(
-    bot: &Bot,
+pub fn make_dialogue_act(
+    bot_id: Uuid,
     d_table: &DialogueTable,
     bot_d_states: &HashMap<Uuid, Box<Option<Uuid>>>,
     dialogue_id: Uuid,
@@ -178,11 +135,11 @@ fn make_dialogue_act(
             // }
 
             let option =
-                current_script.get_next_bot_path(&*(current_d_state.clone()), game_state, bot.id);
+                current_script.get_next_bot_path(&*(current_d_state.clone()), game_state, bot_id);
             if option.is_none() {
                 warn!(format!(
-                    "Bot {:?} is stuck without dialogue option in dialogue {} state {:?}",
-                    bot, current_dialogue_name, current_d_state
+                    "Bot {} is stuck without dialogue option in dialogue {} state {:?}",
+                    bot_id, current_dialogue_name, current_d_state
                 ))
             } else {
             }
@@ -290,9 +247,10 @@ pub fn do_bot_players_actions(
 
 pub fn do_bot_npcs_actions(
     room: &mut Room,
+    d_table: &DialogueTable,
     elapsed_micro: i64,
     spatial_indexes: &SpatialIndexes,
-    _prng: &mut Pcg64Mcg,
+    prng: &mut Pcg64Mcg,
 ) {
     let mut ship_updates: HashMap<Uuid, (Vec<Action>, ShipIdx, Option<Bot>)> = HashMap::new();
 
@@ -310,9 +268,11 @@ pub fn do_bot_npcs_actions(
                 let (npc, bot_acts) = npc_act(
                     &ship.clone(),
                     room_state_read,
+                    d_table,
                     elapsed_micro,
                     &ship_idx,
                     spatial_indexes,
+                    prng,
                 );
                 ship_updates.insert(ship.id, (bot_acts, ship_idx, npc));
             }
@@ -320,8 +280,9 @@ pub fn do_bot_npcs_actions(
     }
 
     for (_ship_id, (acts, idx, npc_update)) in ship_updates.into_iter() {
+        // even without actions, as the npc may keep some timers
+        room.state.locations[idx.location_idx].ships[idx.ship_idx].npc = npc_update;
         for act in acts {
-            room.state.locations[idx.location_idx].ships[idx.ship_idx].npc = npc_update.clone();
             room.state.player_actions.push_back((act, None, None));
         }
     }
@@ -330,14 +291,49 @@ pub fn do_bot_npcs_actions(
 fn npc_act(
     ship: &Ship,
     state: &GameState,
-    _elapsed_micro: i64,
+    d_table: &DialogueTable,
+    elapsed_micro: i64,
     ship_idx: &ShipIdx,
     spatial_indexes: &SpatialIndexes,
+    prng: &mut Pcg64Mcg,
 ) -> (Option<Bot>, Vec<Action>) {
     if ship.npc.is_none() {
         return (None, vec![]);
     }
     let bot = ship.npc.clone().unwrap();
+    if let Some(tree) = behaviour_tree::get_bot_tree(&bot) {
+        let ctx = BtContext {
+            state,
+            d_table,
+            spatial_indexes,
+            elapsed_micro,
+            player_id: None,
+            ship_idx: Some(ship_idx.clone()),
+        };
+        let (bot, bot_acts) = behaviour_tree::bot_tree_act(bot, tree, &ctx, prng);
+        let acts = bot_acts
+            .into_iter()
+            .filter_map(|act| match act {
+                BotAct::Act(act) => Some(act),
+                BotAct::Speak(_) => None,
+            })
+            .collect();
+        return (Some(bot), acts);
+    }
+    let mut bot = bot;
+    if let Some(acts) = pirate_defence::pirate_npc_act(
+        &mut bot,
+        ship,
+        state,
+        elapsed_micro,
+        ship_idx,
+        spatial_indexes,
+    ) {
+        return (Some(bot), acts);
+    }
+    if let Some(acts) = economy::npc_trader_act(&mut bot, ship, state, ship_idx) {
+        return (Some(bot), acts);
+    }
     let mut res = vec![];
     let trait_set: HashSet<AiTrait> = HashSet::from_iter(bot.traits.clone().into_iter());
     let not_landing = ship
@@ -8,7 +8,8 @@ use typescript_definitions::{TypeScriptify, TypescriptDefinition};
 use uuid::Uuid;
 use world::GameState;
 
-use crate::api_struct::{new_bot, AiTrait};
+use crate::api_struct::{new_bot, new_tree_bot, AiTrait, BotDifficulty};
+use crate::behaviour_tree::CARGO_RUSH_TRADER_TREE;
 use crate::bots::add_bot;
 use crate::inventory::has_quest_item;
 use crate::notifications::{Notification, NotificationText};
@@ -21,12 +22,17 @@ use std::iter::FromIterator;
 use wasm_bindgen::prelude::*;
 use world::Player;
 
-pub fn on_create_room(room: &mut Room, prng: &mut Pcg64Mcg) {
+pub const CARGO_RUSH_BOT_COUNT: usize = 4;
+
+pub fn on_create_room(room: &mut Room, bot_difficulty: Option<BotDifficulty>, prng: &mut Pcg64Mcg) {
     let traits = Some(vec![AiTrait::CargoRushHauler]);
-    add_bot(room, new_bot(traits.clone(), prng_id(prng)), prng);
-    add_bot(room, new_bot(traits.clone(), prng_id(prng)), prng);
-    add_bot(room, new_bot(traits.clone(), prng_id(prng)), prng);
-    add_bot(room, new_bot(traits.clone(), prng_id(prng)), prng);
+    for _i in 0..CARGO_RUSH_BOT_COUNT {
+        let bot = match bot_difficulty.unwrap_or(BotDifficulty::Easy) {
+            BotDifficulty::Easy => new_bot(traits.clone(), prng_id(prng)),
+            BotDifficulty::Hard => new_tree_bot(CARGO_RUSH_TRADER_TREE, prng_id(prng)),
+        };
+        add_bot(room, bot, prng);
+    }
 }
 
 pub fn on_ship_docked(state: &mut GameState, player_id: Option<Uuid>, planet_id: Uuid) {
@@ -42,24 +48,50 @@ pub fn on_ship_docked(state: &mut GameState, player_id: Option<Uuid>, planet_id:
     }
 }
 
+pub fn get_quest_systems(state: &GameState) -> Vec<Vec<PlanetV2>> {
+    state
+        .locations
+        .iter()
+        .map(|l| l.planets.clone())
+        .collect::<Vec<_>>()
+}
+
+// systems are planets grouped by their location. When there is more than one system,
+// the cargo always has to be delivered to another one
 pub fn generate_random_quest(
     player: &mut Player,
-    planets: &Vec<PlanetV2>,
+    systems: &Vec<Vec<PlanetV2>>,
     docked_at: Option<Uuid>,
     prng: &mut Pcg64Mcg,
 ) {
+    let planets = systems
+        .iter()
+        .flat_map(|s| s.iter().map(|p| p.clone()))
+        .collect::<Vec<_>>();
     if planets.len() <= 0 {
         return;
     }
-    let from = world::get_random_planet(planets, docked_at, prng);
+    let from = world::get_random_planet(&planets, docked_at, prng);
     if from.is_none() {
         return;
     }
     let from = from.unwrap();
-    let delivery = planets
-        .into_iter()
-        .filter(|p| p.id != from.id)
+    let other_systems = systems
+        .iter()
+        .filter(|s| !s.iter().any(|p| p.id == from.id))
+        .flat_map(|s| s.iter())
         .collect::<Vec<_>>();
+    let delivery = if other_systems.len() > 0 {
+        other_systems
+    } else {
+        planets
+            .iter()
+            .filter(|p| p.id != from.id)
+            .collect::<Vec<_>>()
+    };
+    if delivery.len() <= 0 {
+        return;
+    }
     let to = &delivery[prng.gen_range(0, delivery.len())];
     let reward = prng.gen_range(500, 1001);
     let quest = Quest {
@@ -123,14 +155,14 @@ impl Quest {
 }
 
 pub fn update_quests(state: &mut GameState, prng: &mut Pcg64Mcg) {
-    let quest_planets = state.locations[0].planets.clone();
+    let quest_systems = get_quest_systems(state);
     let mut any_new_quests = vec![];
     let player_ids = state.players.iter().map(|p| p.id).collect::<Vec<_>>();
     for player_id in player_ids {
         if let (Some(mut player), Some(ship)) = indexing::find_player_and_ship_mut(state, player_id)
         {
             if player.quest.is_none() {
-                generate_random_quest(player, &quest_planets, ship.docked_at, prng);
+                generate_random_quest(player, &quest_systems, ship.docked_at, prng);
                 any_new_quests.push(player_id);
             } else {
                 let quest_id = player.quest.as_ref().map(|q| q.id).unwrap();
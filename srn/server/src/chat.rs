@@ -1,4 +1,5 @@
-use crate::{new_id, WSRequest};
+use crate::shutdown::ShutdownStage;
+use crate::{new_id, shutdown, WSRequest};
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use objekt_clonable::objekt::private::sync::mpsc::{Receiver, Sender};
@@ -91,14 +92,26 @@ fn dispatcher_thread() {
 
 pub fn chat_server() {
     let addr = "0.0.0.0:2795";
-    let server = Server::bind(addr).unwrap();
+    let mut server = Server::bind(addr).unwrap();
+    server.set_nonblocking(true).unwrap();
     println!("Chat server has launched on {}", addr);
 
     thread::spawn(|| dispatcher_thread());
 
-    for request in server.filter_map(Result::ok) {
-        thread::spawn(|| handle_request(request));
+    while !shutdown::is_shutting_down() {
+        match server.accept() {
+            Ok(request) => {
+                thread::spawn(|| handle_request(request));
+            }
+            Err(_) => {
+                thread::sleep(Duration::from_millis(CHAT_SLEEP_MS));
+            }
+        }
     }
+    broadcast_message(ServerChatMessage::global_server(
+        "server is shutting down, see you soon",
+    ));
+    println!("Chat server has stopped accepting connections");
 }
 
 fn is_disconnected(client_id: Uuid) -> bool {
@@ -159,6 +172,14 @@ fn handle_request(request: WSRequest) {
         if disconnect_if_bad(client_id) {
             break;
         }
+        if shutdown::get_stage() >= ShutdownStage::Closing {
+            let message = Message::close();
+            sender.send_message(&message).ok();
+            let mut senders = CHAT_CLIENT_SENDERS.lock().unwrap();
+            let index = senders.iter().position(|s| s.0 == client_id);
+            index.map(|index| senders.remove(index));
+            return;
+        }
 
         if let Ok(message) = message_rx.try_recv() {
             match message {
@@ -2,12 +2,11 @@ use crate::abilities::{Ability, SHOOT_COOLDOWN_TICKS};
 use crate::autofocus::{
     extract_closest_into, object_index_into_object_id, object_index_into_object_pos, SpatialIndex,
 };
+use crate::combat_log::DamageLogEntry;
 use crate::effects::{add_effect, LocalEffectCreate};
-use crate::fof::{FofActor, FofOverrides};
+use crate::fof::{friend_or_foe, FofActor, FofOverrides, FriendOrFoe};
 use crate::hp::{object_index_into_health_mut, object_index_into_to_clean_mut};
-use crate::indexing::{
-    find_player_ship_index, GameStateIndexes, ObjectIndexSpecifier, ObjectSpecifier,
-};
+use crate::indexing::{find_ship_index, GameStateIndexes, ObjectIndexSpecifier, ObjectSpecifier};
 use crate::properties::properties_main::ObjectProperty;
 use crate::properties::*;
 use crate::random_stuff::generate_normal_random;
@@ -16,7 +15,7 @@ use crate::vec2::Vec2f64;
 use crate::world::{
     remove_object, GameState, Location, ProcessProps, Ship, SpatialProps, TemplateId, UpdateOptions,
 };
-use crate::{indexing, new_id, world};
+use crate::{combat_log, indexing, new_id, world};
 use rand_pcg::Pcg64Mcg;
 use serde_derive::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
@@ -34,6 +33,8 @@ pub struct Health {
     pub last_damage_dealer: Option<ObjectSpecifier>,
     pub acc_periodic_dmg: f64,
     pub acc_periodic_heal: f64,
+    pub shield: Option<Shield>,
+    pub resistances: Option<Resistances>,
 }
 
 impl Health {
@@ -45,6 +46,8 @@ impl Health {
             last_damage_dealer: None,
             acc_periodic_dmg: 0.0,
             acc_periodic_heal: 0.0,
+            shield: None,
+            resistances: None,
         }
     }
 
@@ -56,10 +59,94 @@ impl Health {
             last_damage_dealer: None,
             acc_periodic_dmg: 0.0,
             acc_periodic_heal: 0.0,
+            shield: None,
+            resistances: None,
+        }
+    }
+
+    // resistances reduce the damage first, then the shield absorbs as much as it can, and the rest goes to the hull
+    pub fn apply_damage(&mut self, amount: f64, damage_type: &DamageType) -> DamageDone {
+        let amount = self
+            .resistances
+            .as_ref()
+            .map_or(amount, |r| amount * (1.0 - r.get(damage_type)));
+        let shield = if let Some(shield) = self.shield.as_mut() {
+            let absorbed = amount.min(shield.current);
+            shield.current -= absorbed;
+            shield.regen_delay_ticks_remaining = shield.regen_delay_ticks;
+            absorbed
+        } else {
+            0.0
+        };
+        let hull = amount - shield;
+        self.current = (self.current - hull).max(0.0);
+        DamageDone { shield, hull }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct Shield {
+    pub current: f64,
+    pub max: f64,
+    pub regen_per_tick: f64,
+    // any hit postpones the regeneration for that long
+    pub regen_delay_ticks: i32,
+    pub regen_delay_ticks_remaining: i32,
+}
+
+impl Shield {
+    pub fn new(max: f64, regen_per_tick: f64, regen_delay_ticks: i32) -> Shield {
+        Shield {
+            current: max,
+            max,
+            regen_per_tick,
+            regen_delay_ticks,
+            regen_delay_ticks_remaining: 0,
+        }
+    }
+
+    pub fn update(&mut self, elapsed_micro: i64) {
+        if self.regen_delay_ticks_remaining > 0 {
+            self.regen_delay_ticks_remaining =
+                (self.regen_delay_ticks_remaining - elapsed_micro as i32).max(0);
+        } else if self.current < self.max {
+            self.current =
+                (self.current + self.regen_per_tick * elapsed_micro as f64).min(self.max);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify, PartialEq)]
+pub enum DamageType {
+    Kinetic,
+    Explosive,
+    Thermal,
+}
+
+// shares of the damage of every type that are ignored, 0..1
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify, Default)]
+pub struct Resistances {
+    pub kinetic: f64,
+    pub explosive: f64,
+    pub thermal: f64,
+}
+
+impl Resistances {
+    pub fn get(&self, damage_type: &DamageType) -> f64 {
+        match damage_type {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Explosive => self.explosive,
+            DamageType::Thermal => self.thermal,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamageDone {
+    pub shield: f64,
+    pub hull: f64,
+}
+
 pub fn validate_shoot(
     target: ObjectSpecifier,
     loc: &Location,
@@ -144,16 +231,15 @@ pub const SHIP_SHOOT_STRENGTH: f64 = 20.0;
 
 pub fn resolve_shoot(
     state: &mut GameState,
-    player_shooting: Uuid,
+    shooting_ship_id: Uuid,
     target: ObjectSpecifier,
     active_turret_id: i32,
     indexes: &GameStateIndexes,
     prng: &mut Pcg64Mcg,
 ) {
-    if let Some(ship_loc) = find_player_ship_index(state, player_shooting) {
+    if let Some(ship_loc) = find_ship_index(state, shooting_ship_id) {
         let loc = &state.locations[ship_loc.location_idx];
         let shooting_ship_read = &loc.ships[ship_loc.ship_idx].clone();
-        let shooting_ship_id = shooting_ship_read.id;
         let shoot_ability = find_turret_ability(shooting_ship_read, active_turret_id);
         if shoot_ability.is_none() {
             return;
@@ -163,23 +249,39 @@ pub fn resolve_shoot(
         match target {
             ObjectSpecifier::Unknown => {}
             ObjectSpecifier::Ship { id: target_ship_id } => {
-                let dmg = SHIP_SHOOT_STRENGTH;
                 let target_ship = state.locations[ship_loc.location_idx]
                     .ships
                     .iter_mut()
                     .find(|s| s.id == target_ship_id);
                 let damaged = if let Some(target_ship) = target_ship {
-                    target_ship.health.current -= dmg;
+                    let done = target_ship
+                        .health
+                        .apply_damage(SHIP_SHOOT_STRENGTH, &DamageType::Kinetic);
                     target_ship.health.last_damage_dealer = Some(ObjectSpecifier::Ship {
                         id: shooting_ship_id,
                     });
-                    true
+                    Some(done)
                 } else {
-                    false
+                    None
                 };
-                if damaged {
-                    add_effect(
-                        LocalEffectCreate::DmgDone { hp: dmg as i32 },
+                if let Some(done) = damaged {
+                    let at_ticks = state.ticks;
+                    combat_log::record_damage(
+                        state,
+                        vec![DamageLogEntry::new(
+                            at_ticks,
+                            ObjectSpecifier::Ship {
+                                id: shooting_ship_id,
+                            },
+                            Some(shooting_ship_id),
+                            Some(active_turret_id),
+                            target_ship_id,
+                            DamageType::Kinetic,
+                            &done,
+                        )],
+                    );
+                    add_damage_effects(
+                        &done,
                         ObjectSpecifier::Ship {
                             id: shooting_ship_id,
                         },
@@ -242,12 +344,12 @@ pub fn resolve_shoot(
 
 pub fn resolve_launch(
     state: &mut GameState,
-    player_shooting: Uuid,
+    shooting_ship_id: Uuid,
     active_turret_id: i32,
     _client: bool,
     prng: &mut Pcg64Mcg,
 ) {
-    if let Some(ship_loc) = find_player_ship_index(state, player_shooting) {
+    if let Some(ship_loc) = find_ship_index(state, shooting_ship_id) {
         let loc = &mut state.locations[ship_loc.location_idx];
         let shooting_ship = &mut loc.ships[ship_loc.ship_idx];
         let launch_ability = find_turret_ability(shooting_ship, active_turret_id);
@@ -283,11 +385,8 @@ pub fn resolve_launch(
         instance.set_id(loc.short_counter);
         instance.set_position_from(&shooting_ship.spatial.position);
 
-        match &mut instance {
-            Projectile::Rocket(rocket) => {
-                rocket.fof_overrides = shooting_ship.fof_overrides.clone();
-            }
-        }
+        instance.set_fof_overrides(shooting_ship.fof_overrides.clone());
+        instance.set_launched_by(Some(shooting_ship.id));
 
         let mut new_rot = -shooting_ship.spatial.rotation_rad;
         let deviation = generate_normal_random(0.0, 0.15, prng);
@@ -303,7 +402,11 @@ pub fn resolve_launch(
                 .expect("new velocity should be non-zero")
                 .scalar_mul(shooting_ship.spatial.radius + proj_template.get_spatial().radius),
         );
-        instance.get_spatial_mut().velocity = new_velocity;
+        instance.get_spatial_mut().velocity = match instance {
+            // mines are dropped in place, the velocity was only needed to put it off the ship
+            Projectile::Mine(_) => Vec2f64::zero(),
+            _ => new_velocity,
+        };
         // because visual coordinates are inverted, we need negation here while not negating the velocity
         instance.get_spatial_mut().rotation_rad = -new_rot;
         loc.projectiles.push(instance);
@@ -317,10 +420,16 @@ pub fn guide_projectile(
 ) -> (f64, f64, f64) {
     let eps = 1e-6;
     let (gas, turn, brake) = match proj {
-        Projectile::Rocket(props) => {
-            let current_spatial = &props.spatial;
-            let current_movement = &props.movement;
-
+        Projectile::Rocket(RocketProps {
+            spatial: current_spatial,
+            movement: current_movement,
+            ..
+        })
+        | Projectile::Mine(MineProps {
+            spatial: current_spatial,
+            movement: current_movement,
+            ..
+        }) => {
             guide_accelerated_object(
                 &SpatialProps {
                     position: target_spatial.position,
@@ -343,6 +452,7 @@ pub fn guide_projectile(
                 current_movement,
             )
         }
+        Projectile::Slug(_) | Projectile::Emp(_) => proj.get_idle_controls(),
     };
     return (gas, turn, brake);
 }
@@ -467,6 +577,88 @@ pub const DEFAULT_PROJECTILE_ACC: f64 = 75.0 / 1e6 / 1e6;
 pub const DEFAULT_PROJECTILE_ROT_SPEED: f64 = PI * 2.0 / 1.0 / 1e6;
 pub const DEFAULT_PROJECTILE_ROT_ACC: f64 = PI / 1e6 / 1e6;
 pub const DEFAULT_PROJECTILE_EXPIRATION_TICKS: i32 = 15 * 1000 * 1000;
+pub const SLUG_SPEED: f64 = 60.0 / 1e6;
+pub const SLUG_DAMAGE: f64 = 15.0;
+pub const MINE_TRIGGER_RADIUS: f64 = 30.0;
+pub const MINE_LIFETIME_TICKS: i32 = 60 * 1000 * 1000;
+pub const EMP_BURST_RADIUS: f64 = 15.0;
+pub const EMP_DISABLE_TICKS: i32 = 3 * 1000 * 1000;
+// enough to shoot down a rocket or a mine with one hit
+pub const POINT_DEFENCE_DAMAGE: f64 = 10.0;
+
+pub fn update_point_defence(
+    state: &mut GameState,
+    loc_idx: usize,
+    sp_idx: &SpatialIndex,
+    indexes: &GameStateIndexes,
+    current_tick: u64,
+    prng: &mut Pcg64Mcg,
+) {
+    let loc = &state.locations[loc_idx];
+    let mut shots = vec![];
+    for (ship_idx, ship) in loc.ships.iter().enumerate() {
+        let ability_idx = ship.abilities.iter().position(|a| {
+            matches!(a, Ability::PointDefence { .. }) && a.get_current_cooldown() <= 0
+        });
+        if ability_idx.is_none() {
+            continue;
+        }
+        let ability_idx = ability_idx.unwrap();
+        let ship_pos = &ship.spatial.position;
+        let closest_hostile = sp_idx
+            .rad_search(ship_pos, ship.abilities[ability_idx].get_distance())
+            .into_iter()
+            .filter_map(|ois| match ois {
+                ObjectIndexSpecifier::Projectile { idx } => {
+                    let fof = friend_or_foe(
+                        state,
+                        FofActor::ObjectIdx {
+                            spec: ObjectIndexSpecifier::Ship { idx: ship_idx },
+                        },
+                        FofActor::ObjectIdx { spec: ois.clone() },
+                        loc_idx,
+                    );
+                    if fof == FriendOrFoe::Foe {
+                        Some(&loc.projectiles[idx])
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .min_by(|a, b| {
+                let dist_a = a.get_spatial().position.euclidean_distance(ship_pos);
+                let dist_b = b.get_spatial().position.euclidean_distance(ship_pos);
+                dist_a
+                    .partial_cmp(&dist_b)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        if let Some(proj) = closest_hostile {
+            shots.push((
+                ship_idx,
+                ability_idx,
+                ObjectSpecifier::Ship { id: ship.id },
+                ObjectSpecifier::Projectile { id: proj.get_id() },
+            ));
+        }
+    }
+    let mut log_entries = vec![];
+    let loc = &mut state.locations[loc_idx];
+    for (ship_idx, ability_idx, source, target) in shots.into_iter() {
+        loc.ships[ship_idx].abilities[ability_idx].set_max_cooldown();
+        log_entries.append(&mut damage_objects(
+            loc,
+            &vec![target],
+            POINT_DEFENCE_DAMAGE,
+            &DamageType::Kinetic,
+            &source,
+            indexes,
+            current_tick,
+            prng,
+        ));
+    }
+    combat_log::record_damage(state, log_entries);
+}
 
 pub fn update_projectile_collisions(
     loc: &mut Location,
@@ -476,65 +668,74 @@ pub fn update_projectile_collisions(
     indexes: &mut GameStateIndexes,
     current_tick: u64,
     prng: &mut Pcg64Mcg,
-) -> Vec<(ObjectSpecifier, f64)> {
-    let damages: Vec<(ObjectIndexSpecifier, f64)> = vec![];
-    let mut current_idx = -1;
+) -> Vec<DamageLogEntry> {
+    let mut log_entries = vec![];
     let mut exploded_ids = vec![];
-    for proj in loc.projectiles.iter_mut() {
-        current_idx += 1;
+    let mut direct_hits = vec![];
+    let mut emp_bursts = vec![];
+    for (current_idx, proj) in loc.projectiles.iter().enumerate() {
         let any_coll = sp_idx
             .rad_search(&proj.get_spatial().position, proj.get_spatial().radius)
             .into_iter()
             .filter(|os| match os {
                 // prevent collision detection with itself
-                ObjectIndexSpecifier::Projectile { idx } => *idx != (current_idx as usize),
+                ObjectIndexSpecifier::Projectile { idx } => *idx != current_idx,
                 _ => true,
             })
             .collect::<Vec<ObjectIndexSpecifier>>();
         if any_coll.len() > 0 {
-            // let mut damaged: Vec<(ObjectIndexSpecifier, f64)> = sp_idx
-            //     .rad_search(&proj.get_spatial().position, proj.get_spatial().radius)
-            //     .into_iter()
-            //     .filter(|os| match os {
-            //         // prevent collision detection with itself
-            //         ObjectIndexSpecifier::Projectile { idx } => *idx != (current_idx as usize),
-            //         _ => true,
-            //     })
-            //     .map(|os| (os, proj.get_direct_damage()))
-            //     .collect();
-            // warn2!(
-            //     "boom proj {} on {:?}",
-            //     proj.get_id(),
-            //     any_coll.iter().map(|os| os.clone()).collect::<Vec<_>>()
-            // );
-            // if let Some(exp) = proj.get_explosion_props() {
-            //     explosions.push((
-            //         exp.clone(),
-            //         proj.get_spatial().position.clone(),
-            //         Some(proj.get_id()),
-            //     ))
-            // }
-            // damages.append(&mut damaged);
-            exploded_ids.push(ObjectSpecifier::Projectile { id: proj.get_id() });
+            let proj_id = ObjectSpecifier::Projectile { id: proj.get_id() };
+            match proj {
+                Projectile::Rocket(_) | Projectile::Mine(_) => {
+                    // the explosion will happen on death, see hp::update_hp_effects
+                }
+                Projectile::Slug(_) => {
+                    let hit: Vec<ObjectSpecifier> = any_coll
+                        .iter()
+                        .filter_map(|ois| object_index_into_object_id(ois, loc))
+                        .collect();
+                    direct_hits.push((proj_id.clone(), hit, proj.get_direct_damage()));
+                }
+                Projectile::Emp(props) => {
+                    emp_bursts.push((
+                        props.spatial.position,
+                        props.burst_radius,
+                        props.disable_ticks,
+                    ));
+                }
+            }
+            exploded_ids.push(proj_id);
         }
     }
+    for (source, hit, amount) in direct_hits.into_iter() {
+        log_entries.append(&mut damage_objects(
+            loc,
+            &hit,
+            amount,
+            &DamageType::Kinetic,
+            &source,
+            indexes,
+            current_tick,
+            prng,
+        ));
+    }
+    for (pos, radius, disable_ticks) in emp_bursts.into_iter() {
+        apply_emp_burst(loc, &pos, radius, disable_ticks);
+    }
     for exp_id in exploded_ids.into_iter() {
         let source = &exp_id.clone();
         damage_objects(
             loc,
             &vec![exp_id],
             INSTAKILL,
+            &DamageType::Explosive,
             source,
             indexes,
             current_tick,
             prng,
         );
     }
-    // kind of 'kinetic' part of the damage, not the explosion
-    damages
-        .into_iter()
-        .filter_map(|(ois, d)| object_index_into_object_id(&ois, loc).map(|os| (os, d)))
-        .collect()
+    log_entries
 }
 
 #[skip_serializing_none]
@@ -542,89 +743,203 @@ pub fn update_projectile_collisions(
 #[serde(tag = "tag", content = "fields")]
 pub enum Projectile {
     Rocket(RocketProps),
+    Slug(SlugProps),
+    Mine(MineProps),
+    Emp(EmpProps),
 }
 
 impl Projectile {
     pub fn get_health_mut(&mut self) -> Option<&mut Health> {
         match self {
             Projectile::Rocket(props) => Some(&mut props.health),
+            Projectile::Slug(props) => Some(&mut props.health),
+            Projectile::Mine(props) => Some(&mut props.health),
+            Projectile::Emp(props) => Some(&mut props.health),
         }
     }
     pub fn get_explosion_props(&self) -> Option<&ExplosionProps> {
         match self {
             Projectile::Rocket(rocket_props) => Some(&rocket_props.explosion_props),
+            Projectile::Slug(_) => None,
+            Projectile::Mine(mine_props) => Some(&mine_props.explosion_props),
+            Projectile::Emp(_) => None,
         }
     }
     pub fn get_to_clean_mut(&mut self) -> &mut bool {
         match self {
             Projectile::Rocket(props) => &mut props.to_clean,
+            Projectile::Slug(props) => &mut props.to_clean,
+            Projectile::Mine(props) => &mut props.to_clean,
+            Projectile::Emp(props) => &mut props.to_clean,
         }
     }
 
     pub fn get_to_clean(&self) -> &bool {
         match self {
             Projectile::Rocket(props) => &props.to_clean,
+            Projectile::Slug(props) => &props.to_clean,
+            Projectile::Mine(props) => &props.to_clean,
+            Projectile::Emp(props) => &props.to_clean,
         }
     }
-    pub fn get_markers_mut(&mut self) -> &mut Option<String> {
+    // only guided projectiles have acceleration markers
+    pub fn get_markers_mut(&mut self) -> Option<&mut Option<String>> {
         match self {
-            Projectile::Rocket(props) => &mut props.markers,
+            Projectile::Rocket(props) => Some(&mut props.markers),
+            Projectile::Slug(_) => None,
+            Projectile::Mine(props) => Some(&mut props.markers),
+            Projectile::Emp(_) => None,
         }
     }
 
     pub fn get_properties(&self) -> &Vec<ObjectProperty> {
         match self {
             Projectile::Rocket(props) => &props.properties,
+            Projectile::Slug(props) => &props.properties,
+            Projectile::Mine(props) => &props.properties,
+            Projectile::Emp(props) => &props.properties,
         }
     }
 
     pub fn get_properties_mut(&mut self) -> &mut Vec<ObjectProperty> {
         match self {
             Projectile::Rocket(props) => &mut props.properties,
+            Projectile::Slug(props) => &mut props.properties,
+            Projectile::Mine(props) => &mut props.properties,
+            Projectile::Emp(props) => &mut props.properties,
         }
     }
 
     pub fn get_target(&self) -> Option<ObjectSpecifier> {
         match self {
             Projectile::Rocket(props) => props.target.clone(),
+            Projectile::Slug(_) => None,
+            Projectile::Mine(props) => props.target.clone(),
+            Projectile::Emp(_) => None,
+        }
+    }
+
+    // how far the projectile looks for a new target, if it can have one at all
+    pub fn get_acquire_radius(&self) -> Option<f64> {
+        match self {
+            Projectile::Rocket(_) => Some(REACQUIRE_RADIUS),
+            Projectile::Slug(_) => None,
+            Projectile::Mine(props) => Some(props.trigger_radius),
+            Projectile::Emp(_) => None,
+        }
+    }
+
+    // gas, turn & brake when there is nothing to guide the projectile to
+    pub fn get_idle_controls(&self) -> (f64, f64, f64) {
+        match self {
+            Projectile::Rocket(_) => (1.0, 0.0, 0.0),
+            Projectile::Slug(_) => (1.0, 0.0, 0.0),
+            // stays where it was dropped until triggered
+            Projectile::Mine(_) => (0.0, 0.0, 1.0),
+            Projectile::Emp(_) => (1.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn set_controls(&mut self, gas: f64, turn: f64, brake: f64) {
+        match self {
+            Projectile::Rocket(props) => {
+                props.gas = gas;
+                props.turn = turn;
+                props.brake = brake;
+            }
+            Projectile::Slug(_) => {}
+            Projectile::Mine(props) => {
+                props.gas = gas;
+                props.turn = turn;
+                props.brake = brake;
+            }
+            Projectile::Emp(_) => {}
         }
     }
 
     pub fn get_movement(&self) -> &Movement {
         match self {
             Projectile::Rocket(props) => &props.movement,
+            Projectile::Slug(props) => &props.movement,
+            Projectile::Mine(props) => &props.movement,
+            Projectile::Emp(props) => &props.movement,
         }
     }
 
     pub fn get_spatial(&self) -> &SpatialProps {
         match self {
             Projectile::Rocket(props) => &props.spatial,
+            Projectile::Slug(props) => &props.spatial,
+            Projectile::Mine(props) => &props.spatial,
+            Projectile::Emp(props) => &props.spatial,
         }
     }
 
     pub fn get_direct_damage(&self) -> f64 {
         match self {
             Projectile::Rocket(_) => 0.0,
+            Projectile::Slug(props) => props.damage,
+            Projectile::Mine(_) => 0.0,
+            Projectile::Emp(_) => 0.0,
         }
     }
 
     pub fn get_spatial_mut(&mut self) -> &mut SpatialProps {
         match self {
             Projectile::Rocket(props) => &mut props.spatial,
+            Projectile::Slug(props) => &mut props.spatial,
+            Projectile::Mine(props) => &mut props.spatial,
+            Projectile::Emp(props) => &mut props.spatial,
+        }
+    }
+
+    pub fn get_fof_overrides(&self) -> Option<&FofOverrides> {
+        match self {
+            Projectile::Rocket(props) => props.fof_overrides.as_ref(),
+            Projectile::Slug(props) => props.fof_overrides.as_ref(),
+            Projectile::Mine(props) => props.fof_overrides.as_ref(),
+            Projectile::Emp(props) => props.fof_overrides.as_ref(),
+        }
+    }
+
+    pub fn set_fof_overrides(&mut self, val: Option<FofOverrides>) {
+        match self {
+            Projectile::Rocket(props) => props.fof_overrides = val,
+            Projectile::Slug(props) => props.fof_overrides = val,
+            Projectile::Mine(props) => props.fof_overrides = val,
+            Projectile::Emp(props) => props.fof_overrides = val,
+        }
+    }
+
+    // the ship id, so the projectile belongs to the same side as its launcher, see fof
+    pub fn get_launched_by(&self) -> Option<Uuid> {
+        match self {
+            Projectile::Rocket(props) => props.launched_by,
+            Projectile::Slug(props) => props.launched_by,
+            Projectile::Mine(props) => props.launched_by,
+            Projectile::Emp(props) => props.launched_by,
+        }
+    }
+
+    pub fn set_launched_by(&mut self, val: Option<Uuid>) {
+        match self {
+            Projectile::Rocket(props) => props.launched_by = val,
+            Projectile::Slug(props) => props.launched_by = val,
+            Projectile::Mine(props) => props.launched_by = val,
+            Projectile::Emp(props) => props.launched_by = val,
         }
     }
 
     pub fn set_target(&mut self, t: &ObjectSpecifier) {
         match self {
             Projectile::Rocket(props) => props.target = Some(t.clone()),
+            Projectile::Slug(_) => {}
+            Projectile::Mine(props) => props.target = Some(t.clone()),
+            Projectile::Emp(_) => {}
         }
     }
     pub fn set_position_from(&mut self, from: &Vec2f64) {
-        match self {
-            Projectile::Rocket(props) => {
-                props.spatial.position = from.clone();
-            }
-        }
+        self.get_spatial_mut().position = from.clone();
     }
 }
 
@@ -632,12 +947,18 @@ impl Projectile {
     pub fn get_id(&self) -> i32 {
         match self {
             Projectile::Rocket(RocketProps { id, .. }) => *id,
+            Projectile::Slug(SlugProps { id, .. }) => *id,
+            Projectile::Mine(MineProps { id, .. }) => *id,
+            Projectile::Emp(EmpProps { id, .. }) => *id,
         }
     }
 
     pub fn set_id(&mut self, val: i32) {
         match self {
             Projectile::Rocket(RocketProps { id, .. }) => *id = val,
+            Projectile::Slug(SlugProps { id, .. }) => *id = val,
+            Projectile::Mine(MineProps { id, .. }) => *id = val,
+            Projectile::Emp(EmpProps { id, .. }) => *id = val,
         };
     }
 }
@@ -655,11 +976,64 @@ pub struct RocketProps {
     pub health: Health,
     pub to_clean: bool,
     pub fof_overrides: Option<FofOverrides>,
+    pub launched_by: Option<Uuid>,
     pub gas: f64,
     pub turn: f64,
     pub brake: f64,
 }
 
+// unguided, deals kinetic damage to whatever it hits instead of exploding
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct SlugProps {
+    pub id: i32,
+    pub spatial: SpatialProps,
+    pub movement: Movement,
+    pub properties: Vec<ObjectProperty>,
+    pub health: Health,
+    pub to_clean: bool,
+    pub fof_overrides: Option<FofOverrides>,
+    pub launched_by: Option<Uuid>,
+    pub damage: f64,
+}
+
+// stays in place until a foe comes into trigger_radius, then homes in like a rocket
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct MineProps {
+    pub id: i32,
+    pub spatial: SpatialProps,
+    pub movement: Movement,
+    pub properties: Vec<ObjectProperty>,
+    pub target: Option<ObjectSpecifier>,
+    pub explosion_props: ExplosionProps,
+    pub markers: Option<String>,
+    pub health: Health,
+    pub to_clean: bool,
+    pub fof_overrides: Option<FofOverrides>,
+    pub launched_by: Option<Uuid>,
+    pub trigger_radius: f64,
+    pub gas: f64,
+    pub turn: f64,
+    pub brake: f64,
+}
+
+// unguided, on hit disables all ships in burst_radius, see apply_emp_burst
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct EmpProps {
+    pub id: i32,
+    pub spatial: SpatialProps,
+    pub movement: Movement,
+    pub properties: Vec<ObjectProperty>,
+    pub health: Health,
+    pub to_clean: bool,
+    pub fof_overrides: Option<FofOverrides>,
+    pub launched_by: Option<Uuid>,
+    pub burst_radius: f64,
+    pub disable_ticks: i32,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
 pub struct Explosion {
@@ -667,12 +1041,19 @@ pub struct Explosion {
     pub spatial: SpatialProps,
     pub base: ExplosionProps,
     pub parent_projectile_id: Option<i32>,
+    // inherited from the parent projectile
+    pub launched_by: Option<Uuid>,
     pub decay_expand: ProcessProps,
     pub damaged: HashSet<ObjectSpecifier>,
     pub to_clean: bool,
 }
 
-pub fn gen_turrets(count: usize, _prng: &mut Pcg64Mcg) -> Vec<(Ability, ShipTurret)> {
+// guns go first, then one turret per launcher, see ship_classes::ShipClass::launchers
+pub fn gen_turrets(
+    count: usize,
+    launchers: &[TemplateId],
+    _prng: &mut Pcg64Mcg,
+) -> Vec<(Ability, ShipTurret)> {
     let mut res = vec![];
     for i in 0..count {
         let id = i as i32;
@@ -686,17 +1067,19 @@ pub fn gen_turrets(count: usize, _prng: &mut Pcg64Mcg) -> Vec<(Ability, ShipTurr
             ShipTurret { id },
         ));
     }
-    let id = res.len() as i32;
-    res.push((
-        Ability::Launch {
-            cooldown_ticks_remaining: 0,
-            turret_id: id,
-            projectile_template_id: TemplateId::Rocket as i32,
-            cooldown_normalized: 0.0,
-            cooldown_ticks_max: SHOOT_COOLDOWN_TICKS,
-        },
-        ShipTurret { id },
-    ));
+    for template_id in launchers.iter() {
+        let id = res.len() as i32;
+        res.push((
+            Ability::Launch {
+                cooldown_ticks_remaining: 0,
+                turret_id: id,
+                projectile_template_id: *template_id as i32,
+                cooldown_normalized: 0.0,
+                cooldown_ticks_max: SHOOT_COOLDOWN_TICKS,
+            },
+            ShipTurret { id },
+        ));
+    }
     res
 }
 
@@ -723,6 +1106,12 @@ pub fn create_explosion(
 ) {
     loc.short_counter += 1;
     let time_to_expand = props.radius / props.spread_speed;
+    let launched_by = from_projectile_id.and_then(|id| {
+        loc.projectiles
+            .iter()
+            .find(|p| p.get_id() == id)
+            .and_then(|p| p.get_launched_by())
+    });
     let exp = Explosion {
         id: loc.short_counter,
         spatial: SpatialProps {
@@ -734,6 +1123,7 @@ pub fn create_explosion(
         },
         base: props.clone(),
         parent_projectile_id: from_projectile_id,
+        launched_by,
         decay_expand: ProcessProps {
             progress_normalized: 0.0,
             remaining_ticks: time_to_expand as i32,
@@ -766,7 +1156,8 @@ pub fn update_explosions(
     indexes: &GameStateIndexes,
     current_tick: u64,
     prng: &mut Pcg64Mcg,
-) {
+) -> Vec<DamageLogEntry> {
+    let mut log_entries = vec![];
     for exp in loc.explosions.iter_mut() {
         if exp.decay_expand.apply(elapsed_ticks) {
             exp.to_clean = true;
@@ -807,15 +1198,16 @@ pub fn update_explosions(
                 exp.damaged.insert(oid.clone());
             }
         }
-        damage_objects(
+        log_entries.append(&mut damage_objects(
             loc,
             &shockwave_damaged.iter().map(|i| i.1.clone()).collect(),
             exp_r.base.damage,
+            &DamageType::Explosive,
             &ObjectSpecifier::Explosion { id: exp_r.id },
             indexes,
             current_tick,
             prng,
-        );
+        ));
 
         // apply constant push
         push_objects(
@@ -826,6 +1218,7 @@ pub fn update_explosions(
             &exp_r.spatial.position,
         );
     }
+    log_entries
 }
 
 pub fn push_objects(
@@ -900,11 +1293,15 @@ pub fn damage_objects(
     loc: &mut Location,
     targets: &Vec<ObjectSpecifier>,
     amount: f64,
+    damage_type: &DamageType,
     source: &ObjectSpecifier,
     indexes: &GameStateIndexes,
     current_tick: u64,
     prng: &mut Pcg64Mcg,
-) {
+) -> Vec<DamageLogEntry> {
+    let dealer_ship_id = get_dealer_ship_id(loc, source);
+    let dealer = dealer_ship_id.map_or(source.clone(), |id| ObjectSpecifier::Ship { id });
+    let mut log_entries = vec![];
     for os in targets {
         let damage = if let (Some(health), Some(ois)) = indexes
             .reverse_id_index
@@ -912,21 +1309,43 @@ pub fn damage_objects(
             .map_or((None, None), |ois| {
                 (object_index_into_health_mut(ois, loc), Some(ois))
             }) {
-            health.current -= amount;
-            health.current = health.current.max(0.0);
-            health.last_damage_dealer = Some(os.clone());
+            let done = if amount >= INSTAKILL {
+                // neither shields nor resistances can save from it
+                health.current = 0.0;
+                DamageDone {
+                    shield: 0.0,
+                    hull: amount,
+                }
+            } else {
+                health.apply_damage(amount, damage_type)
+            };
+            health.last_damage_dealer = Some(dealer.clone());
             if health.current == 0.0 {
                 if let Some(to_clean) = object_index_into_to_clean_mut(ois, loc) {
                     *to_clean = true;
                 };
             }
-            amount
+            done
         } else {
-            0.0
+            DamageDone {
+                shield: 0.0,
+                hull: 0.0,
+            }
         };
-        if damage > 0.0 && damage < INSTAKILL {
-            add_effect(
-                LocalEffectCreate::DmgDone { hp: damage as i32 },
+        if damage.hull < INSTAKILL {
+            if let ObjectSpecifier::Ship { id } = os {
+                log_entries.push(DamageLogEntry::new(
+                    current_tick,
+                    source.clone(),
+                    dealer_ship_id,
+                    None,
+                    *id,
+                    damage_type.clone(),
+                    &damage,
+                ));
+            }
+            add_damage_effects(
+                &damage,
                 source.clone(),
                 None,
                 os.clone(),
@@ -937,6 +1356,65 @@ pub fn damage_objects(
             )
         }
     }
+    log_entries
+}
+
+// the ship responsible for the damage done by the source, e.g. the one that launched the rocket
+fn get_dealer_ship_id(loc: &Location, source: &ObjectSpecifier) -> Option<Uuid> {
+    match source {
+        ObjectSpecifier::Ship { id } => Some(*id),
+        ObjectSpecifier::Projectile { id } => loc
+            .projectiles
+            .iter()
+            .find(|p| p.get_id() == *id)
+            .and_then(|p| p.get_launched_by()),
+        ObjectSpecifier::Explosion { id } => loc
+            .explosions
+            .iter()
+            .find(|e| e.id == *id)
+            .and_then(|e| e.launched_by),
+        _ => None,
+    }
+}
+
+pub fn add_damage_effects(
+    done: &DamageDone,
+    from: ObjectSpecifier,
+    extra_from_id: Option<i32>,
+    to: ObjectSpecifier,
+    loc: &mut Location,
+    indexes: &GameStateIndexes,
+    current_tick: u64,
+    prng: &mut Pcg64Mcg,
+) {
+    if done.shield > 0.0 {
+        add_effect(
+            LocalEffectCreate::ShieldHit {
+                hp: done.shield as i32,
+            },
+            from.clone(),
+            extra_from_id,
+            to.clone(),
+            loc,
+            indexes,
+            current_tick,
+            prng,
+        )
+    }
+    if done.hull > 0.0 {
+        add_effect(
+            LocalEffectCreate::DmgDone {
+                hp: done.hull as i32,
+            },
+            from,
+            extra_from_id,
+            to,
+            loc,
+            indexes,
+            current_tick,
+            prng,
+        )
+    }
 }
 
 pub fn heal_objects(
@@ -984,6 +1462,7 @@ pub fn try_reacquire_target(
     index: &SpatialIndex,
     state: &GameState,
     loc_idx: usize,
+    radius: f64,
 ) -> Option<ObjectIndexSpecifier> {
     let mut around_neutral = vec![];
     let mut around_hostile = vec![];
@@ -998,7 +1477,55 @@ pub fn try_reacquire_target(
         proj_pos,
         &mut around_neutral,
         &mut around_hostile,
-        REACQUIRE_RADIUS,
+        radius,
     );
     return around_hostile.first().map(|v| (*v).clone());
 }
+
+// friendly ships are not spared, so it's better to not stand too close to the burst
+pub fn apply_emp_burst(loc: &mut Location, pos: &Vec2f64, radius: f64, disable_ticks: i32) {
+    for ship in loc.ships.iter_mut() {
+        if ship.spatial.position.euclidean_distance(pos) - ship.spatial.radius > radius {
+            continue;
+        }
+        replace_property(
+            &mut ship.properties,
+            ObjectPropertyKey::EmpDisabled,
+            ObjectProperty::EmpDisabled(ProcessProps::from(disable_ticks)),
+        );
+        disable_ship(ship, disable_ticks);
+    }
+}
+
+fn disable_ship(ship: &mut Ship, remaining_ticks: i32) {
+    ship.movement_markers = Default::default();
+    for ability in ship.abilities.iter_mut() {
+        // reusing the cooldowns blocks the abilities and shows how long they are disabled
+        if ability.get_current_cooldown() < remaining_ticks {
+            ability.set_current_cooldown(remaining_ticks);
+        }
+    }
+}
+
+pub fn update_emp_disabled(ships: &mut Vec<Ship>, elapsed_ticks: i64) {
+    for ship in ships.iter_mut() {
+        let remaining = if let Some(ObjectProperty::EmpDisabled(process)) =
+            find_property_mut(&mut ship.properties, ObjectPropertyKey::EmpDisabled)
+        {
+            process.apply(elapsed_ticks as i32);
+            process.remaining_ticks
+        } else {
+            continue;
+        };
+        if remaining <= 0 {
+            ensure_no_property(&mut ship.properties, ObjectPropertyKey::EmpDisabled);
+        } else {
+            // the player can still send movement actions, so they have to be dropped every time
+            disable_ship(ship, remaining);
+        }
+    }
+}
+
+pub fn is_emp_disabled(ship: &Ship) -> bool {
+    has_property(&ship.properties, ObjectPropertyKey::EmpDisabled)
+}
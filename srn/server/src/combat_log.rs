@@ -0,0 +1,209 @@
+use crate::combat::{DamageDone, DamageType};
+use crate::indexing::{find_my_player, find_ship_index, index_players_by_ship_id, ObjectSpecifier};
+use crate::world::{GameState, Ship};
+use serde_derive::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use typescript_definitions::{TypeScriptify, TypescriptDefinition};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+pub const COMBAT_LOG_LIFETIME_TICKS: u64 = 60 * 1000 * 1000;
+// on top of the lifetime, so a big fight cannot blow up the state
+pub const MAX_DAMAGE_ENTRIES: usize = 1000;
+pub const MAX_KILL_RECORDS: usize = 100;
+// damage done to the victim this long before its death counts as an assist
+pub const ASSIST_WINDOW_TICKS: u64 = 10 * 1000 * 1000;
+// share of MoneyOnKill that every assisting player gets, while the killer gets the full amount
+pub const ASSIST_MONEY_SHARE: f64 = 0.5;
+
+// server-only, see world::update_events - clients don't get it in the state and have to use the REST api
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct CombatLog {
+    pub damage: Vec<DamageLogEntry>,
+    pub kills: Vec<KillRecord>,
+}
+
+impl CombatLog {
+    pub fn new() -> Self {
+        Self {
+            damage: vec![],
+            kills: vec![],
+        }
+    }
+
+    pub fn trim(&mut self, current_ticks: u64) {
+        let is_fresh = |at_ticks: u64| at_ticks + COMBAT_LOG_LIFETIME_TICKS >= current_ticks;
+        self.damage.retain(|e| is_fresh(e.at_ticks));
+        self.kills.retain(|k| is_fresh(k.at_ticks));
+        if self.damage.len() > MAX_DAMAGE_ENTRIES {
+            self.damage.drain(0..self.damage.len() - MAX_DAMAGE_ENTRIES);
+        }
+        if self.kills.len() > MAX_KILL_RECORDS {
+            self.kills.drain(0..self.kills.len() - MAX_KILL_RECORDS);
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct DamageLogEntry {
+    pub at_ticks: u64,
+    // the object that did the damage, e.g. a projectile, an explosion or a star
+    pub source: ObjectSpecifier,
+    // the ship responsible for the source, like the one that launched the rocket
+    pub dealer_ship_id: Option<Uuid>,
+    // resolved when recorded, since the dealer ship may be long dead when its rocket hits
+    pub dealer_player_id: Option<Uuid>,
+    pub turret_id: Option<i32>,
+    pub target_ship_id: Uuid,
+    pub target_player_id: Option<Uuid>,
+    pub damage_type: DamageType,
+    pub shield: f64,
+    pub hull: f64,
+}
+
+impl DamageLogEntry {
+    pub fn new(
+        at_ticks: u64,
+        source: ObjectSpecifier,
+        dealer_ship_id: Option<Uuid>,
+        turret_id: Option<i32>,
+        target_ship_id: Uuid,
+        damage_type: DamageType,
+        done: &DamageDone,
+    ) -> Self {
+        Self {
+            at_ticks,
+            source,
+            dealer_ship_id,
+            dealer_player_id: None,
+            turret_id,
+            target_ship_id,
+            target_player_id: None,
+            damage_type,
+            shield: done.shield,
+            hull: done.hull,
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct KillRecord {
+    pub at_ticks: u64,
+    pub ship_id: Uuid,
+    pub ship_name: String,
+    pub player_id: Option<Uuid>,
+    // Health.last_damage_dealer of the victim
+    pub killer: Option<ObjectSpecifier>,
+    pub killer_name: Option<String>,
+    pub killer_player_id: Option<Uuid>,
+    pub assist_player_ids: Vec<Uuid>,
+}
+
+pub fn record_damage(state: &mut GameState, mut entries: Vec<DamageLogEntry>) {
+    if entries.len() == 0 || state.combat_log.is_none() {
+        return;
+    }
+    {
+        let players_by_ship_id = index_players_by_ship_id(&state.players);
+        for entry in entries.iter_mut() {
+            entry.dealer_player_id = entry
+                .dealer_ship_id
+                .and_then(|id| players_by_ship_id.get(&id).map(|p| p.id));
+            entry.target_player_id = players_by_ship_id.get(&entry.target_ship_id).map(|p| p.id);
+        }
+    }
+    if let Some(log) = state.combat_log.as_mut() {
+        log.damage.append(&mut entries);
+    }
+}
+
+fn get_ship_name(state: &GameState, ship: Option<&Ship>, player_id: Option<Uuid>) -> String {
+    player_id
+        .and_then(|id| find_my_player(state, id))
+        .map(|p| p.name.clone())
+        .or_else(|| ship.and_then(|s| s.name.clone()))
+        .unwrap_or_else(|| "Unknown ship".to_string())
+}
+
+// has to be called before the victim ship is removed, while it's still possible to find its player
+pub fn credit_kill(state: &GameState, ship: &Ship, player_id: Option<Uuid>) -> KillRecord {
+    let recent_damage: Vec<&DamageLogEntry> = state.combat_log.as_ref().map_or(vec![], |log| {
+        log.damage
+            .iter()
+            .filter(|e| {
+                e.target_ship_id == ship.id
+                    && e.at_ticks + ASSIST_WINDOW_TICKS >= state.ticks
+                    && e.shield + e.hull > 0.0
+            })
+            .collect()
+    });
+    let killer = ship.health.last_damage_dealer.clone();
+    let killer_ship_id = match &killer {
+        Some(ObjectSpecifier::Ship { id }) => Some(*id),
+        _ => None,
+    };
+    let killer_player_id = killer_ship_id.and_then(|killer_ship_id| {
+        index_players_by_ship_id(&state.players)
+            .get(&killer_ship_id)
+            .map(|p| p.id)
+            .or_else(|| {
+                recent_damage
+                    .iter()
+                    .rev()
+                    .find(|e| e.dealer_ship_id == Some(killer_ship_id))
+                    .and_then(|e| e.dealer_player_id)
+            })
+    });
+    let killer_name = killer_ship_id.map(|killer_ship_id| {
+        let killer_ship = find_ship_index(state, killer_ship_id)
+            .map(|idx| &state.locations[idx.location_idx].ships[idx.ship_idx]);
+        get_ship_name(state, killer_ship, killer_player_id)
+    });
+    let mut assist_player_ids = vec![];
+    for entry in recent_damage.iter() {
+        if let Some(assist_id) = entry.dealer_player_id {
+            if Some(assist_id) != killer_player_id
+                && Some(assist_id) != player_id
+                && !assist_player_ids.contains(&assist_id)
+            {
+                assist_player_ids.push(assist_id);
+            }
+        }
+    }
+    KillRecord {
+        at_ticks: state.ticks,
+        ship_id: ship.id,
+        ship_name: get_ship_name(state, Some(ship), player_id),
+        player_id,
+        killer,
+        killer_name,
+        killer_player_id,
+        assist_player_ids,
+    }
+}
+
+pub fn record_kill(state: &mut GameState, kill: KillRecord) {
+    if let Some(log) = state.combat_log.as_mut() {
+        log.kills.push(kill);
+    }
+}
+
+pub fn get_kill_text(state: &GameState, kill: &KillRecord) -> String {
+    let assists = kill
+        .assist_player_ids
+        .iter()
+        .filter_map(|id| find_my_player(state, *id).map(|p| p.name.clone()))
+        .collect::<Vec<_>>();
+    let text = if let Some(killer_name) = &kill.killer_name {
+        format!("{} destroyed {}", killer_name, kill.ship_name)
+    } else {
+        format!("{} was destroyed", kill.ship_name)
+    };
+    if assists.len() > 0 {
+        format!("{}, assisted by {}", text, assists.join(", "))
+    } else {
+        text
+    }
+}
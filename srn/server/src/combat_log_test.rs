@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod combat_log_test {
+    use crate::autofocus::build_spatial_index;
+    use crate::combat::{update_projectile_collisions, DamageDone, DamageType, SLUG_DAMAGE};
+    use crate::combat_log::{
+        credit_kill, get_kill_text, record_damage, CombatLog, DamageLogEntry, ASSIST_WINDOW_TICKS,
+        COMBAT_LOG_LIFETIME_TICKS, MAX_DAMAGE_ENTRIES,
+    };
+    use crate::indexing::{index_state, ObjectSpecifier};
+    use crate::pirate_defence::on_ship_killed;
+    use crate::test_helpers::{add_player_ship, new_state};
+    use crate::vec2::Vec2f64;
+    use crate::world::{spawn_ship, GameMode, GameState, ShipTemplate, TemplateId, UpdateOptions};
+    use crate::{new_id, seed_prng};
+    use uuid::Uuid;
+
+    const AT: Vec2f64 = Vec2f64 { x: 321.5, y: 123.5 };
+    const FAR: Vec2f64 = Vec2f64 {
+        x: -321.5,
+        y: -123.5,
+    };
+
+    fn hit(at_ticks: u64, dealer_ship_id: Uuid, target_ship_id: Uuid) -> DamageLogEntry {
+        DamageLogEntry::new(
+            at_ticks,
+            ObjectSpecifier::Ship { id: dealer_ship_id },
+            Some(dealer_ship_id),
+            None,
+            target_ship_id,
+            DamageType::Kinetic,
+            &DamageDone {
+                shield: 0.0,
+                hull: 10.0,
+            },
+        )
+    }
+
+    #[test]
+    pub fn projectile_damage_is_credited_to_its_launcher() {
+        let mut prng = seed_prng("combat log slug".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "combat log");
+        let (player_id, dealer_ship_id) =
+            add_player_ship(&mut state, false, Some("shooter"), Some(FAR), &mut prng);
+        let target_ship_id =
+            spawn_ship(&mut state, None, ShipTemplate::pirate(Some(AT)), &mut prng).id;
+        let mut slug = state
+            .projectile_templates
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|t| t.get_id() == TemplateId::Slug as i32)
+            .unwrap()
+            .clone();
+        slug.set_id(1);
+        slug.set_position_from(&AT);
+        slug.set_launched_by(Some(dealer_ship_id));
+        state.locations[0].projectiles.push(slug);
+
+        let state_clone = state.clone();
+        let mut indexes = index_state(&state_clone);
+        let sp_idx = build_spatial_index(&state.locations[0], 0);
+        let entries = update_projectile_collisions(
+            &mut state.locations[0],
+            &UpdateOptions::new(),
+            &sp_idx,
+            0,
+            &mut indexes,
+            state_clone.ticks,
+            &mut prng,
+        );
+        record_damage(&mut state, entries);
+
+        let log = state.combat_log.as_ref().unwrap();
+        assert_eq!(log.damage.len(), 1);
+        let entry = &log.damage[0];
+        assert_eq!(entry.source, ObjectSpecifier::Projectile { id: 1 });
+        assert_eq!(entry.dealer_ship_id, Some(dealer_ship_id));
+        assert_eq!(entry.dealer_player_id, Some(player_id));
+        assert_eq!(entry.target_ship_id, target_ship_id);
+        assert_eq!(entry.shield + entry.hull, SLUG_DAMAGE);
+        let target = state.locations[0]
+            .ships
+            .iter()
+            .find(|s| s.id == target_ship_id)
+            .unwrap();
+        assert_eq!(
+            target.health.last_damage_dealer,
+            Some(ObjectSpecifier::Ship { id: dealer_ship_id })
+        );
+    }
+
+    #[test]
+    pub fn kill_is_credited_to_the_killer_with_recent_assists() {
+        let mut prng = seed_prng("combat log kill".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "combat log");
+        let (killer_id, killer_ship_id) =
+            add_player_ship(&mut state, false, Some("killer"), Some(FAR), &mut prng);
+        let (assist_id, assist_ship_id) =
+            add_player_ship(&mut state, false, Some("helper"), Some(FAR), &mut prng);
+        let (late_id, late_ship_id) =
+            add_player_ship(&mut state, false, Some("late"), Some(FAR), &mut prng);
+        let mut victim =
+            spawn_ship(&mut state, None, ShipTemplate::pirate(Some(AT)), &mut prng).clone();
+        state.ticks = ASSIST_WINDOW_TICKS * 2;
+        record_damage(
+            &mut state,
+            vec![
+                hit(0, late_ship_id, victim.id),
+                hit(state.ticks - 1000, assist_ship_id, victim.id),
+                hit(state.ticks, killer_ship_id, victim.id),
+            ],
+        );
+        victim.health.last_damage_dealer = Some(ObjectSpecifier::Ship { id: killer_ship_id });
+
+        let kill = credit_kill(&state, &victim, None);
+        assert_eq!(kill.killer_player_id, Some(killer_id));
+        assert_eq!(kill.assist_player_ids, vec![assist_id]);
+        assert_eq!(
+            get_kill_text(&state, &kill),
+            "killer destroyed Pirate, assisted by helper"
+        );
+
+        let money =
+            |state: &GameState, id: Uuid| state.players.iter().find(|p| p.id == id).unwrap().money;
+        let before = vec![killer_id, assist_id, late_id]
+            .into_iter()
+            .map(|id| money(&state, id))
+            .collect::<Vec<_>>();
+        // the pirate class gives 100 on kill
+        on_ship_killed(&mut state, victim, kill);
+        assert_eq!(money(&state, killer_id) - before[0], 100);
+        assert_eq!(money(&state, assist_id) - before[1], 50);
+        assert_eq!(money(&state, late_id) - before[2], 0);
+    }
+
+    #[test]
+    pub fn log_is_trimmed_by_age_and_size() {
+        let mut log = CombatLog::new();
+        let (dealer, target) = (new_id(), new_id());
+        log.damage.push(hit(0, dealer, target));
+        for i in 0..MAX_DAMAGE_ENTRIES + 1 {
+            log.damage
+                .push(hit(COMBAT_LOG_LIFETIME_TICKS + i as u64, dealer, target));
+        }
+        log.trim(COMBAT_LOG_LIFETIME_TICKS + 1);
+        // the first one is too old, and the next one doesn't fit
+        assert_eq!(log.damage.len(), MAX_DAMAGE_ENTRIES);
+        assert_eq!(log.damage[0].at_ticks, COMBAT_LOG_LIFETIME_TICKS + 1);
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod combat_test {
+    use crate::combat::{DamageDone, DamageType, Health, Resistances, Shield};
+    use crate::ship_classes::{get_ship_class, PLAYER_SHIP_CLASS};
+
+    fn shielded() -> Health {
+        let mut health = Health::new(100.0);
+        health.shield = Some(Shield::new(30.0, 10.0 / 1e6, 1000 * 1000));
+        health.resistances = Some(Resistances {
+            kinetic: 0.0,
+            explosive: 0.5,
+            thermal: 0.0,
+        });
+        health
+    }
+
+    #[test]
+    pub fn shield_absorbs_damage_before_hull() {
+        let mut health = shielded();
+        assert_eq!(
+            health.apply_damage(20.0, &DamageType::Kinetic),
+            DamageDone {
+                shield: 20.0,
+                hull: 0.0
+            }
+        );
+        assert_eq!(
+            health.apply_damage(20.0, &DamageType::Thermal),
+            DamageDone {
+                shield: 10.0,
+                hull: 10.0
+            }
+        );
+        assert_eq!(health.current, 90.0);
+        assert_eq!(health.shield.as_ref().unwrap().current, 0.0);
+    }
+
+    #[test]
+    pub fn resistances_reduce_damage_by_type() {
+        let mut health = shielded();
+        health.shield = None;
+        health.apply_damage(20.0, &DamageType::Explosive);
+        assert_eq!(health.current, 90.0);
+        health.apply_damage(20.0, &DamageType::Kinetic);
+        assert_eq!(health.current, 70.0);
+        health.apply_damage(1000.0, &DamageType::Kinetic);
+        assert_eq!(health.current, 0.0);
+    }
+
+    #[test]
+    pub fn shield_regenerates_only_after_the_delay() {
+        let mut health = shielded();
+        health.apply_damage(30.0, &DamageType::Kinetic);
+        let shield = health.shield.as_mut().unwrap();
+        shield.update(500 * 1000);
+        assert_eq!(shield.current, 0.0);
+        shield.update(500 * 1000);
+        assert_eq!(shield.current, 0.0);
+        shield.update(1000 * 1000);
+        assert!((shield.current - 10.0).abs() < 1e-9);
+        shield.update(10 * 1000 * 1000);
+        assert_eq!(shield.current, shield.max);
+    }
+
+    #[test]
+    pub fn player_class_has_shield() {
+        let health = get_ship_class(PLAYER_SHIP_CLASS).unwrap().to_health();
+        let shield = health.shield.unwrap();
+        assert_eq!(shield.current, shield.max);
+        assert!(health.resistances.is_some());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::dialogue::DialogueTable;
+use crate::indexing::GameStateCaches;
+use crate::perf::Sampler;
+use crate::replay::{ReplayDiffed, ReplayError, ValueDiff};
+use crate::world::{GameState, ProcessedPlayerAction, UpdateOptions, AABB};
+use crate::{seed_prng, world};
+use rand::prelude::*;
+use rand_pcg::Pcg64Mcg;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Divergence {
+    pub ticks: u64,
+    // hex, since u64 does not fit into js numbers
+    pub expected_hash: String,
+    pub actual_hash: String,
+    // what has to be applied to the expected state to get the re-simulated one
+    pub diffs: Vec<ValueDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeterminismReport {
+    pub checked_frames: u32,
+    pub last_checked_ticks: u64,
+    pub divergence: Option<Divergence>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResimulationOptions {
+    // has to be the same elapsed that the original updates were done with, as the prng is re-seeded on every update
+    pub step_ticks: u64,
+    pub client: bool,
+}
+
+// same as in the wasm update - the prng is carried over in the state itself, so updates can be repeated from any state
+pub fn continuous_state_prng(state: &mut GameState) -> Pcg64Mcg {
+    let mut prng = if let Some(next_seed) = state.next_seed.as_ref() {
+        Pcg64Mcg::seed_from_u64(*next_seed as u64)
+    } else {
+        seed_prng(state.seed.clone())
+    };
+    state.next_seed = Some(prng.next_u32());
+    prng
+}
+
+// some fields are intentionally non-deterministic or have unstable ordering, while the contents are the same
+fn cement_state_fields(state: &GameState) -> GameState {
+    let mut state = state.clone();
+    state.start_time_ticks = 0;
+    for loc in state.locations.iter_mut() {
+        loc.ships.sort_by_key(|s| s.id);
+    }
+    state
+}
+
+// -0.0 and 0.0 are equal, but serialized differently
+fn normalize_negative_zeros(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Number(n) => {
+            if n.as_f64()
+                .map_or(false, |f| f == 0.0 && f.is_sign_negative())
+            {
+                *value = serde_json::json!(0.0);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items.iter_mut() {
+                normalize_negative_zeros(item);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for (_, field) in fields.iter_mut() {
+                normalize_negative_zeros(field);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn hash_state(state: &GameState) -> u64 {
+    // json objects are sorted by key, so hashmap iteration order does not affect the hash
+    let mut value =
+        serde_json::to_value(cement_state_fields(state)).expect("Couldn't erase typing");
+    normalize_negative_zeros(&mut value);
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in value.to_string().as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// processed actions are only kept for a while in every state, so the full log has to be stitched from many of them
+pub fn collect_action_log<'a, I: IntoIterator<Item = &'a GameState>>(
+    states: I,
+) -> Vec<ProcessedPlayerAction> {
+    let mut seen = HashSet::new();
+    let mut log = vec![];
+    for state in states {
+        for action in state.processed_player_actions.iter() {
+            let key = (
+                action.processed_at_ticks,
+                action.packet_tag.clone(),
+                serde_json::to_string(&action.action).unwrap_or_default(),
+            );
+            if seen.insert(key) {
+                log.push(action.clone());
+            }
+        }
+    }
+    log.sort_by_key(|a| a.processed_at_ticks);
+    log
+}
+
+pub fn extract_replay_frames(replay: &ReplayDiffed) -> Result<Vec<GameState>, ReplayError> {
+    let mut frames = vec![];
+    for ticks in replay.marks_ticks.iter() {
+        frames.push(replay.get_state_at(*ticks, &mut None)?);
+    }
+    Ok(frames)
+}
+
+fn check_frame(expected: &GameState, actual: &GameState) -> Option<Divergence> {
+    let expected_hash = hash_state(expected);
+    let actual_hash = hash_state(actual);
+    if expected_hash == actual_hash {
+        return None;
+    }
+    Some(Divergence {
+        ticks: expected.ticks,
+        expected_hash: format!("{:016x}", expected_hash),
+        actual_hash: format!("{:016x}", actual_hash),
+        diffs: ReplayDiffed::calc_diff_batch(
+            &cement_state_fields(expected),
+            &cement_state_fields(actual),
+        ),
+    })
+}
+
+// re-simulates the first frame with the logged actions, and compares the result with every next frame.
+// only replays recorded with a fixed step and the state-carried prng (like the ones of srn-sim) can be checked -
+// the server loop updates rooms with a variable elapsed and a shared prng, none of which get into the replay,
+// so its replays diverge on the first frame with any randomness in it
+pub fn check_determinism(
+    frames: &Vec<GameState>,
+    log: &Vec<ProcessedPlayerAction>,
+    opts: &ResimulationOptions,
+    d_table: &DialogueTable,
+    caches: &mut GameStateCaches,
+) -> DeterminismReport {
+    let mut report = DeterminismReport {
+        checked_frames: 0,
+        last_checked_ticks: 0,
+        divergence: None,
+    };
+    let mut state = match frames.first() {
+        Some(first) => first.clone(),
+        None => return report,
+    };
+    report.last_checked_ticks = state.ticks;
+    let step_ticks = opts.step_ticks.max(1);
+    let mut next_action_idx = log
+        .iter()
+        .position(|a| a.processed_at_ticks > state.ticks)
+        .unwrap_or(log.len());
+    let mut sampler = Sampler::empty();
+    for expected in frames.iter().skip(1) {
+        while state.ticks < expected.ticks {
+            // actions are processed right after the ticks increment of the update iteration,
+            // so feeding them right before the update they happened in gets the same processed_at_ticks
+            while next_action_idx < log.len()
+                && log[next_action_idx].processed_at_ticks <= state.ticks + step_ticks
+            {
+                let action = &log[next_action_idx];
+                state.player_actions.push_back((
+                    action.action.clone(),
+                    action.packet_tag.clone(),
+                    Some(action.processed_at_ticks),
+                ));
+                next_action_idx += 1;
+            }
+            let mut prng = continuous_state_prng(&mut state);
+            let (new_state, new_sampler, _indexes) = world::update_world(
+                state,
+                step_ticks as i64,
+                opts.client,
+                sampler,
+                UpdateOptions {
+                    limit_area: AABB::maxed(),
+                    force_non_determinism: None,
+                },
+                &mut prng,
+                d_table,
+                caches,
+            );
+            state = new_state;
+            sampler = new_sampler;
+        }
+        report.checked_frames += 1;
+        report.last_checked_ticks = expected.ticks;
+        if let Some(divergence) = check_frame(expected, &state) {
+            report.divergence = Some(divergence);
+            return report;
+        }
+    }
+    report
+}
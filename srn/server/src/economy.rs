@@ -0,0 +1,318 @@
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+use uuid::Uuid;
+
+use crate::api_struct::{AiTrait, Bot};
+use crate::behaviour_tree::{find_best_trade, BtTradePlan};
+use crate::bots::{travel_to_planet, BotAct};
+use crate::indexing::find_planet;
+use crate::inventory::{
+    add_item, cleanup_inventory_from_zeros, count_items_of_types, group_items_of_same_type,
+    InventoryItem, InventoryItemType,
+};
+use crate::market::{get_default_stock, update_planet_prices};
+use crate::prng_id;
+use crate::properties::{find_property, ObjectProperty, ObjectPropertyKey, PlanetTypeProps};
+use crate::system_gen::PlanetType;
+use crate::world::{spawn_ship, GameState, PlanetV2, Ship, ShipIdx, ShipTemplate};
+use crate::world_actions::Action;
+
+pub const ECONOMY_UPDATE_EVERY_TICKS: i64 = 10 * 1000 * 1000;
+// production stops at that many default stocks, so the producers don't pile up goods forever
+pub const MAX_STOCK_COEFF: i32 = 5;
+pub const FREIGHTER_SHIP_CLASS: &str = "freighter";
+// only in the locations with at least 2 planets, as there is nowhere to go otherwise
+pub const NPC_TRADERS_PER_LOCATION: usize = 2;
+pub const NPC_TRADER_CAPACITY: i32 = 30;
+// per unit, the npc traders don't bother with smaller margins
+pub const NPC_TRADER_MIN_MARGIN: i32 = 10;
+
+const RANDOM_PLANET_TYPES: [PlanetType; 4] = [
+    PlanetType::Ice,
+    PlanetType::Jovian,
+    PlanetType::Jungle,
+    PlanetType::Barren,
+];
+
+// amounts are per economy update
+pub struct PlanetEconomy {
+    pub produces: Vec<(InventoryItemType, i32)>,
+    pub consumes: Vec<(InventoryItemType, i32)>,
+}
+
+pub fn get_planet_economy(planet_type: &PlanetType) -> PlanetEconomy {
+    match planet_type {
+        PlanetType::Unknown => PlanetEconomy {
+            produces: vec![],
+            consumes: vec![(InventoryItemType::Food, 5)],
+        },
+        // farms and pharmacy
+        PlanetType::Jungle => PlanetEconomy {
+            produces: vec![
+                (InventoryItemType::Food, 20),
+                (InventoryItemType::Medicament, 5),
+            ],
+            consumes: vec![
+                (InventoryItemType::CommonMineral, 5),
+                (InventoryItemType::HandWeapon, 1),
+            ],
+        },
+        PlanetType::Ice => PlanetEconomy {
+            produces: vec![
+                (InventoryItemType::Medicament, 5),
+                (InventoryItemType::UncommonMineral, 3),
+            ],
+            consumes: vec![
+                (InventoryItemType::Food, 15),
+                (InventoryItemType::HandWeapon, 1),
+            ],
+        },
+        // orbital factories
+        PlanetType::Jovian => PlanetEconomy {
+            produces: vec![
+                (InventoryItemType::HandWeapon, 2),
+                (InventoryItemType::TurretModule, 1),
+                (InventoryItemType::HullPlatingModule, 1),
+                (InventoryItemType::EngineModule, 1),
+                (InventoryItemType::TractorModule, 1),
+            ],
+            consumes: vec![
+                (InventoryItemType::CommonMineral, 10),
+                (InventoryItemType::UncommonMineral, 5),
+                (InventoryItemType::RareMineral, 2),
+                (InventoryItemType::Food, 10),
+            ],
+        },
+        // mines
+        PlanetType::Barren => PlanetEconomy {
+            produces: vec![
+                (InventoryItemType::CommonMineral, 15),
+                (InventoryItemType::UncommonMineral, 5),
+                (InventoryItemType::RareMineral, 2),
+            ],
+            consumes: vec![
+                (InventoryItemType::Food, 15),
+                (InventoryItemType::Medicament, 3),
+            ],
+        },
+    }
+}
+
+pub fn get_planet_type(planet: &PlanetV2) -> PlanetType {
+    match find_property(&planet.properties, ObjectPropertyKey::PlanetType) {
+        Some(ObjectProperty::PlanetType(props)) => props.planet_type.clone(),
+        _ => PlanetType::Unknown,
+    }
+}
+
+pub fn assign_planet_types(state: &mut GameState, prng: &mut Pcg64Mcg) {
+    for loc in state.locations.iter_mut() {
+        for planet in loc.planets.iter_mut() {
+            let planet_type =
+                RANDOM_PLANET_TYPES[prng.gen_range(0, RANDOM_PLANET_TYPES.len())].clone();
+            planet
+                .properties
+                .push(ObjectProperty::PlanetType(PlanetTypeProps { planet_type }));
+        }
+    }
+}
+
+// returns the actual change, since the stock cannot go below zero
+fn change_stock(
+    wares: &mut Vec<InventoryItem>,
+    it: &InventoryItemType,
+    delta: i32,
+    prng: &mut Pcg64Mcg,
+) -> i32 {
+    group_items_of_same_type(wares);
+    let current = count_items_of_types(wares, &vec![it.clone()]);
+    let new_quantity = (current + delta).max(0);
+    if let Some(item) = wares.iter_mut().find(|i| i.item_type == *it) {
+        item.quantity = new_quantity;
+    } else if new_quantity > 0 {
+        wares.push(InventoryItem::new(it.clone(), new_quantity, prng_id(prng)));
+    }
+    cleanup_inventory_from_zeros(wares);
+    new_quantity - current
+}
+
+// server-only, the client doesn't have the market
+pub fn update_economy(state: &mut GameState, elapsed_micro: i64, prng: &mut Pcg64Mcg) {
+    if let Some(market) = state.market.as_mut() {
+        market.time_before_next_economy_update -= elapsed_micro;
+        if market.time_before_next_economy_update > 0 {
+            return;
+        }
+        market.time_before_next_economy_update = ECONOMY_UPDATE_EVERY_TICKS;
+    } else {
+        return;
+    }
+    load_npc_traders(state, prng);
+    produce_and_consume(state, prng);
+    spawn_npc_traders(state, prng);
+}
+
+fn produce_and_consume(state: &mut GameState, prng: &mut Pcg64Mcg) {
+    let planets = state
+        .locations
+        .iter()
+        .flat_map(|l| l.planets.iter().map(|p| (p.id, get_planet_type(p))))
+        .collect::<Vec<_>>();
+    let market = state.market.as_mut().unwrap();
+    for (planet_id, planet_type) in planets {
+        // not stocked yet, see market::shake_market
+        let wares = match market.wares.get_mut(&planet_id) {
+            Some(wares) => wares,
+            None => continue,
+        };
+        let economy = get_planet_economy(&planet_type);
+        for (it, amount) in economy.produces.iter() {
+            let current = count_items_of_types(wares, &vec![it.clone()]);
+            let room = (get_default_stock(it) * MAX_STOCK_COEFF - current).max(0);
+            change_stock(wares, it, (*amount).min(room), prng);
+        }
+        for (it, amount) in economy.consumes.iter() {
+            change_stock(wares, it, -*amount, prng);
+        }
+        let prices = market.prices.entry(planet_id).or_default();
+        update_planet_prices(prices, wares, market.price_modifiers.get(&planet_id));
+    }
+}
+
+fn is_npc_trader(ship: &Ship) -> bool {
+    ship.npc
+        .as_ref()
+        .map_or(false, |npc| npc.traits.contains(&AiTrait::NpcTrader))
+}
+
+// the goods are moved while the trader is docked, so loading takes up to one economy update
+fn load_npc_traders(state: &mut GameState, prng: &mut Pcg64Mcg) {
+    let market = match state.market.as_mut() {
+        Some(market) => market,
+        None => return,
+    };
+    for loc in state.locations.iter_mut() {
+        for ship in loc.ships.iter_mut() {
+            let docked_at = match ship.docked_at {
+                Some(docked_at) => docked_at,
+                None => continue,
+            };
+            let plan = match ship.npc.as_ref().and_then(|npc| npc.memory.trade.clone()) {
+                Some(plan) => plan,
+                None => continue,
+            };
+            let wares = market.wares.entry(docked_at).or_insert(vec![]);
+            let new_plan = if !plan.bought && docked_at == plan.from_id {
+                let taken = -change_stock(wares, &plan.item, -plan.quantity, prng);
+                if taken > 0 {
+                    add_item(
+                        &mut ship.inventory,
+                        InventoryItem::new(plan.item.clone(), taken, prng_id(prng)),
+                    );
+                    Some(BtTradePlan {
+                        bought: true,
+                        quantity: taken,
+                        ..plan
+                    })
+                } else {
+                    None
+                }
+            } else if plan.bought && docked_at == plan.to_id {
+                let carried = count_items_of_types(&ship.inventory, &vec![plan.item.clone()]);
+                ship.inventory.retain(|i| i.item_type != plan.item);
+                change_stock(wares, &plan.item, carried, prng);
+                None
+            } else {
+                continue;
+            };
+            if let Some(npc) = ship.npc.as_mut() {
+                npc.memory.trade = new_plan;
+            }
+        }
+    }
+}
+
+fn spawn_npc_traders(state: &mut GameState, prng: &mut Pcg64Mcg) {
+    for loc_idx in 0..state.locations.len() {
+        let loc = &state.locations[loc_idx];
+        if loc.planets.len() < 2 {
+            continue;
+        }
+        let existing = loc.ships.iter().filter(|s| is_npc_trader(s)).count();
+        let location_id = loc.id;
+        for _i in existing..NPC_TRADERS_PER_LOCATION {
+            let planets = &state.locations[loc_idx].planets;
+            let at = planets[prng.gen_range(0, planets.len())]
+                .spatial
+                .position
+                .clone();
+            let template = match ShipTemplate::of_class(FREIGHTER_SHIP_CLASS, Some(at)) {
+                Some(template) => template.in_location(location_id),
+                None => {
+                    warn!(format!(
+                        "{} ship class is not defined",
+                        FREIGHTER_SHIP_CLASS
+                    ));
+                    return;
+                }
+            };
+            spawn_ship(state, None, template, prng);
+        }
+    }
+}
+
+// the same planner as the one of the trading bots, but the freighters move the goods for free,
+// limited only by their capacity and the goods that are part of the economy
+pub fn find_npc_trade_plan(state: &GameState, location_idx: usize) -> Option<BtTradePlan> {
+    let market = state.market.as_ref()?;
+    let planets = &state.locations.get(location_idx)?.planets;
+    find_best_trade(
+        market,
+        planets,
+        |item, _buy_price, stock| {
+            if get_default_stock(item) > 0 {
+                stock.min(NPC_TRADER_CAPACITY)
+            } else {
+                0
+            }
+        },
+        |margin, _profit| margin >= NPC_TRADER_MIN_MARGIN,
+    )
+}
+
+pub fn npc_trader_act(
+    bot: &mut Bot,
+    ship: &Ship,
+    state: &GameState,
+    ship_idx: &ShipIdx,
+) -> Option<Vec<Action>> {
+    if !bot.traits.contains(&AiTrait::NpcTrader) {
+        return None;
+    }
+    let planet_exists = |id: Uuid| find_planet(state, &id).is_some();
+    let plan = bot
+        .memory
+        .trade
+        .take()
+        .filter(|plan| planet_exists(plan.from_id) && planet_exists(plan.to_id))
+        .or_else(|| find_npc_trade_plan(state, ship_idx.location_idx));
+    let target = match plan.as_ref() {
+        Some(plan) if plan.bought => plan.to_id,
+        Some(plan) => plan.from_id,
+        // waits for the prices to change
+        None => return Some(vec![]),
+    };
+    bot.memory.trade = plan;
+    if ship.docked_at == Some(target) {
+        return Some(vec![]);
+    }
+    Some(
+        travel_to_planet(state, ship, None, target)
+            .into_iter()
+            .filter_map(|act| match act {
+                BotAct::Act(act) => Some(act),
+                BotAct::Speak(_) => None,
+            })
+            .collect(),
+    )
+}
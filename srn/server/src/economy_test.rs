@@ -0,0 +1,262 @@
+#[cfg(test)]
+mod economy_test {
+    use crate::api_struct::AiTrait;
+    use crate::economy::{
+        npc_trader_act, update_economy, ECONOMY_UPDATE_EVERY_TICKS, FREIGHTER_SHIP_CLASS,
+        NPC_TRADERS_PER_LOCATION, NPC_TRADER_CAPACITY,
+    };
+    use crate::indexing::{find_player_and_ship_mut, find_ship_index, find_ship_mut};
+    use crate::inventory::{count_items_of_types, InventoryItem, InventoryItemType};
+    use crate::market::{
+        apply_price_event, attempt_trade, get_default_price, get_default_stock, get_stock_price,
+        update_planet_prices, PriceEvent, TradeAction,
+    };
+    use crate::properties::{
+        ensure_no_property, replace_property, ObjectProperty, ObjectPropertyKey, PlanetTypeProps,
+    };
+    use crate::system_gen::PlanetType;
+    use crate::test_helpers::{add_player_ship, new_state};
+    use crate::world::{spawn_ship, GameMode, GameState, ShipTemplate};
+    use crate::world_actions::Action;
+    use crate::{new_id, seed_prng};
+    use uuid::Uuid;
+
+    const FOOD: InventoryItemType = InventoryItemType::Food;
+    const MINERAL: InventoryItemType = InventoryItemType::CommonMineral;
+
+    fn first_planet_ids(state: &GameState) -> (Uuid, Uuid) {
+        let planets = &state.locations[0].planets;
+        (planets[0].id, planets[1].id)
+    }
+
+    fn set_planet_type(state: &mut GameState, planet_id: Uuid, planet_type: Option<PlanetType>) {
+        let planet = state.locations[0]
+            .planets
+            .iter_mut()
+            .find(|p| p.id == planet_id)
+            .unwrap();
+        match planet_type {
+            Some(planet_type) => replace_property(
+                &mut planet.properties,
+                ObjectPropertyKey::PlanetType,
+                ObjectProperty::PlanetType(PlanetTypeProps { planet_type }),
+            ),
+            None => ensure_no_property(&mut planet.properties, ObjectPropertyKey::PlanetType),
+        }
+    }
+
+    fn stock(state: &mut GameState, planet_id: Uuid, items: Vec<(InventoryItemType, i32)>) {
+        let market = state.market.as_mut().unwrap();
+        let wares: Vec<InventoryItem> = items
+            .into_iter()
+            .map(|(it, quantity)| InventoryItem::new(it, quantity, new_id()))
+            .collect();
+        update_planet_prices(market.prices.get_mut(&planet_id).unwrap(), &wares, None);
+        market.wares.insert(planet_id, wares);
+    }
+
+    fn count_stock(state: &GameState, planet_id: Uuid, it: InventoryItemType) -> i32 {
+        count_items_of_types(
+            state
+                .market
+                .as_ref()
+                .unwrap()
+                .wares
+                .get(&planet_id)
+                .unwrap(),
+            &vec![it],
+        )
+    }
+
+    fn get_sell_price(state: &GameState, planet_id: Uuid, it: InventoryItemType) -> i32 {
+        state.market.as_ref().unwrap().prices[&planet_id][&it].sell
+    }
+
+    #[test]
+    pub fn stock_drives_prices_and_trades_move_them() {
+        let default_stock = get_default_stock(&FOOD);
+        let default_price = get_default_price(&FOOD);
+        assert_eq!(
+            get_stock_price(&FOOD, default_stock).sell,
+            default_price.sell
+        );
+        assert!(get_stock_price(&FOOD, default_stock / 4).sell > default_price.sell);
+        assert!(get_stock_price(&FOOD, default_stock * 4).buy < default_price.buy);
+        assert_eq!(
+            get_stock_price(&FOOD, 0).sell,
+            get_stock_price(&FOOD, 1).sell
+        );
+
+        let mut prng = seed_prng("economy trade".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "economy");
+        let (planet_id, _) = first_planet_ids(&state);
+        let (player_id, _ship_id) = add_player_ship(&mut state, false, None, None, &mut prng);
+        stock(&mut state, planet_id, vec![(FOOD, default_stock)]);
+        find_player_and_ship_mut(&mut state, player_id)
+            .0
+            .unwrap()
+            .money = 100000;
+
+        let price_before = get_sell_price(&state, planet_id, FOOD);
+        attempt_trade(
+            &mut state,
+            player_id,
+            TradeAction {
+                planet_id,
+                sells_to_planet: vec![],
+                buys_from_planet: vec![(FOOD, default_stock / 2)],
+            },
+            &mut prng,
+        );
+        assert_eq!(count_stock(&state, planet_id, FOOD), default_stock / 2);
+        assert!(get_sell_price(&state, planet_id, FOOD) > price_before);
+    }
+
+    #[test]
+    pub fn price_events_outlive_the_economy_update() {
+        let mut prng = seed_prng("economy events".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "economy");
+        let (planet_id, _) = first_planet_ids(&state);
+        set_planet_type(&mut state, planet_id, Some(PlanetType::Barren));
+        stock(
+            &mut state,
+            planet_id,
+            vec![(FOOD, get_default_stock(&FOOD))],
+        );
+        {
+            let market = state.market.as_mut().unwrap();
+            let wares = market.wares.get_mut(&planet_id).unwrap();
+            let modifiers = market.price_modifiers.entry(planet_id).or_default();
+            apply_price_event(modifiers, PriceEvent::FoodShortage, wares, &mut prng);
+            market.time_before_next_economy_update = 0;
+        }
+
+        update_economy(&mut state, 1, &mut prng);
+        let stock_price = get_stock_price(&FOOD, count_stock(&state, planet_id, FOOD));
+        assert_eq!(
+            get_sell_price(&state, planet_id, FOOD),
+            (stock_price.sell as f64 * 1.5).round() as i32
+        );
+    }
+
+    #[test]
+    pub fn planets_produce_and_consume_by_type() {
+        let mut prng = seed_prng("economy production".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "economy");
+        let (mine_id, farm_id) = first_planet_ids(&state);
+        set_planet_type(&mut state, mine_id, Some(PlanetType::Barren));
+        set_planet_type(&mut state, farm_id, Some(PlanetType::Jungle));
+        for planet_id in vec![mine_id, farm_id] {
+            stock(
+                &mut state,
+                planet_id,
+                vec![
+                    (FOOD, get_default_stock(&FOOD)),
+                    (MINERAL, get_default_stock(&MINERAL)),
+                ],
+            );
+        }
+
+        state
+            .market
+            .as_mut()
+            .unwrap()
+            .time_before_next_economy_update = 0;
+        update_economy(&mut state, 1, &mut prng);
+        assert_eq!(
+            state
+                .market
+                .as_ref()
+                .unwrap()
+                .time_before_next_economy_update,
+            ECONOMY_UPDATE_EVERY_TICKS
+        );
+        assert_eq!(count_stock(&state, mine_id, MINERAL), 115);
+        assert_eq!(count_stock(&state, mine_id, FOOD), 185);
+        assert_eq!(count_stock(&state, farm_id, MINERAL), 95);
+        assert_eq!(count_stock(&state, farm_id, FOOD), 220);
+        assert!(
+            get_sell_price(&state, mine_id, MINERAL) < get_sell_price(&state, farm_id, MINERAL)
+        );
+        assert!(get_sell_price(&state, mine_id, FOOD) > get_sell_price(&state, farm_id, FOOD));
+
+        // nothing happens until the next update
+        update_economy(&mut state, 1, &mut prng);
+        assert_eq!(count_stock(&state, mine_id, MINERAL), 115);
+        let traders = state.locations[0]
+            .ships
+            .iter()
+            .filter(|s| {
+                s.npc
+                    .as_ref()
+                    .map_or(false, |npc| npc.traits.contains(&AiTrait::NpcTrader))
+            })
+            .count();
+        assert_eq!(traders, NPC_TRADERS_PER_LOCATION);
+    }
+
+    #[test]
+    pub fn npc_traders_move_goods_between_planets() {
+        let mut prng = seed_prng("economy traders".to_string());
+        let mut state = new_state(&GameMode::CargoRush, "economy");
+        let (from_id, to_id) = first_planet_ids(&state);
+        // no production, so only the trader changes the stock
+        set_planet_type(&mut state, from_id, None);
+        set_planet_type(&mut state, to_id, None);
+        stock(&mut state, from_id, vec![(MINERAL, 500)]);
+        stock(&mut state, to_id, vec![]);
+        let at = state.locations[0].planets[0].spatial.position.clone();
+        let trader_id = spawn_ship(
+            &mut state,
+            None,
+            ShipTemplate::of_class(FREIGHTER_SHIP_CLASS, Some(at)).unwrap(),
+            &mut prng,
+        )
+        .id;
+        let act = |state: &mut GameState| {
+            let ship_idx = find_ship_index(state, trader_id).unwrap();
+            let ship = state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx].clone();
+            let mut bot = ship.npc.clone().unwrap();
+            let acts = npc_trader_act(&mut bot, &ship, state, &ship_idx).unwrap();
+            find_ship_mut(state, trader_id).unwrap().npc = Some(bot);
+            acts
+        };
+        let flies_to = |acts: &Vec<Action>, planet_id: Uuid| {
+            acts.iter()
+                .any(|a| matches!(a, Action::DockNavigate { target, .. } if *target == planet_id))
+        };
+
+        assert!(flies_to(&act(&mut state), from_id));
+        find_ship_mut(&mut state, trader_id).unwrap().docked_at = Some(from_id);
+        assert!(act(&mut state).is_empty());
+        state
+            .market
+            .as_mut()
+            .unwrap()
+            .time_before_next_economy_update = 0;
+        update_economy(&mut state, 1, &mut prng);
+        assert_eq!(
+            count_stock(&state, from_id, MINERAL),
+            500 - NPC_TRADER_CAPACITY
+        );
+        let trader = find_ship_mut(&mut state, trader_id).unwrap();
+        assert_eq!(
+            count_items_of_types(&trader.inventory, &vec![MINERAL]),
+            NPC_TRADER_CAPACITY
+        );
+        trader.docked_at = None;
+
+        assert!(flies_to(&act(&mut state), to_id));
+        find_ship_mut(&mut state, trader_id).unwrap().docked_at = Some(to_id);
+        state
+            .market
+            .as_mut()
+            .unwrap()
+            .time_before_next_economy_update = 0;
+        update_economy(&mut state, 1, &mut prng);
+        assert_eq!(count_stock(&state, to_id, MINERAL), NPC_TRADER_CAPACITY);
+        let trader = find_ship_mut(&mut state, trader_id).unwrap();
+        assert_eq!(count_items_of_types(&trader.inventory, &vec![MINERAL]), 0);
+        assert!(trader.npc.as_ref().unwrap().memory.trade.is_none());
+    }
+}
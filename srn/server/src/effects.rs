@@ -60,6 +60,12 @@ pub fn add_effect(
                 last_tick: current_tick,
                 position: effect_pos,
             },
+            LocalEffectCreate::ShieldHit { hp } => LocalEffect::ShieldHit {
+                hp,
+                key,
+                last_tick: current_tick,
+                position: effect_pos,
+            },
         })
     } else {
         warn2!("Attempt to add effect without position for from:{from:?} to:{to:?}");
@@ -124,6 +130,7 @@ fn form_effect_key(
             LocalEffectCreate::DmgDone { .. } => "D",
             LocalEffectCreate::Heal { .. } => "H",
             LocalEffectCreate::PickUp { .. } => "P",
+            LocalEffectCreate::ShieldHit { .. } => "S",
         },
         from,
         to,
@@ -137,6 +144,7 @@ pub enum LocalEffectCreate {
     DmgDone { hp: i32 },
     Heal { hp: i32 },
     PickUp { text: String },
+    ShieldHit { hp: i32 },
 }
 
 impl LocalEffectCreate {
@@ -145,6 +153,7 @@ impl LocalEffectCreate {
         match self {
             LocalEffectCreate::DmgDone { .. } => true,
             LocalEffectCreate::Heal { .. } => true,
+            LocalEffectCreate::ShieldHit { .. } => true,
             _ => false,
         }
     }
@@ -172,6 +181,13 @@ pub enum LocalEffect {
         last_tick: u64,
         position: Vec2f64,
     },
+    // damage absorbed by the shield, as opposed to DmgDone to the hull
+    ShieldHit {
+        hp: i32,
+        key: String,
+        last_tick: u64,
+        position: Vec2f64,
+    },
 }
 
 impl LocalEffect {
@@ -181,6 +197,7 @@ impl LocalEffect {
             LocalEffect::DmgDone { key, .. } => Some(&key),
             LocalEffect::Heal { key, .. } => Some(&key),
             LocalEffect::PickUp { key, .. } => Some(&key),
+            LocalEffect::ShieldHit { key, .. } => Some(&key),
         }
     }
 
@@ -190,6 +207,7 @@ impl LocalEffect {
             LocalEffect::DmgDone { last_tick, .. } => Some(*last_tick),
             LocalEffect::Heal { last_tick, .. } => Some(*last_tick),
             LocalEffect::PickUp { last_tick, .. } => Some(*last_tick),
+            LocalEffect::ShieldHit { last_tick, .. } => Some(*last_tick),
         }
     }
 }
@@ -222,6 +240,18 @@ impl LocalEffect {
                 *last_tick = current_tick;
                 *position = new_pos;
             }
+            LocalEffect::ShieldHit {
+                hp,
+                last_tick,
+                position,
+                ..
+            } => {
+                extract!(eff, LocalEffectCreate::ShieldHit { hp: new_hp } => {
+                    *hp += new_hp;
+                });
+                *last_tick = current_tick;
+                *position = new_pos;
+            }
             // Cannot be updated
             _ => {}
         }
@@ -0,0 +1,247 @@
+use crate::abilities::Ability;
+use crate::combat;
+use crate::indexing::find_my_ship_mut;
+use crate::inventory::{add_item, InventoryItem, InventoryItemType};
+use crate::prng_id;
+use crate::ship_classes::{get_ship_class, ShipClass, MAX_SHIP_TURRETS, PLAYER_SHIP_CLASS};
+use crate::spatial_movement::Movement;
+use crate::world::{GameState, Ship};
+use rand_pcg::Pcg64Mcg;
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+use typescript_definitions::{TypeScriptify, TypescriptDefinition};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+pub const HULL_PLATING_HEALTH: f64 = 25.0;
+// shares of the class values, added per fitted module
+pub const ENGINE_UPGRADE_SPEED_BONUS: f64 = 0.1;
+pub const ENGINE_UPGRADE_ACC_BONUS: f64 = 0.2;
+pub const TRACTOR_AMPLIFIER_RANGE: f64 = 15.0;
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    TypescriptDefinition,
+    TypeScriptify,
+)]
+pub enum ShipModule {
+    ExtraTurret,
+    HullPlating,
+    EngineUpgrade,
+    TractorAmplifier,
+}
+
+impl ShipModule {
+    pub fn from_item_type(iit: &InventoryItemType) -> Option<ShipModule> {
+        match iit {
+            InventoryItemType::TurretModule => Some(ShipModule::ExtraTurret),
+            InventoryItemType::HullPlatingModule => Some(ShipModule::HullPlating),
+            InventoryItemType::EngineModule => Some(ShipModule::EngineUpgrade),
+            InventoryItemType::TractorModule => Some(ShipModule::TractorAmplifier),
+            _ => None,
+        }
+    }
+
+    pub fn to_item_type(&self) -> InventoryItemType {
+        match self {
+            ShipModule::ExtraTurret => InventoryItemType::TurretModule,
+            ShipModule::HullPlating => InventoryItemType::HullPlatingModule,
+            ShipModule::EngineUpgrade => InventoryItemType::EngineModule,
+            ShipModule::TractorAmplifier => InventoryItemType::TractorModule,
+        }
+    }
+}
+
+#[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
+#[serde(tag = "tag")]
+pub enum FittingAction {
+    Unknown,
+    // takes one module from the inventory stack
+    Fit { item_id: Uuid },
+    // puts one fitted module of that kind back to the inventory
+    Unfit { module: ShipModule },
+}
+
+pub fn count_modules(ship: &Ship, module: &ShipModule) -> usize {
+    ship.modules.iter().filter(|m| *m == module).count()
+}
+
+// ships that were created without a class, like in some old tests, are treated as the player ones
+fn get_base_class(ship: &Ship) -> Option<&'static ShipClass> {
+    ship.class_id
+        .as_ref()
+        .map_or(get_ship_class(PLAYER_SHIP_CLASS), |id| get_ship_class(id))
+}
+
+pub fn can_fit_module(ship: &Ship, module: &ShipModule) -> Result<(), String> {
+    let class = get_base_class(ship).ok_or_else(|| "ship class is not defined".to_string())?;
+    if ship.modules.len() >= class.module_slots {
+        return Err(format!(
+            "all {} module slots are already taken",
+            class.module_slots
+        ));
+    }
+    if *module == ShipModule::ExtraTurret
+        && class.turret_count + count_modules(ship, module) >= MAX_SHIP_TURRETS
+    {
+        return Err(format!(
+            "no more than {} turrets are allowed",
+            MAX_SHIP_TURRETS
+        ));
+    }
+    Ok(())
+}
+
+pub fn apply_fitting_action(
+    state: &mut GameState,
+    player_id: Uuid,
+    action: FittingAction,
+    prng: &mut Pcg64Mcg,
+) {
+    let ship = find_my_ship_mut(state, player_id);
+    if ship.is_none() {
+        return;
+    }
+    let ship = ship.unwrap();
+    if ship.docked_at.is_none() {
+        warn!(format!(
+            "fitting {:?} by {} is only possible while docked",
+            action, player_id
+        ));
+        return;
+    }
+    match action {
+        FittingAction::Unknown => {}
+        FittingAction::Fit { item_id } => {
+            let item_pos = ship.inventory.iter().position(|i| i.id == item_id);
+            let module = item_pos.and_then(|pos| {
+                ShipModule::from_item_type(&ship.inventory[pos].item_type).map(|m| (pos, m))
+            });
+            if let Some((pos, module)) = module {
+                if let Err(err) = can_fit_module(ship, &module) {
+                    log!(format!("cannot fit {:?} by {}: {}", module, player_id, err));
+                    return;
+                }
+                ship.inventory[pos].quantity -= 1;
+                if ship.inventory[pos].quantity <= 0 {
+                    ship.inventory.remove(pos);
+                }
+                ship.modules.push(module);
+                apply_ship_modules(ship, prng);
+            } else {
+                warn!(format!(
+                    "invalid fit of item {} by {}, no module found in player inventory",
+                    item_id, player_id
+                ));
+            }
+        }
+        FittingAction::Unfit { module } => {
+            if let Some(pos) = ship.modules.iter().position(|m| *m == module) {
+                ship.modules.remove(pos);
+                add_item(
+                    &mut ship.inventory,
+                    InventoryItem::new(module.to_item_type(), 1, prng_id(prng)),
+                );
+                apply_ship_modules(ship, prng);
+            } else {
+                warn!(format!(
+                    "invalid unfit of {:?} by {}, no such module fitted",
+                    module, player_id
+                ));
+            }
+        }
+    }
+}
+
+// existing turrets keep their cooldowns, so only the added ones come from the generator.
+// the launchers always take the ids after the last gun, so they are matched by their order and re-numbered instead
+fn keep_turret_ability(existing: &[Ability], generated: &Ability, gun_count: usize) -> Ability {
+    match generated {
+        Ability::Shoot { turret_id, .. } => existing
+            .iter()
+            .find(|a| matches!(a, Ability::Shoot { turret_id: id, .. } if id == turret_id))
+            .cloned()
+            .unwrap_or_else(|| generated.clone()),
+        Ability::Launch {
+            turret_id,
+            projectile_template_id,
+            ..
+        } => existing
+            .iter()
+            .filter(|a| matches!(a, Ability::Launch { .. }))
+            .nth((*turret_id as usize).saturating_sub(gun_count))
+            .filter(|a| match a {
+                Ability::Launch {
+                    projectile_template_id: kept,
+                    ..
+                } => kept == projectile_template_id,
+                _ => false,
+            })
+            .map(|a| {
+                let mut kept = a.clone();
+                if let Ability::Launch { turret_id: id, .. } = &mut kept {
+                    *id = *turret_id;
+                }
+                kept
+            })
+            .unwrap_or_else(|| generated.clone()),
+        _ => generated.clone(),
+    }
+}
+
+// recalculates everything that modules affect from the ship class, so fitting and unfitting are symmetrical
+pub fn apply_ship_modules(ship: &mut Ship, prng: &mut Pcg64Mcg) {
+    let class = get_base_class(ship);
+    if class.is_none() {
+        return;
+    }
+    let class = class.unwrap();
+
+    let turret_count =
+        (class.turret_count + count_modules(ship, &ShipModule::ExtraTurret)).min(MAX_SHIP_TURRETS);
+    let turrets = combat::gen_turrets(turret_count, &class.launchers, prng);
+    let mut abilities: Vec<Ability> = turrets
+        .iter()
+        .map(|(a, _t)| keep_turret_ability(&ship.abilities, a, turret_count))
+        .collect();
+    abilities.extend(
+        ship.abilities
+            .iter()
+            .filter(|a| !matches!(a, Ability::Shoot { .. } | Ability::Launch { .. }))
+            .cloned(),
+    );
+    ship.abilities = abilities;
+    ship.turrets = turrets.into_iter().map(|(_a, t)| t).collect();
+
+    let max_health =
+        class.health + HULL_PLATING_HEALTH * count_modules(ship, &ShipModule::HullPlating) as f64;
+    let added_health = max_health - ship.health.max;
+    ship.health.max = max_health;
+    // new plating comes intact, while removing it cannot destroy the ship
+    if added_health > 0.0 {
+        ship.health.current += added_health;
+    }
+    ship.health.current = ship.health.current.min(max_health);
+
+    let engines = count_modules(ship, &ShipModule::EngineUpgrade) as f64;
+    let mut movement = class.movement.clone();
+    movement.max_linear_speed *= 1.0 + ENGINE_UPGRADE_SPEED_BONUS * engines;
+    movement.acc_linear *= 1.0 + ENGINE_UPGRADE_ACC_BONUS * engines;
+    let (accelerated, legacy) = (movement.to_movement(), movement.to_legacy_movement());
+    ship.movement_definition = match ship.movement_definition {
+        Movement::ShipMonotonous { .. } => legacy.clone(),
+        _ => accelerated.clone(),
+    };
+    for ability in ship.abilities.iter_mut() {
+        if let Ability::ToggleMovement { movements, .. } = ability {
+            *movements = vec![accelerated.clone(), legacy.clone()];
+        }
+    }
+}
@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod fitting_test {
+    use crate::abilities::Ability;
+    use crate::fitting::{
+        apply_fitting_action, FittingAction, ShipModule, HULL_PLATING_HEALTH,
+        TRACTOR_AMPLIFIER_RANGE,
+    };
+    use crate::indexing::{find_my_player_mut, find_my_ship_mut};
+    use crate::inventory::{InventoryItem, InventoryItemType};
+    use crate::market::{attempt_trade, TradeAction};
+    use crate::ship_classes::{get_ship_class, PLAYER_SHIP_CLASS};
+    use crate::spatial_movement::Movement;
+    use crate::test_helpers::{add_player_ship_in_location, new_state};
+    use crate::tractoring::{get_tractor_range, MAX_TRACTOR_DIST};
+    use crate::world::{GameMode, GameState};
+    use crate::{new_id, seed_prng};
+    use rand_pcg::Pcg64Mcg;
+    use uuid::Uuid;
+
+    fn docked_player(prng: &mut Pcg64Mcg) -> (GameState, Uuid, Uuid) {
+        let mut state = new_state(&GameMode::CargoRush, "fitting");
+        let (player_id, _ship_id) = add_player_ship_in_location(&mut state, 0, prng);
+        let planet_id = state.locations[0].planets[0].id;
+        find_my_ship_mut(&mut state, player_id).unwrap().docked_at = Some(planet_id);
+        (state, player_id, planet_id)
+    }
+
+    fn buy_module(
+        state: &mut GameState,
+        player_id: Uuid,
+        planet_id: Uuid,
+        iit: InventoryItemType,
+        prng: &mut Pcg64Mcg,
+    ) -> Uuid {
+        let market = state.market.as_mut().unwrap();
+        market.wares.insert(
+            planet_id,
+            vec![InventoryItem::new(iit.clone(), 1, new_id())],
+        );
+        find_my_player_mut(state, player_id).unwrap().money = 100000;
+        attempt_trade(
+            state,
+            player_id,
+            TradeAction {
+                planet_id,
+                sells_to_planet: vec![],
+                buys_from_planet: vec![(iit.clone(), 1)],
+            },
+            prng,
+        );
+        find_my_ship_mut(state, player_id)
+            .unwrap()
+            .inventory
+            .iter()
+            .find(|i| i.item_type == iit)
+            .expect("module was not bought")
+            .id
+    }
+
+    fn fit(state: &mut GameState, player_id: Uuid, item_id: Uuid, prng: &mut Pcg64Mcg) {
+        apply_fitting_action(state, player_id, FittingAction::Fit { item_id }, prng);
+    }
+
+    fn max_linear_speed(movement: &Movement) -> f64 {
+        match movement {
+            Movement::ShipAccelerated {
+                max_linear_speed, ..
+            } => *max_linear_speed,
+            _ => panic!("ship movement is not accelerated"),
+        }
+    }
+
+    #[test]
+    pub fn can_buy_and_fit_modules() {
+        let mut prng = seed_prng("fitting".to_string());
+        let (mut state, player_id, planet_id) = docked_player(&mut prng);
+        let class = get_ship_class(PLAYER_SHIP_CLASS).unwrap();
+        let base_speed = max_linear_speed(
+            &find_my_ship_mut(&mut state, player_id)
+                .unwrap()
+                .movement_definition,
+        );
+
+        for iit in vec![
+            InventoryItemType::TurretModule,
+            InventoryItemType::HullPlatingModule,
+            InventoryItemType::EngineModule,
+            InventoryItemType::TractorModule,
+        ] {
+            let item_id = buy_module(&mut state, player_id, planet_id, iit, &mut prng);
+            fit(&mut state, player_id, item_id, &mut prng);
+        }
+
+        let ship = find_my_ship_mut(&mut state, player_id).unwrap();
+        assert_eq!(ship.modules.len(), 4);
+        assert_eq!(ship.inventory.len(), 0);
+        assert_eq!(ship.turrets.len(), class.turret_count + 2);
+        assert_eq!(ship.health.max, class.health + HULL_PLATING_HEALTH);
+        assert_eq!(ship.health.current, ship.health.max);
+        assert!(max_linear_speed(&ship.movement_definition) > base_speed);
+        assert_eq!(
+            get_tractor_range(ship),
+            MAX_TRACTOR_DIST + TRACTOR_AMPLIFIER_RANGE
+        );
+
+        for module in vec![
+            ShipModule::ExtraTurret,
+            ShipModule::HullPlating,
+            ShipModule::EngineUpgrade,
+            ShipModule::TractorAmplifier,
+        ] {
+            apply_fitting_action(
+                &mut state,
+                player_id,
+                FittingAction::Unfit { module },
+                &mut prng,
+            );
+        }
+        let ship = find_my_ship_mut(&mut state, player_id).unwrap();
+        assert_eq!(ship.modules.len(), 0);
+        assert_eq!(ship.inventory.len(), 4);
+        assert_eq!(ship.turrets.len(), class.turret_count + 1);
+        assert_eq!(ship.health.max, class.health);
+        assert_eq!(max_linear_speed(&ship.movement_definition), base_speed);
+        assert_eq!(get_tractor_range(ship), MAX_TRACTOR_DIST);
+    }
+
+    #[test]
+    pub fn cannot_fit_undocked_or_over_slots() {
+        let mut prng = seed_prng("fitting limits".to_string());
+        let (mut state, player_id, planet_id) = docked_player(&mut prng);
+        let slots = get_ship_class(PLAYER_SHIP_CLASS).unwrap().module_slots;
+
+        let item_id = buy_module(
+            &mut state,
+            player_id,
+            planet_id,
+            InventoryItemType::HullPlatingModule,
+            &mut prng,
+        );
+        find_my_ship_mut(&mut state, player_id).unwrap().docked_at = None;
+        fit(&mut state, player_id, item_id, &mut prng);
+        assert_eq!(
+            find_my_ship_mut(&mut state, player_id)
+                .unwrap()
+                .modules
+                .len(),
+            0
+        );
+
+        find_my_ship_mut(&mut state, player_id).unwrap().docked_at = Some(planet_id);
+        for _i in 0..slots + 1 {
+            let item_id = buy_module(
+                &mut state,
+                player_id,
+                planet_id,
+                InventoryItemType::HullPlatingModule,
+                &mut prng,
+            );
+            fit(&mut state, player_id, item_id, &mut prng);
+        }
+        let ship = find_my_ship_mut(&mut state, player_id).unwrap();
+        assert_eq!(ship.modules.len(), slots);
+        assert_eq!(ship.inventory[0].quantity, 2);
+    }
+
+    fn turret_cooldowns(state: &mut GameState, player_id: Uuid) -> Vec<i32> {
+        find_my_ship_mut(state, player_id)
+            .unwrap()
+            .abilities
+            .iter()
+            .filter(|a| matches!(a, Ability::Shoot { .. } | Ability::Launch { .. }))
+            .map(|a| a.get_current_cooldown())
+            .collect()
+    }
+
+    #[test]
+    pub fn fitting_keeps_turret_cooldowns() {
+        let mut prng = seed_prng("fitting cooldowns".to_string());
+        let (mut state, player_id, planet_id) = docked_player(&mut prng);
+        for ability in find_my_ship_mut(&mut state, player_id)
+            .unwrap()
+            .abilities
+            .iter_mut()
+        {
+            if matches!(ability, Ability::Shoot { .. } | Ability::Launch { .. }) {
+                ability.set_current_cooldown(10);
+            }
+        }
+        let before = turret_cooldowns(&mut state, player_id);
+
+        let item_id = buy_module(
+            &mut state,
+            player_id,
+            planet_id,
+            InventoryItemType::TurretModule,
+            &mut prng,
+        );
+        fit(&mut state, player_id, item_id, &mut prng);
+        let fitted = turret_cooldowns(&mut state, player_id);
+        assert_eq!(fitted.len(), before.len() + 1);
+        // the new gun is ready, the rest, including the launcher, keep reloading
+        assert_eq!(fitted.iter().filter(|c| **c == 10).count(), before.len());
+        assert_eq!(fitted.iter().filter(|c| **c == 0).count(), 1);
+
+        let item_id = buy_module(
+            &mut state,
+            player_id,
+            planet_id,
+            InventoryItemType::HullPlatingModule,
+            &mut prng,
+        );
+        fit(&mut state, player_id, item_id, &mut prng);
+        assert_eq!(turret_cooldowns(&mut state, player_id), fitted);
+
+        apply_fitting_action(
+            &mut state,
+            player_id,
+            FittingAction::Unfit {
+                module: ShipModule::ExtraTurret,
+            },
+            &mut prng,
+        );
+        assert_eq!(turret_cooldowns(&mut state, player_id), before);
+    }
+}
@@ -1,9 +1,9 @@
-use crate::combat::Projectile;
 use crate::indexing::{find_owning_player, ObjectIndexSpecifier, ObjectSpecifier};
-use crate::world::Location;
+use crate::world::{Location, Ship, ShipIdx, SpatialIndexes};
 use crate::{pirate_defence, GameMode, GameState};
 use serde_derive::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use typescript_definitions::*;
 use uuid::Uuid;
@@ -96,15 +96,50 @@ fn find_fof_overrides<'a, 'b>(
                 state.locations[loc_idx].ships[*idx].fof_overrides.as_ref()
             }
             ObjectIndexSpecifier::Projectile { idx } => {
-                match &state.locations[loc_idx].projectiles[*idx] {
-                    Projectile::Rocket(rocket) => rocket.fof_overrides.as_ref(),
-                }
+                state.locations[loc_idx].projectiles[*idx].get_fof_overrides()
             }
             _ => None,
         },
     }
 }
 
+// for the ship's own weapons and movement, so only the ships are considered
+pub fn find_closest_foe<'a>(
+    state: &'a GameState,
+    ship_idx: &ShipIdx,
+    spatial_indexes: &SpatialIndexes,
+    radius: f64,
+) -> Option<&'a Ship> {
+    let loc = &state.locations[ship_idx.location_idx];
+    let ship = &loc.ships[ship_idx.ship_idx];
+    let loc_sp_idx = spatial_indexes.values.get(&ship_idx.location_idx)?;
+    let me = FofActor::ObjectIdx {
+        spec: ObjectIndexSpecifier::Ship {
+            idx: ship_idx.ship_idx,
+        },
+    };
+    loc_sp_idx
+        .rad_search(&ship.as_vec(), radius)
+        .iter()
+        .filter_map(|spec| match spec {
+            ObjectIndexSpecifier::Ship { idx } => {
+                if friend_or_foe_idx(state, me.clone(), spec, ship_idx.location_idx)
+                    == FriendOrFoe::Foe
+                {
+                    loc.ships.get(*idx)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .min_by(|a, b| {
+            let dist_a = a.as_vec().euclidean_distance(&ship.as_vec());
+            let dist_b = b.as_vec().euclidean_distance(&ship.as_vec());
+            dist_a.partial_cmp(&dist_b).unwrap_or(Ordering::Equal)
+        })
+}
+
 pub fn resolve_player_id(actor: &FofActor, state: &GameState, loc_idx: usize) -> Option<Uuid> {
     match actor {
         FofActor::Player { id } => Some(*id),
@@ -1,5 +1,7 @@
 use crate::autofocus::{object_index_into_object_id, SpatialIndex};
-use crate::combat::{create_explosion, damage_objects, heal_objects, ExplosionProps, Health};
+use crate::combat::{
+    create_explosion, damage_objects, heal_objects, DamageType, ExplosionProps, Health,
+};
 use crate::indexing::{
     index_players_by_ship_id, GameStateIndexes, ObjectIndexSpecifier, ObjectSpecifier,
 };
@@ -8,16 +10,15 @@ use crate::world::{
     GameState, Location, ProcessProps, SpatialProps, Wreck, PLANET_HEALTH_REGEN_PER_TICK,
 };
 use crate::world_events::GameEvent;
-use crate::{indexing, prng_id, world_events};
+use crate::{combat_log, indexing, prng_id, world_events};
 use rand_pcg::Pcg64Mcg;
 
-pub const SHIP_REGEN_PER_SEC: f64 = 5.0;
 const STAR_INSIDE_DAMAGE_PER_SEC: f64 = 50.0;
 const STAR_DAMAGE_PER_SEC_NEAR: f64 = 25.0;
 const STAR_DAMAGE_PER_SEC_FAR: f64 = 7.5;
 const STAR_INSIDE_RADIUS: f64 = 0.5;
 const STAR_CLOSE_RADIUS: f64 = 0.68;
-const STAR_FAR_RADIUS: f64 = 1.1;
+pub const STAR_FAR_RADIUS: f64 = 1.1;
 const DMG_EFFECT_MIN: f64 = 5.0;
 const HEAL_EFFECT_MIN: f64 = 5.0;
 
@@ -27,7 +28,6 @@ pub fn update_hp_effects(
     elapsed_micro: i64,
     prng: &mut Pcg64Mcg,
     _client: bool,
-    _extra_damages: Vec<(ObjectSpecifier, f64)>,
     _spatial_index: &mut SpatialIndex,
     indexes: &mut GameStateIndexes,
 ) {
@@ -105,15 +105,17 @@ pub fn update_hp_effects(
                 star_id,
             ) {
                 // that's kind of stupid to pass array of 1 item in a loop, but because of coupling with heal which doesn't yet have the effect...
-                damage_objects(
+                let log_entries = damage_objects(
                     &mut state.locations[loc_idx],
                     &vec![id],
                     change.2 as f64,
+                    &DamageType::Thermal,
                     &ObjectSpecifier::Star { id: star_id },
                     indexes,
                     state.ticks,
                     prng,
                 );
+                combat_log::record_damage(state, log_entries);
             }
         } else {
             // ship self-regen here
@@ -132,6 +134,12 @@ pub fn update_hp_effects(
         }
     }
 
+    for ship in state.locations[loc_idx].ships.iter_mut() {
+        if let Some(shield) = ship.health.shield.as_mut() {
+            shield.update(elapsed_micro);
+        }
+    }
+
     let mut ship_death_effects = vec![];
     for ship in state.locations[loc_idx].ships.iter_mut() {
         if ship.health.current <= 0.0 {
@@ -168,6 +176,14 @@ pub fn update_hp_effects(
             indexes,
             loc_idx,
         );
+        let kill = combat_log::credit_kill(state, &ship_clone, pid);
+        combat_log::record_kill(state, kill.clone());
+        let kill_event = GameEvent::ShipKilled {
+            state_id,
+            ship: ship_clone.clone(),
+            text_representation: combat_log::get_kill_text(state, &kill),
+            kill,
+        };
         let event =
             if let Some(player) = pid.and_then(|pid| indexing::find_my_player_mut(state, pid)) {
                 player.ship_id = None;
@@ -186,6 +202,7 @@ pub fn update_hp_effects(
                 }
             };
         world_events::fire_saved_event(state, event);
+        world_events::fire_saved_event(state, kill_event);
     }
 
     for planet in state.locations[loc_idx].planets.iter_mut() {
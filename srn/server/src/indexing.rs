@@ -732,21 +732,20 @@ pub fn find_player_location_idx(state: &GameState, player_id: Uuid) -> Option<i3
         return None;
     }
     let ship_id = player.ship_id.unwrap();
-    let mut idx = -1;
-    let mut found = false;
-    for loc in state.locations.iter() {
-        idx += 1;
-        for ship in loc.ships.iter() {
-            if ship.id == ship_id {
-                found = true;
-                break;
+    // a ship that has just jumped stays in the old location until cleanup, marked as to_clean,
+    // so such copy is only used if there is no other one (e.g. when the ship is dead)
+    let mut cleaned_idx = None;
+    for (idx, loc) in state.locations.iter().enumerate() {
+        if let Some(ship) = loc.ships.iter().find(|s| s.id == ship_id) {
+            if !ship.to_clean {
+                return Some(idx as i32);
+            }
+            if cleaned_idx.is_none() {
+                cleaned_idx = Some(idx as i32);
             }
-        }
-        if found {
-            break;
         }
     }
-    return if !found { None } else { Some(idx) };
+    return cleaned_idx;
 }
 
 pub fn build_full_spatial_indexes(state: &GameState) -> SpatialIndexes {
@@ -786,16 +785,16 @@ pub fn find_owning_player(
     loc_idx: usize,
     spec: &ObjectIndexSpecifier,
 ) -> Option<Uuid> {
-    match spec {
-        ObjectIndexSpecifier::Ship { idx } => {
-            let ship_id = state.locations[loc_idx].ships[*idx].id;
-            let player = state
-                .players
-                .iter()
-                .find(|p| p.ship_id.map_or(false, |sid| sid == ship_id))
-                .map(|p| p.id);
-            return player;
+    let ship_id = match spec {
+        ObjectIndexSpecifier::Ship { idx } => Some(state.locations[loc_idx].ships[*idx].id),
+        ObjectIndexSpecifier::Projectile { idx } => {
+            state.locations[loc_idx].projectiles[*idx].get_launched_by()
         }
         _ => None,
-    }
+    }?;
+    state
+        .players
+        .iter()
+        .find(|p| p.ship_id.map_or(false, |sid| sid == ship_id))
+        .map(|p| p.id)
 }
@@ -14,8 +14,8 @@ use crate::tractoring::{IMovable, IMovableType};
 use crate::world::{Container, NatSpawnMineral, Rarity};
 use crate::{new_id, prng_id};
 use itertools::Itertools;
-use wasm_bindgen::convert::{FromWasmAbi, WasmAbi};
 use serde_with::skip_serializing_none;
+use wasm_bindgen::convert::{FromWasmAbi, WasmAbi};
 
 #[derive(
     Serialize,
@@ -38,6 +38,11 @@ pub enum InventoryItemType {
     Food,
     Medicament,
     HandWeapon,
+    // see fitting::ShipModule
+    TurretModule,
+    HullPlatingModule,
+    EngineModule,
+    TractorModule,
 }
 
 #[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
@@ -65,6 +70,10 @@ pub fn inventory_item_type_to_stackable(iit: &InventoryItemType) -> bool {
         InventoryItemType::Food => true,
         InventoryItemType::Medicament => true,
         InventoryItemType::HandWeapon => true,
+        InventoryItemType::TurretModule => true,
+        InventoryItemType::HullPlatingModule => true,
+        InventoryItemType::EngineModule => true,
+        InventoryItemType::TractorModule => true,
     }
 }
 
@@ -91,6 +100,10 @@ pub fn get_iit_display_name(iit: InventoryItemType) -> String {
         InventoryItemType::Food => "Food".to_string(),
         InventoryItemType::Medicament => "Medicament".to_string(),
         InventoryItemType::HandWeapon => "Hand weapon".to_string(),
+        InventoryItemType::TurretModule => "Turret module".to_string(),
+        InventoryItemType::HullPlatingModule => "Hull plating".to_string(),
+        InventoryItemType::EngineModule => "Engine upgrade".to_string(),
+        InventoryItemType::TractorModule => "Tractor amplifier".to_string(),
     };
 }
 
@@ -1,24 +1,172 @@
 use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use typescript_definitions::{TypeScriptify, TypescriptDefinition};
 use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
 use crate::indexing::{find_and_extract_ship, find_my_ship, find_player_ship_index};
 use crate::new_id;
-use crate::world::{GameState, Ship};
+use crate::trajectory::TrajectoryResult;
+use crate::vec2::Vec2f64;
+use crate::world::{GameState, Location, Ship};
 
 #[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
 pub struct LocationChangePayload {
     pub id: Uuid,
 }
 
+#[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
+pub struct GalaxyMapLocation {
+    pub id: Uuid,
+    pub star_name: Option<String>,
+    pub position: Vec2f64,
+    pub adjacent_location_ids: Vec<Uuid>,
+    pub planet_ids: Vec<Uuid>,
+}
+
+// everything the client needs to plan jumps without having the full state of other locations
+#[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
+pub struct GalaxyMap {
+    pub locations: Vec<GalaxyMapLocation>,
+}
+
+pub fn build_galaxy_map(state: &GameState) -> GalaxyMap {
+    GalaxyMap {
+        locations: state
+            .locations
+            .iter()
+            .map(|l| GalaxyMapLocation {
+                id: l.id,
+                star_name: l.star.as_ref().map(|s| s.name.clone()),
+                position: l.position.clone(),
+                adjacent_location_ids: l.adjacent_location_ids.clone(),
+                planet_ids: l.planets.iter().map(|p| p.id).collect(),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone, PartialEq)]
+pub enum RouteMetric {
+    Jumps,
+    Distance,
+}
+
+#[derive(Serialize, TypescriptDefinition, TypeScriptify, Deserialize, Debug, Clone)]
+pub struct LocationRoute {
+    // from the start location to the target one, both included
+    pub location_ids: Vec<Uuid>,
+    // sum of the distances between positions of the locations in the route
+    pub distance: f64,
+}
+
+impl LocationRoute {
+    pub fn jumps(&self) -> u32 {
+        (self.location_ids.len() - 1) as u32
+    }
+}
+
+// Dijkstra over adjacent_location_ids. Routes with equal jumps are compared by distance and vice versa.
+// The galaxy is small, so there is no need for a heap
+pub fn find_route(
+    state: &GameState,
+    from: Uuid,
+    to: Uuid,
+    metric: &RouteMetric,
+) -> Option<LocationRoute> {
+    let locations_by_id = state
+        .locations
+        .iter()
+        .map(|l| (l.id, l))
+        .collect::<HashMap<_, _>>();
+    if !locations_by_id.contains_key(&from) || !locations_by_id.contains_key(&to) {
+        return None;
+    }
+    let cost = |jumps: u32, distance: f64| match metric {
+        RouteMetric::Jumps => (jumps as f64, distance),
+        RouteMetric::Distance => (distance, jumps as f64),
+    };
+    // id -> (jumps, distance, previous id)
+    let mut best: HashMap<Uuid, (u32, f64, Option<Uuid>)> = HashMap::new();
+    let mut visited = HashSet::new();
+    best.insert(from, (0, 0.0, None));
+    loop {
+        let current = state
+            .locations
+            .iter()
+            .filter(|l| !visited.contains(&l.id))
+            .filter_map(|l| best.get(&l.id).map(|b| (l, cost(b.0, b.1))))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        let current = match current {
+            Some((current, _)) => current,
+            None => return None,
+        };
+        if current.id == to {
+            break;
+        }
+        visited.insert(current.id);
+        let (jumps, distance, _) = best.get(&current.id).unwrap().clone();
+        for adj_id in current.adjacent_location_ids.iter() {
+            if visited.contains(adj_id) {
+                continue;
+            }
+            if let Some(adj) = locations_by_id.get(adj_id) {
+                let candidate = (
+                    jumps + 1,
+                    distance + current.position.euclidean_distance(&adj.position),
+                    Some(current.id),
+                );
+                let is_better = best.get(adj_id).map_or(true, |existing| {
+                    cost(candidate.0, candidate.1) < cost(existing.0, existing.1)
+                });
+                if is_better {
+                    best.insert(*adj_id, candidate);
+                }
+            }
+        }
+    }
+    let mut location_ids = vec![to];
+    let mut current = to;
+    while let Some(prev) = best.get(&current).and_then(|b| b.2) {
+        location_ids.push(prev);
+        current = prev;
+    }
+    location_ids.reverse();
+    Some(LocationRoute {
+        location_ids,
+        distance: best.get(&to).unwrap().1,
+    })
+}
+
+pub const DEPARTURE_POINT_DISTANCE: f64 = 100.0;
+pub const DEPARTURE_POINT_RADIUS: f64 = 10.0;
+
+// jumps along a route start from a point near the star, facing the next location
+pub fn get_departure_point(from: &Location, to: &Location) -> Vec2f64 {
+    let center = from
+        .star
+        .as_ref()
+        .map_or(Vec2f64::zero(), |s| s.spatial.position.clone());
+    let dir = to
+        .position
+        .subtract(&from.position)
+        .normalize()
+        .unwrap_or(Vec2f64 { x: 1.0, y: 0.0 });
+    center.add(&dir.scalar_mul(DEPARTURE_POINT_DISTANCE))
+}
+
 pub fn try_move_player_ship(state: &mut GameState, player_id: Uuid, location_id: Uuid) -> bool {
     if !can_be_moved_player(state, player_id, location_id) {
         return false;
     }
     let ship = find_and_extract_ship(state, player_id);
     let location = state.locations.iter_mut().find(|l| l.id == location_id);
-    return if let (Some(location), Some(ship)) = (location, ship) {
+    return if let (Some(location), Some(mut ship)) = (location, ship) {
+        // all the targets belong to the old location
+        ship.navigate_target = None;
+        ship.dock_target = None;
+        ship.trajectory = vec![];
+        ship.trajectory_v2 = TrajectoryResult::Inaccessible;
         location.ships.push(ship);
         true
     } else {
@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod locations_test {
+    use crate::cargo_rush::{generate_random_quest, get_quest_systems};
+    use crate::indexing::find_my_ship_mut;
+    use crate::indexing::{find_my_player_mut, find_player_location_idx, GameStateCaches};
+    use crate::locations::{build_galaxy_map, find_route, try_move_player_ship, RouteMetric};
+    use crate::long_actions::{
+        try_start_long_action_player_owned, update_auto_jumps, LongAction, LongActionStart,
+    };
+    use crate::net::{get_client_location_id, ServerToClientMessage, XCastStateDiff};
+    use crate::seed_prng;
+    use crate::system_gen::{seed_state, GenStateOpts};
+    use crate::test_helpers::add_player_ship_in_location;
+    use crate::vec2::Vec2f64;
+    use crate::world::{GameMode, GameState};
+    use crate::xcast::XCast;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    fn galaxy(system_count: u32, seed: &str) -> GameState {
+        let opts: GenStateOpts = serde_json::from_value(serde_json::json!({
+            "system_count": system_count,
+            "max_planets_in_system": 3,
+            "max_satellites_for_planet": 0,
+        }))
+        .unwrap();
+        seed_state(
+            &GameMode::CargoRush,
+            seed.to_string(),
+            Some(opts),
+            &mut GameStateCaches::new(),
+        )
+    }
+
+    fn with_player(state: &mut GameState, location_idx: usize) -> Uuid {
+        let mut prng = seed_prng("player".to_string());
+        add_player_ship_in_location(state, location_idx, &mut prng).0
+    }
+
+    #[test]
+    pub fn galaxy_is_always_connected() {
+        for system_count in 2..9 {
+            let state = galaxy(system_count, "connected");
+            let map = build_galaxy_map(&state);
+            assert_eq!(map.locations.len(), system_count as usize);
+            let mut reached = HashSet::new();
+            let mut queue = vec![map.locations[0].id];
+            while let Some(id) = queue.pop() {
+                if !reached.insert(id) {
+                    continue;
+                }
+                let loc = map.locations.iter().find(|l| l.id == id).unwrap();
+                queue.extend(loc.adjacent_location_ids.iter());
+            }
+            assert_eq!(reached.len(), map.locations.len());
+        }
+    }
+
+    #[test]
+    pub fn can_spawn_into_any_location() {
+        let mut state = galaxy(3, "spawn");
+        let player_id = with_player(&mut state, 2);
+        assert_eq!(find_player_location_idx(&state, player_id), Some(2));
+    }
+
+    #[test]
+    pub fn quests_span_systems() {
+        let mut state = galaxy(3, "quests");
+        let player_id = with_player(&mut state, 0);
+        let systems = get_quest_systems(&state);
+        let mut prng = seed_prng("quests".to_string());
+        for _i in 0..20 {
+            let player = find_my_player_mut(&mut state, player_id).unwrap();
+            generate_random_quest(player, &systems, None, &mut prng);
+            let quest = player.quest.clone().unwrap();
+            let system_of = |planet_id: Uuid| {
+                systems
+                    .iter()
+                    .position(|s| s.iter().any(|p| p.id == planet_id))
+            };
+            assert_ne!(system_of(quest.from_id), system_of(quest.to_id));
+        }
+    }
+
+    #[test]
+    pub fn jump_switches_client_location() {
+        let mut state = galaxy(2, "jump");
+        let player_id = with_player(&mut state, 0);
+        let target_id = state.locations[1].id;
+        let last_location_id = get_client_location_id(&state, player_id);
+        assert_eq!(last_location_id, Some(state.locations[0].id));
+
+        assert!(try_move_player_ship(&mut state, player_id, target_id));
+        assert_eq!(find_player_location_idx(&state, player_id), Some(1));
+        assert_eq!(get_client_location_id(&state, player_id), Some(target_id));
+
+        let diff = ServerToClientMessage::XCastStateDiff(XCastStateDiff {
+            state: state.clone(),
+            diffs: vec![],
+            xcast: XCast::Broadcast(state.id),
+        });
+        match diff.clone().rebase_for_client(player_id, Some(target_id)) {
+            ServerToClientMessage::XCastStateDiff(_) => {}
+            _ => panic!("diff in the same location should stay a diff"),
+        }
+        match diff.rebase_for_client(player_id, last_location_id) {
+            ServerToClientMessage::XCastStateChange(state, _) => {
+                assert_eq!(get_client_location_id(&state, player_id), Some(target_id));
+            }
+            _ => panic!("diff after a jump should become the full state"),
+        }
+    }
+
+    // location i gets position positions[i], and the links are bidirectional
+    fn rewire(state: &mut GameState, positions: Vec<(f64, f64)>, links: Vec<(usize, usize)>) {
+        for (i, (x, y)) in positions.into_iter().enumerate() {
+            state.locations[i].position = Vec2f64 { x, y };
+            state.locations[i].adjacent_location_ids = vec![];
+        }
+        for (a, b) in links.into_iter() {
+            let (a_id, b_id) = (state.locations[a].id, state.locations[b].id);
+            state.locations[a].adjacent_location_ids.push(b_id);
+            state.locations[b].adjacent_location_ids.push(a_id);
+        }
+    }
+
+    #[test]
+    pub fn can_find_route_by_jumps_and_by_distance() {
+        let mut state = galaxy(5, "route");
+        rewire(
+            &mut state,
+            vec![
+                (0.0, 0.0),
+                (0.0, 500.0),
+                (33.0, 0.0),
+                (100.0, 0.0),
+                (66.0, 0.0),
+            ],
+            vec![(0, 1), (1, 3), (0, 2), (2, 4), (4, 3)],
+        );
+        let ids = state.locations.iter().map(|l| l.id).collect::<Vec<_>>();
+        let by_jumps = find_route(&state, ids[0], ids[3], &RouteMetric::Jumps).unwrap();
+        assert_eq!(by_jumps.location_ids, vec![ids[0], ids[1], ids[3]]);
+        assert_eq!(by_jumps.jumps(), 2);
+        let by_distance = find_route(&state, ids[0], ids[3], &RouteMetric::Distance).unwrap();
+        assert_eq!(
+            by_distance.location_ids,
+            vec![ids[0], ids[2], ids[4], ids[3]]
+        );
+        assert!((by_distance.distance - 100.0).abs() < 1e-9);
+
+        rewire(&mut state, vec![(0.0, 0.0); 5], vec![(0, 1)]);
+        assert!(find_route(&state, ids[0], ids[3], &RouteMetric::Jumps).is_none());
+    }
+
+    #[test]
+    pub fn auto_jump_goes_through_the_route() {
+        let mut state = galaxy(3, "auto jump");
+        rewire(
+            &mut state,
+            vec![(0.0, 0.0), (100.0, 0.0), (200.0, 0.0)],
+            vec![(0, 1), (1, 2)],
+        );
+        let player_id = with_player(&mut state, 0);
+        let target_id = state.locations[2].id;
+        let mut prng = seed_prng("auto jump".to_string());
+        assert!(try_start_long_action_player_owned(
+            &mut state,
+            player_id,
+            LongActionStart::AutoJump {
+                to: target_id,
+                metric: RouteMetric::Jumps,
+            },
+            &mut prng,
+        ));
+        let mut visited = vec![];
+        for _i in 0..10 {
+            let loc_idx = find_player_location_idx(&state, player_id).unwrap() as usize;
+            visited.push(loc_idx);
+            update_auto_jumps(&mut state, loc_idx, &mut prng);
+            let ship = find_my_ship_mut(&mut state, player_id).unwrap();
+            // instead of the real navigation and the jump timer
+            if let Some(target) = ship.navigate_target {
+                ship.spatial.position = target;
+            }
+            let jump_to = ship.long_actions.iter().find_map(|la| match la {
+                LongAction::TransSystemJump { to, .. } => Some(*to),
+                _ => None,
+            });
+            if let Some(jump_to) = jump_to {
+                ship.long_actions
+                    .retain(|la| !matches!(la, LongAction::TransSystemJump { .. }));
+                assert!(try_move_player_ship(&mut state, player_id, jump_to));
+                for loc in state.locations.iter_mut() {
+                    loc.ships.retain(|s| !s.to_clean);
+                }
+            }
+            let ship = find_my_ship_mut(&mut state, player_id).unwrap();
+            if !ship
+                .long_actions
+                .iter()
+                .any(|la| matches!(la, LongAction::AutoJump { .. }))
+            {
+                break;
+            }
+        }
+        visited.dedup();
+        assert_eq!(visited, vec![0, 1, 2]);
+    }
+}
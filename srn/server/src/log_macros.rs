@@ -0,0 +1,44 @@
+// the server side logging, shared by the server and the simulation binary. the world crate has its own
+// ones that log into the browser console
+
+#[allow(unused_macros)]
+macro_rules! log {
+    ($($t:tt)*) => {
+        (println!("log: {}", ($($t)*).to_string()))
+    }
+}
+
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($t:tt)*) => {
+        (eprintln!("warn: {}", ($($t)*).to_string()))
+    }
+}
+
+#[allow(unused_macros)]
+macro_rules! err {
+    ($($t:tt)*) => {
+        (eprintln!("err: {}", ($($t)*).to_string()))
+    }
+}
+
+#[allow(unused_macros)]
+macro_rules! log2 {
+    ($($arg:tt)*) => {
+        println!("log: {}", format_args!($($arg)*));
+    }
+}
+
+#[allow(unused_macros)]
+macro_rules! warn2 {
+    ($($arg:tt)*) => {
+        println!("warn: {}", format_args!($($arg)*));
+    }
+}
+
+#[allow(unused_macros)]
+macro_rules! err2 {
+    ($($arg:tt)*) => {
+        println!("err: {}", format_args!($($arg)*));
+    }
+}
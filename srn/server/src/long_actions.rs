@@ -11,9 +11,11 @@ use crate::indexing::{
     find_my_player_mut, find_my_ship_mut, find_player_idx_by_ship_id, find_player_ship_index,
     GameStateIndexes, ObjectSpecifier,
 };
+use crate::locations::RouteMetric;
 use crate::planet_movement::IBodyV2;
+use crate::trajectory::TrajectoryResult;
 use crate::vec2::Vec2f64;
-use crate::world::{spawn_ship, GameState, ShipIdx, ShipTemplate, PLAYER_RESPAWN_TIME_MC};
+use crate::world::{spawn_ship, GameState, Ship, ShipIdx, ShipTemplate, PLAYER_RESPAWN_TIME_MC};
 use crate::{abilities, combat, indexing, locations, prng_id, spatial_movement, world};
 
 use rand::prelude::*;
@@ -27,6 +29,11 @@ pub enum LongActionStart {
     TransSystemJump {
         to: Uuid,
     },
+    // travel through several locations, navigating to the departure point in each of them
+    AutoJump {
+        to: Uuid,
+        metric: RouteMetric,
+    },
     Respawn,
     Shoot {
         target: ObjectSpecifier,
@@ -80,6 +87,15 @@ pub enum LongAction {
         micro_left: i32,
         percentage: u32,
     },
+    // not timed, driven by update_auto_jumps until the ship gets to the target location
+    AutoJump {
+        id: Uuid,
+        to: Uuid,
+        metric: RouteMetric,
+        jumps_total: u32,
+        jumps_left: u32,
+        percentage: u32,
+    },
     Shoot {
         id: Uuid,
         target: ObjectSpecifier,
@@ -122,6 +138,14 @@ pub fn erase_details(la: LongAction) -> LongAction {
             micro_left: 0,
             percentage: 0,
         },
+        LongAction::AutoJump { .. } => LongAction::AutoJump {
+            id: Default::default(),
+            to: Default::default(),
+            metric: RouteMetric::Jumps,
+            jumps_total: 0,
+            jumps_left: 0,
+            percentage: 0,
+        },
         LongAction::Shoot { .. } => LongAction::Shoot {
             id: Default::default(),
             target: Default::default(),
@@ -172,6 +196,12 @@ pub fn cancel_all_long_actions_of_type(la: &mut Vec<LongAction>, template: LongA
     mem::swap(la, &mut new_la);
 }
 
+// manual movement takes the control from the auto-jump
+pub fn cancel_auto_jump(ship: &mut Ship) {
+    ship.long_actions
+        .retain(|la| !matches!(la, LongAction::AutoJump { .. }));
+}
+
 pub fn try_start_long_action_ship_only(
     state: &mut GameState,
     ship_idx: &ShipIdx,
@@ -185,6 +215,9 @@ pub fn try_start_long_action_ship_only(
             try_start_shoot(state, target, Some(ship_idx.clone()), turret_id, prng);
             true
         }
+        LongActionStart::Launch { turret_id } => {
+            try_start_launch(state, Some(ship_idx.clone()), turret_id, prng)
+        }
         LongActionStart::DockInternal { to_planet, .. } => {
             try_start_dock(state, to_planet, ship_idx.clone(), prng)
         }
@@ -228,6 +261,31 @@ pub fn try_start_long_action_player_owned(
             });
             revalidate(&mut ship.long_actions);
         }
+        LongActionStart::AutoJump { to, metric } => {
+            let ship_idx = find_player_ship_index(state, player_id);
+            if ship_idx.is_none() {
+                return false;
+            }
+            let ship_idx = ship_idx.unwrap();
+            let from = state.locations[ship_idx.location_idx].id;
+            let route = locations::find_route(state, from, to, &metric);
+            if route.as_ref().map_or(true, |r| r.jumps() == 0) {
+                return false;
+            }
+            let jumps = route.unwrap().jumps();
+            let ship = &mut state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx];
+            // the new route replaces the old one
+            cancel_auto_jump(ship);
+            ship.long_actions.push(LongAction::AutoJump {
+                id: prng_id(prng),
+                to,
+                metric,
+                jumps_total: jumps,
+                jumps_left: jumps,
+                percentage: 0,
+            });
+            revalidate(&mut ship.long_actions);
+        }
         LongActionStart::Respawn => {
             let ship_idx = find_player_ship_index(state, player_id);
             if ship_idx.is_some() {
@@ -413,6 +471,7 @@ fn try_start_shoot(
             Ability::BlowUpOnLand => {}
             Ability::ShootAll => {}
             Ability::ToggleMovement { .. } => {}
+            Ability::PointDefence { .. } => {}
             Ability::Launch { turret_id, .. } => {
                 if *turret_id == shooting_turret_id {
                     ability.set_max_cooldown();
@@ -467,6 +526,7 @@ fn try_start_launch(
             Ability::BlowUpOnLand => {}
             Ability::ShootAll => {}
             Ability::ToggleMovement { .. } => {}
+            Ability::PointDefence { .. } => {}
             Ability::Launch { turret_id, .. } => {
                 if *turret_id == shooting_turret_id {
                     ability.set_max_cooldown();
@@ -482,6 +542,7 @@ fn try_start_launch(
 // for multiple - unique by some criteria, e.g. turret_id
 fn revalidate(long_actions: &mut Vec<LongAction>) {
     let mut has_jump = false;
+    let mut has_auto_jump = false;
     let mut has_dock = false;
     let mut has_undock = false;
     let mut active_turret_ids: HashSet<i32> = HashSet::new();
@@ -497,6 +558,13 @@ fn revalidate(long_actions: &mut Vec<LongAction>) {
                 has_jump = true;
                 return true;
             }
+            LongAction::AutoJump { .. } => {
+                if has_auto_jump {
+                    return false;
+                }
+                has_auto_jump = true;
+                return true;
+            }
             LongAction::Shoot { turret_id, .. } => {
                 if active_turret_ids.contains(turret_id) {
                     return false;
@@ -573,12 +641,15 @@ pub fn finish_long_act(
                 locations::try_move_player_ship(state, player_id.unwrap(), to);
             }
         }
+        LongAction::AutoJump { .. } => {
+            // finished by update_auto_jumps
+        }
         LongAction::Shoot {
             target, turret_id, ..
         } => {
-            if player_id.is_some() {
-                combat::resolve_shoot(state, player_id.unwrap(), target, turret_id, indexes, prng);
-            }
+            // npc ships shoot too, so it's resolved by the ship rather than by the player
+            let ship_id = state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx].id;
+            combat::resolve_shoot(state, ship_id, target, turret_id, indexes, prng);
         }
         LongAction::Dock { to_planet, .. } => {
             let planet = indexing::find_planet(state, &to_planet).map(|p| p.clone());
@@ -595,9 +666,8 @@ pub fn finish_long_act(
             spatial_movement::undock_ship(state, ship_idx, client, player, prng);
         }
         LongAction::Launch { turret_id, .. } => {
-            if player_id.is_some() {
-                combat::resolve_launch(state, player_id.unwrap(), turret_id, client, prng);
-            }
+            let ship_id = state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx].id;
+            combat::resolve_launch(state, ship_id, turret_id, client, prng);
         }
     }
 }
@@ -639,6 +709,7 @@ pub fn tick_long_act(act: LongAction, micro_passed: i64) -> (LongAction, bool) {
                 left > 0,
             )
         }
+        LongAction::AutoJump { .. } => (act, true),
         LongAction::Shoot {
             micro_left,
             id,
@@ -742,3 +813,118 @@ pub fn tick_long_act_player(act: LongActionPlayer, micro_passed: i64) -> (LongAc
 fn calc_percentage(left: i32, max: i32) -> u32 {
     (((max as f32 - left as f32) / max as f32).max(0.0) * 100.0) as u32
 }
+
+enum AutoJumpStep {
+    Wait,
+    Stop,
+    Undock,
+    Fly(Vec2f64),
+    Jump(Uuid),
+}
+
+// ships with AutoJump fly to the departure point of the current location and jump to the next one in the route.
+// The route is re-planned on every step, so it stays valid even if the ship was moved by something else
+pub fn update_auto_jumps(state: &mut GameState, loc_idx: usize, prng: &mut Pcg64Mcg) {
+    let mut steps = vec![];
+    let loc = &state.locations[loc_idx];
+    for (ship_idx, ship) in loc.ships.iter().enumerate() {
+        if ship.to_clean {
+            continue;
+        }
+        let auto_jump = ship.long_actions.iter().find_map(|la| match la {
+            LongAction::AutoJump { to, metric, .. } => Some((*to, metric.clone())),
+            _ => None,
+        });
+        let (to, metric) = match auto_jump {
+            Some(auto_jump) => auto_jump,
+            None => continue,
+        };
+        if ship
+            .long_actions
+            .iter()
+            .any(|la| matches!(la, LongAction::TransSystemJump { .. }))
+        {
+            steps.push((ship_idx, AutoJumpStep::Wait, None));
+            continue;
+        }
+        let route = locations::find_route(state, loc.id, to, &metric);
+        let route = match route {
+            Some(route) if route.jumps() > 0 => route,
+            _ => {
+                steps.push((ship_idx, AutoJumpStep::Stop, None));
+                continue;
+            }
+        };
+        let next_id = route.location_ids[1];
+        let step = if ship.docked_at.is_some() {
+            AutoJumpStep::Undock
+        } else {
+            let next = state.locations.iter().find(|l| l.id == next_id).unwrap();
+            let departure = locations::get_departure_point(loc, next);
+            if departure.euclidean_distance(&ship.spatial.position)
+                > locations::DEPARTURE_POINT_RADIUS
+            {
+                AutoJumpStep::Fly(departure)
+            } else {
+                AutoJumpStep::Jump(next_id)
+            }
+        };
+        steps.push((ship_idx, step, Some(route.jumps())));
+    }
+
+    for (ship_idx, step, jumps) in steps.into_iter() {
+        let ship_idx = ShipIdx {
+            location_idx: loc_idx,
+            ship_idx,
+        };
+        if let AutoJumpStep::Undock = step {
+            let ship_id = state.locations[loc_idx].ships[ship_idx.ship_idx].id;
+            let player_idx = find_player_idx_by_ship_id(state, ship_id);
+            spatial_movement::undock_ship(state, ship_idx.clone(), false, player_idx, prng);
+        }
+        let ship = &mut state.locations[loc_idx].ships[ship_idx.ship_idx];
+        if let Some(jumps) = jumps {
+            for la in ship.long_actions.iter_mut() {
+                if let LongAction::AutoJump {
+                    jumps_total,
+                    jumps_left,
+                    percentage,
+                    ..
+                } = la
+                {
+                    *jumps_left = jumps;
+                    *jumps_total = (*jumps_total).max(jumps);
+                    *percentage =
+                        ((*jumps_total - jumps) as f32 / *jumps_total as f32 * 100.0) as u32;
+                }
+            }
+        }
+        match step {
+            AutoJumpStep::Wait | AutoJumpStep::Undock => {}
+            AutoJumpStep::Stop => cancel_auto_jump(ship),
+            AutoJumpStep::Fly(departure) => {
+                if ship
+                    .navigate_target
+                    .map_or(true, |t| t.euclidean_distance(&departure) > 0.0)
+                {
+                    ship.dock_target = None;
+                    ship.navigate_target = Some(departure);
+                    ship.trajectory = vec![];
+                    ship.trajectory_v2 = TrajectoryResult::Inaccessible;
+                }
+            }
+            AutoJumpStep::Jump(to) => {
+                ship.navigate_target = None;
+                ship.trajectory = vec![];
+                ship.trajectory_v2 = TrajectoryResult::Inaccessible;
+                ship.long_actions.push(LongAction::TransSystemJump {
+                    id: prng_id(prng),
+                    to,
+                    micro_left: TRANS_SYSTEM_JUMP_TIME,
+                    percentage: 0,
+                });
+                revalidate(&mut ship.long_actions);
+            }
+        }
+    }
+}
@@ -77,47 +77,8 @@ use crate::vec2::Vec2f64;
 use crate::world::{spawn_ship, update_rule_specifics, UpdateOptions, AABB};
 use world_actions::Action;
 
-#[allow(unused_macros)]
-macro_rules! log {
-    ($($t:tt)*) => {
-        (println!("log: {}", ($($t)*).to_string()))
-    }
-}
-
-#[allow(unused_macros)]
-macro_rules! warn {
-    ($($t:tt)*) => {
-        (eprintln!("warn: {}", ($($t)*).to_string()))
-    }
-}
-
-#[allow(unused_macros)]
-macro_rules! err {
-    ($($t:tt)*) => {
-        (eprintln!("err: {}", ($($t)*).to_string()))
-    }
-}
-
-#[allow(unused_macros)]
-macro_rules! log2 {
-    ($($arg:tt)*) => {
-        println!("log: {}", format_args!($($arg)*));
-    }
-}
-
-#[allow(unused_macros)]
-macro_rules! warn2 {
-    ($($arg:tt)*) => {
-        println!("warn: {}", format_args!($($arg)*));
-    }
-}
-
-#[allow(unused_macros)]
-macro_rules! err2 {
-    ($($arg:tt)*) => {
-        println!("err: {}", format_args!($($arg)*));
-    }
-}
+#[macro_use]
+mod log_macros;
 
 macro_rules! cast {
     ($target: expr, $pat: path) => {{
@@ -139,58 +100,50 @@ extern crate websocket;
 #[macro_use]
 extern crate num_derive;
 
-mod abilities;
 mod api;
-mod api_struct;
-mod autofocus;
-mod bots;
-mod cargo_rush;
+#[cfg(feature = "avro")]
+mod avro;
+mod avro_test;
+mod behaviour_tree_test;
 mod chat;
-mod combat;
-mod dialogue;
-mod effects;
-mod fof;
-mod hp;
-mod indexing;
-mod interpolation;
-mod inventory;
+mod combat_log_test;
+mod combat_test;
+mod economy_test;
+mod fitting_test;
 mod inventory_test;
-mod locations;
-mod long_actions;
+mod locations_test;
 mod main_ws_server;
-mod market;
 mod net;
-mod notifications;
-mod perf;
-mod pirate_defence;
-mod planet_movement;
-mod properties;
-mod random_stuff;
-mod replay;
+mod pirate_defence_test;
+mod projectiles_test;
+mod replay_binary_test;
+mod replay_recording;
+mod replay_recording_test;
+mod replay_test;
 mod replays_api;
 mod resources;
 mod resources_api;
 mod rooms_api;
-mod sandbox;
 mod sandbox_api;
-mod self_inspect;
 mod server_events;
-mod spatial_movement;
+mod ship_classes_test;
+mod shutdown;
+mod shutdown_test;
+mod snapshots;
+mod snapshots_test;
 mod states;
-mod substitutions;
-mod system_gen;
+#[cfg(test)]
+mod test_helpers;
 mod tid;
-mod tractoring;
-mod trajectory;
-mod tutorial;
-#[allow(dead_code)]
-mod vec2;
+mod trajectory_test;
 mod vec2_test;
-pub mod world;
-mod world_actions;
-mod world_events;
+mod wire_binary;
+mod wire_binary_test;
+mod world_modules;
 mod xcast;
 
+use world_modules::*;
+
 use properties::*;
 
 struct LastCheck {
@@ -304,8 +257,7 @@ impl Fairing for CORS {
 #[launch]
 fn rocket() -> rocket::Rocket {
     ctrlc::set_handler(move || {
-        println!("\nReceived Ctrl+C, terminating process...");
-        std::process::exit(0);
+        shutdown::on_termination_signal();
     })
     .expect("Error setting Ctrl-C handler");
 
@@ -317,35 +269,45 @@ fn rocket() -> rocket::Rocket {
             d_table.scripts.insert(script.id, script);
         }
     }
+    ship_classes::validate_ship_classes();
+    behaviour_tree::validate_behaviour_trees();
 
-    make_thread("ws_s")
-        .spawn(|| {
-            main_ws_server::websocket_server();
-        })
-        .ok();
-
-    make_thread("ch_s")
-        .spawn(|| {
-            chat_server();
-        })
-        .ok();
-
-    make_thread("main")
-        .spawn(|| {
-            main_thread();
-        })
-        .ok();
-
-    make_thread("disp")
-        .spawn(move || main_ws_server::dispatcher_thread())
-        .ok();
-
-    make_thread("ws_clean")
-        .spawn(|| main_ws_server::cleanup_bad_clients_thread())
-        .ok();
-    make_thread("watch_repl")
-        .spawn(|| replays_api::watch_replay_folder())
-        .ok();
+    if !*snapshots::DISABLE_SNAPSHOTS {
+        // must happen before the main thread starts ticking, so the restored rooms are not treated as new
+        let mut cont = STATE.write().unwrap();
+        snapshots::restore_rooms(&mut cont);
+    }
+
+    shutdown::register_thread(make_thread("ws_s").spawn(|| {
+        main_ws_server::websocket_server();
+    }));
+
+    shutdown::register_thread(make_thread("ch_s").spawn(|| {
+        chat_server();
+    }));
+
+    shutdown::register_thread(make_thread("main").spawn(|| {
+        main_thread();
+    }));
+
+    shutdown::register_thread(
+        make_thread("disp").spawn(move || main_ws_server::dispatcher_thread()),
+    );
+
+    shutdown::register_thread(
+        make_thread("ws_clean").spawn(|| main_ws_server::cleanup_bad_clients_thread()),
+    );
+    shutdown::register_thread(
+        make_thread("watch_repl").spawn(|| replays_api::watch_replay_folder()),
+    );
+    if !*snapshots::DISABLE_SNAPSHOTS {
+        shutdown::register_thread(make_thread("snap").spawn(|| snapshots::snapshots_thread()));
+    }
+    if *replay_recording::ENABLE_REPLAY_RECORDING {
+        shutdown::register_thread(
+            make_thread("repl_rec").spawn(|| replay_recording::replay_recording_thread()),
+        );
+    }
 
     sandbox::init_saved_states();
     rocket::ignite()
@@ -376,6 +338,7 @@ fn rocket() -> rocket::Rocket {
             routes![
                 replays_api::get_saved_replays,
                 replays_api::get_replay_by_id,
+                replays_api::get_replay_binary_by_id,
             ],
         )
         .mount(
@@ -383,12 +346,18 @@ fn rocket() -> rocket::Rocket {
             routes![
                 rooms_api::get_rooms,
                 rooms_api::create_room,
-                rooms_api::get_rooms_for_mode
+                rooms_api::get_rooms_for_mode,
+                rooms_api::get_galaxy_map,
+                rooms_api::get_combat_log,
+                rooms_api::get_route
             ],
         )
         .mount(
             "/api/resources",
-            routes![resources_api::get_dialogue_scripts],
+            routes![
+                resources_api::get_dialogue_scripts,
+                resources_api::get_ship_classes
+            ],
         )
 }
 
@@ -435,11 +404,33 @@ fn main_thread() {
     let mut sampler_consume_elapsed = 0;
     let mut events_elapsed = 0;
     let mut full_broadcast_elapsed: i64 = 0;
+    let mut snapshot_elapsed: i64 = 0;
+    let mut replay_recording_elapsed: i64 = 0;
     let mut frame_count = 0;
     let mut over_budget_frame = 0;
     let mut shortcut_frame = 0;
+    let mut shutdown_announced_at = None;
 
     loop {
+        // checked only here, so the frame that was in progress when the shutdown was requested is always finished
+        if shutdown::is_shutting_down() {
+            let cont = STATE.write().unwrap();
+            let now_ms = Local::now().timestamp_millis();
+            match shutdown_announced_at {
+                None => {
+                    shutdown::announce_shutdown(&cont);
+                    shutdown_announced_at = Some(now_ms);
+                }
+                Some(at) if now_ms - at >= shutdown::SHUTDOWN_WARNING_MS as i64 => {
+                    if !*snapshots::DISABLE_SNAPSHOTS {
+                        snapshots::take_snapshot(&cont);
+                    }
+                    log!("main thread has stopped");
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
         let now = Local::now();
         let elapsed = now - last;
         // log!(format!("iter {}", now));
@@ -579,6 +570,28 @@ fn main_thread() {
             continue;
         }
 
+        if !*snapshots::DISABLE_SNAPSHOTS {
+            if snapshot_elapsed > *snapshots::SNAPSHOT_INTERVAL_TICKS {
+                let snapshot_mark = sampler.start(SamplerMarks::TakeRoomsSnapshot as u32);
+                snapshots::take_snapshot(&cont);
+                sampler.end(snapshot_mark);
+                snapshot_elapsed = 0;
+            } else {
+                snapshot_elapsed += elapsed_micro;
+            }
+        }
+
+        if *replay_recording::ENABLE_REPLAY_RECORDING {
+            if replay_recording_elapsed > *replay_recording::REPLAY_RECORDING_INTERVAL_TICKS {
+                let recording_mark = sampler.start(SamplerMarks::RecordReplayFrames as u32);
+                replay_recording::record_frames(&cont);
+                sampler.end(recording_mark);
+                replay_recording_elapsed = 0;
+            } else {
+                replay_recording_elapsed += elapsed_micro;
+            }
+        }
+
         sampler.end(total_mark);
 
         if sampler.budget < 0 {
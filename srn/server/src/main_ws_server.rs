@@ -25,16 +25,19 @@ use crate::net::{
     ClientOpCode, PersonalizeUpdate, Pong, ServerToClientMessage, ShipsWrapper, SwitchRoomPayload,
     TagConfirm, Wrapper, XCastStateDiff,
 };
+use crate::shutdown::ShutdownStage;
 use crate::states::{get_state_id_cont, select_state, select_state_mut, STATE};
+use crate::wire_binary::{decode_wire_frame, WirePayload, WIRE_BINARY_PROTOCOL};
 use crate::world::{GameState, Player, Ship};
 use crate::world_actions::is_world_update_action;
 use crate::world_actions::Action;
 use crate::world_events::GameEvent;
 use crate::xcast::XCast;
 use crate::{
-    dialogue, indexing, inventory, long_actions, market, notifications, sandbox, states, world,
-    xcast, DialogueRequest, LastCheck, WSRequest, DEFAULT_SLEEP_MS, DIALOGUE_TABLE, MAX_ERRORS,
-    MAX_ERRORS_SAMPLE_INTERVAL, MAX_MESSAGES_PER_INTERVAL, MAX_MESSAGE_SAMPLE_INTERVAL_MS,
+    dialogue, indexing, inventory, long_actions, market, notifications, sandbox, shutdown, states,
+    world, xcast, DialogueRequest, LastCheck, WSRequest, DEFAULT_SLEEP_MS, DIALOGUE_TABLE,
+    MAX_ERRORS, MAX_ERRORS_SAMPLE_INTERVAL, MAX_MESSAGES_PER_INTERVAL,
+    MAX_MESSAGE_SAMPLE_INTERVAL_MS,
 };
 use typescript_definitions::{TypeScriptify, TypescriptDefinition};
 use websocket::sync::Client;
@@ -109,17 +112,37 @@ lazy_static! {
 
 pub fn websocket_server() {
     let addr = "0.0.0.0:2794";
-    let server =
+    let mut server =
         Server::bind(addr).expect(format!("failed to bind to {}, cannot start", addr).as_str());
+    // otherwise accept blocks forever, and there is no way to stop accepting connections on shutdown
+    server
+        .set_nonblocking(true)
+        .expect("failed to make WS server non-blocking, cannot start");
     log!(format!("WS server has launched on {}", addr));
 
-    for request in server.filter_map(Result::ok) {
-        thread::spawn(|| handle_request(request));
+    while !shutdown::is_shutting_down() {
+        match server.accept() {
+            Ok(request) => {
+                thread::spawn(|| handle_request(request));
+            }
+            Err(_) => {
+                thread::sleep(Duration::from_millis(DEFAULT_SLEEP_MS));
+            }
+        }
     }
+    log!("WS server has stopped accepting connections");
 }
 
 fn handle_request(request: WSRequest) {
-    if !request.protocols().contains(&"rust-websocket".to_string()) {
+    // newer clients offer both protocols, and the binary one is preferred
+    let protocol = if request
+        .protocols()
+        .contains(&WIRE_BINARY_PROTOCOL.to_string())
+    {
+        WIRE_BINARY_PROTOCOL
+    } else if request.protocols().contains(&"rust-websocket".to_string()) {
+        "rust-websocket"
+    } else {
         match request.reject() {
             Ok(_) => {}
             Err(_) => {
@@ -127,9 +150,10 @@ fn handle_request(request: WSRequest) {
             }
         }
         return;
-    }
+    };
+    let binary = protocol == WIRE_BINARY_PROTOCOL;
 
-    let accept_result = request.use_protocol("rust-websocket").accept();
+    let accept_result = request.use_protocol(protocol).accept();
     let client = match accept_result {
         Ok(client) => client,
         Err(_) => {
@@ -148,7 +172,10 @@ fn handle_request(request: WSRequest) {
     };
 
     let client_id = Uuid::new_v4();
-    println!("Connection from {}, id={}", ip, client_id);
+    println!(
+        "Connection from {}, id={}, protocol={}",
+        ip, client_id, protocol
+    );
 
     let (public_client_sender, public_client_receiver) = bounded::<ServerToClientMessage>(128);
     CLIENT_SENDERS
@@ -187,6 +214,8 @@ fn handle_request(request: WSRequest) {
         }
     });
 
+    // the location of the last state sent to the client, to know whether diffs can be applied by it
+    let mut last_location_id: Option<Uuid> = None;
     loop {
         if is_disconnected(client_id) {
             break;
@@ -213,12 +242,24 @@ fn handle_request(request: WSRequest) {
                     };
                 }
                 OwnedMessage::Text(msg) => on_client_text_message(client_id, msg),
+                OwnedMessage::Binary(bytes) => on_client_binary_message(client_id, bytes),
                 _ => {}
             }
         }
         // whenever some other function sends a message, we have to put it to socket
         while let Ok(message) = public_client_receiver.try_recv() {
-            on_message_to_send_to_client(client_id, &mut socket_sender, &message)
+            on_message_to_send_to_client(
+                client_id,
+                &mut socket_sender,
+                &message,
+                binary,
+                &mut last_location_id,
+            )
+        }
+        // at this stage the dispatcher has already delivered everything, including the shutdown announcement
+        if shutdown::get_stage() >= ShutdownStage::Closing {
+            on_server_shutdown_close(ip, client_id, &mut socket_sender);
+            return;
         }
         // 1ms spacing between client message processing to not overload server accidentally.
         // this value should be fairly low, as it is a guaranteed ping
@@ -274,6 +315,8 @@ fn on_message_to_send_to_client(
     client_id: Uuid,
     sender: &mut Writer<TcpStream>,
     message: &ServerToClientMessage,
+    binary: bool,
+    last_location_id: &mut Option<Uuid>,
 ) {
     if is_disconnected(client_id) {
         return;
@@ -284,7 +327,18 @@ fn on_message_to_send_to_client(
         xcast::check_message_casting(client_id, &message, current_state_id)
     });
     if should_send {
-        let message = Message::text(message.clone().patch_for_client(client_id).serialize());
+        let message = message
+            .clone()
+            .rebase_for_client(client_id, *last_location_id);
+        if let Some(location_id) = message.get_client_location_id(client_id) {
+            *last_location_id = Some(location_id);
+        }
+        let patched = message.patch_for_client(client_id);
+        let message = if binary {
+            Message::binary(patched.serialize_binary())
+        } else {
+            Message::text(patched.serialize())
+        };
         sender
             .send_message(&message)
             .map_err(|e| {
@@ -338,7 +392,35 @@ fn on_client_text_message(client_id: Uuid, msg: String) {
         return;
     }
     let number = parse_opcode.ok().unwrap();
+    on_client_op_code(client_id, number, second, third);
+}
 
+fn on_client_binary_message(client_id: Uuid, bytes: Vec<u8>) {
+    if check_message_overflow_happened(client_id) {
+        return;
+    }
+    let frame = match decode_wire_frame(&bytes) {
+        Ok(frame) => frame,
+        Err(e) => {
+            eprintln!("Corrupted binary message {:?}", e);
+            return;
+        }
+    };
+    // handlers are shared with the text protocol, so they still get the json
+    let data = match frame.payload {
+        WirePayload::Value(value) => value.to_string(),
+        WirePayload::Empty => "".to_string(),
+        WirePayload::Diffs(_) => {
+            eprintln!("Unexpected diffs from client, opcode {}", frame.opcode);
+            return;
+        }
+    };
+    let data = data.as_str();
+    let tag = frame.tag.as_deref();
+    on_client_op_code(client_id, frame.opcode as u32, &data, tag.as_ref());
+}
+
+fn on_client_op_code(client_id: Uuid, number: u32, second: &&str, third: Option<&&str>) {
     let op_code = FromPrimitive::from_u32(number);
     if op_code.is_none() {
         eprintln!("Unknown opcode {}", number);
@@ -635,6 +717,10 @@ pub fn dispatcher_thread() {
     let client_senders = CLIENT_SENDERS.clone();
     let unwrapped = MAIN_DISPATCHER.1.lock().unwrap();
     loop {
+        // the main thread is already stopped at this stage, so once the queue is empty nothing new will come
+        if shutdown::get_stage() >= ShutdownStage::Draining && unwrapped.is_empty() {
+            break;
+        }
         while let Ok(msg) = unwrapped.try_recv() {
             for sender in client_senders.lock().unwrap().iter() {
                 let send = sender.1.send(msg.clone());
@@ -692,10 +778,27 @@ fn on_client_close(ip: SocketAddr, client_id: Uuid, sender: &mut Writer<TcpStrea
     x_cast_state(state.clone(), XCast::Broadcast(state_id));
 }
 
+fn on_server_shutdown_close(ip: SocketAddr, client_id: Uuid, sender: &mut Writer<TcpStream>) {
+    let message = Message::close();
+    sender.send_message(&message).ok();
+    let mut senders = CLIENT_SENDERS.lock().unwrap();
+    CLIENT_SENDERS_SET.remove(&client_id);
+    let index = senders.iter().position(|s| s.0 == client_id);
+    index.map(|index| senders.remove(index));
+    println!(
+        "Client {} id {} disconnected due to server shutdown",
+        ip, client_id
+    );
+}
+
 pub fn is_disconnected(client_id: Uuid) -> bool {
     return !CLIENT_SENDERS_SET.contains(&client_id);
 }
 
+pub fn connected_clients_count() -> usize {
+    CLIENT_SENDERS.lock().unwrap().len()
+}
+
 pub fn notify_state_changed(state_id: Uuid, target_client_id: Uuid) {
     match MAIN_DISPATCHER
         .0
@@ -713,7 +816,7 @@ pub fn notify_state_changed(state_id: Uuid, target_client_id: Uuid) {
 }
 
 pub fn cleanup_bad_clients_thread() {
-    loop {
+    while !shutdown::is_shutting_down() {
         let client_errors = CLIENT_ERRORS.lock().unwrap();
         let clients = client_errors
             .clone()
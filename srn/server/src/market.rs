@@ -11,7 +11,7 @@ use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
 use crate::indexing::find_player_and_ship_mut;
-use crate::inventory::{add_item, consume_items_of_type};
+use crate::inventory::{add_item, consume_items_of_type, count_items_of_types};
 use crate::inventory::{
     add_items, cleanup_inventory_from_zeros, inventory_item_type_to_stackable, shake_items,
     InventoryItem, InventoryItemType,
@@ -21,14 +21,25 @@ use crate::world::{GameState, PlanetV2};
 
 pub type Wares = HashMap<Uuid, Vec<InventoryItem>>;
 pub type Prices = HashMap<Uuid, HashMap<InventoryItemType, Price>>;
+pub type PriceModifiers = HashMap<Uuid, HashMap<InventoryItemType, PriceModifier>>;
 
 pub const SHAKE_MARKET_EVERY_TICKS: i64 = 60 * 1000 * 1000;
+// how strongly the stock deviation from the default one moves the price, see get_stock_price
+const STOCK_PRICE_ELASTICITY: f64 = 0.5;
+const MIN_STOCK_PRICE_COEFF: f64 = 0.25;
+const MAX_STOCK_PRICE_COEFF: f64 = 4.0;
 
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
 pub struct Market {
     pub wares: HashMap<Uuid, Vec<InventoryItem>>,
     pub prices: HashMap<Uuid, HashMap<InventoryItemType, Price>>,
     pub time_before_next_shake: i64,
+    // see economy::update_economy. the markets from before the economy get it on the first update
+    #[serde(default)]
+    pub time_before_next_economy_update: i64,
+    // left by the price events of shake_market, the prices are the stock ones multiplied by them
+    #[serde(default)]
+    pub price_modifiers: PriceModifiers,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
@@ -37,12 +48,29 @@ pub struct Price {
     pub buy: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TypescriptDefinition, TypeScriptify)]
+pub struct PriceModifier {
+    pub sell: f64,
+    pub buy: f64,
+}
+
+impl PriceModifier {
+    pub fn none() -> PriceModifier {
+        PriceModifier {
+            sell: 1.0,
+            buy: 1.0,
+        }
+    }
+}
+
 impl Market {
     pub fn new() -> Market {
         Market {
             wares: HashMap::new(),
             prices: Default::default(),
             time_before_next_shake: 1000,
+            time_before_next_economy_update: 0,
+            price_modifiers: HashMap::new(),
         }
     }
 }
@@ -171,6 +199,15 @@ pub fn attempt_trade(
                 }
             }
         }
+        // so the next trader gets a worse deal
+        update_planet_prices(
+            market
+                .prices
+                .entry(act.planet_id)
+                .or_insert(make_default_prices()),
+            &planet_inventory,
+            market.price_modifiers.get(&act.planet_id),
+        );
         market.wares.insert(act.planet_id, planet_inventory);
     }
 
@@ -186,47 +223,117 @@ fn make_default_prices() -> HashMap<InventoryItemType, Price> {
     let mut res = HashMap::new();
     for item in InventoryItemType::iter() {
         let it: InventoryItemType = item;
-        let price = match it {
-            InventoryItemType::Unknown => Price { sell: 0, buy: 0 },
-            InventoryItemType::CommonMineral => Price { sell: 110, buy: 90 },
-            InventoryItemType::UncommonMineral => Price {
-                sell: 220,
-                buy: 180,
-            },
-            InventoryItemType::RareMineral => Price {
-                sell: 540,
-                buy: 460,
-            },
-            InventoryItemType::QuestCargo => Price { sell: 1000, buy: 0 },
-            InventoryItemType::Food => Price { sell: 50, buy: 40 },
-            InventoryItemType::Medicament => Price {
-                sell: 160,
-                buy: 140,
-            },
-            InventoryItemType::HandWeapon => Price {
-                sell: 250,
-                buy: 200,
-            },
-        };
+        let price = get_default_price(&it);
         res.insert(it, price);
     }
     res
 }
 
+pub fn get_default_price(it: &InventoryItemType) -> Price {
+    match it {
+        InventoryItemType::Unknown => Price { sell: 0, buy: 0 },
+        InventoryItemType::CommonMineral => Price { sell: 110, buy: 90 },
+        InventoryItemType::UncommonMineral => Price {
+            sell: 220,
+            buy: 180,
+        },
+        InventoryItemType::RareMineral => Price {
+            sell: 540,
+            buy: 460,
+        },
+        InventoryItemType::QuestCargo => Price { sell: 1000, buy: 0 },
+        InventoryItemType::Food => Price { sell: 50, buy: 40 },
+        InventoryItemType::Medicament => Price {
+            sell: 160,
+            buy: 140,
+        },
+        InventoryItemType::HandWeapon => Price {
+            sell: 250,
+            buy: 200,
+        },
+        InventoryItemType::TurretModule => Price {
+            sell: 1500,
+            buy: 1000,
+        },
+        InventoryItemType::HullPlatingModule => Price {
+            sell: 800,
+            buy: 550,
+        },
+        InventoryItemType::EngineModule => Price {
+            sell: 1200,
+            buy: 800,
+        },
+        InventoryItemType::TractorModule => Price {
+            sell: 600,
+            buy: 400,
+        },
+    }
+}
+
+// the stock a planet has by default, and the one that gives the default price
+pub fn get_default_stock(it: &InventoryItemType) -> i32 {
+    match it {
+        InventoryItemType::Unknown => 0,
+        InventoryItemType::CommonMineral => 100,
+        InventoryItemType::UncommonMineral => 50,
+        InventoryItemType::RareMineral => 20,
+        InventoryItemType::QuestCargo => 0,
+        InventoryItemType::Food => 200,
+        InventoryItemType::Medicament => 50,
+        InventoryItemType::HandWeapon => 10,
+        InventoryItemType::TurretModule => 2,
+        InventoryItemType::HullPlatingModule => 4,
+        InventoryItemType::EngineModule => 2,
+        InventoryItemType::TractorModule => 3,
+    }
+}
+
+// scarce goods are expensive and abundant ones are cheap, for both sides of the deal
+pub fn get_stock_price(it: &InventoryItemType, stock: i32) -> Price {
+    let default_price = get_default_price(it);
+    let default_stock = get_default_stock(it);
+    if default_stock <= 0 {
+        return default_price;
+    }
+    let coeff = (default_stock as f64 / stock.max(1) as f64)
+        .powf(STOCK_PRICE_ELASTICITY)
+        .max(MIN_STOCK_PRICE_COEFF)
+        .min(MAX_STOCK_PRICE_COEFF);
+    Price {
+        sell: (default_price.sell as f64 * coeff).round() as i32,
+        buy: (default_price.buy as f64 * coeff).round() as i32,
+    }
+}
+
+// only for the goods that have a stock, so the quest cargo keeps its price
+pub fn update_planet_prices(
+    prices: &mut HashMap<InventoryItemType, Price>,
+    wares: &Vec<InventoryItem>,
+    modifiers: Option<&HashMap<InventoryItemType, PriceModifier>>,
+) {
+    for it in InventoryItemType::iter() {
+        if get_default_stock(&it) <= 0 {
+            continue;
+        }
+        let stock = count_items_of_types(wares, &vec![it.clone()]);
+        let price = get_stock_price(&it, stock);
+        let price = match modifiers.and_then(|m| m.get(&it)) {
+            Some(modifier) => Price {
+                sell: (price.sell as f64 * modifier.sell).round() as i32,
+                buy: (price.buy as f64 * modifier.buy).round() as i32,
+            },
+            None => price,
+        };
+        prices.insert(it.clone(), price);
+    }
+}
+
 fn make_default_wares(prng: &mut Pcg64Mcg) -> Vec<InventoryItem> {
     let mut res = vec![];
     for item in InventoryItemType::iter() {
         let it: InventoryItemType = item;
-        let item = match it {
-            InventoryItemType::Unknown => InventoryItem::new(it, 0, prng_id(prng)),
-            InventoryItemType::CommonMineral => InventoryItem::new(it, 100, prng_id(prng)),
-            InventoryItemType::UncommonMineral => InventoryItem::new(it, 50, prng_id(prng)),
-            InventoryItemType::RareMineral => InventoryItem::new(it, 20, prng_id(prng)),
-            InventoryItemType::QuestCargo => InventoryItem::new(it, 0, prng_id(prng)),
-            InventoryItemType::Food => InventoryItem::new(it, 200, prng_id(prng)),
-            InventoryItemType::Medicament => InventoryItem::new(it, 50, prng_id(prng)),
-            InventoryItemType::HandWeapon => InventoryItem::new(it, 10, prng_id(prng)),
-        };
+        let quantity = get_default_stock(&it);
+        let item = InventoryItem::new(it, quantity, prng_id(prng));
         if item.quantity != 0 {
             res.push(item);
         }
@@ -245,6 +352,10 @@ pub fn get_default_value(it: &InventoryItemType) -> i32 {
         InventoryItemType::Food => 45,
         InventoryItemType::Medicament => 150,
         InventoryItemType::HandWeapon => 225,
+        InventoryItemType::TurretModule => 1250,
+        InventoryItemType::HullPlatingModule => 675,
+        InventoryItemType::EngineModule => 1000,
+        InventoryItemType::TractorModule => 500,
     }
 }
 
@@ -264,24 +375,35 @@ pub fn shake_market(
     planets: Vec<PlanetV2>,
     wares: &mut Wares,
     prices: &mut Prices,
+    modifiers: &mut PriceModifiers,
     prng: &mut Pcg64Mcg,
 ) {
     for planet in planets {
         let planet_prices = prices.entry(planet.id).or_insert(make_default_prices());
+        let planet_modifiers = modifiers.entry(planet.id).or_default();
         let planet_wares = wares.entry(planet.id).or_insert(make_default_wares(prng));
-        shift_market(planet_prices, planet_wares, planet.name.clone(), prng);
+        shift_market(
+            planet_prices,
+            planet_modifiers,
+            planet_wares,
+            planet.name.clone(),
+            prng,
+        );
     }
 }
 
 pub fn shift_market(
     prices: &mut HashMap<InventoryItemType, Price>,
+    modifiers: &mut HashMap<InventoryItemType, PriceModifier>,
     wares: &mut Vec<InventoryItem>,
     _planet_name: String,
     prng: &mut Pcg64Mcg,
 ) {
     let event = gen_price_event(prng);
     // log!(format!("Market event {:?} on {}", event, planet_name));
-    apply_price_event(prices, event, wares, prng);
+    apply_price_event(modifiers, event, wares, prng);
+    // so the event outlives the next economy update, which prices the stock the same way
+    update_planet_prices(prices, wares, Some(modifiers));
 }
 
 const NORMALIZE_DRIFT_PRICE_PERCENTAGE_PER_EVENT: f64 = 20.0;
@@ -308,7 +430,7 @@ pub enum PriceEvent {
 }
 
 pub fn apply_price_event(
-    prices: &mut HashMap<InventoryItemType, Price>,
+    prices: &mut HashMap<InventoryItemType, PriceModifier>,
     event: PriceEvent,
     wares: &mut Vec<InventoryItem>,
     prng: &mut Pcg64Mcg,
@@ -422,19 +544,13 @@ pub fn apply_price_event(
 }
 
 fn apply_normalize_event(
-    prices: &mut HashMap<InventoryItemType, Price>,
+    prices: &mut HashMap<InventoryItemType, PriceModifier>,
     wares: &mut Vec<InventoryItem>,
     prng: &mut Pcg64Mcg,
 ) {
-    let default_prices = make_default_prices();
-    for (it, price) in prices {
-        let default = default_prices.get(&it).unwrap();
-        let sell_diff = ((default.sell - price.sell) as f64 / 100.0
-            * NORMALIZE_DRIFT_PRICE_PERCENTAGE_PER_EVENT) as i32;
-        price.sell += sell_diff;
-        let buy_diff = ((default.buy - price.buy) as f64 / 100.0
-            * NORMALIZE_DRIFT_PRICE_PERCENTAGE_PER_EVENT) as i32;
-        price.buy += buy_diff;
+    for price in prices.values_mut() {
+        price.sell += (1.0 - price.sell) / 100.0 * NORMALIZE_DRIFT_PRICE_PERCENTAGE_PER_EVENT;
+        price.buy += (1.0 - price.buy) / 100.0 * NORMALIZE_DRIFT_PRICE_PERCENTAGE_PER_EVENT;
     }
     shake_items(wares);
 
@@ -483,37 +599,33 @@ enum PriceVariant {
     Abundance,
 }
 
+// the events multiply the stock price, see update_planet_prices
 fn set_price(
-    prices: &mut HashMap<InventoryItemType, Price>,
+    prices: &mut HashMap<InventoryItemType, PriceModifier>,
     target_type: &InventoryItemType,
     variant: PriceVariant,
 ) {
-    let default_prices = make_default_prices();
-    let default_price = default_prices.get(&target_type).unwrap();
     let new_price = match variant {
-        PriceVariant::Normal => Price {
-            sell: (default_price.sell as f64 * 1.0) as i32,
-            buy: (default_price.buy as f64 * 1.0) as i32,
-        },
-        PriceVariant::Stagnated => Price {
-            sell: (default_price.sell as f64 * 0.5) as i32,
-            buy: (default_price.buy as f64 * 0.5) as i32,
+        PriceVariant::Normal => PriceModifier::none(),
+        PriceVariant::Stagnated => PriceModifier {
+            sell: 0.5,
+            buy: 0.5,
         },
-        PriceVariant::Booming => Price {
-            sell: (default_price.sell as f64 * 1.5) as i32,
-            buy: (default_price.buy as f64 * 1.5) as i32,
+        PriceVariant::Booming => PriceModifier {
+            sell: 1.5,
+            buy: 1.5,
         },
-        PriceVariant::Prospering => Price {
-            sell: (default_price.sell as f64 * 2.0) as i32,
-            buy: (default_price.buy as f64 * 2.0) as i32,
+        PriceVariant::Prospering => PriceModifier {
+            sell: 2.0,
+            buy: 2.0,
         },
-        PriceVariant::Deficit => Price {
-            sell: (default_price.sell as f64 * 1.5) as i32,
-            buy: (default_price.buy as f64 * 2.5) as i32,
+        PriceVariant::Deficit => PriceModifier {
+            sell: 1.5,
+            buy: 2.5,
         },
-        PriceVariant::Abundance => Price {
-            sell: (default_price.sell as f64 * 0.75) as i32,
-            buy: (default_price.buy as f64 * 0.25) as i32,
+        PriceVariant::Abundance => PriceModifier {
+            sell: 0.75,
+            buy: 0.25,
         },
     };
     prices.insert(target_type.clone(), new_price);
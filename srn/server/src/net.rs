@@ -2,11 +2,14 @@ use num_traits::real::Real;
 use std::collections::{HashMap, VecDeque};
 
 use crate::api_struct::RoomId;
+#[cfg(feature = "avro")]
+use crate::avro::{AvroError, AvroSchemas, AvroType};
 use crate::dialogue::Dialogue;
 use crate::indexing::{find_my_player, find_my_ship, find_player_location_idx};
 use crate::market::Market;
 use crate::replay::ValueDiff;
 use crate::system_gen::GenStateOpts;
+use crate::wire_binary::{encode_wire_frame, WireFrame, WirePayload};
 use crate::world::{GameMode, GameState, Location, ProcessedPlayerAction, Ship};
 use crate::world_events::GameEvent;
 use crate::xcast::XCast;
@@ -71,6 +74,7 @@ pub const MAX_PROCESSED_ACTIONS_SHARE_TIME_TICKS: f64 = 5.0 * 1000.0 * 1000.0;
 pub fn patch_state_for_all_clients(state: &mut GameState) {
     state.market = None;
     state.breadcrumbs = None;
+    state.combat_log = None;
     state.events = None;
     state.player_actions = VecDeque::with_capacity(0);
     state.gen_opts = None;
@@ -282,6 +286,12 @@ pub fn patch_state_for_client_impl(mut state: GameState, player_id: Uuid) -> Gam
     return state;
 }
 
+// the location that patch_state_for_client_impl leaves for the client
+pub fn get_client_location_id(state: &GameState, player_id: Uuid) -> Option<Uuid> {
+    let idx = find_player_location_idx(state, player_id).unwrap_or(0);
+    state.locations.get(idx as usize).map(|l| l.id)
+}
+
 fn should_processed_player_action_be_sent(
     player_id: Uuid,
     my_ship_id: Option<Uuid>,
@@ -294,6 +304,37 @@ fn should_processed_player_action_be_sent(
 }
 
 impl ServerToClientMessage {
+    pub fn get_client_location_id(&self, client_id: Uuid) -> Option<Uuid> {
+        match self {
+            ServerToClientMessage::ObsoleteStateChangeExclusive(state, _)
+            | ServerToClientMessage::ObsoleteStateBroadcast(state)
+            | ServerToClientMessage::XCastStateChange(state, _) => {
+                get_client_location_id(state, client_id)
+            }
+            ServerToClientMessage::XCastStateDiff(diff) => {
+                get_client_location_id(&diff.state, client_id)
+            }
+            _ => None,
+        }
+    }
+
+    // the client applies diffs on top of the only location it has, so when its ship gets into another
+    // location (or the client has no state at all yet), the whole state has to be sent instead
+    pub fn rebase_for_client(self, client_id: Uuid, last_location_id: Option<Uuid>) -> Self {
+        match self {
+            ServerToClientMessage::XCastStateDiff(diff) => {
+                if last_location_id.is_some()
+                    && get_client_location_id(&diff.state, client_id) == last_location_id
+                {
+                    ServerToClientMessage::XCastStateDiff(diff)
+                } else {
+                    ServerToClientMessage::XCastStateChange(diff.state, diff.xcast)
+                }
+            }
+            m => m,
+        }
+    }
+
     pub fn patch_for_client(self, client_id: Uuid) -> Self {
         match self {
             ServerToClientMessage::ObsoleteStateChangeExclusive(state, id) => {
@@ -326,35 +367,154 @@ impl ServerToClientMessage {
     }
     pub fn serialize(&self) -> String {
         let (code, serialized) = match self {
-            ServerToClientMessage::ObsoleteStateBroadcast(state) => {
-                (1, serde_json::to_string(&state).unwrap())
-            }
-            ServerToClientMessage::ObsoleteStateChangeExclusive(state, _unused) => {
-                (2, serde_json::to_string(&state).unwrap())
-            }
-            ServerToClientMessage::TagConfirm(tag_confirm, _unused) => {
-                (3, serde_json::to_string(&tag_confirm).unwrap())
-            }
-            ServerToClientMessage::ObsoleteMulticastPartialShipUpdate(ships, _, _) => {
-                (4, serde_json::to_string(ships).unwrap())
+            ServerToClientMessage::ObsoleteStateBroadcast(state) => (
+                ServerOpCode::ObsoleteStateBroadcast,
+                serde_json::to_string(&state).unwrap(),
+            ),
+            ServerToClientMessage::ObsoleteStateChangeExclusive(state, _unused) => (
+                ServerOpCode::ObsoleteStateChangeExclusive,
+                serde_json::to_string(&state).unwrap(),
+            ),
+            ServerToClientMessage::TagConfirm(tag_confirm, _unused) => (
+                ServerOpCode::TagConfirm,
+                serde_json::to_string(&tag_confirm).unwrap(),
+            ),
+            ServerToClientMessage::ObsoleteMulticastPartialShipUpdate(ships, _, _) => (
+                ServerOpCode::ObsoleteMulticastPartialShipUpdate,
+                serde_json::to_string(ships).unwrap(),
+            ),
+            ServerToClientMessage::DialogueStateChange(dialogue, _, _) => (
+                ServerOpCode::DialogueStateChange,
+                serde_json::to_string(dialogue).unwrap(),
+            ),
+            ServerToClientMessage::XCastGameEvent(event, _) => (
+                ServerOpCode::XCastGameEvent,
+                serde_json::to_string(event).unwrap(),
+            ),
+            ServerToClientMessage::RoomSwitched(_) => (ServerOpCode::RoomSwitched, "".to_owned()),
+            ServerToClientMessage::XCastStateChange(state, _) => (
+                ServerOpCode::XCastStateChange,
+                serde_json::to_string(&state).unwrap(),
+            ),
+            ServerToClientMessage::RoomLeave(_) => (ServerOpCode::RoomLeave, "".to_owned()),
+            ServerToClientMessage::Pong(msg) => {
+                (ServerOpCode::Pong, msg.your_average_for_server.to_string())
             }
-            ServerToClientMessage::DialogueStateChange(dialogue, _, _) => {
-                (5, serde_json::to_string(dialogue).unwrap())
-            }
-            ServerToClientMessage::XCastGameEvent(event, _) => {
-                (6, serde_json::to_string(event).unwrap())
-            }
-            ServerToClientMessage::RoomSwitched(_) => (7, "".to_owned()),
-            ServerToClientMessage::XCastStateChange(state, _) => {
-                (8, serde_json::to_string(&state).unwrap())
-            }
-            ServerToClientMessage::RoomLeave(_) => (9, "".to_owned()),
-            ServerToClientMessage::Pong(msg) => (10, msg.your_average_for_server.to_string()),
-            ServerToClientMessage::XCastStateDiff(val) => {
-                (11, serde_json::to_string(&val.diffs).unwrap())
+            ServerToClientMessage::XCastStateDiff(val) => (
+                ServerOpCode::XCastStateDiff,
+                serde_json::to_string(&val.diffs).unwrap(),
+            ),
+        };
+        format!("{}_%_{}", code as u8, serialized)
+    }
+
+    // same opcodes and payloads as the text format, but diffs are packed instead of being json
+    pub fn serialize_binary(&self) -> Vec<u8> {
+        let (code, payload) = match self {
+            ServerToClientMessage::ObsoleteStateBroadcast(state) => (
+                ServerOpCode::ObsoleteStateBroadcast,
+                WirePayload::Value(serde_json::to_value(&state).unwrap()),
+            ),
+            ServerToClientMessage::ObsoleteStateChangeExclusive(state, _unused) => (
+                ServerOpCode::ObsoleteStateChangeExclusive,
+                WirePayload::Value(serde_json::to_value(&state).unwrap()),
+            ),
+            ServerToClientMessage::TagConfirm(tag_confirm, _unused) => (
+                ServerOpCode::TagConfirm,
+                WirePayload::Value(serde_json::to_value(&tag_confirm).unwrap()),
+            ),
+            ServerToClientMessage::ObsoleteMulticastPartialShipUpdate(ships, _, _) => (
+                ServerOpCode::ObsoleteMulticastPartialShipUpdate,
+                WirePayload::Value(serde_json::to_value(ships).unwrap()),
+            ),
+            ServerToClientMessage::DialogueStateChange(dialogue, _, _) => (
+                ServerOpCode::DialogueStateChange,
+                WirePayload::Value(serde_json::to_value(dialogue).unwrap()),
+            ),
+            ServerToClientMessage::XCastGameEvent(event, _) => (
+                ServerOpCode::XCastGameEvent,
+                WirePayload::Value(serde_json::to_value(event).unwrap()),
+            ),
+            ServerToClientMessage::RoomSwitched(_) => {
+                (ServerOpCode::RoomSwitched, WirePayload::Empty)
             }
+            ServerToClientMessage::XCastStateChange(state, _) => (
+                ServerOpCode::XCastStateChange,
+                WirePayload::Value(serde_json::to_value(&state).unwrap()),
+            ),
+            ServerToClientMessage::RoomLeave(_) => (ServerOpCode::RoomLeave, WirePayload::Empty),
+            ServerToClientMessage::Pong(msg) => (
+                ServerOpCode::Pong,
+                WirePayload::Value(serde_json::to_value(msg.your_average_for_server).unwrap()),
+            ),
+            ServerToClientMessage::XCastStateDiff(val) => (
+                ServerOpCode::XCastStateDiff,
+                WirePayload::Diffs(val.diffs.clone()),
+            ),
+        };
+        encode_wire_frame(&WireFrame {
+            opcode: code as u8,
+            tag: None,
+            payload,
+        })
+    }
+
+    // opcode byte followed by a single avro datum, the wrappers of the text format are dropped
+    #[cfg(feature = "avro")]
+    pub fn serialize_avro(&self, schemas: &AvroSchemas) -> Result<Vec<u8>, AvroError> {
+        let (code, payload) = match self {
+            ServerToClientMessage::ObsoleteStateBroadcast(state) => (
+                ServerOpCode::ObsoleteStateBroadcast,
+                schemas.encode_typed(&AvroType::named("GameState"), state)?,
+            ),
+            ServerToClientMessage::ObsoleteStateChangeExclusive(state, _unused) => (
+                ServerOpCode::ObsoleteStateChangeExclusive,
+                schemas.encode_typed(&AvroType::named("GameState"), state)?,
+            ),
+            ServerToClientMessage::TagConfirm(tag_confirm, _unused) => (
+                ServerOpCode::TagConfirm,
+                schemas.encode_typed(&AvroType::String, &tag_confirm.tag)?,
+            ),
+            ServerToClientMessage::ObsoleteMulticastPartialShipUpdate(ships, _, _) => (
+                ServerOpCode::ObsoleteMulticastPartialShipUpdate,
+                schemas.encode_typed(&AvroType::array(AvroType::named("Ship")), &ships.ships)?,
+            ),
+            ServerToClientMessage::DialogueStateChange(dialogue, _, _) => (
+                ServerOpCode::DialogueStateChange,
+                schemas.encode_typed(
+                    &AvroType::optional(AvroType::named("Dialogue")),
+                    &dialogue.value,
+                )?,
+            ),
+            ServerToClientMessage::XCastGameEvent(event, _) => (
+                ServerOpCode::XCastGameEvent,
+                schemas.encode_typed(&AvroType::named("GameEvent"), &event.value)?,
+            ),
+            ServerToClientMessage::RoomSwitched(_) => (ServerOpCode::RoomSwitched, vec![]),
+            ServerToClientMessage::XCastStateChange(state, _) => (
+                ServerOpCode::XCastStateChange,
+                schemas.encode_typed(&AvroType::named("GameState"), state)?,
+            ),
+            ServerToClientMessage::RoomLeave(_) => (ServerOpCode::RoomLeave, vec![]),
+            ServerToClientMessage::Pong(msg) => (
+                ServerOpCode::Pong,
+                schemas.encode_typed(&AvroType::Int, &msg.your_average_for_server)?,
+            ),
+            // diffs are partial values of any type, so there is no schema for them
+            ServerToClientMessage::XCastStateDiff(val) => (
+                ServerOpCode::XCastStateDiff,
+                schemas.encode_typed(
+                    &AvroType::array(AvroType::String),
+                    &val.diffs
+                        .iter()
+                        .map(|d| serde_json::to_string(d).unwrap())
+                        .collect::<Vec<_>>(),
+                )?,
+            ),
         };
-        format!("{}_%_{}", code, serialized)
+        let mut res = vec![code as u8];
+        res.extend(payload);
+        Ok(res)
     }
 }
 
@@ -375,6 +535,21 @@ pub struct ClientErr {
     pub message: String,
 }
 
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy)]
+pub enum ServerOpCode {
+    ObsoleteStateBroadcast = 1,
+    ObsoleteStateChangeExclusive = 2,
+    TagConfirm = 3,
+    ObsoleteMulticastPartialShipUpdate = 4,
+    DialogueStateChange = 5,
+    XCastGameEvent = 6,
+    RoomSwitched = 7,
+    XCastStateChange = 8,
+    RoomLeave = 9,
+    Pong = 10,
+    XCastStateDiff = 11,
+}
+
 #[derive(FromPrimitive, ToPrimitive, Debug, Clone)]
 pub enum ClientOpCode {
     Unknown = 0,
@@ -286,6 +286,9 @@ pub enum SamplerMarks {
     UpdatePropertiesRules = 69,
     UpdateCleanup = 70,
     UpdateExplosions = 71,
+    TakeRoomsSnapshot = 72,
+    RecordReplayFrames = 73,
+    UpdateEconomy = 74,
 }
 
 impl Display for SamplerMarks {
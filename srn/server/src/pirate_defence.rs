@@ -1,40 +1,188 @@
 use itertools::Itertools;
+use std::cmp::Ordering;
 use std::f64::consts::PI;
 
 use rand::prelude::*;
 use rand::Rng;
 use rand_pcg::Pcg64Mcg;
+use serde_derive::{Deserialize, Serialize};
+use typescript_definitions::{TypeScriptify, TypescriptDefinition};
 use uuid::Uuid;
+use wasm_bindgen::prelude::*;
 
 use crate::abilities::{Ability, SHOOT_DEFAULT_DISTANCE};
 use crate::api_struct::AiTrait;
 use crate::api_struct::{new_bot, Bot, Room};
-use crate::bots::{add_bot, BotAct};
+use crate::behaviour_tree::{attack_acts, flee_point, navigate_act};
+use crate::bots::add_bot;
+use crate::combat_log::{KillRecord, ASSIST_MONEY_SHARE};
 use crate::dialogue::DialogueStatesForPlayer;
 use crate::fof::{resolve_player_id, FofActor, FriendOrFoe};
-use crate::indexing::{
-    find_player_ship_index, index_players_by_ship_id, index_state, GameStateIndexes,
-    ObjectIndexSpecifier, ObjectSpecifier,
-};
-use crate::long_actions::LongActionStart;
-use crate::properties::{find_property, ObjectProperty, ObjectPropertyKey};
+use crate::indexing::{index_state, GameStateIndexes, ObjectIndexSpecifier};
+use crate::properties::{find_property, has_property, ObjectProperty, ObjectPropertyKey};
+use crate::ship_classes::PIRATE_SHIP_CLASS;
 use crate::vec2::Vec2f64;
 use crate::world::TimeMarks::BotAction;
-use crate::world::{GameOver, GameState, PlanetV2, Ship, ShipTemplate, SpatialIndexes, TimeMarks};
+use crate::world::{GameOver, GameState, PlanetV2, Ship, ShipIdx, ShipTemplate, SpatialIndexes};
 use crate::world_actions::Action;
 use crate::world_events::{fire_saved_event, GameEvent};
-use crate::{fire_event, fof, indexing, prng_id, world, DialogueTable};
+use crate::{fire_event, fof, indexing, prng_id, world};
 
-pub fn on_pirate_spawn(state: &mut GameState, at: &Vec2f64, prng: &mut Pcg64Mcg) {
+pub fn on_pirate_spawn(
+    state: &mut GameState,
+    at: &Vec2f64,
+    class_id: &String,
+    health_multiplier: f64,
+    npc_traits: Option<Vec<AiTrait>>,
+    prng: &mut Pcg64Mcg,
+) {
+    if let Some(waves) = state.pirate_waves.as_mut() {
+        waves.pending_spawns = waves.pending_spawns.saturating_sub(1);
+    }
     if !state.paused {
-        world::spawn_ship(state, None, ShipTemplate::pirate(Some(at.clone())), prng);
+        let mut template = ShipTemplate::of_class(class_id.as_str(), Some(at.clone()))
+            .unwrap_or_else(|| ShipTemplate::pirate(Some(at.clone())));
+        if let Some(npc_traits) = npc_traits {
+            template = template.with_npc_traits(npc_traits);
+        }
+        let ship_id = world::spawn_ship(state, None, template, prng).id;
+        if let Some(ship) = indexing::find_ship_mut(state, ship_id) {
+            ship.health.max *= health_multiplier;
+            ship.health.current = ship.health.max;
+        }
     }
 }
 
 const SHIP_PLANET_HIT_NORMALIZED: f64 = 0.1;
 const PIRATE_SPAWN_DIST: f64 = 100.0;
-const PIRATE_SPAWN_COUNT: usize = 1;
-const PIRATE_SPAWN_INTERVAL_TICKS: u32 = 3 * 1000 * 1000;
+const PIRATE_SCOUT_SHIP_CLASS: &str = "pirate_scout";
+const PIRATE_BRUTE_SHIP_CLASS: &str = "pirate_brute";
+// every player after the first one brings that share of the wave ships more
+pub const WAVE_SHIPS_PER_EXTRA_PLAYER: f64 = 0.5;
+// every wave after the first one makes pirate hulls that much stronger
+pub const WAVE_HEALTH_GROWTH: f64 = 0.15;
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct PirateWaveGroup {
+    pub class_id: String,
+    // for a single player, see scale_wave_ship_count
+    pub count: usize,
+    // the class ones are used when not set, see bots::npc_act
+    pub npc_traits: Option<Vec<AiTrait>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct PirateWave {
+    pub groups: Vec<PirateWaveGroup>,
+    // since the previous wave was cleared, or since the game start for the first one
+    pub delay_ticks: u64,
+    // paid to every player when the whole wave is destroyed
+    pub clear_bonus: i32,
+}
+
+fn group(class_id: &str, count: usize, npc_traits: Option<Vec<AiTrait>>) -> PirateWaveGroup {
+    PirateWaveGroup {
+        class_id: class_id.to_string(),
+        count,
+        npc_traits,
+    }
+}
+
+fn wave(groups: Vec<PirateWaveGroup>, delay_sec: u64, clear_bonus: i32) -> PirateWave {
+    PirateWave {
+        groups,
+        delay_ticks: delay_sec * 1000 * 1000,
+        clear_bonus,
+    }
+}
+
+// hunt the players on the way to the planet, but run away when damaged
+fn raiders() -> Option<Vec<AiTrait>> {
+    Some(vec![
+        AiTrait::PirateAttacker,
+        AiTrait::PirateEvasive,
+        AiTrait::ImmediatePlanetLand,
+    ])
+}
+
+// stay around the closest non-escort pirate and shoot whatever comes close
+fn escorts() -> Option<Vec<AiTrait>> {
+    Some(vec![
+        AiTrait::PirateEscort,
+        AiTrait::PirateAttacker,
+        AiTrait::ImmediatePlanetLand,
+    ])
+}
+
+fn attackers() -> Option<Vec<AiTrait>> {
+    Some(vec![AiTrait::PirateAttacker, AiTrait::ImmediatePlanetLand])
+}
+
+pub fn get_pirate_waves() -> Vec<PirateWave> {
+    vec![
+        wave(vec![group(PIRATE_SHIP_CLASS, 2, None)], 5, 100),
+        wave(
+            vec![
+                group(PIRATE_SHIP_CLASS, 2, None),
+                group(PIRATE_SCOUT_SHIP_CLASS, 2, raiders()),
+            ],
+            15,
+            150,
+        ),
+        wave(vec![group(PIRATE_SCOUT_SHIP_CLASS, 5, raiders())], 15, 200),
+        wave(
+            vec![
+                group(PIRATE_SHIP_CLASS, 3, escorts()),
+                group(PIRATE_BRUTE_SHIP_CLASS, 1, attackers()),
+            ],
+            20,
+            300,
+        ),
+        wave(
+            vec![
+                group(PIRATE_SHIP_CLASS, 3, escorts()),
+                group(PIRATE_SCOUT_SHIP_CLASS, 3, raiders()),
+                group(PIRATE_BRUTE_SHIP_CLASS, 2, attackers()),
+            ],
+            20,
+            500,
+        ),
+    ]
+}
+
+// progress of the wave defined in get_pirate_waves, lives in the GameState
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct PirateWaves {
+    // the wave that is being fought, or the next one if it has not started yet
+    pub current: usize,
+    pub total: usize,
+    pub in_progress: bool,
+    pub next_wave_at_ticks: u64,
+    // ships of the current wave that are not spawned yet, see on_pirate_spawn
+    pub pending_spawns: usize,
+}
+
+impl PirateWaves {
+    pub fn new(start_ticks: u64) -> Self {
+        let waves = get_pirate_waves();
+        Self {
+            current: 0,
+            total: waves.len(),
+            in_progress: false,
+            next_wave_at_ticks: start_ticks + waves.first().map_or(0, |w| w.delay_ticks),
+            pending_spawns: 0,
+        }
+    }
+}
+
+pub fn scale_wave_ship_count(count: usize, player_count: usize) -> usize {
+    let extra_players = player_count.max(1) - 1;
+    (count as f64 * (1.0 + WAVE_SHIPS_PER_EXTRA_PLAYER * extra_players as f64)).ceil() as usize
+}
+
+pub fn get_wave_health_multiplier(wave_idx: usize) -> f64 {
+    1.0 + WAVE_HEALTH_GROWTH * wave_idx as f64
+}
 
 pub fn on_ship_docked(state: &mut GameState, ship: Ship, planet: PlanetV2) {
     if ship
@@ -51,6 +199,7 @@ pub fn on_ship_docked(state: &mut GameState, ship: Ship, planet: PlanetV2) {
                 if health.current <= 0.0 {
                     state.game_over = Some(GameOver {
                         reason: format!("Your planet {} was captured by pirates. All is lost, and you have been defeated.", planet.name),
+                        victory: false,
                     })
                 }
             }
@@ -58,7 +207,7 @@ pub fn on_ship_docked(state: &mut GameState, ship: Ship, planet: PlanetV2) {
     }
 }
 
-pub fn on_ship_died(state: &mut GameState, ship: Ship) {
+pub fn on_ship_killed(state: &mut GameState, ship: Ship, kill: KillRecord) {
     if let Some(prop) = ship
         .properties
         .iter()
@@ -66,22 +215,12 @@ pub fn on_ship_died(state: &mut GameState, ship: Ship) {
     {
         match prop {
             ObjectProperty::MoneyOnKill(props) => {
-                if let Some(killer) = ship.health.last_damage_dealer {
-                    let player_id = match killer {
-                        ObjectSpecifier::Ship { id } => {
-                            let mapping = index_players_by_ship_id(&state.players);
-                            if let Some(player) = mapping.get(&id) {
-                                Some(player.id)
-                            } else {
-                                None
-                            }
-                        }
-                        _ => None,
-                    };
-                    if let Some(player_id) = player_id {
-                        if let Some(player) = state.players.iter_mut().find(|p| p.id == player_id) {
-                            player.money += props.amount;
-                        }
+                let assist_amount = (props.amount as f64 * ASSIST_MONEY_SHARE) as i32;
+                for player in state.players.iter_mut() {
+                    if kill.killer_player_id == Some(player.id) {
+                        player.money += props.amount;
+                    } else if kill.assist_player_ids.contains(&player.id) {
+                        player.money += assist_amount;
                     }
                 }
             }
@@ -91,31 +230,91 @@ pub fn on_ship_died(state: &mut GameState, ship: Ship) {
 }
 
 pub fn update_state_pirate_defence(state: &mut GameState, prng: &mut Pcg64Mcg) {
-    let current_ticks = state.ticks as u32;
-    if world::every(
-        PIRATE_SPAWN_INTERVAL_TICKS,
-        current_ticks,
-        state
-            .interval_data
-            .as_ref()
-            .and_then(|id| id.get(&TimeMarks::PirateSpawn))
-            .map(|m| *m),
-    ) {
-        let target_count = PIRATE_SPAWN_COUNT * state.players.len();
-        state
-            .interval_data
-            .as_mut()
-            .map(|id| id.insert(TimeMarks::PirateSpawn, current_ticks));
-        for _i in 0..target_count {
-            fire_saved_event(
-                state,
-                GameEvent::PirateSpawn {
-                    state_id: state.id,
-                    at: gen_pirate_spawn(&state.locations[0].planets.get(0).unwrap(), prng),
-                },
-            );
+    let waves = get_pirate_waves();
+    let progress = state.pirate_waves.clone();
+    if progress.is_none() || state.game_over.is_some() {
+        return;
+    }
+    let progress = progress.unwrap();
+    if progress.in_progress {
+        if progress.pending_spawns == 0 && count_pirates(state) == 0 {
+            on_wave_cleared(state, &waves);
+        }
+    } else if state.ticks >= progress.next_wave_at_ticks {
+        if let Some(wave) = waves.get(progress.current) {
+            start_wave(state, wave, progress.current, prng);
+        }
+    }
+}
+
+fn count_pirates(state: &GameState) -> usize {
+    state
+        .locations
+        .iter()
+        .map(|loc| {
+            loc.ships
+                .iter()
+                .filter(|s| has_property(&s.properties, ObjectPropertyKey::PirateShip))
+                .count()
+        })
+        .sum()
+}
+
+fn start_wave(state: &mut GameState, wave: &PirateWave, wave_idx: usize, prng: &mut Pcg64Mcg) {
+    let player_count = state.players.len();
+    let health_multiplier = get_wave_health_multiplier(wave_idx);
+    let mut spawns = vec![];
+    for group in wave.groups.iter() {
+        for _i in 0..scale_wave_ship_count(group.count, player_count) {
+            spawns.push(GameEvent::PirateSpawn {
+                state_id: state.id,
+                at: gen_pirate_spawn(&state.locations[0].planets.get(0).unwrap(), prng),
+                class_id: group.class_id.clone(),
+                health_multiplier,
+                npc_traits: group.npc_traits.clone(),
+            });
         }
     }
+    if let Some(waves) = state.pirate_waves.as_mut() {
+        waves.in_progress = true;
+        waves.pending_spawns = spawns.len();
+    }
+    log!(format!(
+        "pirate wave {} of {} started with {} ships in state {}",
+        wave_idx + 1,
+        waves_total(state),
+        spawns.len(),
+        state.id
+    ));
+    for spawn in spawns.into_iter() {
+        fire_saved_event(state, spawn);
+    }
+}
+
+fn waves_total(state: &GameState) -> usize {
+    state.pirate_waves.as_ref().map_or(0, |w| w.total)
+}
+
+fn on_wave_cleared(state: &mut GameState, waves: &Vec<PirateWave>) {
+    let current_ticks = state.ticks;
+    let progress = state.pirate_waves.as_mut().unwrap();
+    let cleared = &waves[progress.current];
+    progress.current += 1;
+    progress.in_progress = false;
+    let next_wave = waves.get(progress.current);
+    if let Some(next_wave) = next_wave {
+        progress.next_wave_at_ticks = current_ticks + next_wave.delay_ticks;
+    }
+    for player in state.players.iter_mut() {
+        player.money += cleared.clear_bonus;
+    }
+    if next_wave.is_none() {
+        state.game_over = Some(GameOver {
+            reason: "All pirate waves have been repelled, and your planet is safe. Victory!"
+                .to_string(),
+            victory: true,
+        });
+    }
 }
 
 pub fn gen_pirate_spawn(planet: &&PlanetV2, prng: &mut Pcg64Mcg) -> Vec2f64 {
@@ -135,91 +334,147 @@ pub fn on_create_room(room: &mut Room, prng: &mut Pcg64Mcg) {
     add_bot(room, new_bot(traits.clone(), prng_id(prng)), prng);
 }
 
-pub fn bot_planet_defender_act(
-    bot: Bot,
+// how far the attackers look for player ships to chase
+pub const PIRATE_ENGAGE_DISTANCE: f64 = SHOOT_DEFAULT_DISTANCE * 2.0;
+// evasive pirates break off once their hull gets below that share
+pub const PIRATE_EVADE_HEALTH_SHARE: f64 = 0.4;
+pub const PIRATE_EVADE_TICKS: i64 = 5 * 1000 * 1000;
+const PIRATE_EVADE_DISTANCE: f64 = 50.0;
+// from the leader's edge
+pub const PIRATE_ESCORT_DISTANCE: f64 = 8.0;
+const PIRATE_ESCORT_SLOTS: usize = 6;
+// navigation resets the trajectory, so it's not worth doing for small shifts of a moving target
+const PIRATE_RENAVIGATE_DISTANCE: f64 = 3.0;
+
+// None means that no pirate trait is interested in the ship right now,
+// so the rest of bots::npc_act (like ImmediatePlanetLand) can take over
+pub fn pirate_npc_act(
+    bot: &mut Bot,
+    ship: &Ship,
     state: &GameState,
-    _bot_elapsed_micro: i64,
-    _d_table: &DialogueTable,
+    elapsed_micro: i64,
+    ship_idx: &ShipIdx,
     spatial_indexes: &SpatialIndexes,
-    prng: &mut Pcg64Mcg,
-) -> (Bot, Vec<BotAct>) {
-    let bot_id = bot.id;
-    let nothing = (bot.clone(), vec![]);
-    let ship_loc = find_player_ship_index(state, bot.id);
-    if let Some(ship_loc) = ship_loc {
-        if let Some(loc_sp_idx) = spatial_indexes.values.get(&ship_loc.location_idx) {
-            let my_ship = &state.locations[ship_loc.location_idx].ships[ship_loc.ship_idx];
-            let valid_targets = loc_sp_idx.rad_search(&my_ship.as_vec(), SHOOT_DEFAULT_DISTANCE);
-            let mut all_acts = vec![];
-
-            let foe_ships = valid_targets
-                .iter()
-                .filter_map(|sp| match sp {
-                    ObjectIndexSpecifier::Ship { idx } => {
-                        let target_ship = &state.locations[ship_loc.location_idx].ships[*idx];
-                        let is_foe = target_ship
-                            .npc
-                            .clone()
-                            .map(|n| {
-                                n.traits
-                                    .iter()
-                                    .any(|t| matches!(t, AiTrait::ImmediatePlanetLand))
-                            })
-                            .map_or(false, |t| t);
-                        if is_foe {
-                            Some(target_ship)
-                        } else {
-                            None
-                        }
-                    }
-                    _ => None,
-                })
-                .collect::<Vec<_>>();
-            if let Some(first) = foe_ships.first() {
-                for turret_ab in my_ship
-                    .abilities
-                    .iter()
-                    .filter(|a| matches!(a, Ability::Shoot { .. }))
-                {
-                    if turret_ab.get_current_cooldown() == 0 {
-                        let act = Action::LongActionStart {
-                            long_action_start: LongActionStart::Shoot {
-                                target: ObjectSpecifier::Ship { id: first.id },
-                                turret_id: match turret_ab {
-                                    Ability::Shoot { turret_id, .. } => turret_id.clone(),
-                                    _ => Default::default(),
-                                },
-                            },
-                            player_id: Some(bot_id),
-                            ship_id: my_ship.id,
-                        };
-                        all_acts.push(BotAct::Act(act));
-                    }
-                }
-            }
-            let def_planet = &state.locations[0].planets[0];
-            let rad = def_planet.spatial.radius;
-            if my_ship
-                .as_vec()
-                .euclidean_distance(&def_planet.spatial.position)
-                > rad * 1.5
-                && my_ship.trajectory.len() == 0
-            {
-                let random_shift_x = prng.gen_range(-rad, rad);
-                let random_shift_y = prng.gen_range(-rad, rad);
-                all_acts.push(BotAct::Act(Action::Navigate {
-                    ship_id: my_ship.id,
-                    target: def_planet.spatial.position.add(&Vec2f64 {
-                        x: random_shift_x,
-                        y: random_shift_y,
-                    }),
-                }));
-            }
-            return (bot, all_acts);
+) -> Option<Vec<Action>> {
+    let has_trait = |t: AiTrait| bot.traits.contains(&t);
+    let (evasive, attacker, escort) = (
+        has_trait(AiTrait::PirateEvasive),
+        has_trait(AiTrait::PirateAttacker),
+        has_trait(AiTrait::PirateEscort),
+    );
+    if evasive {
+        if let Some(acts) = pirate_evade(bot, ship, state, elapsed_micro, ship_idx, spatial_indexes)
+        {
+            return Some(acts);
+        }
+    }
+    // escorts only shoot what comes close, while the others chase
+    let attack = if attacker {
+        pirate_attack(ship, state, ship_idx, spatial_indexes, !escort)
+    } else {
+        None
+    };
+    let follow = if escort {
+        pirate_escort(ship, state, ship_idx)
+    } else {
+        None
+    };
+    if attack.is_none() && follow.is_none() {
+        return None;
+    }
+    let mut acts = attack.unwrap_or(vec![]);
+    acts.append(&mut follow.unwrap_or(vec![]));
+    Some(acts)
+}
+
+// the bot timer is 0 before the break-off, counts down during it and stays negative after it,
+// so the pirate returns to the fight instead of fleeing forever
+fn pirate_evade(
+    bot: &mut Bot,
+    ship: &Ship,
+    state: &GameState,
+    elapsed_micro: i64,
+    ship_idx: &ShipIdx,
+    spatial_indexes: &SpatialIndexes,
+) -> Option<Vec<Action>> {
+    let mut timer = bot.timer.unwrap_or(0);
+    if timer < 0 {
+        return None;
+    }
+    if timer == 0 {
+        if ship.health.current >= ship.health.max * PIRATE_EVADE_HEALTH_SHARE {
+            return None;
         }
+        timer = PIRATE_EVADE_TICKS;
+    }
+    let left = timer - elapsed_micro;
+    bot.timer = Some(if left > 0 { left } else { -1 });
+    if left <= 0 {
+        return None;
+    }
+    let foe = fof::find_closest_foe(state, ship_idx, spatial_indexes, PIRATE_ENGAGE_DISTANCE);
+    let away = foe.and_then(|foe| flee_point(ship, foe, PIRATE_EVADE_DISTANCE));
+    Some(away.map_or(vec![], |away| navigate_pirate(ship, away)))
+}
+
+fn pirate_attack(
+    ship: &Ship,
+    state: &GameState,
+    ship_idx: &ShipIdx,
+    spatial_indexes: &SpatialIndexes,
+    chase: bool,
+) -> Option<Vec<Action>> {
+    let radius = if chase {
+        PIRATE_ENGAGE_DISTANCE
+    } else {
+        SHOOT_DEFAULT_DISTANCE
+    };
+    let foe = fof::find_closest_foe(state, ship_idx, spatial_indexes, radius)?;
+    let mut acts = attack_acts(ship, foe, None);
+    if chase && foe.as_vec().euclidean_distance(&ship.as_vec()) > SHOOT_DEFAULT_DISTANCE {
+        acts.append(&mut navigate_pirate(ship, foe.as_vec()));
     }
+    Some(acts)
+}
 
-    return nothing;
+fn is_escort(ship: &Ship) -> bool {
+    ship.npc
+        .as_ref()
+        .map_or(false, |npc| npc.traits.contains(&AiTrait::PirateEscort))
+}
+
+fn pirate_escort(ship: &Ship, state: &GameState, ship_idx: &ShipIdx) -> Option<Vec<Action>> {
+    let loc = &state.locations[ship_idx.location_idx];
+    let leader = loc
+        .ships
+        .iter()
+        .filter(|s| {
+            s.id != ship.id
+                && has_property(&s.properties, ObjectPropertyKey::PirateShip)
+                && !is_escort(s)
+        })
+        .min_by(|a, b| {
+            let dist_a = a.as_vec().euclidean_distance(&ship.as_vec());
+            let dist_b = b.as_vec().euclidean_distance(&ship.as_vec());
+            dist_a.partial_cmp(&dist_b).unwrap_or(Ordering::Equal)
+        })?;
+    // by the order in the location, so the escorts of the same leader take different places around it
+    let slot = loc.ships[..ship_idx.ship_idx]
+        .iter()
+        .filter(|s| is_escort(s))
+        .count()
+        % PIRATE_ESCORT_SLOTS;
+    let angle = PI * 2.0 * slot as f64 / PIRATE_ESCORT_SLOTS as f64;
+    let offset = Vec2f64 { x: 1.0, y: 0.0 }
+        .rotate(angle)
+        .scalar_mul(leader.spatial.radius + PIRATE_ESCORT_DISTANCE);
+    Some(navigate_pirate(ship, leader.as_vec().add(&offset)))
+}
+
+fn navigate_pirate(ship: &Ship, target: Vec2f64) -> Vec<Action> {
+    navigate_act(ship, target, PIRATE_RENAVIGATE_DISTANCE)
+        .into_iter()
+        .collect()
 }
 
 pub fn friend_or_foe_p2o(
@@ -236,10 +491,25 @@ pub fn friend_or_foe_p2o(
             // if we are here, then it's not a player's ship, therefore hostile
             FriendOrFoe::Foe
         }
+        ObjectIndexSpecifier::Projectile { .. } => {
+            // same, it was not launched by a player
+            FriendOrFoe::Foe
+        }
         _ => FriendOrFoe::Neutral,
     }
 }
 
+fn is_launched_by_pirate(state: &GameState, proj_idx: usize, loc_idx: usize) -> bool {
+    let loc = &state.locations[loc_idx];
+    loc.projectiles
+        .get(proj_idx)
+        .and_then(|proj| proj.get_launched_by())
+        .and_then(|ship_id| loc.ships.iter().find(|s| s.id == ship_id))
+        .map_or(false, |ship| {
+            has_property(&ship.properties, ObjectPropertyKey::PirateShip)
+        })
+}
+
 pub fn friend_or_foe(
     state: &GameState,
     actor_a: FofActor,
@@ -259,7 +529,10 @@ pub fn friend_or_foe(
         match &actor_a {
             FofActor::Player { .. } => {}
             FofActor::ObjectIdx { spec } => match spec {
-                ObjectIndexSpecifier::Projectile { .. } => {
+                ObjectIndexSpecifier::Projectile { idx: proj_idx } => {
+                    if is_launched_by_pirate(state, *proj_idx, loc_idx) {
+                        return FriendOrFoe::Neutral;
+                    }
                     match &actor_b {
                         FofActor::Player { .. } => {}
                         FofActor::ObjectIdx { spec } => {
@@ -271,7 +544,7 @@ pub fn friend_or_foe(
                                     )
                                     .is_some()
                                     {
-                                        // rockets, no matter from whom (assuming only players can shoot for now)
+                                        // rockets of players, or of somebody who is already dead,
                                         // are hostile to pirate ships
                                         return FriendOrFoe::Foe;
                                     }
@@ -0,0 +1,264 @@
+#[cfg(test)]
+mod pirate_defence_test {
+    use crate::api_struct::{AiTrait, Bot};
+    use crate::fof::{friend_or_foe, FofActor, FriendOrFoe};
+    use crate::indexing::{
+        build_full_spatial_indexes, find_ship_index, find_ship_mut, ObjectIndexSpecifier,
+        ObjectSpecifier,
+    };
+    use crate::long_actions::LongActionStart;
+    use crate::pirate_defence::{
+        get_pirate_waves, get_wave_health_multiplier, on_pirate_spawn, pirate_npc_act,
+        scale_wave_ship_count, update_state_pirate_defence, PIRATE_ESCORT_DISTANCE,
+        PIRATE_EVADE_HEALTH_SHARE, PIRATE_EVADE_TICKS,
+    };
+    use crate::properties::{has_property, ObjectPropertyKey};
+    use crate::ship_classes::get_ship_class;
+    use crate::test_helpers::{add_player_ship, new_state};
+    use crate::vec2::Vec2f64;
+    use crate::world::{
+        add_player, spawn_ship, GameMode, GameState, Ship, ShipTemplate, TemplateId,
+    };
+    use crate::world_actions::Action;
+    use crate::world_events::GameEvent;
+    use crate::{new_id, seed_prng};
+    use rand_pcg::Pcg64Mcg;
+    use uuid::Uuid;
+
+    const AT: Vec2f64 = Vec2f64 { x: 321.5, y: 123.5 };
+
+    fn spawn_pirate(
+        state: &mut GameState,
+        at: Vec2f64,
+        npc_traits: Option<Vec<AiTrait>>,
+        prng: &mut Pcg64Mcg,
+    ) -> Uuid {
+        let mut template = ShipTemplate::pirate(Some(at));
+        if let Some(npc_traits) = npc_traits {
+            template = template.with_npc_traits(npc_traits);
+        }
+        spawn_ship(state, None, template, prng).id
+    }
+
+    fn act(state: &GameState, ship_id: Uuid, elapsed_micro: i64) -> (Bot, Option<Vec<Action>>) {
+        let ship_idx = find_ship_index(state, ship_id).unwrap();
+        let ship = &state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx];
+        let mut bot = ship.npc.clone().unwrap();
+        let acts = pirate_npc_act(
+            &mut bot,
+            ship,
+            state,
+            elapsed_micro,
+            &ship_idx,
+            &build_full_spatial_indexes(state),
+        );
+        (bot, acts)
+    }
+
+    fn shoots_at(acts: &Vec<Action>, target_ship_id: Uuid) -> bool {
+        acts.iter().any(|a| match a {
+            Action::LongActionStart {
+                long_action_start:
+                    LongActionStart::Shoot {
+                        target: ObjectSpecifier::Ship { id },
+                        ..
+                    },
+                player_id: None,
+                ..
+            } => *id == target_ship_id,
+            _ => false,
+        })
+    }
+
+    fn spawn_pirates(state: &mut GameState, prng: &mut Pcg64Mcg) {
+        while let Some(event) = state.events.as_mut().unwrap().pop_front() {
+            if let GameEvent::PirateSpawn {
+                at,
+                class_id,
+                health_multiplier,
+                npc_traits,
+                ..
+            } = event
+            {
+                on_pirate_spawn(state, &at, &class_id, health_multiplier, npc_traits, prng);
+            }
+        }
+    }
+
+    fn pirates(state: &GameState) -> Vec<&Ship> {
+        state.locations[0]
+            .ships
+            .iter()
+            .filter(|s| has_property(&s.properties, ObjectPropertyKey::PirateShip))
+            .collect()
+    }
+
+    #[test]
+    pub fn waves_scale_with_players_and_progress() {
+        assert_eq!(scale_wave_ship_count(2, 0), 2);
+        assert_eq!(scale_wave_ship_count(2, 1), 2);
+        assert_eq!(scale_wave_ship_count(2, 2), 3);
+        assert_eq!(scale_wave_ship_count(2, 3), 4);
+        assert_eq!(get_wave_health_multiplier(0), 1.0);
+        assert!(get_wave_health_multiplier(2) > get_wave_health_multiplier(1));
+        for wave in get_pirate_waves() {
+            for group in wave.groups {
+                assert!(
+                    get_ship_class(&group.class_id).is_some(),
+                    "{} is not defined",
+                    group.class_id
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn cleared_waves_pay_bonus_and_end_in_victory() {
+        let mut prng = seed_prng("waves".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "waves");
+        for _i in 0..2 {
+            add_player(&mut state, new_id(), false, None, &mut prng);
+        }
+        let waves = get_pirate_waves();
+        for (i, wave) in waves.iter().enumerate() {
+            update_state_pirate_defence(&mut state, &mut prng);
+            assert!(!state.pirate_waves.as_ref().unwrap().in_progress);
+
+            state.ticks = state.pirate_waves.as_ref().unwrap().next_wave_at_ticks;
+            update_state_pirate_defence(&mut state, &mut prng);
+            let expected: usize = wave
+                .groups
+                .iter()
+                .map(|g| scale_wave_ship_count(g.count, 2))
+                .sum();
+            assert_eq!(
+                state.pirate_waves.as_ref().unwrap().pending_spawns,
+                expected
+            );
+            spawn_pirates(&mut state, &mut prng);
+            assert_eq!(pirates(&state).len(), expected);
+            let first = pirates(&state)[0];
+            let class = get_ship_class(first.class_id.as_ref().unwrap()).unwrap();
+            assert!((first.health.max - class.health * get_wave_health_multiplier(i)).abs() < 1e-9);
+            update_state_pirate_defence(&mut state, &mut prng);
+            assert_eq!(state.pirate_waves.as_ref().unwrap().current, i);
+
+            let money_before = state.players[0].money;
+            state.locations[0]
+                .ships
+                .retain(|s| !has_property(&s.properties, ObjectPropertyKey::PirateShip));
+            update_state_pirate_defence(&mut state, &mut prng);
+            assert_eq!(state.pirate_waves.as_ref().unwrap().current, i + 1);
+            assert_eq!(state.players[0].money - money_before, wave.clear_bonus);
+            if i + 1 < waves.len() {
+                assert!(state.game_over.is_none());
+            }
+        }
+        assert!(state.game_over.unwrap().victory);
+    }
+
+    #[test]
+    pub fn attackers_shoot_players_and_evasive_ones_break_off() {
+        let mut prng = seed_prng("pirate attack".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "pirate ai");
+        let (_player_id, player_ship_id) =
+            add_player_ship(&mut state, false, None, Some(AT), &mut prng);
+        let pirate_id = spawn_pirate(
+            &mut state,
+            AT.add(&Vec2f64 { x: 10.0, y: 0.0 }),
+            Some(vec![AiTrait::PirateAttacker, AiTrait::PirateEvasive]),
+            &mut prng,
+        );
+
+        let acts = act(&state, pirate_id, 0).1.unwrap();
+        assert!(shoots_at(&acts, player_ship_id));
+        assert!(acts.iter().any(|a| matches!(
+            a,
+            Action::LongActionStart {
+                long_action_start: LongActionStart::Launch { .. },
+                ..
+            }
+        )));
+
+        let pirate = find_ship_mut(&mut state, pirate_id).unwrap();
+        pirate.health.current = pirate.health.max * PIRATE_EVADE_HEALTH_SHARE / 2.0;
+        let (bot, acts) = act(&state, pirate_id, 0);
+        assert_eq!(bot.timer, Some(PIRATE_EVADE_TICKS));
+        match acts.unwrap().as_slice() {
+            [Action::Navigate { target, .. }] => assert!(target.x > AT.x + 10.0),
+            other => panic!("expected to break off, got {:?}", other),
+        }
+
+        // the break-off happens only once
+        find_ship_mut(&mut state, pirate_id).unwrap().npc = Some(bot);
+        let (bot, acts) = act(&state, pirate_id, PIRATE_EVADE_TICKS);
+        assert!(bot.timer.unwrap() < 0);
+        assert!(shoots_at(&acts.unwrap(), player_ship_id));
+    }
+
+    #[test]
+    pub fn escorts_keep_around_the_leader() {
+        let mut prng = seed_prng("pirate escort".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "pirate ai");
+        let leader_id = spawn_pirate(&mut state, AT, None, &mut prng);
+        let escort_id = spawn_pirate(
+            &mut state,
+            AT.add(&Vec2f64 { x: 0.0, y: 40.0 }),
+            Some(vec![AiTrait::PirateEscort, AiTrait::ImmediatePlanetLand]),
+            &mut prng,
+        );
+
+        let leader_radius = {
+            let idx = find_ship_index(&state, leader_id).unwrap();
+            state.locations[idx.location_idx].ships[idx.ship_idx]
+                .spatial
+                .radius
+        };
+        match act(&state, escort_id, 0).1.unwrap().as_slice() {
+            [Action::Navigate { target, .. }] => {
+                let dist = target.euclidean_distance(&AT);
+                assert!((dist - (leader_radius + PIRATE_ESCORT_DISTANCE)).abs() < 1e-6);
+            }
+            other => panic!("expected to follow the leader, got {:?}", other),
+        }
+
+        // the other traits take over without a leader
+        state.locations[0].ships.retain(|s| s.id != leader_id);
+        assert!(act(&state, escort_id, 0).1.is_none());
+    }
+
+    #[test]
+    pub fn pirate_rockets_are_not_hostile_to_pirates() {
+        let mut prng = seed_prng("pirate rockets".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "pirate ai");
+        let pirate_id = spawn_pirate(&mut state, AT, None, &mut prng);
+        let mut rocket = state
+            .projectile_templates
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|t| t.get_id() == TemplateId::Rocket as i32)
+            .unwrap()
+            .clone();
+        rocket.set_launched_by(Some(pirate_id));
+        state.locations[0].projectiles.push(rocket);
+        let rocket_idx = state.locations[0].projectiles.len() - 1;
+        let pirate_idx = find_ship_index(&state, pirate_id).unwrap().ship_idx;
+        let fof = |state: &GameState| {
+            friend_or_foe(
+                state,
+                FofActor::ObjectIdx {
+                    spec: ObjectIndexSpecifier::Projectile { idx: rocket_idx },
+                },
+                FofActor::ObjectIdx {
+                    spec: ObjectIndexSpecifier::Ship { idx: pirate_idx },
+                },
+                0,
+            )
+        };
+        assert_eq!(fof(&state), FriendOrFoe::Neutral);
+
+        state.locations[0].projectiles[rocket_idx].set_launched_by(None);
+        assert_eq!(fof(&state), FriendOrFoe::Foe);
+    }
+}
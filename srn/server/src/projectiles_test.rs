@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod projectiles_test {
+    use crate::abilities::Ability;
+    use crate::autofocus::build_spatial_index;
+    use crate::combat::{
+        try_reacquire_target, update_emp_disabled, update_point_defence,
+        update_projectile_collisions, EMP_DISABLE_TICKS, MINE_TRIGGER_RADIUS, SLUG_DAMAGE,
+    };
+    use crate::indexing::{index_state, ObjectIndexSpecifier};
+    use crate::properties::{has_property, ObjectPropertyKey};
+    use crate::seed_prng;
+    use crate::test_helpers::{add_player_ship, new_state};
+    use crate::vec2::Vec2f64;
+    use crate::world::{spawn_ship, GameMode, GameState, ShipTemplate, TemplateId, UpdateOptions};
+    use crate::world_actions::MoveAxisParam;
+    use rand_pcg::Pcg64Mcg;
+
+    const AT: Vec2f64 = Vec2f64 { x: 321.5, y: 123.5 };
+
+    fn state_with_ship(mode: &GameMode, template: ShipTemplate, prng: &mut Pcg64Mcg) -> GameState {
+        let mut state = new_state(mode, "projectiles");
+        spawn_ship(&mut state, None, template, prng);
+        state
+    }
+
+    fn add_projectile(state: &mut GameState, template_id: TemplateId, at: &Vec2f64) {
+        let mut proj = state
+            .projectile_templates
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|t| t.get_id() == template_id as i32)
+            .expect("no projectile template")
+            .clone();
+        let loc = &mut state.locations[0];
+        loc.short_counter += 1;
+        proj.set_id(loc.short_counter);
+        proj.set_position_from(at);
+        loc.projectiles.push(proj);
+    }
+
+    fn collide(state: &mut GameState, prng: &mut Pcg64Mcg) {
+        let state_clone = state.clone();
+        let mut indexes = index_state(&state_clone);
+        let sp_idx = build_spatial_index(&state.locations[0], 0);
+        update_projectile_collisions(
+            &mut state.locations[0],
+            &UpdateOptions::new(),
+            &sp_idx,
+            0,
+            &mut indexes,
+            state_clone.ticks,
+            prng,
+        );
+    }
+
+    #[test]
+    pub fn slug_deals_direct_kinetic_damage() {
+        let mut prng = seed_prng("slug".to_string());
+        let mut state = state_with_ship(
+            &GameMode::CargoRush,
+            ShipTemplate::player(Some(AT)),
+            &mut prng,
+        );
+        let health = state.locations[0].ships.last().unwrap().health.clone();
+        let total_before = health.current + health.shield.as_ref().map_or(0.0, |s| s.current);
+        add_projectile(&mut state, TemplateId::Slug, &AT);
+        assert_eq!(
+            state.locations[0].projectiles[0].get_direct_damage(),
+            SLUG_DAMAGE
+        );
+
+        collide(&mut state, &mut prng);
+
+        let health = &state.locations[0].ships.last().unwrap().health;
+        let total_after = health.current + health.shield.as_ref().map_or(0.0, |s| s.current);
+        assert!((total_before - total_after - SLUG_DAMAGE).abs() < 1e-9);
+        assert_eq!(
+            state.locations[0].projectiles[0]
+                .get_health_mut()
+                .unwrap()
+                .current,
+            0.0
+        );
+    }
+
+    #[test]
+    pub fn emp_disables_ship_for_a_duration() {
+        let mut prng = seed_prng("emp".to_string());
+        let mut state = state_with_ship(
+            &GameMode::CargoRush,
+            ShipTemplate::player(Some(AT)),
+            &mut prng,
+        );
+        state.locations[0]
+            .ships
+            .last_mut()
+            .unwrap()
+            .movement_markers
+            .gas = Some(MoveAxisParam {
+            forward: true,
+            last_tick: state.ticks as u32,
+        });
+        add_projectile(&mut state, TemplateId::Emp, &AT);
+
+        collide(&mut state, &mut prng);
+
+        let ship = state.locations[0].ships.last().unwrap();
+        assert!(has_property(
+            &ship.properties,
+            ObjectPropertyKey::EmpDisabled
+        ));
+        assert!(ship.movement_markers.gas.is_none());
+        assert!(ship
+            .abilities
+            .iter()
+            .filter(|a| matches!(a, Ability::Shoot { .. } | Ability::Launch { .. }))
+            .all(|a| a.get_current_cooldown() >= EMP_DISABLE_TICKS));
+
+        update_emp_disabled(&mut state.locations[0].ships, EMP_DISABLE_TICKS as i64);
+        let ship = state.locations[0].ships.last().unwrap();
+        assert!(!has_property(
+            &ship.properties,
+            ObjectPropertyKey::EmpDisabled
+        ));
+    }
+
+    #[test]
+    pub fn mine_is_triggered_only_by_foes_in_range() {
+        let mut prng = seed_prng("mine".to_string());
+        let mut state = state_with_ship(
+            &GameMode::PirateDefence,
+            ShipTemplate::pirate(Some(AT)),
+            &mut prng,
+        );
+        let far = AT.add(&Vec2f64 {
+            x: MINE_TRIGGER_RADIUS * 2.0,
+            y: 0.0,
+        });
+        let near = AT.add(&Vec2f64 {
+            x: MINE_TRIGGER_RADIUS * 0.5,
+            y: 0.0,
+        });
+        add_projectile(&mut state, TemplateId::Mine, &far);
+        add_projectile(&mut state, TemplateId::Mine, &near);
+        let radius = state.locations[0].projectiles[0]
+            .get_acquire_radius()
+            .unwrap();
+        assert_eq!(radius, MINE_TRIGGER_RADIUS);
+
+        let sp_idx = build_spatial_index(&state.locations[0], 0);
+        let acquired: Vec<Option<ObjectIndexSpecifier>> = vec![&far, &near]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, pos)| {
+                try_reacquire_target(
+                    ObjectIndexSpecifier::Projectile { idx },
+                    pos,
+                    &sp_idx,
+                    &state,
+                    0,
+                    radius,
+                )
+            })
+            .collect();
+        assert!(acquired[0].is_none());
+        assert!(matches!(
+            acquired[1],
+            Some(ObjectIndexSpecifier::Ship { .. })
+        ));
+    }
+
+    #[test]
+    pub fn point_defence_shoots_down_only_hostile_projectiles() {
+        let mut prng = seed_prng("point defence".to_string());
+        let mut state = new_state(&GameMode::PirateDefence, "projectiles");
+        let (_player_id, ship_id) = add_player_ship(&mut state, false, None, Some(AT), &mut prng);
+        let near = AT.add(&Vec2f64 { x: 10.0, y: 0.0 });
+        add_projectile(&mut state, TemplateId::Rocket, &near);
+        add_projectile(&mut state, TemplateId::Rocket, &near);
+        // the first one is our own
+        state.locations[0].projectiles[0].set_launched_by(Some(ship_id));
+
+        let state_clone = state.clone();
+        let indexes = index_state(&state_clone);
+        let sp_idx = build_spatial_index(&state.locations[0], 0);
+        update_point_defence(&mut state, 0, &sp_idx, &indexes, 0, &mut prng);
+
+        let projectiles = &mut state.locations[0].projectiles;
+        assert!(projectiles[0].get_health_mut().unwrap().current > 0.0);
+        assert_eq!(projectiles[1].get_health_mut().unwrap().current, 0.0);
+        let ability = state.locations[0]
+            .ships
+            .last()
+            .unwrap()
+            .abilities
+            .iter()
+            .find(|a| matches!(a, Ability::PointDefence { .. }))
+            .expect("player ship has no point defence");
+        assert!(ability.get_current_cooldown() > 0);
+    }
+}
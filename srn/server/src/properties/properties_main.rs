@@ -1,6 +1,7 @@
 use crate::autofocus::SpatialIndex;
 use crate::indexing::{GameStateIndexes, ObjectIndexSpecifier};
 use crate::properties::decay;
+use crate::system_gen::PlanetType;
 use crate::world::{GameState, Location, ProcessProps, UpdateOptions};
 use serde_derive::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
@@ -12,6 +13,11 @@ pub struct MoneyOnKillProps {
     pub amount: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct PlanetTypeProps {
+    pub planet_type: PlanetType,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
 #[serde(tag = "tag", content = "fields")]
@@ -24,6 +30,10 @@ pub enum ObjectProperty {
     MoneyOnKill(MoneyOnKillProps),
     Decays(ProcessProps),
     Lifetime(ProcessProps),
+    // hit by an EMP burst, see combat::update_emp_disabled
+    EmpDisabled(ProcessProps),
+    // what the planet produces and consumes, see economy::get_planet_economy
+    PlanetType(PlanetTypeProps),
 }
 
 #[skip_serializing_none]
@@ -39,6 +49,8 @@ pub enum ObjectPropertyKey {
     MoneyOnKill,
     Decays,
     Lifetime,
+    EmpDisabled,
+    PlanetType,
 }
 
 impl ObjectProperty {
@@ -54,6 +66,8 @@ impl ObjectProperty {
             ObjectProperty::PirateShip => ObjectPropertyKey::PirateShip,
             ObjectProperty::Lifetime { .. } => ObjectPropertyKey::Lifetime,
             ObjectProperty::Invulnerable => ObjectPropertyKey::Invulnerable,
+            ObjectProperty::EmpDisabled { .. } => ObjectPropertyKey::EmpDisabled,
+            ObjectProperty::PlanetType { .. } => ObjectPropertyKey::PlanetType,
         }
     }
 }
@@ -350,6 +350,8 @@ pub fn seed_asteroids(star: &Star, prng: &mut Pcg64Mcg) -> Vec<Asteroid> {
                 last_damage_dealer: None,
                 acc_periodic_dmg: 0.0,
                 acc_periodic_heal: 0.0,
+                shield: None,
+                resistances: None,
             },
             rot_movement: RotationMovement::None,
             to_clean: false,
@@ -31,6 +31,8 @@ pub enum ReplayError {
     BadPatch,
     InvalidRewind(u32),
     NotImplemented,
+    BadFormat(String),
+    UnsupportedFormatVersion(u16),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -145,6 +147,8 @@ pub type PrevState = GameState;
 pub type NextState = GameState;
 pub type CurrState = GameState;
 
+pub const DEFAULT_KEYFRAME_EVERY_TICKS: u32 = 10 * 1000 * 1000;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReplayDiffed {
     pub id: Uuid,
@@ -157,6 +161,13 @@ pub struct ReplayDiffed {
     pub max_time_ms: u32,
     pub current_millis: f64,
     pub marks_ticks: Vec<u32>,
+    // (diff_mark, full state at that mark), so restoration doesn't have to start from the initial state.
+    // replays made before keyframes have none of these, see migrate_keyframes
+    #[serde(default)]
+    pub keyframes: Vec<(u32, GameState)>,
+    // 0 means no keyframes are produced when adding new states
+    #[serde(default)]
+    pub keyframe_every_ticks: u32,
 }
 
 impl ReplayRaw {
@@ -200,6 +211,8 @@ impl ReplayDiffed {
             max_time_ms: 0,
             current_millis: 0.0,
             marks_ticks: vec![],
+            keyframes: vec![],
+            keyframe_every_ticks: DEFAULT_KEYFRAME_EVERY_TICKS,
         }
     }
 
@@ -219,9 +232,44 @@ impl ReplayDiffed {
         self.diffs.push((ticks as u32, new_diff));
         self.max_time_ms = millis as u32;
         self.marks_ticks.push(ticks as u32);
+        if self.is_keyframe_due(ticks as u32) {
+            if let Some(current_state) = &self.current_state {
+                self.keyframes.push((ticks as u32, current_state.clone()));
+            }
+        }
         Ok(())
     }
 
+    fn is_keyframe_due(&self, ticks: u32) -> bool {
+        if self.keyframe_every_ticks == 0 {
+            return false;
+        }
+        let last_keyframe_ticks = self
+            .keyframes
+            .last()
+            .map_or(self.initial_state.ticks as u32, |k| k.0);
+        ticks.saturating_sub(last_keyframe_ticks) >= self.keyframe_every_ticks
+    }
+
+    // (re)builds keyframes by restoring every state once, for the replays that were made without them
+    pub fn migrate_keyframes(&mut self, every_ticks: u32) -> Result<(), ReplayError> {
+        self.keyframes = vec![];
+        self.keyframe_every_ticks = every_ticks;
+        let mut current = self.initial_state.clone();
+        for i in 0..self.diffs.len() {
+            current = Self::apply_diff_batch(&current, &self.diffs[i].1)?;
+            let ticks = self.diffs[i].0;
+            if self.is_keyframe_due(ticks) {
+                self.keyframes.push((ticks, current.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn needs_keyframes_migration(&self) -> bool {
+        self.keyframes.is_empty() && !self.diffs.is_empty()
+    }
+
     fn update_current(&mut self, new_diff: &Vec<ValueDiff>) -> Result<(), ReplayError> {
         if let Some(current_state) = &self.current_state {
             self.current_state = Some(ReplayDiffed::apply_diff_batch(&current_state, new_diff)?);
@@ -281,9 +329,8 @@ impl ReplayDiffed {
         }
 
         return match result {
-            Ok(()) => {
-                Ok(serde_json::from_value::<GameState>(current).expect("Couldn't restore typing"))
-            }
+            Ok(()) => serde_json::from_value::<GameState>(current)
+                .map_err(|e| ReplayError::BadFormat(format!("bad patched state, err {}", e))),
             Err(e) => {
                 warn!(format!("bad patch {:?}", e));
                 Err(ReplayError::BadPatch)
@@ -359,52 +406,61 @@ impl ReplayDiffed {
         count: usize,
         sampler: &mut Option<Sampler>,
         from_idx: usize,
+        from_state: &GameState,
     ) -> Result<GameState, ReplayError> {
-        let diffs: Vec<Vec<ValueDiff>> = self
-            .diffs
-            .iter()
-            .skip(from_idx)
-            .take(count)
-            .map(|d| d.1.clone())
-            .collect();
-        let mut current = if from_idx > 0 {
-            self.current_state
-                .as_ref()
-                .unwrap_or(&self.initial_state)
-                .clone()
-        } else {
-            self.initial_state.clone()
-        };
-        for batch in diffs {
+        let mut current = from_state.clone();
+        for (_, batch) in self.diffs.iter().skip(from_idx).take(count) {
             let sid = sampler
                 .as_mut()
                 .map(|s| s.start(SamplerMarks::ApplyReplayDiffBatch as u32));
-            current = Self::apply_diff_batch(&current, &batch)?;
+            current = Self::apply_diff_batch(&current, batch)?;
             sampler.as_mut().zip(sid).map(|(s, i)| s.end(i));
         }
         Ok(current)
     }
 
+    // marks are recorded in the ticks order, so are the keyframes
+    fn get_mark_index(&self, ticks: u32) -> Option<usize> {
+        self.marks_ticks.binary_search(&ticks).ok()
+    }
+
+    // the closest state at or before the mark index that is already restored - the initial one,
+    // the preceding keyframe or the current state when moving forwards
+    fn find_closest_restored(&self, index: usize) -> (usize, &GameState) {
+        let mut closest = (0, &self.initial_state);
+        let index_ticks = self.marks_ticks[index];
+        let keyframes_before = self
+            .keyframes
+            .partition_point(|(ticks, _)| *ticks <= index_ticks);
+        let keyframe = keyframes_before
+            .checked_sub(1)
+            .map(|i| &self.keyframes[i])
+            .and_then(|(ticks, state)| self.get_mark_index(*ticks).map(|i| (i, state)));
+        if let Some(keyframe) = keyframe {
+            if keyframe.0 > closest.0 {
+                closest = keyframe;
+            }
+        }
+        let current = self
+            .current_state
+            .as_ref()
+            .and_then(|s| self.get_mark_index(s.ticks as u32).map(|i| (i, s)));
+        if let Some(current) = current {
+            if current.0 <= index && current.0 > closest.0 {
+                closest = current;
+            }
+        }
+        closest
+    }
+
     pub fn get_state_at(
         &self,
         ticks: u32,
         sampler: &mut Option<Sampler>,
     ) -> Result<GameState, ReplayError> {
-        let index = self.marks_ticks.iter().position(|mark| *mark == ticks);
-        let current_ticks = self.current_state.as_ref().map_or(0, |s| s.ticks) as u32;
-        let current_index = self
-            .marks_ticks
-            .iter()
-            .position(|mark| *mark == current_ticks)
-            .unwrap_or(0);
-        if let Some(index) = index {
-            if current_index <= index {
-                // forwards, optimization will work
-                return Ok(self.apply_n_diffs(index - current_index, sampler, current_index)?);
-            } else {
-                // backwards, no optimization
-                return Ok(self.apply_n_diffs(index, sampler, 0)?);
-            }
+        if let Some(index) = self.get_mark_index(ticks) {
+            let (from_idx, from_state) = self.find_closest_restored(index);
+            return Ok(self.apply_n_diffs(index - from_idx, sampler, from_idx, from_state)?);
         }
         return Err(ReplayError::InvalidRewind(
             self.current_state.as_ref().map_or(0, |s| s.ticks as u32),
@@ -442,14 +498,10 @@ impl ReplayDiffed {
         caches: &mut GameStateCaches,
     ) -> Result<(PrevState, NextState, CurrState), ReplayError> {
         let prev_ticks_index = self
-            .marks_ticks
-            .iter()
-            .position(|mark| *mark == prev_ticks)
+            .get_mark_index(prev_ticks)
             .ok_or(ReplayError::InvalidRewind(prev_ticks))?;
         let next_ticks_index = self
-            .marks_ticks
-            .iter()
-            .position(|mark| *mark == next_ticks)
+            .get_mark_index(next_ticks)
             .ok_or(ReplayError::InvalidRewind(next_ticks))?;
 
         // only allow replay interpolation between the adjacent subsequent states
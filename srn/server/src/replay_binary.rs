@@ -0,0 +1,605 @@
+use crate::perf::{Sampler, SamplerMarks};
+use crate::replay::{ReplayDiffed, ReplayError, ValueDiff, DEFAULT_KEYFRAME_EVERY_TICKS};
+use crate::world::{GameState, GAME_STATE_VERSION};
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Compact replay container, all numbers are little-endian, lengths and indexes are LEB128 varints:
+//   magic "SRNR", format version u16, game state version u32
+//   string table - every object key, string value and diff path is stored only once
+//   meta - id, name, max_time_ms, current_millis, marks_ticks
+//   index - (ticks, offset) of every diff batch, (batch index, ticks, offset) of every keyframe
+//   body - initial state, length-prefixed diff batches, then keyframes
+// A keyframe is a full state after applying the batch with its index, so seeking
+// can start from the nearest keyframe instead of the initial state.
+pub const REPLAY_BINARY_MAGIC: &[u8; 4] = b"SRNR";
+pub const REPLAY_BINARY_FORMAT_VERSION: u16 = 1;
+pub const REPLAY_BINARY_EXTENSION: &str = "srnr";
+
+const VALUE_NULL: u8 = 0;
+const VALUE_FALSE: u8 = 1;
+const VALUE_TRUE: u8 = 2;
+const VALUE_UINT: u8 = 3;
+const VALUE_INT: u8 = 4;
+const VALUE_FLOAT: u8 = 5;
+const VALUE_STRING: u8 = 6;
+const VALUE_ARRAY: u8 = 7;
+const VALUE_OBJECT: u8 = 8;
+
+// nested arrays and objects, the game state is nowhere near that deep, but a malformed input could
+// otherwise overflow the stack
+pub(crate) const MAX_VALUE_DEPTH: usize = 128;
+
+const DIFF_ADDED: u8 = 0;
+const DIFF_MODIFIED: u8 = 1;
+const DIFF_REMOVED: u8 = 2;
+
+#[derive(Default)]
+pub(crate) struct StringTable {
+    pub(crate) strings: Vec<String>,
+    indexes: HashMap<String, u64>,
+}
+
+impl StringTable {
+    pub(crate) fn intern(&mut self, str: &String) -> u64 {
+        if let Some(idx) = self.indexes.get(str) {
+            return *idx;
+        }
+        let idx = self.strings.len() as u64;
+        self.strings.push(str.clone());
+        self.indexes.insert(str.clone(), idx);
+        idx
+    }
+}
+
+pub(crate) struct ByteWriter {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub(crate) fn new() -> Self {
+        ByteWriter { bytes: vec![] }
+    }
+
+    pub(crate) fn u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub(crate) fn u16(&mut self, v: u16) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn f64(&mut self, v: f64) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn varint(&mut self, mut v: u64) {
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    pub(crate) fn str(&mut self, v: &str) {
+        self.varint(v.len() as u64);
+        self.bytes.extend_from_slice(v.as_bytes());
+    }
+
+    pub(crate) fn value(&mut self, v: &Value, table: &mut StringTable) {
+        match v {
+            Value::Null => self.u8(VALUE_NULL),
+            Value::Bool(false) => self.u8(VALUE_FALSE),
+            Value::Bool(true) => self.u8(VALUE_TRUE),
+            Value::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    self.u8(VALUE_UINT);
+                    self.varint(u);
+                } else if let Some(i) = n.as_i64() {
+                    self.u8(VALUE_INT);
+                    // zigzag, so small negative numbers stay small
+                    self.varint(((i << 1) ^ (i >> 63)) as u64);
+                } else {
+                    self.u8(VALUE_FLOAT);
+                    self.f64(n.as_f64().unwrap_or(0.0));
+                }
+            }
+            Value::String(s) => {
+                self.u8(VALUE_STRING);
+                let idx = table.intern(s);
+                self.varint(idx);
+            }
+            Value::Array(arr) => {
+                self.u8(VALUE_ARRAY);
+                self.varint(arr.len() as u64);
+                for item in arr.iter() {
+                    self.value(item, table);
+                }
+            }
+            Value::Object(obj) => {
+                self.u8(VALUE_OBJECT);
+                self.varint(obj.len() as u64);
+                for (key, item) in obj.iter() {
+                    let idx = table.intern(key);
+                    self.varint(idx);
+                    self.value(item, table);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn diff_batch(&mut self, batch: &Vec<ValueDiff>, table: &mut StringTable) {
+        let ops = batch
+            .iter()
+            .filter(|d| !matches!(d, ValueDiff::Unchanged))
+            .collect::<Vec<_>>();
+        self.varint(ops.len() as u64);
+        for op in ops {
+            match op {
+                ValueDiff::Unchanged => {}
+                ValueDiff::Added(path, v) => {
+                    self.u8(DIFF_ADDED);
+                    let idx = table.intern(path);
+                    self.varint(idx);
+                    self.value(v, table);
+                }
+                ValueDiff::Modified(path, v) => {
+                    self.u8(DIFF_MODIFIED);
+                    let idx = table.intern(path);
+                    self.varint(idx);
+                    self.value(v, table);
+                }
+                ValueDiff::Removed(path) => {
+                    self.u8(DIFF_REMOVED);
+                    let idx = table.intern(path);
+                    self.varint(idx);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], pos: usize) -> Self {
+        ByteReader { bytes, pos }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    pub(crate) fn take(&mut self, count: usize) -> Result<&'a [u8], ReplayError> {
+        let end = match self.pos.checked_add(count) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => {
+                return Err(ReplayError::BadFormat(format!(
+                    "unexpected end of data at {}",
+                    self.pos
+                )));
+            }
+        };
+        let res = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(res)
+    }
+
+    // every counted element takes at least one byte, so anything bigger is malformed - and it must not
+    // be trusted for the pre-allocation either
+    pub(crate) fn count(&mut self) -> Result<usize, ReplayError> {
+        let count = self.varint()?;
+        if count > self.remaining() as u64 {
+            return Err(ReplayError::BadFormat(format!(
+                "bad count {} at {}",
+                count, self.pos
+            )));
+        }
+        Ok(count as usize)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, ReplayError> {
+        let mut buf = [0u8; 2];
+        buf.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, ReplayError> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, ReplayError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, ReplayError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(f64::from_le_bytes(buf))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, ReplayError> {
+        let mut res: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(ReplayError::BadFormat(format!(
+                    "varint is too long at {}",
+                    self.pos
+                )));
+            }
+            res |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(res);
+            }
+            shift += 7;
+        }
+    }
+
+    pub(crate) fn str(&mut self) -> Result<String, ReplayError> {
+        let len = self.count()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| ReplayError::BadFormat(format!("bad string, err {}", e)))
+    }
+
+    pub(crate) fn table_str(&mut self, strings: &Vec<String>) -> Result<String, ReplayError> {
+        let idx = self.varint()? as usize;
+        strings
+            .get(idx)
+            .map(|s| s.clone())
+            .ok_or(ReplayError::BadFormat(format!("bad string index {}", idx)))
+    }
+
+    pub(crate) fn value(&mut self, strings: &Vec<String>) -> Result<Value, ReplayError> {
+        self.nested_value(strings, 0)
+    }
+
+    fn nested_value(&mut self, strings: &Vec<String>, depth: usize) -> Result<Value, ReplayError> {
+        if depth > MAX_VALUE_DEPTH {
+            return Err(ReplayError::BadFormat(format!(
+                "value is nested too deep at {}",
+                self.pos
+            )));
+        }
+        let tag = self.u8()?;
+        Ok(match tag {
+            VALUE_NULL => Value::Null,
+            VALUE_FALSE => Value::Bool(false),
+            VALUE_TRUE => Value::Bool(true),
+            VALUE_UINT => Value::Number(Number::from(self.varint()?)),
+            VALUE_INT => {
+                let zigzag = self.varint()?;
+                let i = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+                Value::Number(Number::from(i))
+            }
+            VALUE_FLOAT => {
+                let f = self.f64()?;
+                Number::from_f64(f)
+                    .map(|n| Value::Number(n))
+                    .unwrap_or(Value::Null)
+            }
+            VALUE_STRING => Value::String(self.table_str(strings)?),
+            VALUE_ARRAY => {
+                let len = self.count()?;
+                let mut arr = Vec::with_capacity(len);
+                for _ in 0..len {
+                    arr.push(self.nested_value(strings, depth + 1)?);
+                }
+                Value::Array(arr)
+            }
+            VALUE_OBJECT => {
+                let len = self.count()?;
+                let mut obj = Map::new();
+                for _ in 0..len {
+                    let key = self.table_str(strings)?;
+                    obj.insert(key, self.nested_value(strings, depth + 1)?);
+                }
+                Value::Object(obj)
+            }
+            _ => {
+                return Err(ReplayError::BadFormat(format!(
+                    "unknown value tag {} at {}",
+                    tag,
+                    self.pos - 1
+                )));
+            }
+        })
+    }
+
+    pub(crate) fn diff_batch(
+        &mut self,
+        strings: &Vec<String>,
+    ) -> Result<Vec<ValueDiff>, ReplayError> {
+        let count = self.count()?;
+        let mut batch = Vec::with_capacity(count);
+        for _ in 0..count {
+            let tag = self.u8()?;
+            let path = self.table_str(strings)?;
+            batch.push(match tag {
+                DIFF_ADDED => ValueDiff::Added(path, self.value(strings)?),
+                DIFF_MODIFIED => ValueDiff::Modified(path, self.value(strings)?),
+                DIFF_REMOVED => ValueDiff::Removed(path),
+                _ => {
+                    return Err(ReplayError::BadFormat(format!(
+                        "unknown diff tag {} at {}",
+                        tag,
+                        self.pos - 1
+                    )));
+                }
+            });
+        }
+        Ok(batch)
+    }
+}
+
+fn state_to_value(state: &GameState) -> Value {
+    serde_json::to_value(state).expect("Couldn't erase typing")
+}
+
+fn value_to_state(value: Value) -> Result<GameState, ReplayError> {
+    serde_json::from_value::<GameState>(value)
+        .map_err(|e| ReplayError::BadFormat(format!("bad state, err {}", e)))
+}
+
+pub fn write_replay_binary(replay: &ReplayDiffed) -> Result<Vec<u8>, ReplayError> {
+    let migrated;
+    let replay = if replay.needs_keyframes_migration() {
+        let mut clone = replay.clone();
+        clone.migrate_keyframes(DEFAULT_KEYFRAME_EVERY_TICKS)?;
+        migrated = clone;
+        &migrated
+    } else {
+        replay
+    };
+    let mut table = StringTable::default();
+    let mut body = ByteWriter::new();
+    body.value(&state_to_value(&replay.initial_state), &mut table);
+
+    let mut batch_index = vec![];
+    for (ticks, batch) in replay.diffs.iter() {
+        let mut encoded = ByteWriter::new();
+        encoded.diff_batch(batch, &mut table);
+        batch_index.push((*ticks, body.bytes.len() as u64));
+        body.u32(encoded.bytes.len() as u32);
+        body.bytes.extend(encoded.bytes);
+    }
+    let mut keyframe_index = vec![];
+    for (ticks, state) in replay.keyframes.iter() {
+        // mark 0 is the initial state, so the keyframe at mark n is the state after the batch n - 1
+        let batch_idx = match replay.marks_ticks.iter().position(|m| m == ticks) {
+            Some(mark_idx) if mark_idx > 0 => (mark_idx - 1) as u32,
+            _ => continue,
+        };
+        keyframe_index.push((batch_idx, *ticks, body.bytes.len() as u64));
+        body.value(&state_to_value(state), &mut table);
+    }
+
+    let mut meta = ByteWriter::new();
+    meta.bytes.extend_from_slice(replay.id.as_bytes());
+    let name_idx = table.intern(&replay.name);
+    meta.varint(name_idx);
+    meta.u32(replay.max_time_ms);
+    meta.f64(replay.current_millis);
+    meta.varint(replay.marks_ticks.len() as u64);
+    for mark in replay.marks_ticks.iter() {
+        meta.u32(*mark);
+    }
+
+    let mut out = ByteWriter::new();
+    out.bytes.extend_from_slice(REPLAY_BINARY_MAGIC);
+    out.u16(REPLAY_BINARY_FORMAT_VERSION);
+    out.u32(replay.initial_state.version);
+    out.varint(table.strings.len() as u64);
+    for str in table.strings.iter() {
+        out.str(str);
+    }
+    out.bytes.extend(meta.bytes);
+    out.varint(batch_index.len() as u64);
+    for (ticks, offset) in batch_index {
+        out.u32(ticks);
+        out.u64(offset);
+    }
+    out.varint(keyframe_index.len() as u64);
+    for (batch_idx, ticks, offset) in keyframe_index {
+        out.u32(batch_idx);
+        out.u32(ticks);
+        out.u64(offset);
+    }
+    out.u64(body.bytes.len() as u64);
+    out.bytes.extend(body.bytes);
+    Ok(out.bytes)
+}
+
+pub fn convert_json_to_binary(json: &str) -> Result<Vec<u8>, ReplayError> {
+    let replay = serde_json::from_str::<ReplayDiffed>(json)
+        .map_err(|e| ReplayError::BadFormat(format!("bad json replay, err {}", e)))?;
+    write_replay_binary(&replay)
+}
+
+#[derive(Debug, Clone)]
+pub struct CompactReplay {
+    pub id: Uuid,
+    pub name: String,
+    pub game_state_version: u32,
+    pub max_time_ms: u32,
+    pub current_millis: f64,
+    pub marks_ticks: Vec<u32>,
+    strings: Vec<String>,
+    // (ticks, offset in body)
+    batches: Vec<(u32, u64)>,
+    // (batch index, ticks, offset in body)
+    keyframes: Vec<(u32, u32, u64)>,
+    body: Vec<u8>,
+}
+
+impl CompactReplay {
+    pub fn parse(bytes: &[u8]) -> Result<CompactReplay, ReplayError> {
+        let mut reader = ByteReader::new(bytes, 0);
+        if reader.take(REPLAY_BINARY_MAGIC.len())? != REPLAY_BINARY_MAGIC {
+            return Err(ReplayError::BadFormat("not a binary replay".to_string()));
+        }
+        let format_version = reader.u16()?;
+        if format_version != REPLAY_BINARY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedFormatVersion(format_version));
+        }
+        let game_state_version = reader.u32()?;
+        let strings_count = reader.count()?;
+        let mut strings = Vec::with_capacity(strings_count);
+        for _ in 0..strings_count {
+            strings.push(reader.str()?);
+        }
+        let mut id_bytes = [0u8; 16];
+        id_bytes.copy_from_slice(reader.take(16)?);
+        let id = Uuid::from_bytes(id_bytes);
+        let name = reader.table_str(&strings)?;
+        let max_time_ms = reader.u32()?;
+        let current_millis = reader.f64()?;
+        let marks_count = reader.count()?;
+        let mut marks_ticks = Vec::with_capacity(marks_count);
+        for _ in 0..marks_count {
+            marks_ticks.push(reader.u32()?);
+        }
+        let batches_count = reader.count()?;
+        let mut batches = Vec::with_capacity(batches_count);
+        for _ in 0..batches_count {
+            batches.push((reader.u32()?, reader.u64()?));
+        }
+        let keyframes_count = reader.count()?;
+        let mut keyframes = Vec::with_capacity(keyframes_count);
+        for _ in 0..keyframes_count {
+            keyframes.push((reader.u32()?, reader.u32()?, reader.u64()?));
+        }
+        let body_len = reader.u64()?;
+        if body_len > reader.remaining() as u64 {
+            return Err(ReplayError::BadFormat(format!(
+                "bad body length {}",
+                body_len
+            )));
+        }
+        let body = reader.take(body_len as usize)?.to_vec();
+        let bad_offset = batches
+            .iter()
+            .map(|(_, offset)| *offset)
+            .chain(keyframes.iter().map(|(_, _, offset)| *offset))
+            .any(|offset| offset >= body_len);
+        let bad_keyframe = keyframes
+            .iter()
+            .any(|(batch_idx, _, _)| *batch_idx as usize >= batches.len());
+        if bad_offset || bad_keyframe {
+            return Err(ReplayError::BadFormat("bad replay index".to_string()));
+        }
+        Ok(CompactReplay {
+            id,
+            name,
+            game_state_version,
+            max_time_ms,
+            current_millis,
+            marks_ticks,
+            strings,
+            batches,
+            keyframes,
+            body,
+        })
+    }
+
+    fn read_state(&self, offset: u64) -> Result<GameState, ReplayError> {
+        let mut reader = ByteReader::new(&self.body, offset as usize);
+        value_to_state(reader.value(&self.strings)?)
+    }
+
+    fn read_batch(&self, idx: usize) -> Result<Vec<ValueDiff>, ReplayError> {
+        let (_, offset) = *self
+            .batches
+            .get(idx)
+            .ok_or(ReplayError::BadFormat(format!("no diff batch {}", idx)))?;
+        let mut reader = ByteReader::new(&self.body, offset as usize);
+        let len = reader.u32()? as usize;
+        let batch_start = reader.pos;
+        let batch = reader.diff_batch(&self.strings)?;
+        if reader.pos - batch_start != len {
+            return Err(ReplayError::BadFormat(format!("bad diff batch {}", idx)));
+        }
+        Ok(batch)
+    }
+
+    pub fn initial_state(&self) -> Result<GameState, ReplayError> {
+        self.read_state(0)
+    }
+
+    pub fn get_state_at(
+        &self,
+        ticks: u32,
+        sampler: &mut Option<Sampler>,
+    ) -> Result<GameState, ReplayError> {
+        // mark 0 is the initial state, mark n is the state after applying batch n - 1
+        let mark_index = self
+            .marks_ticks
+            .binary_search(&ticks)
+            .map_err(|_| ReplayError::InvalidRewind(ticks))?;
+        let nearest_keyframe = self
+            .keyframes
+            .iter()
+            .filter(|(batch_idx, _, _)| (*batch_idx as usize) < mark_index)
+            .last();
+        let (mut current, first_batch) = match nearest_keyframe {
+            Some((batch_idx, _, offset)) => (self.read_state(*offset)?, *batch_idx as usize + 1),
+            None => (self.initial_state()?, 0),
+        };
+        for idx in first_batch..mark_index {
+            let sid = sampler
+                .as_mut()
+                .map(|s| s.start(SamplerMarks::ApplyReplayDiffBatch as u32));
+            current = ReplayDiffed::apply_diff_batch(&current, &self.read_batch(idx)?)?;
+            sampler.as_mut().zip(sid).map(|(s, i)| s.end(i));
+        }
+        Ok(current)
+    }
+
+    pub fn to_replay_diffed(&self) -> Result<ReplayDiffed, ReplayError> {
+        let mut replay = ReplayDiffed::new(self.id);
+        replay.name = self.name.clone();
+        replay.initial_state = self.initial_state()?;
+        for idx in 0..self.batches.len() {
+            replay
+                .diffs
+                .push((self.batches[idx].0, self.read_batch(idx)?));
+        }
+        replay.max_time_ms = self.max_time_ms;
+        replay.current_millis = self.current_millis;
+        replay.marks_ticks = self.marks_ticks.clone();
+        for (_, ticks, offset) in self.keyframes.iter() {
+            replay.keyframes.push((*ticks, self.read_state(*offset)?));
+        }
+        Ok(replay)
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.game_state_version == GAME_STATE_VERSION
+    }
+}
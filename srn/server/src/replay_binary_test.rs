@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod replay_binary_test {
+    use crate::new_id;
+    use crate::replay::ReplayDiffed;
+    use crate::replay_binary::{
+        write_replay_binary, ByteReader, ByteWriter, CompactReplay, MAX_VALUE_DEPTH,
+        REPLAY_BINARY_FORMAT_VERSION, REPLAY_BINARY_MAGIC,
+    };
+    use crate::world::{GameState, GAME_STATE_VERSION};
+
+    const VALUE_ARRAY: u8 = 7;
+
+    fn make_replay_bytes() -> Vec<u8> {
+        let mut state = GameState::new();
+        state.id = new_id();
+        let mut replay = ReplayDiffed::new(new_id());
+        replay.name = "test".to_string();
+        replay.add(state.clone()).unwrap();
+        for i in 1..5 {
+            state.ticks = i * 1000;
+            state.millis = i as u32;
+            replay.add(state.clone()).unwrap();
+        }
+        write_replay_binary(&replay).unwrap()
+    }
+
+    fn header() -> ByteWriter {
+        let mut writer = ByteWriter::new();
+        writer.bytes.extend_from_slice(REPLAY_BINARY_MAGIC);
+        writer.u16(REPLAY_BINARY_FORMAT_VERSION);
+        writer.u32(GAME_STATE_VERSION);
+        writer
+    }
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        for _ in 0..depth {
+            writer.u8(VALUE_ARRAY);
+            writer.varint(1);
+        }
+        writer.u8(VALUE_ARRAY);
+        writer.varint(0);
+        writer.bytes
+    }
+
+    #[test]
+    fn parses_valid_replay() {
+        let bytes = make_replay_bytes();
+        let replay = CompactReplay::parse(&bytes).unwrap();
+        assert_eq!(replay.marks_ticks, vec![0, 1000, 2000, 3000, 4000]);
+        assert_eq!(replay.get_state_at(4000, &mut None).unwrap().ticks, 4000);
+    }
+
+    #[test]
+    fn rejects_truncated_replay() {
+        let bytes = make_replay_bytes();
+        let step = bytes.len() / 100 + 1;
+        for len in (0..bytes.len()).step_by(step) {
+            assert!(CompactReplay::parse(&bytes[0..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_huge_counts() {
+        let mut writer = header();
+        writer.varint(u64::MAX);
+        assert!(CompactReplay::parse(&writer.bytes).is_err());
+
+        let mut writer = header();
+        // one string, then a string with a huge length
+        writer.varint(2);
+        writer.str("test");
+        writer.varint(u64::MAX >> 1);
+        assert!(CompactReplay::parse(&writer.bytes).is_err());
+
+        let mut writer = ByteWriter::new();
+        writer.u8(VALUE_ARRAY);
+        writer.varint(1 << 40);
+        assert!(ByteReader::new(&writer.bytes, 0).value(&vec![]).is_err());
+        assert!(ByteReader::new(&writer.bytes, 0)
+            .diff_batch(&vec![])
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_deeply_nested_values() {
+        let bytes = nested_arrays(MAX_VALUE_DEPTH);
+        assert!(ByteReader::new(&bytes, 0).value(&vec![]).is_ok());
+        let bytes = nested_arrays(MAX_VALUE_DEPTH + 1);
+        assert!(ByteReader::new(&bytes, 0).value(&vec![]).is_err());
+        let bytes = nested_arrays(1000 * 1000);
+        assert!(ByteReader::new(&bytes, 0).value(&vec![]).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_reads() {
+        let bytes = vec![1, 2, 3];
+        assert!(ByteReader::new(&bytes, usize::MAX).u8().is_err());
+        assert!(ByteReader::new(&bytes, 2).u16().is_err());
+        assert!(ByteReader::new(&bytes, 0).take(usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::iter::FromIterator;
+use std::sync::{Arc, Mutex, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use crossbeam::channel::{bounded, Receiver, Sender};
+use lazy_static::lazy_static;
+use uuid::Uuid;
+
+use crate::new_id;
+use crate::replay::ReplayDiffed;
+use crate::replay_binary::{write_replay_binary, REPLAY_BINARY_EXTENSION};
+use crate::replays_api::REPLAYS_STORE;
+use crate::shutdown;
+use crate::shutdown::ShutdownStage;
+use crate::states::StateContainer;
+use crate::world::GameState;
+
+const REPLAYS_DIR: &str = "replays";
+const DEFAULT_RECORDING_INTERVAL_MS: i64 = 250;
+const DEFAULT_RECORDING_MAX_DURATION_SEC: i64 = 10 * 60;
+const RECORDING_SLEEP_MS: u64 = 50;
+
+lazy_static! {
+    pub static ref ENABLE_REPLAY_RECORDING: bool = env::var("ENABLE_REPLAY_RECORDING").is_ok();
+}
+
+lazy_static! {
+    pub static ref REPLAY_RECORDING_INTERVAL_TICKS: i64 = env::var("REPLAY_RECORDING_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_RECORDING_INTERVAL_MS)
+        * 1000;
+}
+
+lazy_static! {
+    // replays are kept in memory until rotated, so very long games are split into several replays
+    pub static ref REPLAY_RECORDING_MAX_DURATION_TICKS: u64 =
+        env::var("REPLAY_RECORDING_MAX_DURATION_SEC")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RECORDING_MAX_DURATION_SEC as u64)
+            * 1000
+            * 1000;
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    pub room_name: String,
+    pub state: GameState,
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordingCommand {
+    // states of all the rooms that exist at the moment. recordings for the states that are
+    // not in the list anymore (room closed, or the game restarted with a new state) are rotated
+    Frames(Vec<RecordedFrame>),
+    GameEnded(Uuid),
+}
+
+lazy_static! {
+    pub static ref RECORDING_COMMANDS: (
+        Arc<Mutex<Sender<RecordingCommand>>>,
+        Arc<Mutex<Receiver<RecordingCommand>>>
+    ) = {
+        let (sender, receiver) = bounded::<RecordingCommand>(128);
+        (Arc::new(Mutex::new(sender)), Arc::new(Mutex::new(receiver)))
+    };
+}
+
+fn send_command(command: RecordingCommand) {
+    let sender = RECORDING_COMMANDS.0.lock().unwrap();
+    if let Err(e) = sender.try_send(command) {
+        warn!(format!(
+            "failed to schedule replay recording command, err {}",
+            e
+        ));
+    }
+}
+
+// cloning is done under the state lock, but diffing is done in the recording thread
+pub fn record_frames(cont: &RwLockWriteGuard<StateContainer>) {
+    let frames = cont
+        .rooms
+        .values
+        .iter()
+        // the state is frozen after the game has ended, until it gets replaced by the new one
+        .filter(|r| !r.state.paused)
+        .map(|r| RecordedFrame {
+            room_name: r.name.clone(),
+            state: r.state.clone(),
+        })
+        .collect::<Vec<_>>();
+    send_command(RecordingCommand::Frames(frames));
+}
+
+pub fn on_game_ended(state_id: Uuid) {
+    send_command(RecordingCommand::GameEnded(state_id));
+}
+
+pub struct Recording {
+    pub replay: ReplayDiffed,
+    pub start_ticks: u64,
+}
+
+impl Recording {
+    fn new(room_name: &String, state: &GameState) -> Self {
+        let mut replay = ReplayDiffed::new(new_id());
+        replay.name = format!(
+            "{} {:?} {}",
+            room_name,
+            state.mode,
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        Recording {
+            replay,
+            start_ticks: state.ticks,
+        }
+    }
+}
+
+// the recordings of all the states, fed with the commands of the main thread
+pub struct Recorder {
+    pub recordings: HashMap<Uuid, Recording>,
+    // states that cannot be recorded anymore, so the warning is only reported once
+    pub stopped: HashSet<Uuid>,
+    replays_path: String,
+    max_duration_ticks: u64,
+}
+
+impl Recorder {
+    pub fn new(replays_path: String, max_duration_ticks: u64) -> Self {
+        Recorder {
+            recordings: HashMap::new(),
+            stopped: HashSet::new(),
+            replays_path,
+            max_duration_ticks,
+        }
+    }
+
+    pub fn process(&mut self, command: RecordingCommand) {
+        match command {
+            RecordingCommand::Frames(frames) => {
+                let present: HashSet<Uuid> = HashSet::from_iter(frames.iter().map(|f| f.state.id));
+                let gone = self
+                    .recordings
+                    .keys()
+                    .filter(|id| !present.contains(id))
+                    .map(|id| id.clone())
+                    .collect::<Vec<_>>();
+                for state_id in gone {
+                    self.rotate(state_id);
+                }
+                self.stopped.retain(|id| present.contains(id));
+                for frame in frames {
+                    self.add_frame(frame);
+                }
+            }
+            RecordingCommand::GameEnded(state_id) => {
+                self.rotate(state_id);
+                self.stopped.remove(&state_id);
+            }
+        }
+    }
+
+    pub fn rotate_all(&mut self) {
+        let state_ids = self.recordings.keys().cloned().collect::<Vec<_>>();
+        for state_id in state_ids {
+            self.rotate(state_id);
+        }
+    }
+
+    fn add_frame(&mut self, mut frame: RecordedFrame) {
+        // server-only like in patch_state_for_all_clients, the replay only needs what the clients see
+        frame.state.combat_log = None;
+        let state_id = frame.state.id;
+        if self.stopped.contains(&state_id) {
+            return;
+        }
+        // replay marks are u32, so anything beyond that (~71 minutes of a state) cannot be represented,
+        // the recorded part is kept and the rest of the game is not recorded
+        if frame.state.ticks > u32::MAX as u64 {
+            warn!(format!(
+                "state {} is too long to be recorded, stopping its replay recording",
+                state_id
+            ));
+            self.rotate(state_id);
+            self.stopped.insert(state_id);
+            return;
+        }
+        let max_duration_ticks = self.max_duration_ticks;
+        let too_long = self.recordings.get(&state_id).map_or(false, |r| {
+            frame.state.ticks - r.start_ticks > max_duration_ticks
+        });
+        if too_long {
+            self.rotate(state_id);
+        }
+        let recording = self
+            .recordings
+            .entry(state_id)
+            .or_insert_with(|| Recording::new(&frame.room_name, &frame.state));
+        if let Err(e) = recording.replay.add(frame.state) {
+            warn!(format!(
+                "failed to record replay frame for state {}, err {:?}, dropping the recording",
+                state_id, e
+            ));
+            self.recordings.remove(&state_id);
+        }
+    }
+
+    fn rotate(&mut self, state_id: Uuid) {
+        let recording = match self.recordings.remove(&state_id) {
+            Some(recording) => recording,
+            None => return,
+        };
+        let mut replay = recording.replay;
+        if replay.diffs.is_empty() {
+            return;
+        }
+        replay.current_state = None;
+        replay.next_state = None;
+        let id = replay.id;
+        // registering first, so the replays folder watcher will not try to pick up the file
+        {
+            let mut store = REPLAYS_STORE.write().unwrap();
+            store.insert(id, replay.clone());
+        }
+        match write_replay(&self.replays_path, &replay) {
+            Ok(()) => {
+                log!(format!("recorded replay {} '{}'", id, replay.name));
+            }
+            Err(e) => {
+                err!(format!("failed to write recorded replay {}, err {}", id, e));
+            }
+        }
+    }
+}
+
+pub fn replay_recording_thread() {
+    let mut recorder = Recorder::new(
+        format!("resources/{}", REPLAYS_DIR),
+        *REPLAY_RECORDING_MAX_DURATION_TICKS,
+    );
+    // the main thread has stopped before the closing stage, so the last drain gets all the frames
+    while shutdown::get_stage() < ShutdownStage::Closing {
+        process_pending_commands(&mut recorder);
+        thread::sleep(Duration::from_millis(RECORDING_SLEEP_MS));
+    }
+    process_pending_commands(&mut recorder);
+    recorder.rotate_all();
+}
+
+fn process_pending_commands(recorder: &mut Recorder) {
+    let receiver = RECORDING_COMMANDS.1.lock().unwrap();
+    while let Ok(command) = receiver.try_recv() {
+        recorder.process(command);
+    }
+}
+
+fn write_replay(replays_path: &str, replay: &ReplayDiffed) -> std::io::Result<()> {
+    let bytes = write_replay_binary(replay)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+    // the extension is not the replay one until the write is complete, so a half-written file is never read
+    let tmp_path = format!("{}/{}.tmp", replays_path, replay.id);
+    let final_path = format!("{}/{}.{}", replays_path, replay.id, REPLAY_BINARY_EXTENSION);
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(tmp_path, final_path)?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod replay_recording_test {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use uuid::Uuid;
+
+    use crate::new_id;
+    use crate::replay_binary::REPLAY_BINARY_EXTENSION;
+    use crate::replay_recording::{
+        RecordedFrame, Recorder, RecordingCommand, REPLAY_RECORDING_MAX_DURATION_TICKS,
+    };
+    use crate::replays_api::REPLAYS_STORE;
+    use crate::test_helpers::new_state;
+    use crate::world::{GameMode, GameState};
+
+    const FRAME_TICKS: u64 = 250 * 1000;
+
+    fn make_recorder() -> (Recorder, String) {
+        let root = env::temp_dir()
+            .join(format!("srn-replays-{}", new_id()))
+            .to_string_lossy()
+            .to_string();
+        fs::create_dir_all(&root).unwrap();
+        (
+            Recorder::new(root.clone(), *REPLAY_RECORDING_MAX_DURATION_TICKS),
+            root,
+        )
+    }
+
+    fn frames_at(state: &GameState, ticks: u64) -> RecordingCommand {
+        let mut state = state.clone();
+        state.ticks = ticks;
+        state.millis = (ticks / 1000) as u32;
+        RecordingCommand::Frames(vec![RecordedFrame {
+            room_name: "room".to_string(),
+            state,
+        }])
+    }
+
+    fn is_stored(replay_id: Uuid) -> bool {
+        REPLAYS_STORE.read().unwrap().contains_key(&replay_id)
+    }
+
+    #[test]
+    pub fn frames_are_recorded_until_the_game_ends() {
+        let (mut recorder, root) = make_recorder();
+        let state = new_state(&GameMode::CargoRush, "recording");
+        for i in 0..3 {
+            recorder.process(frames_at(&state, i * FRAME_TICKS));
+        }
+        let replay_id = recorder.recordings[&state.id].replay.id;
+        assert_eq!(
+            recorder.recordings[&state.id].replay.marks_ticks,
+            vec![0, FRAME_TICKS as u32, 2 * FRAME_TICKS as u32]
+        );
+        assert!(state.combat_log.is_some());
+        assert!(recorder.recordings[&state.id]
+            .replay
+            .initial_state
+            .combat_log
+            .is_none());
+        assert!(!is_stored(replay_id));
+
+        recorder.process(RecordingCommand::GameEnded(state.id));
+        assert!(recorder.recordings.is_empty());
+        assert!(is_stored(replay_id));
+        assert!(Path::new(&format!(
+            "{}/{}.{}",
+            root, replay_id, REPLAY_BINARY_EXTENSION
+        ))
+        .exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn long_recordings_are_split_at_the_limit() {
+        let (mut recorder, root) = make_recorder();
+        let state = new_state(&GameMode::CargoRush, "recording limit");
+        recorder.process(frames_at(&state, 0));
+        recorder.process(frames_at(&state, FRAME_TICKS));
+        let first_id = recorder.recordings[&state.id].replay.id;
+
+        let after_limit = *REPLAY_RECORDING_MAX_DURATION_TICKS + FRAME_TICKS;
+        recorder.process(frames_at(&state, after_limit));
+        assert!(is_stored(first_id));
+        let second = &recorder.recordings[&state.id];
+        assert_ne!(second.replay.id, first_id);
+        assert_eq!(second.start_ticks, after_limit);
+        assert_eq!(second.replay.marks_ticks, vec![after_limit as u32]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn recording_stops_when_ticks_do_not_fit_the_marks() {
+        let (mut recorder, root) = make_recorder();
+        let state = new_state(&GameMode::CargoRush, "recording overflow");
+        recorder.process(frames_at(&state, 0));
+        recorder.process(frames_at(&state, FRAME_TICKS));
+        let replay_id = recorder.recordings[&state.id].replay.id;
+
+        let overflow = u32::MAX as u64 + 1;
+        recorder.process(frames_at(&state, overflow));
+        assert!(is_stored(replay_id));
+        assert!(recorder.recordings.is_empty());
+        assert!(recorder.stopped.contains(&state.id));
+        recorder.process(frames_at(&state, overflow + FRAME_TICKS));
+        assert!(recorder.recordings.is_empty());
+
+        // a state that is gone does not need to be remembered anymore
+        recorder.process(RecordingCommand::Frames(vec![]));
+        assert!(recorder.stopped.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
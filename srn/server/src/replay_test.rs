@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod replay_test {
+    use crate::replay::{ReplayDiffed, DEFAULT_KEYFRAME_EVERY_TICKS};
+    use crate::replay_binary::{write_replay_binary, CompactReplay};
+    use crate::resources::{get_jsons_from_res_dir, read_json_from_res_dir};
+
+    #[test]
+    pub fn checked_in_replays_can_be_loaded() {
+        let files = get_jsons_from_res_dir("replays");
+        assert!(files.len() > 0);
+        for file in files {
+            let mut replay = read_json_from_res_dir::<ReplayDiffed>("replays", &file)
+                .unwrap_or_else(|e| panic!("could not read replay {}, err {:?}", file, e));
+            let last_mark = *replay.marks_ticks.last().unwrap();
+            let restored = replay.get_state_at(last_mark, &mut None).unwrap();
+            assert_eq!(restored.ticks as u32, last_mark);
+
+            assert!(replay.needs_keyframes_migration());
+            replay
+                .migrate_keyframes(DEFAULT_KEYFRAME_EVERY_TICKS)
+                .unwrap();
+            let restored_from_keyframe = replay.get_state_at(last_mark, &mut None).unwrap();
+            assert_eq!(
+                serde_json::to_value(&restored_from_keyframe).unwrap(),
+                serde_json::to_value(&restored).unwrap()
+            );
+
+            let compact = CompactReplay::parse(&write_replay_binary(&replay).unwrap()).unwrap();
+            assert!(compact.is_compatible());
+            assert_eq!(compact.marks_ticks, replay.marks_ticks);
+        }
+    }
+}
@@ -1,7 +1,15 @@
-use crate::replay::{ReplayDiffed, ReplayFrame, ReplayListItem, ReplayRaw};
+use crate::replay::{
+    ReplayDiffed, ReplayFrame, ReplayListItem, ReplayRaw, DEFAULT_KEYFRAME_EVERY_TICKS,
+};
+use crate::replay_binary::{
+    convert_json_to_binary, write_replay_binary, CompactReplay, REPLAY_BINARY_EXTENSION,
+};
 use crate::resources::{
-    get_jsons_from_res_dir, read_json, read_json_from_res_dir, ResourceReadError,
+    get_files_from_res_dir, get_jsons_from_res_dir, read_json, read_json_from_res_dir,
+    ResourceReadError,
 };
+use crate::shutdown;
+use crate::shutdown::SHUTDOWN_POLL_MS;
 use crate::system_gen::seed_state;
 use crate::{
     get_prng, new_id, world, DialogueTable, GameMode, GameState, Sampler, SpatialIndexes,
@@ -13,6 +21,7 @@ use mut_static::MutStatic;
 use rocket_contrib::json::Json;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::{DirEntry, ReadDir};
 use std::iter::FromIterator;
 use std::path::Path;
@@ -27,8 +36,62 @@ lazy_static! {
         MutStatic::from(HashMap::new());
 }
 
+lazy_static! {
+    // converts every json replay in the replays folder to the binary format on startup
+    pub static ref CONVERT_JSON_REPLAYS: bool = env::var("CONVERT_JSON_REPLAYS").is_ok();
+}
+
+lazy_static! {
+    // json replays without keyframes always get them when loaded, this also writes them back to disk
+    pub static ref MIGRATE_REPLAY_KEYFRAMES: bool = env::var("MIGRATE_REPLAY_KEYFRAMES").is_ok();
+}
+
+fn migrate_replay_keyframes(replay: &mut ReplayDiffed, file: &String) {
+    if !replay.needs_keyframes_migration() {
+        return;
+    }
+    if let Err(err) = replay.migrate_keyframes(DEFAULT_KEYFRAME_EVERY_TICKS) {
+        warn!(format!(
+            "could not insert keyframes into replay {file}, err is {err:?}"
+        ));
+        return;
+    }
+    if *MIGRATE_REPLAY_KEYFRAMES {
+        let result = serde_json::to_string(replay)
+            .map_err(|e| format!("{}", e))
+            .and_then(|json| {
+                fs::write(format!("resources/replays/{}.json", file), json)
+                    .map_err(|e| format!("{}", e))
+            });
+        match result {
+            Ok(()) => {
+                log!(format!("inserted keyframes into replay {}", file));
+            }
+            Err(err) => {
+                warn!(format!(
+                    "could not write migrated replay {file}, err is {err}"
+                ));
+            }
+        }
+    }
+}
+
+pub fn read_binary_replay(path: String) -> Result<ReplayDiffed, String> {
+    let bytes =
+        fs::read(path.clone()).map_err(|e| format!("failed to read {}, err {}", path, e))?;
+    let compact = CompactReplay::parse(&bytes).map_err(|e| format!("{:?}", e))?;
+    if !compact.is_compatible() {
+        return Err(format!(
+            "incompatible state version {}",
+            compact.game_state_version
+        ));
+    }
+    compact.to_replay_diffed().map_err(|e| format!("{:?}", e))
+}
+
 fn check_for_new_replays(blacklisted: &mut HashSet<String>) {
-    let files = get_jsons_from_res_dir("replays");
+    let json_files = get_jsons_from_res_dir("replays");
+    let binary_files = get_files_from_res_dir("replays", REPLAY_BINARY_EXTENSION);
     let existing_keys = REPLAYS_STORE
         .read()
         .unwrap()
@@ -37,12 +100,34 @@ fn check_for_new_replays(blacklisted: &mut HashSet<String>) {
         .collect::<Vec<String>>();
     let existing_keys_set: HashSet<String> = HashSet::from_iter(existing_keys);
     let mut to_pick: Vec<ReplayDiffed> = vec![];
-    for file in files {
+    for file in binary_files {
         if !existing_keys_set.contains(&file) && !blacklisted.contains(&file) {
+            log!(format!("found a new binary replay {}", file));
+            let result = read_binary_replay(format!(
+                "resources/replays/{}.{}",
+                file, REPLAY_BINARY_EXTENSION
+            ));
+            match result {
+                Ok(result) => {
+                    to_pick.push(result);
+                }
+                Err(err) => {
+                    warn!(format!(
+                        "could not read binary replay from file {file}, err is {err}"
+                    ));
+                    blacklisted.insert(file);
+                }
+            }
+        }
+    }
+    for file in json_files {
+        let picked_binary = to_pick.iter().any(|r| r.id.to_string() == file);
+        if !existing_keys_set.contains(&file) && !blacklisted.contains(&file) && !picked_binary {
             log!(format!("found a new replay {}", file));
             let result = read_json_from_res_dir::<ReplayDiffed>("replays", &file);
             match result {
-                Ok(result) => {
+                Ok(mut result) => {
+                    migrate_replay_keyframes(&mut result, &file);
                     to_pick.push(result);
                 }
                 Err(err) => {
@@ -62,11 +147,49 @@ fn check_for_new_replays(blacklisted: &mut HashSet<String>) {
     }
 }
 
+const CHECK_FOR_NEW_REPLAYS_EVERY_MS: u64 = 5 * 1000;
+
+fn convert_json_replays() {
+    let binary_files: HashSet<String> =
+        HashSet::from_iter(get_files_from_res_dir("replays", REPLAY_BINARY_EXTENSION));
+    for file in get_jsons_from_res_dir("replays") {
+        if binary_files.contains(&file) {
+            continue;
+        }
+        let result = fs::read_to_string(format!("resources/replays/{}.json", file))
+            .map_err(|e| format!("{}", e))
+            .and_then(|json| convert_json_to_binary(json.as_str()).map_err(|e| format!("{:?}", e)))
+            .and_then(|bytes| {
+                fs::write(
+                    format!("resources/replays/{}.{}", file, REPLAY_BINARY_EXTENSION),
+                    bytes,
+                )
+                .map_err(|e| format!("{}", e))
+            });
+        match result {
+            Ok(()) => {
+                log!(format!("converted replay {} to the binary format", file));
+            }
+            Err(err) => {
+                warn!(format!("could not convert replay {file}, err is {err}"));
+            }
+        }
+    }
+}
+
 pub fn watch_replay_folder() {
+    if *CONVERT_JSON_REPLAYS {
+        convert_json_replays();
+    }
     let mut blacklisted = HashSet::new();
-    loop {
-        check_for_new_replays(&mut blacklisted);
-        thread::sleep(Duration::from_secs(5));
+    let mut since_last_check_ms = CHECK_FOR_NEW_REPLAYS_EVERY_MS;
+    while !shutdown::is_shutting_down() {
+        if since_last_check_ms >= CHECK_FOR_NEW_REPLAYS_EVERY_MS {
+            check_for_new_replays(&mut blacklisted);
+            since_last_check_ms = 0;
+        }
+        thread::sleep(Duration::from_millis(SHUTDOWN_POLL_MS));
+        since_last_check_ms += SHUTDOWN_POLL_MS;
     }
 }
 
@@ -96,3 +219,15 @@ pub fn get_replay_by_id(replay_id: String) -> Json<Option<ReplayDiffed>> {
     };
     Json(item)
 }
+
+#[get("/<replay_id>/binary")]
+pub fn get_replay_binary_by_id(replay_id: String) -> Option<Vec<u8>> {
+    let item = {
+        let store = REPLAYS_STORE.read().unwrap();
+        let item = Uuid::from_str(replay_id.as_str())
+            .ok()
+            .and_then(|id| store.get(&id).map(|r| r.clone()));
+        item
+    };
+    item.and_then(|replay| write_replay_binary(&replay).ok())
+}
@@ -4,6 +4,10 @@ use std::fs;
 use std::ops::Deref;
 
 pub fn get_jsons_from_res_dir(dir: &str) -> Vec<String> {
+    get_files_from_res_dir(dir, "json")
+}
+
+pub fn get_files_from_res_dir(dir: &str, ext: &str) -> Vec<String> {
     let entries = fs::read_dir(format!("resources/{}", dir))
         .unwrap()
         .filter_map(|e| {
@@ -15,7 +19,7 @@ pub fn get_jsons_from_res_dir(dir: &str) -> Vec<String> {
                 .path()
                 .extension()
                 .map(|s| s.to_string_lossy().to_string());
-            if file_ext.map_or(false, |ext| ext == "json") {
+            if file_ext.map_or(false, |file_ext| file_ext == ext) {
                 Some(file_name)
             } else {
                 None
@@ -3,6 +3,7 @@ use std::mem;
 
 use crate::dialogue::DialogueTable;
 use crate::indexing::GameStateCaches;
+use crate::ship_classes::{get_ship_classes_sorted, ShipClass};
 use crate::DIALOGUE_TABLE;
 use rocket::http::Status;
 use rocket_contrib::json::Json;
@@ -13,3 +14,8 @@ pub fn get_dialogue_scripts() -> Json<DialogueTable> {
     let table = { DIALOGUE_TABLE.lock().unwrap().clone() };
     Json(*table)
 }
+
+#[get("/ship_classes")]
+pub fn get_ship_classes() -> Json<Vec<ShipClass>> {
+    Json(get_ship_classes_sorted())
+}
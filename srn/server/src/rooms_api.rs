@@ -18,6 +18,8 @@ use uuid::Uuid;
 
 use crate::api_struct::RoomsState;
 use crate::api_struct::*;
+use crate::combat_log::CombatLog;
+use crate::locations::{build_galaxy_map, find_route, GalaxyMap, LocationRoute, RouteMetric};
 use crate::server_events::fire_event;
 use crate::states::{RoomHeader, StateContainer, ROOMS_READ};
 use crate::world::{GameMode, GameState, PlayerId};
@@ -63,8 +65,8 @@ pub fn get_rooms_for_mode(game_mode: String) -> Json<Vec<RoomHeader>> {
     Json(rooms)
 }
 
-#[post("/create/<game_mode>")]
-pub fn create_room(game_mode: String) -> Json<RoomIdResponse> {
+#[post("/create/<game_mode>?<bot_difficulty>")]
+pub fn create_room(game_mode: String, bot_difficulty: Option<String>) -> Json<RoomIdResponse> {
     log!(format!("create room request for game mode {}", game_mode));
     let mode =
         serde_json::from_str::<crate::world::GameMode>(format!("\"{}\"", game_mode).as_str());
@@ -74,23 +76,97 @@ pub fn create_room(game_mode: String) -> Json<RoomIdResponse> {
         });
     }
     let mode = mode.ok().unwrap();
+    // an unknown difficulty is the default one, same as no difficulty at all
+    let bot_difficulty = bot_difficulty
+        .and_then(|d| serde_json::from_str::<BotDifficulty>(format!("\"{}\"", d).as_str()).ok());
     let room_id = new_id();
     fire_event(GameEvent::CreateRoomRequest {
         mode,
         room_id,
         bots_seed: None,
+        bot_difficulty,
     });
 
     return Json(RoomIdResponse { room_id });
 }
 
+#[get("/<room_id>/galaxy_map")]
+pub fn get_galaxy_map(room_id: String) -> Json<Option<GalaxyMap>> {
+    let room_id = Uuid::parse_str(room_id.as_str());
+    if room_id.is_err() {
+        return Json(None);
+    }
+    let room_id = room_id.unwrap();
+    let cont = crate::STATE.read().unwrap();
+    let map = cont
+        .rooms
+        .idx_by_room_id
+        .get(&room_id)
+        .and_then(|idx| cont.rooms.values.get(*idx))
+        .map(|room| build_galaxy_map(&room.state));
+    Json(map)
+}
+
+#[get("/<room_id>/combat_log")]
+pub fn get_combat_log(room_id: String) -> Json<Option<CombatLog>> {
+    let room_id = Uuid::parse_str(room_id.as_str());
+    if room_id.is_err() {
+        return Json(None);
+    }
+    let room_id = room_id.unwrap();
+    let cont = crate::STATE.read().unwrap();
+    let log = cont
+        .rooms
+        .idx_by_room_id
+        .get(&room_id)
+        .and_then(|idx| cont.rooms.values.get(*idx))
+        .and_then(|room| room.state.combat_log.clone());
+    Json(log)
+}
+
+#[get("/<room_id>/route/<from>/<to>/<metric>")]
+pub fn get_route(
+    room_id: String,
+    from: String,
+    to: String,
+    metric: String,
+) -> Json<Option<LocationRoute>> {
+    let metric = serde_json::from_str::<RouteMetric>(format!("\"{}\"", metric).as_str());
+    let ids = (
+        Uuid::parse_str(room_id.as_str()),
+        Uuid::parse_str(from.as_str()),
+        Uuid::parse_str(to.as_str()),
+    );
+    let (room_id, from, to, metric) = match (ids, metric) {
+        ((Ok(room_id), Ok(from), Ok(to)), Ok(metric)) => (room_id, from, to, metric),
+        _ => return Json(None),
+    };
+    let cont = crate::STATE.read().unwrap();
+    let route = cont
+        .rooms
+        .idx_by_room_id
+        .get(&room_id)
+        .and_then(|idx| cont.rooms.values.get(*idx))
+        .and_then(|room| find_route(&room.state, from, to, &metric));
+    Json(route)
+}
+
 pub fn create_room_impl(
     cont: &mut RwLockWriteGuard<StateContainer>,
     mode: &GameMode,
     room_id: Uuid,
     bots_seed: Option<String>,
+    bot_difficulty: Option<BotDifficulty>,
 ) {
-    let (state_id, room) = world::make_room(&mode, room_id, &mut get_prng(), bots_seed, None, None);
+    let (state_id, room) = world::make_room(
+        &mode,
+        room_id,
+        &mut get_prng(),
+        bots_seed,
+        bot_difficulty,
+        None,
+        None,
+    );
     let bot_len = room.bots.len();
     cont.rooms.values.push(room);
     log!(format!(
@@ -169,7 +245,8 @@ pub fn cleanup_empty_rooms(cont: &mut RwLockWriteGuard<StateContainer>) {
             .iter()
             .filter_map(|room| {
                 room.last_players_mark.map_or(None, |mark| {
-                    if (mark - curr_millis).abs() > ROOM_CLEANUP_NO_PLAYERS_TIMEOUT_MS {
+                    // the mark can be in the future for rooms restored from snapshots, to give players time to rejoin
+                    if curr_millis - mark > ROOM_CLEANUP_NO_PLAYERS_TIMEOUT_MS {
                         Some(room.id)
                     } else {
                         None
@@ -24,7 +24,7 @@ use crate::system_gen::{
 };
 use crate::vec2::Vec2f64;
 use crate::world::{
-    Asteroid, AsteroidBelt, GameState, Location, PlanetV2, Ship, SpatialProps, Star,
+    Asteroid, AsteroidBelt, GameState, Location, PlanetV2, Ship, ShipTemplate, SpatialProps, Star,
 };
 use crate::{indexing, prng_id};
 use crate::{new_id, world};
@@ -68,6 +68,12 @@ pub struct SBAddAsteroid {
     id: Option<ReferencableId>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct SBAddShip {
+    class_id: String,
+    position: Vec2f64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
 pub struct SBAddStar {
     radius: f64,
@@ -114,6 +120,7 @@ pub enum SandboxCommand {
     GetSomeWares,
     AddPlanet(SBAddPlanet),
     AddAsteroid(SBAddAsteroid),
+    AddShip(SBAddShip),
     Teleport(SBTeleport),
     SetFofOverrides(SBSetFofOverrides),
     SetupState(SBSetupState),
@@ -307,6 +314,8 @@ pub fn mutate_state(state: &mut GameState, player_id: Uuid, cmd: SandboxCommand)
                         last_damage_dealer: None,
                         acc_periodic_dmg: 0.0,
                         acc_periodic_heal: 0.0,
+                        shield: None,
+                        resistances: None,
                     },
                     rot_movement: RotationMovement::None,
                     to_clean: false,
@@ -364,6 +373,22 @@ pub fn mutate_state(state: &mut GameState, player_id: Uuid, cmd: SandboxCommand)
                 );
             }
         }
+        SandboxCommand::AddShip(cmd) => {
+            if let Some(loc) = indexing::find_player_ship_index(state, player_id) {
+                let location_id = state.locations[loc.location_idx].id;
+                match ShipTemplate::of_class(cmd.class_id.as_str(), Some(cmd.position)) {
+                    Some(template) => {
+                        world::spawn_ship(
+                            state,
+                            None,
+                            template.in_location(location_id),
+                            &mut prng,
+                        );
+                    }
+                    None => warn!(format!("no ship class {}", cmd.class_id)),
+                }
+            }
+        }
         SandboxCommand::SetFofOverrides(cmd) => {
             if let Some(ship) = indexing::find_ship_mut(state, cmd.ship_id) {
                 ship.fof_overrides = cmd.overrides;
@@ -23,7 +23,7 @@ use crate::substitutions::substitute_notification_texts;
 use crate::world;
 use crate::world::{spawn_ship, GameMode, GameState, Player};
 use crate::xcast::XCast;
-use crate::{cargo_rush, indexing, pirate_defence, tutorial};
+use crate::{cargo_rush, indexing, pirate_defence, replay_recording, tutorial};
 use crate::{get_prng, SamplerMarks};
 
 lazy_static! {
@@ -88,7 +88,8 @@ pub fn handle_events(
                             );
                         }
                     }
-                    GameEvent::ShipDied { state_id, .. } => {
+                    GameEvent::ShipDied { state_id, .. }
+                    | GameEvent::ShipKilled { state_id, .. } => {
                         let state = crate::states::select_state_by_id_mut(cont, state_id);
                         if state.is_none() {
                             warn!("event in non-existent state");
@@ -105,6 +106,9 @@ pub fn handle_events(
                             event.clone(),
                             XCast::Broadcast(state_id),
                         );
+                        if *replay_recording::ENABLE_REPLAY_RECORDING {
+                            replay_recording::on_game_ended(state_id);
+                        }
                     }
                     GameEvent::GameStarted { state_id } => {
                         crate::main_ws_server::send_event_to_client(
@@ -132,14 +136,9 @@ pub fn handle_events(
                             continue;
                         }
                         let state = state.unwrap();
-                        let planets = state.locations[0].planets.clone();
+                        let systems = cargo_rush::get_quest_systems(state);
                         if let Some(player) = indexing::find_my_player_mut(state, player_id) {
-                            cargo_rush::generate_random_quest(
-                                player,
-                                &planets.clone(),
-                                None,
-                                &mut prng,
-                            );
+                            cargo_rush::generate_random_quest(player, &systems, None, &mut prng);
                         }
                         substitute_notification_texts(state, HashSet::from_iter(vec![player_id]));
                     }
@@ -150,9 +149,10 @@ pub fn handle_events(
                         mode,
                         room_id,
                         bots_seed,
+                        bot_difficulty,
                     } => {
                         let mark = sampler.start(SamplerMarks::EventsCreateRoom as u32);
-                        create_room_impl(cont, &mode, room_id, bots_seed);
+                        create_room_impl(cont, &mode, room_id, bots_seed, bot_difficulty);
                         log!(format!("room {room_id} created"));
                         sampler.end(mark);
                     }
@@ -167,6 +167,12 @@ pub fn handle_events(
                     GameEvent::SandboxCommandRequest { .. } => {
                         // no commands yet require server-level handling, but it's not a mistake too
                     }
+                    GameEvent::ServerShutdown { state_id, .. } => {
+                        crate::main_ws_server::send_event_to_client(
+                            event.clone(),
+                            XCast::Broadcast(state_id),
+                        );
+                    }
                 }
             }
             Err(_) => {
@@ -0,0 +1,260 @@
+use crate::abilities::Ability;
+use crate::api_struct::AiTrait;
+use crate::combat::{Health, Resistances, Shield};
+use crate::properties::ObjectProperty;
+use crate::spatial_movement::Movement;
+use crate::world::TemplateId;
+use include_dir::{include_dir, Dir};
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use typescript_definitions::{TypeScriptify, TypescriptDefinition};
+use wasm_bindgen::prelude::*;
+
+// every json file there is a ship class with the id equal to the file name.
+// They are embedded into the binary, so the client-side world gets exactly the same classes
+static SHIP_CLASSES_DIR: Dir<'_> =
+    include_dir!("$CARGO_MANIFEST_DIR/../server/resources/ship_classes");
+
+pub const PLAYER_SHIP_CLASS: &str = "player";
+pub const PIRATE_SHIP_CLASS: &str = "pirate";
+const REQUIRED_SHIP_CLASSES: [&str; 2] = [PLAYER_SHIP_CLASS, PIRATE_SHIP_CLASS];
+pub const MAX_SHIP_TURRETS: usize = 8;
+pub const MAX_MODULE_SLOTS: usize = 8;
+
+// all values are per second, not per tick, to be readable
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct ShipClassMovement {
+    pub max_linear_speed: f64,
+    // radians
+    pub max_rotation_speed: f64,
+    // shares of max_linear_speed
+    pub linear_drag: f64,
+    pub acc_linear: f64,
+    pub brake_acc: f64,
+    // share of max_rotation_speed
+    pub acc_angular: f64,
+}
+
+impl ShipClassMovement {
+    pub fn to_movement(&self) -> Movement {
+        let max_linear_speed = self.max_linear_speed / 1000.0 / 1000.0;
+        let max_angular_speed = self.max_rotation_speed / 1000.0 / 1000.0;
+        Movement::ShipAccelerated {
+            max_linear_speed,
+            max_rotation_speed: max_angular_speed,
+            linear_drag: max_linear_speed * self.linear_drag / 1e6,
+            acc_linear: max_linear_speed * self.acc_linear / 1e6,
+            brake_acc: max_linear_speed * self.brake_acc / 1e6,
+            max_turn_speed: max_angular_speed,
+            acc_angular: max_angular_speed * self.acc_angular / 1e6,
+        }
+    }
+
+    pub fn to_legacy_movement(&self) -> Movement {
+        Movement::ShipMonotonous {
+            move_speed: self.max_linear_speed / 1000.0 / 1000.0,
+            turn_speed: self.max_rotation_speed / 1000.0 / 1000.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct ShipClassShield {
+    pub max: f64,
+    pub regen_per_sec: f64,
+    // since the last hit
+    pub regen_delay_sec: f64,
+}
+
+impl ShipClassShield {
+    pub fn to_shield(&self) -> Shield {
+        Shield::new(
+            self.max,
+            self.regen_per_sec / 1000.0 / 1000.0,
+            (self.regen_delay_sec * 1000.0 * 1000.0) as i32,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
+pub struct ShipClass {
+    // taken from the file name
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    // the name for every spawned ship, if any
+    pub ship_name: Option<String>,
+    pub health: f64,
+    pub health_regen_per_sec: f64,
+    pub shield: Option<ShipClassShield>,
+    pub resistances: Option<Resistances>,
+    pub movement: ShipClassMovement,
+    pub can_toggle_legacy_movement: bool,
+    // guns, see combat::gen_turrets
+    pub turret_count: usize,
+    // the projectiles of the launchers, each one takes a turret after the guns
+    #[serde(default = "default_launchers")]
+    pub launchers: Vec<TemplateId>,
+    // how many fitting::ShipModule can be fitted at once
+    pub module_slots: usize,
+    pub abilities: Vec<Ability>,
+    pub properties: Vec<ObjectProperty>,
+    pub npc_traits: Option<Vec<AiTrait>>,
+}
+
+// classes defined before the other projectiles only had a rocket launcher
+fn default_launchers() -> Vec<TemplateId> {
+    vec![TemplateId::Rocket]
+}
+
+impl ShipClass {
+    pub fn to_health(&self) -> Health {
+        let mut health = if self.health_regen_per_sec > 0.0 {
+            Health::new_regen(self.health, self.health_regen_per_sec / 1000.0 / 1000.0)
+        } else {
+            Health::new(self.health)
+        };
+        health.shield = self.shield.as_ref().map(|s| s.to_shield());
+        health.resistances = self.resistances.clone();
+        health
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        if !(self.health > 0.0) {
+            errors.push(format!("{}: health must be positive", self.id));
+        }
+        if !(self.health_regen_per_sec >= 0.0) {
+            errors.push(format!("{}: health regen cannot be negative", self.id));
+        }
+        if let Some(shield) = &self.shield {
+            if !(shield.max > 0.0) {
+                errors.push(format!("{}: shield max must be positive", self.id));
+            }
+            if !(shield.regen_per_sec >= 0.0 && shield.regen_delay_sec >= 0.0) {
+                errors.push(format!(
+                    "{}: shield regen and its delay cannot be negative",
+                    self.id
+                ));
+            }
+        }
+        if let Some(res) = &self.resistances {
+            for (name, value) in [
+                ("kinetic", res.kinetic),
+                ("explosive", res.explosive),
+                ("thermal", res.thermal),
+            ] {
+                if !(value >= 0.0 && value < 1.0) {
+                    errors.push(format!(
+                        "{}: {} resistance must be in [0, 1)",
+                        self.id, name
+                    ));
+                }
+            }
+        }
+        let mov = &self.movement;
+        for (name, value) in [
+            ("max_linear_speed", mov.max_linear_speed),
+            ("max_rotation_speed", mov.max_rotation_speed),
+            ("acc_linear", mov.acc_linear),
+            ("brake_acc", mov.brake_acc),
+            ("acc_angular", mov.acc_angular),
+        ] {
+            if !(value > 0.0) {
+                errors.push(format!("{}: movement {} must be positive", self.id, name));
+            }
+        }
+        if !(mov.linear_drag >= 0.0 && mov.linear_drag < 1.0) {
+            errors.push(format!(
+                "{}: movement linear_drag must be in [0, 1)",
+                self.id
+            ));
+        }
+        if self.turret_count > MAX_SHIP_TURRETS {
+            errors.push(format!(
+                "{}: no more than {} turrets are allowed",
+                self.id, MAX_SHIP_TURRETS
+            ));
+        }
+        if self.launchers.contains(&TemplateId::Unknown) {
+            errors.push(format!("{}: launchers cannot be Unknown", self.id));
+        }
+        if self.module_slots > MAX_MODULE_SLOTS {
+            errors.push(format!(
+                "{}: no more than {} module slots are allowed",
+                self.id, MAX_MODULE_SLOTS
+            ));
+        }
+        if self
+            .abilities
+            .iter()
+            .any(|a| matches!(a, Ability::Shoot { .. } | Ability::Launch { .. }))
+        {
+            errors.push(format!(
+                "{}: weapons are defined by turret_count and launchers, not abilities",
+                self.id
+            ));
+        }
+        errors
+    }
+}
+
+pub fn parse_ship_classes(
+    files: Vec<(String, &str)>,
+) -> Result<HashMap<String, ShipClass>, Vec<String>> {
+    let mut res = HashMap::new();
+    let mut errors = vec![];
+    for (id, json) in files {
+        match serde_json::from_str::<ShipClass>(json) {
+            Ok(mut class) => {
+                class.id = id.clone();
+                errors.append(&mut class.validate());
+                res.insert(id, class);
+            }
+            Err(e) => errors.push(format!("{}: {}", id, e)),
+        }
+    }
+    for id in REQUIRED_SHIP_CLASSES.iter() {
+        if !res.contains_key(*id) {
+            errors.push(format!("{}: class is required but not defined", id));
+        }
+    }
+    if errors.len() > 0 {
+        return Err(errors);
+    }
+    Ok(res)
+}
+
+fn read_embedded_ship_classes() -> Vec<(String, &'static str)> {
+    SHIP_CLASSES_DIR
+        .files()
+        .filter(|f| f.path().extension().map_or(false, |ext| ext == "json"))
+        .filter_map(|f| {
+            let id = f.path().file_stem()?.to_string_lossy().to_string();
+            f.contents_utf8().map(|json| (id, json))
+        })
+        .collect()
+}
+
+lazy_static! {
+    pub static ref SHIP_CLASSES: HashMap<String, ShipClass> =
+        parse_ship_classes(read_embedded_ship_classes())
+            .unwrap_or_else(|errors| panic!("Invalid ship classes: {}", errors.join(", ")));
+}
+
+// fail on bad resources right away instead of the first spawn
+pub fn validate_ship_classes() {
+    lazy_static::initialize(&SHIP_CLASSES);
+    log!(format!("Loaded {} ship classes", SHIP_CLASSES.len()));
+}
+
+pub fn get_ship_class(id: &str) -> Option<&'static ShipClass> {
+    SHIP_CLASSES.get(id)
+}
+
+pub fn get_ship_classes_sorted() -> Vec<ShipClass> {
+    let mut res = SHIP_CLASSES.values().cloned().collect::<Vec<_>>();
+    res.sort_by(|a, b| a.id.cmp(&b.id));
+    res
+}
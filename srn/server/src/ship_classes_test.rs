@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod ship_classes_test {
+    use crate::abilities::Ability;
+    use crate::indexing::GameStateCaches;
+    use crate::seed_prng;
+    use crate::ship_classes::{
+        get_ship_class, get_ship_classes_sorted, parse_ship_classes, PIRATE_SHIP_CLASS,
+        PLAYER_SHIP_CLASS,
+    };
+    use crate::system_gen::seed_state;
+    use crate::test_helpers::new_state;
+    use crate::world::{spawn_ship, GameMode, ShipTemplate, TemplateId};
+
+    const VALID_CLASS: &str = r#"{
+        "name": "Test",
+        "ship_name": null,
+        "health": 10.0,
+        "health_regen_per_sec": 0.0,
+        "movement": {
+            "max_linear_speed": 10.0,
+            "max_rotation_speed": 1.0,
+            "linear_drag": 0.1,
+            "acc_linear": 0.5,
+            "brake_acc": 0.5,
+            "acc_angular": 1.0
+        },
+        "can_toggle_legacy_movement": false,
+        "turret_count": 1,
+        "module_slots": 2,
+        "abilities": [],
+        "properties": [],
+        "npc_traits": null
+    }"#;
+
+    #[test]
+    pub fn embedded_classes_are_valid() {
+        assert!(get_ship_class(PLAYER_SHIP_CLASS).is_some());
+        assert!(get_ship_class(PIRATE_SHIP_CLASS).is_some());
+        let ids = get_ship_classes_sorted()
+            .into_iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+    }
+
+    #[test]
+    pub fn invalid_classes_are_reported() {
+        let broken = VALID_CLASS
+            .replace("\"health\": 10.0", "\"health\": -1.0")
+            .replace("\"turret_count\": 1", "\"turret_count\": 100");
+        let errors = parse_ship_classes(vec![
+            ("player".to_string(), VALID_CLASS),
+            ("broken".to_string(), broken.as_str()),
+            ("garbage".to_string(), "{}"),
+        ])
+        .unwrap_err();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].starts_with("broken: health"));
+        assert!(errors[1].starts_with("broken: no more than"));
+        assert!(errors[2].starts_with("garbage: "));
+        assert!(errors[3].starts_with("pirate: class is required"));
+    }
+
+    #[test]
+    pub fn spawned_ship_follows_class() {
+        let mut state = seed_state(
+            &GameMode::Sandbox,
+            "classes".to_string(),
+            None,
+            &mut GameStateCaches::new(),
+        );
+        let mut prng = seed_prng("classes".to_string());
+        let class = get_ship_class(PIRATE_SHIP_CLASS).unwrap();
+        let ship = spawn_ship(&mut state, None, ShipTemplate::pirate(None), &mut prng).clone();
+        assert_eq!(ship.class_id, Some(PIRATE_SHIP_CLASS.to_string()));
+        assert_eq!(ship.health.max, class.health);
+        assert_eq!(ship.name, class.ship_name);
+        assert_eq!(ship.turrets.len(), class.turret_count + 1);
+        assert_eq!(
+            serde_json::to_value(&ship.movement_definition).unwrap(),
+            serde_json::to_value(&class.movement.to_movement()).unwrap()
+        );
+        assert!(ship
+            .abilities
+            .iter()
+            .any(|a| matches!(a, Ability::BlowUpOnLand)));
+        assert!(ship.npc.is_some());
+    }
+
+    #[test]
+    pub fn every_projectile_has_a_launcher() {
+        let launched = get_ship_classes_sorted()
+            .into_iter()
+            .flat_map(|c| c.launchers.into_iter())
+            .collect::<Vec<_>>();
+        for template_id in [
+            TemplateId::Rocket,
+            TemplateId::Slug,
+            TemplateId::Mine,
+            TemplateId::Emp,
+        ] {
+            assert!(
+                launched.contains(&template_id),
+                "no class launches {:?}",
+                template_id
+            );
+        }
+    }
+
+    #[test]
+    pub fn launchers_follow_the_guns() {
+        let mut state = new_state(&GameMode::Sandbox, "classes");
+        let mut prng = seed_prng("launchers".to_string());
+        let class = get_ship_class("pirate_brute").unwrap();
+        let template = ShipTemplate::of_class("pirate_brute", None).unwrap();
+        let ship = spawn_ship(&mut state, None, template, &mut prng).clone();
+        assert_eq!(
+            ship.turrets.len(),
+            class.turret_count + class.launchers.len()
+        );
+        let launches = ship
+            .abilities
+            .iter()
+            .filter_map(|a| match a {
+                Ability::Launch {
+                    turret_id,
+                    projectile_template_id,
+                    ..
+                } => Some((*turret_id, *projectile_template_id)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let expected = class
+            .launchers
+            .iter()
+            .enumerate()
+            .map(|(i, t)| ((class.turret_count + i) as i32, *t as i32))
+            .collect::<Vec<_>>();
+        assert_eq!(launches, expected);
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, RwLockWriteGuard};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chrono::Local;
+use lazy_static::lazy_static;
+
+use crate::main_ws_server;
+use crate::states::{get_rooms_iter, StateContainer};
+use crate::world_events::GameEvent;
+use crate::xcast::XCast;
+use crate::{make_thread, snapshots};
+use uuid::Uuid;
+
+// how often the looping threads should check whether they have to stop
+pub const SHUTDOWN_POLL_MS: u64 = 100;
+// how long the rooms keep running after the players were warned about the shutdown
+pub const SHUTDOWN_WARNING_MS: u32 = 5 * 1000;
+pub const CLIENTS_DRAIN_TIMEOUT_MS: i64 = 3 * 1000;
+// if anything hangs during the graceful shutdown, we still have to terminate eventually
+pub const FORCE_EXIT_TIMEOUT_SEC: u64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownStage {
+    Running = 0,
+    // new connections are rejected, the main thread announces the shutdown, keeps the rooms running for
+    // SHUTDOWN_WARNING_MS so players see the countdown, and stops
+    Requested = 1,
+    // the main thread has stopped, the dispatcher delivers whatever is left to the clients
+    Draining = 2,
+    // everything is delivered, clients get disconnected and the rest of the threads stop
+    Closing = 3,
+}
+
+pub struct StageCell(AtomicU8);
+
+impl StageCell {
+    pub const fn new() -> StageCell {
+        StageCell(AtomicU8::new(ShutdownStage::Running as u8))
+    }
+
+    pub fn get(&self) -> ShutdownStage {
+        match self.0.load(Ordering::SeqCst) {
+            0 => ShutdownStage::Running,
+            1 => ShutdownStage::Requested,
+            2 => ShutdownStage::Draining,
+            _ => ShutdownStage::Closing,
+        }
+    }
+
+    // stages only go forward one by one, so every thread observes them in the same order.
+    // returns false if the cell is not at the previous stage, e.g. when the shutdown was already requested
+    pub fn advance(&self, to: ShutdownStage) -> bool {
+        if to == ShutdownStage::Running {
+            return false;
+        }
+        self.0
+            .compare_exchange(to as u8 - 1, to as u8, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+static STAGE: StageCell = StageCell::new();
+
+lazy_static! {
+    static ref THREADS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(vec![]);
+}
+
+pub fn get_stage() -> ShutdownStage {
+    STAGE.get()
+}
+
+pub fn is_shutting_down() -> bool {
+    get_stage() != ShutdownStage::Running
+}
+
+fn set_stage(stage: ShutdownStage) {
+    if STAGE.advance(stage) {
+        log!(format!("shutdown stage {:?}", stage));
+    } else {
+        warn!(format!(
+            "cannot switch shutdown stage from {:?} to {:?}",
+            STAGE.get(),
+            stage
+        ));
+    }
+}
+
+pub fn register_thread(spawn_result: std::io::Result<JoinHandle<()>>) {
+    match spawn_result {
+        Ok(handle) => THREADS.lock().unwrap().push(handle),
+        Err(e) => {
+            err!(format!("failed to spawn thread, err {}", e));
+        }
+    }
+}
+
+pub fn on_termination_signal() {
+    if !STAGE.advance(ShutdownStage::Requested) {
+        println!("\nReceived Ctrl+C again, terminating process immediately...");
+        std::process::exit(1);
+    }
+    println!("\nReceived Ctrl+C, shutting down gracefully...");
+    log!(format!("shutdown stage {:?}", ShutdownStage::Requested));
+    spawn_force_exit(Duration::from_secs(FORCE_EXIT_TIMEOUT_SEC), || {
+        err!(format!(
+            "graceful shutdown did not finish in {}s, terminating process",
+            FORCE_EXIT_TIMEOUT_SEC
+        ));
+        std::process::exit(1);
+    });
+    make_thread("shutdown").spawn(|| graceful_shutdown()).ok();
+}
+
+// runs the exit after the timeout regardless of the stage the graceful shutdown is stuck at
+pub fn spawn_force_exit<F: FnOnce() + Send + 'static>(timeout: Duration, exit: F) {
+    make_thread("force_exit")
+        .spawn(move || {
+            thread::sleep(timeout);
+            exit();
+        })
+        .ok();
+}
+
+pub fn make_shutdown_event(state_id: Uuid, shutdown_in_ms: u32) -> GameEvent {
+    GameEvent::ServerShutdown {
+        state_id,
+        shutdown_in_ms,
+        text_representation: format!(
+            "The server is shutting down in {} seconds",
+            shutdown_in_ms / 1000
+        ),
+    }
+}
+
+// called by the main thread when it notices the shutdown request, the rooms then keep running for SHUTDOWN_WARNING_MS
+pub fn announce_shutdown(cont: &RwLockWriteGuard<StateContainer>) {
+    for room in get_rooms_iter(cont) {
+        let state_id = room.state.id;
+        main_ws_server::send_event_to_client(
+            make_shutdown_event(state_id, SHUTDOWN_WARNING_MS),
+            XCast::Broadcast(state_id),
+        );
+    }
+}
+
+fn graceful_shutdown() {
+    join_thread("main-srn");
+    join_thread("ws_s-srn");
+    set_stage(ShutdownStage::Draining);
+    join_thread("disp-srn");
+    set_stage(ShutdownStage::Closing);
+    wait_for_clients_disconnect();
+    if !*snapshots::DISABLE_SNAPSHOTS {
+        join_thread("snap-srn");
+    }
+    join_all_threads();
+    log!("graceful shutdown finished, terminating process");
+    std::process::exit(0);
+}
+
+fn wait_for_clients_disconnect() {
+    let start = Local::now().timestamp_millis();
+    while main_ws_server::connected_clients_count() > 0 {
+        if Local::now().timestamp_millis() - start > CLIENTS_DRAIN_TIMEOUT_MS {
+            warn!(format!(
+                "{} clients did not disconnect in time",
+                main_ws_server::connected_clients_count()
+            ));
+            return;
+        }
+        thread::sleep(Duration::from_millis(SHUTDOWN_POLL_MS));
+    }
+}
+
+fn join_thread(name: &str) {
+    let handle = {
+        let mut threads = THREADS.lock().unwrap();
+        threads
+            .iter()
+            .position(|h| h.thread().name() == Some(name))
+            .map(|idx| threads.remove(idx))
+    };
+    if let Some(handle) = handle {
+        if handle.join().is_err() {
+            warn!(format!("thread {} has panicked before shutdown", name));
+        }
+    }
+}
+
+fn join_all_threads() {
+    let handles = {
+        let mut threads = THREADS.lock().unwrap();
+        threads.drain(..).collect::<Vec<_>>()
+    };
+    for handle in handles {
+        let name = handle.thread().name().unwrap_or("<unnamed>").to_string();
+        if handle.join().is_err() {
+            warn!(format!("thread {} has panicked before shutdown", name));
+        }
+    }
+}
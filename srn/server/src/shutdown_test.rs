@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod shutdown_test {
+    use crate::new_id;
+    use crate::shutdown::{
+        make_shutdown_event, spawn_force_exit, ShutdownStage, StageCell, CLIENTS_DRAIN_TIMEOUT_MS,
+        FORCE_EXIT_TIMEOUT_SEC, SHUTDOWN_WARNING_MS,
+    };
+    use crate::world_events::{GameEvent, ProcessedGameEvent};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    pub fn stages_go_forward_one_by_one() {
+        let stage = StageCell::new();
+        assert_eq!(stage.get(), ShutdownStage::Running);
+        assert!(!stage.advance(ShutdownStage::Draining));
+        assert!(!stage.advance(ShutdownStage::Closing));
+        assert_eq!(stage.get(), ShutdownStage::Running);
+
+        assert!(stage.advance(ShutdownStage::Requested));
+        // a repeated request, e.g. the second Ctrl+C, is not a new stage
+        assert!(!stage.advance(ShutdownStage::Requested));
+        assert!(stage.advance(ShutdownStage::Draining));
+        assert!(!stage.advance(ShutdownStage::Running));
+        assert!(stage.advance(ShutdownStage::Closing));
+        assert_eq!(stage.get(), ShutdownStage::Closing);
+        assert!(!stage.advance(ShutdownStage::Closing));
+        assert!(stage.get() > ShutdownStage::Draining);
+    }
+
+    #[test]
+    pub fn force_exit_runs_after_the_timeout() {
+        let (sender, receiver) = mpsc::channel();
+        spawn_force_exit(Duration::from_millis(200), move || {
+            sender.send(()).unwrap();
+        });
+        assert!(receiver.try_recv().is_err());
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    pub fn force_exit_leaves_time_for_the_graceful_shutdown() {
+        assert_eq!(FORCE_EXIT_TIMEOUT_SEC, 15);
+        assert!(
+            (SHUTDOWN_WARNING_MS as i64 + CLIENTS_DRAIN_TIMEOUT_MS)
+                < FORCE_EXIT_TIMEOUT_SEC as i64 * 1000
+        );
+    }
+
+    #[test]
+    pub fn shutdown_event_is_shown_to_players() {
+        let event = make_shutdown_event(new_id(), SHUTDOWN_WARNING_MS);
+        match &event {
+            GameEvent::ServerShutdown {
+                shutdown_in_ms,
+                text_representation,
+                ..
+            } => {
+                assert_eq!(*shutdown_in_ms, SHUTDOWN_WARNING_MS);
+                assert!(text_representation.contains("5 seconds"));
+            }
+            _ => panic!("not a shutdown event"),
+        }
+        let processed = ProcessedGameEvent::from(event, 1000);
+        assert_eq!(processed.get_processed_at_ticks(), 1000);
+        assert!(processed.is_for_client(None, new_id()));
+    }
+}
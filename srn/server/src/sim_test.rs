@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod sim_test {
+    use uuid::Uuid;
+
+    use crate::indexing::GameStateCaches;
+    use crate::world::GameMode;
+    use crate::{
+        make_d_table, setup_state, simulate, SimulationOptions, SimulationSetup,
+        DEFAULT_REPLAY_INTERVAL_MS, DEFAULT_STEP_MS,
+    };
+
+    // the state and the replay as json values, so the order of the hash maps does not matter
+    fn run(seed: &str) -> (serde_json::Value, serde_json::Value) {
+        let setup = SimulationSetup {
+            mode: GameMode::CargoRush,
+            seed: seed.to_string(),
+            gen_state_opts: None,
+            players: vec![Uuid::parse_str("7b2cbd5e-7c52-4c4b-9d2a-1f4e6a0c3b11").unwrap()],
+        };
+        let opts = SimulationOptions {
+            duration_ticks: 5 * 1000 * 1000,
+            step_ticks: DEFAULT_STEP_MS * 1000,
+            actions: vec![],
+            replay_interval_ticks: Some(DEFAULT_REPLAY_INTERVAL_MS * 1000),
+        };
+        let d_table = make_d_table();
+        let mut caches = GameStateCaches::new();
+        let state = setup_state(&setup, &mut caches);
+        let result = simulate(state, &opts, &d_table, &mut caches);
+        (
+            serde_json::to_value(&result.state).unwrap(),
+            serde_json::to_value(&result.replay.unwrap()).unwrap(),
+        )
+    }
+
+    #[test]
+    pub fn same_seed_simulates_the_same() {
+        let (first_state, first_replay) = run("sim determinism");
+        let (second_state, second_replay) = run("sim determinism");
+        assert_eq!(first_state["ticks"], 5 * 1000 * 1000);
+        assert!(first_state == second_state);
+        assert!(first_replay == second_replay);
+    }
+}
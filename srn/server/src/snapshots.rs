@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::iter::FromIterator;
+use std::sync::{Arc, Mutex, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use crossbeam::channel::{bounded, Receiver, Sender};
+use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::api_struct::{Bot, Room, RoomId};
+use crate::indexing::GameStateCaches;
+use crate::resources::read_json;
+use crate::rooms_api::reindex_rooms;
+use crate::shutdown;
+use crate::shutdown::ShutdownStage;
+use crate::states::StateContainer;
+use crate::world::{GameState, GAME_STATE_VERSION};
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+const DEFAULT_SNAPSHOT_INTERVAL_SEC: i64 = 60;
+const DEFAULT_SNAPSHOT_RETENTION: usize = 5;
+const SNAPSHOTS_WRITE_SLEEP_MS: u64 = 100;
+// restored rooms have nobody connected, so they must survive cleanup_empty_rooms long enough for players to rejoin
+pub const RESTORED_ROOM_GRACE_MS: i64 = 5 * 60 * 1000;
+
+lazy_static! {
+    pub static ref DISABLE_SNAPSHOTS: bool = env::var("DISABLE_SNAPSHOTS").is_ok();
+}
+
+lazy_static! {
+    pub static ref SNAPSHOT_INTERVAL_TICKS: i64 = env::var("SNAPSHOT_INTERVAL_SEC")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL_SEC)
+        * 1000
+        * 1000;
+}
+
+lazy_static! {
+    pub static ref SNAPSHOT_RETENTION: usize = env::var("SNAPSHOT_RETENTION")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SNAPSHOT_RETENTION)
+        .max(1);
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoomSnapshot {
+    pub id: RoomId,
+    pub name: String,
+    pub state: GameState,
+    pub bots: Vec<Bot>,
+    pub bots_seed: Option<String>,
+    pub next_seed: Option<u32>,
+}
+
+impl RoomSnapshot {
+    pub fn from_room(room: &Room) -> Self {
+        let mut state = room.state.clone();
+        // the log of the last minute is not worth restoring, update_events starts a new one
+        state.combat_log = None;
+        RoomSnapshot {
+            id: room.id,
+            name: room.name.clone(),
+            state,
+            bots: room.bots.clone(),
+            bots_seed: room.bots_seed.clone(),
+            next_seed: room.next_seed.clone(),
+        }
+    }
+
+    pub fn into_room(self, restored_at_millis: i64) -> Room {
+        let mut state = self.state;
+        // nobody is connected after the restart, and clients will get new ids anyway,
+        // so only the bot players are carried over. their ships get cleaned up as orphaned
+        let bot_ids: HashSet<_> = HashSet::from_iter(self.bots.iter().map(|b| b.id));
+        state.players.retain(|p| bot_ids.contains(&p.id));
+        Room {
+            id: self.id,
+            name: self.name,
+            state,
+            last_players_mark: Some(restored_at_millis + RESTORED_ROOM_GRACE_MS),
+            bots: self.bots,
+            bots_seed: self.bots_seed,
+            next_seed: self.next_seed,
+            caches: GameStateCaches::new(),
+        }
+    }
+}
+
+// (generation timestamp in millis, snapshots of all the rooms that existed at that moment)
+pub type SnapshotBatch = (i64, Vec<RoomSnapshot>);
+
+lazy_static! {
+    pub static ref SNAPSHOTS: (
+        Arc<Mutex<Sender<SnapshotBatch>>>,
+        Arc<Mutex<Receiver<SnapshotBatch>>>
+    ) = {
+        let (sender, receiver) = bounded::<SnapshotBatch>(16);
+        (Arc::new(Mutex::new(sender)), Arc::new(Mutex::new(receiver)))
+    };
+}
+
+// cloning is done under the state lock, but the slow disk part is done in the snapshots thread
+pub fn take_snapshot(cont: &RwLockWriteGuard<StateContainer>) {
+    let batch = (
+        Local::now().timestamp_millis(),
+        cont.rooms
+            .values
+            .iter()
+            .map(|r| RoomSnapshot::from_room(r))
+            .collect::<Vec<_>>(),
+    );
+    let sender = SNAPSHOTS.0.lock().unwrap();
+    if let Err(e) = sender.try_send(batch) {
+        warn!(format!("failed to schedule rooms snapshot, err {}", e));
+    }
+}
+
+pub fn snapshots_thread() {
+    // the main thread takes the final snapshot before the closing stage, so the last flush will include it
+    while shutdown::get_stage() < ShutdownStage::Closing {
+        flush_pending_snapshots();
+        thread::sleep(Duration::from_millis(SNAPSHOTS_WRITE_SLEEP_MS));
+    }
+    flush_pending_snapshots();
+}
+
+pub fn flush_pending_snapshots() {
+    let receiver = SNAPSHOTS.1.lock().unwrap();
+    while let Ok(batch) = receiver.try_recv() {
+        match write_batch(&snapshots_path(), &batch) {
+            Ok(()) => {
+                prune_old_generations(&snapshots_path(), *SNAPSHOT_RETENTION);
+            }
+            Err(e) => {
+                err!(format!(
+                    "failed to write rooms snapshot {}, err {}",
+                    batch.0, e
+                ));
+            }
+        }
+    }
+}
+
+fn snapshots_path() -> String {
+    format!("resources/{}", SNAPSHOTS_DIR)
+}
+
+pub fn write_batch(root: &str, batch: &SnapshotBatch) -> std::io::Result<()> {
+    let (generation, snapshots) = batch;
+    // the generation folder is renamed into place only when complete, so a crash mid-write
+    // can never produce a half-written generation that restore would pick up
+    let tmp_dir = format!("{}/{}.tmp", root, generation);
+    let final_dir = format!("{}/{}", root, generation);
+    fs::create_dir_all(&tmp_dir)?;
+    for snapshot in snapshots.iter() {
+        let json = serde_json::to_string(snapshot)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(format!("{}/{}.json", tmp_dir, snapshot.id), json)?;
+    }
+    fs::rename(tmp_dir, final_dir)?;
+    Ok(())
+}
+
+// sorted from the newest to the oldest
+pub fn list_generations(root: &str) -> Vec<i64> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut generations = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_string_lossy().parse::<i64>().ok())
+        .collect::<Vec<_>>();
+    generations.sort_by(|a, b| b.cmp(a));
+    generations
+}
+
+pub fn prune_old_generations(root: &str, retention: usize) {
+    for generation in list_generations(root).into_iter().skip(retention) {
+        let path = format!("{}/{}", root, generation);
+        if let Err(e) = fs::remove_dir_all(&path) {
+            warn!(format!("failed to remove old snapshot {}, err {}", path, e));
+        }
+    }
+}
+
+pub fn restore_rooms(cont: &mut RwLockWriteGuard<StateContainer>) {
+    let latest = list_generations(&snapshots_path()).into_iter().nth(0);
+    if latest.is_none() {
+        log!("no rooms snapshots found, starting clean");
+        return;
+    }
+    let latest = latest.unwrap();
+    let rooms = read_generation(&snapshots_path(), latest, Local::now().timestamp_millis());
+    let restored = rooms.len();
+    cont.rooms.values.extend(rooms);
+    reindex_rooms(&mut cont.rooms);
+    log!(format!(
+        "restored {} rooms from snapshot {}",
+        restored, latest
+    ));
+}
+
+// the rooms of one generation, except the unreadable ones and the ones of an incompatible state version
+pub fn read_generation(root: &str, generation: i64, now_millis: i64) -> Vec<Room> {
+    let dir = format!("{}/{}", root, generation);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(format!(
+                "could not read room snapshots from {}, err {}",
+                dir, e
+            ));
+            return vec![];
+        }
+    };
+    let mut files = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+    let mut rooms = vec![];
+    for file in files {
+        let file = file.to_string_lossy().to_string();
+        match read_json::<RoomSnapshot>(file.clone()) {
+            Ok(snapshot) => {
+                if snapshot.state.version != GAME_STATE_VERSION {
+                    warn!(format!(
+                        "skipping room snapshot {} of incompatible state version {}",
+                        file, snapshot.state.version
+                    ));
+                    continue;
+                }
+                rooms.push(snapshot.into_room(now_millis));
+            }
+            Err(err) => {
+                warn!(format!(
+                    "could not read room snapshot from file {file}, err is {err:?}"
+                ));
+            }
+        }
+    }
+    rooms
+}
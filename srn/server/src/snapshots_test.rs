@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod snapshots_test {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use crate::api_struct::Room;
+    use crate::snapshots::{
+        list_generations, prune_old_generations, read_generation, write_batch, RoomSnapshot,
+        RESTORED_ROOM_GRACE_MS,
+    };
+    use crate::test_helpers::add_player_ship;
+    use crate::world::{make_room, GameMode, GAME_STATE_VERSION};
+    use crate::{new_id, seed_prng};
+
+    fn make_room_with_player(seed: &str) -> Room {
+        let mut prng = seed_prng(seed.to_string());
+        let (_, mut room) = make_room(
+            &GameMode::CargoRush,
+            new_id(),
+            &mut prng,
+            Some(seed.to_string()),
+            None,
+            None,
+            None,
+        );
+        add_player_ship(&mut room.state, false, Some("player"), None, &mut prng);
+        room
+    }
+
+    fn make_root() -> String {
+        env::temp_dir()
+            .join(format!("srn-snapshots-{}", new_id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    pub fn snapshot_restores_the_room_with_only_bots() {
+        let room = make_room_with_player("snapshot round trip");
+        let bot_ids = room.bots.iter().map(|b| b.id).collect::<Vec<_>>();
+        assert!(bot_ids.len() > 0);
+        assert_eq!(room.state.players.len(), bot_ids.len() + 1);
+
+        let json = serde_json::to_string(&RoomSnapshot::from_room(&room)).unwrap();
+        let restored = serde_json::from_str::<RoomSnapshot>(json.as_str())
+            .unwrap()
+            .into_room(1000);
+
+        assert_eq!(restored.id, room.id);
+        assert_eq!(restored.name, room.name);
+        assert_eq!(restored.bots_seed, room.bots_seed);
+        assert_eq!(restored.next_seed, room.next_seed);
+        assert_eq!(restored.state.id, room.state.id);
+        assert_eq!(restored.state.locations.len(), room.state.locations.len());
+        assert!(room.state.combat_log.is_some());
+        assert!(restored.state.combat_log.is_none());
+        assert_eq!(
+            restored.bots.iter().map(|b| b.id).collect::<Vec<_>>(),
+            bot_ids
+        );
+        assert_eq!(restored.state.players.len(), bot_ids.len());
+        assert!(restored
+            .state
+            .players
+            .iter()
+            .all(|p| bot_ids.contains(&p.id)));
+        // so the room without players is not cleaned up before they can rejoin
+        assert_eq!(
+            restored.last_players_mark,
+            Some(1000 + RESTORED_ROOM_GRACE_MS)
+        );
+    }
+
+    #[test]
+    pub fn batches_are_moved_in_place_and_pruned() {
+        let root = make_root();
+        let room = make_room_with_player("snapshot batches");
+        for generation in vec![1, 2, 3] {
+            write_batch(&root, &(generation, vec![RoomSnapshot::from_room(&room)])).unwrap();
+        }
+        assert!(!Path::new(&format!("{}/3.tmp", root)).exists());
+        assert!(Path::new(&format!("{}/3/{}.json", root, room.id)).exists());
+        // a write interrupted before the rename is never picked up
+        fs::create_dir_all(format!("{}/4.tmp", root)).unwrap();
+        assert_eq!(list_generations(&root), vec![3, 2, 1]);
+
+        prune_old_generations(&root, 2);
+        assert_eq!(list_generations(&root), vec![3, 2]);
+        assert_eq!(read_generation(&root, 3, 0).len(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn restore_skips_incompatible_versions() {
+        let root = make_root();
+        let compatible = RoomSnapshot::from_room(&make_room_with_player("snapshot current"));
+        let mut outdated = RoomSnapshot::from_room(&make_room_with_player("snapshot outdated"));
+        outdated.state.version = GAME_STATE_VERSION + 1;
+        write_batch(&root, &(1, vec![compatible.clone(), outdated])).unwrap();
+
+        let rooms = read_generation(&root, 1, 0);
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].id, compatible.id);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
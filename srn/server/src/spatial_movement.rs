@@ -1,3 +1,4 @@
+use crate::autofocus::SpatialIndex;
 use crate::combat::{acceleration_markers_to_string, guide_accelerated_object};
 use crate::indexing::{
     find_planet, index_planets_by_id, GameStateCaches, GameStateIndexes, IdKind, ObjectSpecifier,
@@ -8,8 +9,9 @@ use crate::long_actions::{
 };
 use crate::planet_movement::IBodyV2;
 use crate::trajectory::{
-    build_trajectory_accelerated, spatial_distance, TrajectoryItem, TrajectoryRequest,
-    TrajectoryResult, TRAJECTORY_PREFETCH_POINTS,
+    build_trajectory_accelerated_avoiding_obstacles, build_trajectory_accelerated_intercept,
+    spatial_distance, TrajectoryItem, TrajectoryObstacle, TrajectoryResult,
+    TRAJECTORY_PREFETCH_POINTS,
 };
 use crate::vec2::{deg_to_rad, Precision, Vec2f64};
 use crate::world::{GameState, Location, PlanetV2, Ship, ShipIdx, SpatialProps, UpdateOptions};
@@ -441,6 +443,48 @@ pub fn move_ship_towards(target: &Vec2f64, ship_pos: &Vec2f64, max_shift: f64) -
     };
 }
 
+// for every navigating ship, the bodies that are in the way to its target
+pub fn find_navigation_obstacles(
+    loc: &Location,
+    spatial_index: &SpatialIndex,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+    current_ticks: u64,
+) -> HashMap<Uuid, Vec<TrajectoryObstacle>> {
+    let mut res = HashMap::new();
+    for ship in loc.ships.iter() {
+        if ship.docked_at.is_some() {
+            continue;
+        }
+        let (target, exclude) = if let Some(target) = ship.navigate_target {
+            (target, None)
+        } else if let Some(planet_id) = ship.dock_target {
+            let spec = ObjectSpecifier::Planet { id: planet_id };
+            match indexes.bodies_by_id.get(&spec) {
+                Some(planet) => (planet.get_spatial().position, Some(spec)),
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+        let obstacles = trajectory::find_trajectory_obstacles(
+            loc,
+            spatial_index,
+            &ship.spatial.position,
+            &target,
+            ship.movement_definition.get_max_speed(),
+            exclude,
+            current_ticks,
+            indexes,
+            caches,
+        );
+        if obstacles.len() > 0 {
+            res.insert(ship.id, obstacles);
+        }
+    }
+    res
+}
+
 pub fn update_ships_navigation(
     ships: &mut Vec<Ship>,
     elapsed: i64,
@@ -450,7 +494,9 @@ pub fn update_ships_navigation(
     update_every_ticks: u64,
     caches: &mut GameStateCaches,
     current_ticks: u64,
+    obstacles_by_ship: &HashMap<Uuid, Vec<TrajectoryObstacle>>,
 ) {
+    let no_obstacles = vec![];
     let docking_ship_ids: HashSet<Uuid> = HashSet::from_iter(ships.iter().filter_map(|s| {
         let long_act = s
             .long_actions
@@ -487,6 +533,7 @@ pub fn update_ships_navigation(
             ship.trajectory = vec![];
             continue;
         }
+        let obstacles = obstacles_by_ship.get(&ship.id).unwrap_or(&no_obstacles);
         if !ship.docked_at.is_some() {
             match &ship.movement_definition {
                 Movement::None => panic!("ship has no movement, cannot update"),
@@ -496,7 +543,10 @@ pub fn update_ships_navigation(
                     if let Some(target) = ship.navigate_target {
                         let ship_pos = ship.spatial.position.clone();
                         let dist = target.euclidean_distance(&ship_pos);
-                        let dir = target.subtract(&ship_pos);
+                        // either the target itself or a point to go around something on the way
+                        let waypoints = trajectory::avoid_obstacles(&ship_pos, &target, obstacles);
+                        let next_point = waypoints[0];
+                        let dir = next_point.subtract(&ship_pos);
                         ship.spatial.rotation_rad =
                             dir.angle_rad_signed(&Vec2f64 { x: 1.0, y: 0.0 });
                         if dist > 0.0 {
@@ -505,10 +555,13 @@ pub fn update_ships_navigation(
                                 &target,
                                 &ship.movement_definition,
                                 update_every_ticks,
+                                obstacles,
                             );
-                            if dist > max_shift {
-                                let new_pos = move_ship_towards(&target, &ship_pos, max_shift);
+                            if next_point.euclidean_distance(&ship_pos) > max_shift {
+                                let new_pos = move_ship_towards(&next_point, &ship_pos, max_shift);
                                 ship.set_from(&new_pos);
+                            } else if waypoints.len() > 1 {
+                                ship.set_from(&next_point);
                             } else {
                                 ship.set_from(&target);
                                 ship.navigate_target = None;
@@ -535,6 +588,7 @@ pub fn update_ships_navigation(
                                 current_ticks,
                                 indexes,
                                 caches,
+                                obstacles,
                             );
                             if let Some(first) = ship.trajectory.clone().get(0) {
                                 let dir = first.subtract(&ship_pos);
@@ -598,11 +652,24 @@ pub fn update_ships_navigation(
                                     Some(AccelerationMarkers { gas, turn, brake });
                             }
                         } else {
-                            let tr_res = build_trajectory_accelerated(
-                                TrajectoryRequest::StartAndStopPoint { to: target_point },
-                                &ship.movement_definition,
-                                &ship.spatial,
-                            );
+                            let tr_res = match (ship.navigate_target, ship.dock_target) {
+                                // the planet keeps moving, so fly to where it is going to be
+                                (None, Some(planet_id)) => build_trajectory_accelerated_intercept(
+                                    &ObjectSpecifier::Planet { id: planet_id },
+                                    &ship.movement_definition,
+                                    &ship.spatial,
+                                    current_ticks,
+                                    indexes,
+                                    caches,
+                                    obstacles,
+                                ),
+                                _ => build_trajectory_accelerated_avoiding_obstacles(
+                                    target_point,
+                                    &ship.movement_definition,
+                                    &ship.spatial,
+                                    obstacles,
+                                ),
+                            };
 
                             match &tr_res {
                                 TrajectoryResult::Success(trajectory) => {
@@ -11,13 +11,18 @@ use serde_derive::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::combat::{
-    ExplosionProps, Health, Projectile, RocketProps, DEFAULT_PROJECTILE_EXPIRATION_TICKS,
-    DEFAULT_PROJECTILE_ROT_SPEED, DEFAULT_PROJECTILE_SPEED,
+    EmpProps, ExplosionProps, Health, MineProps, Projectile, RocketProps, SlugProps,
+    DEFAULT_PROJECTILE_EXPIRATION_TICKS, DEFAULT_PROJECTILE_ROT_SPEED, DEFAULT_PROJECTILE_SPEED,
+    EMP_BURST_RADIUS, EMP_DISABLE_TICKS, MINE_LIFETIME_TICKS, MINE_TRIGGER_RADIUS, SLUG_DAMAGE,
+    SLUG_SPEED,
 };
+use crate::combat_log::CombatLog;
+use crate::economy::assign_planet_types;
 use crate::indexing::{index_state, GameStateCaches, ObjectSpecifier, Spec};
 use crate::interpolation::get_orbit_phase_table;
 use crate::market::{init_all_planets_market, Market};
 use crate::perf::Sampler;
+use crate::pirate_defence::PirateWaves;
 use crate::properties::{ObjectProperty, PROJECTILE_LIFETIME_TICKS, WRECK_DECAY_TICKS};
 use crate::random_stuff::{
     gen_color, gen_period, gen_planet_count, gen_planet_orbit_period, gen_planet_orbit_speed,
@@ -35,7 +40,9 @@ use crate::{planet_movement, prng_id, seed_prng, world};
 use typescript_definitions::{TypeScriptify, TypescriptDefinition};
 use wasm_bindgen::prelude::*;
 
-#[derive(Serialize, Deserialize, Debug, Clone, TypeScriptify, TypescriptDefinition)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, PartialEq, Eq, TypeScriptify, TypescriptDefinition,
+)]
 pub enum PlanetType {
     Unknown,
     Ice,
@@ -69,10 +76,11 @@ pub fn wire_shake_locations(locations: &mut Vec<Location>, prng: &mut Pcg64Mcg)
     let all_ids = locations.iter().map(|l| l.id.clone()).collect::<Vec<_>>();
     let mut angle: f64 = 0.0;
     let mut loc_pos_by_id = HashMap::new();
+    let slots = LOCATION_COUNT.max(locations.len() as u32);
     for loc in locations.iter_mut() {
         let x = angle.cos() * (DIST + prng.gen_range(0.0, 100.0));
         let y = angle.sin() * (DIST + prng.gen_range(0.0, 100.0));
-        angle += 2.0 * PI / LOCATION_COUNT as f64;
+        angle += 2.0 * PI / slots as f64;
         loc.position = Vec2f64 { x, y };
         loc.adjacent_location_ids = all_ids
             .clone()
@@ -97,6 +105,15 @@ pub fn wire_shake_locations(locations: &mut Vec<Location>, prng: &mut Pcg64Mcg)
             })
             .collect();
     }
+    // the distance cut-off can split the galaxy into parts that cannot reach each other,
+    // so the neighbors on the circle are always connected
+    for i in 1..locations.len() {
+        let (prev_id, curr_id) = (locations[i - 1].id, locations[i].id);
+        if !locations[i].adjacent_location_ids.contains(&prev_id) {
+            locations[i].adjacent_location_ids.push(prev_id);
+            locations[i - 1].adjacent_location_ids.push(curr_id);
+        }
+    }
 }
 
 pub const MIN_CONTAINER_DISTANCE: f64 = 50.0;
@@ -448,6 +465,7 @@ fn make_cargo_rush_state(
     state.market = Some(Market::new());
     init_all_planets_market(&mut state);
     state.id = prng_id(&mut prng);
+    assign_planet_types(&mut state, &mut prng);
     state.mode = GameMode::CargoRush;
     state
 }
@@ -475,8 +493,9 @@ pub fn make_pirate_defence_state(
     state.locations[0].planets[0]
         .properties
         .push(ObjectProperty::PirateDefencePlayersHomePlanet);
-    state.milliseconds_remaining = 5 * 1000 * 60;
+    state.milliseconds_remaining = 10 * 1000 * 60;
     state.mode = GameMode::PirateDefence;
+    state.pirate_waves = Some(PirateWaves::new(state.ticks));
     add_default_templates(&mut state);
     state
 }
@@ -588,6 +607,8 @@ fn make_tutorial_state(prng: &mut Pcg64Mcg, opts: Option<GenStateOpts>) -> GameS
         dialogue_states: Some(Default::default()),
         breadcrumbs: None,
         projectile_templates: None,
+        combat_log: Some(CombatLog::new()),
+        pirate_waves: None,
     };
     add_default_templates(&mut state);
     state
@@ -636,17 +657,124 @@ fn add_default_templates(state: &mut GameState) {
             last_damage_dealer: None,
             acc_periodic_dmg: 0.0,
             acc_periodic_heal: 0.0,
+            shield: None,
+            resistances: None,
         },
         to_clean: false,
         fof_overrides: None,
+        launched_by: None,
         gas: 0.0,
         turn: 0.0,
         brake: 0.0,
     });
+    let slug_template = Projectile::Slug(SlugProps {
+        id: TemplateId::Slug as i32,
+        spatial: SpatialProps {
+            position: Default::default(),
+            velocity: Vec2f64 {
+                x: SLUG_SPEED,
+                y: 0.0,
+            },
+            angular_velocity: 0.0,
+            rotation_rad: 0.0,
+            radius: 0.4,
+        },
+        movement: Movement::ShipAccelerated {
+            max_linear_speed: SLUG_SPEED,
+            max_rotation_speed: 0.0,
+            linear_drag: 0.0,
+            acc_linear: SLUG_SPEED * 4.0 / 1e6,
+            max_turn_speed: 0.0,
+            acc_angular: 0.0,
+            brake_acc: 0.0,
+        },
+        properties: vec![ObjectProperty::Lifetime(ProcessProps::from(
+            PROJECTILE_LIFETIME_TICKS,
+        ))],
+        health: Health::new(1.0),
+        to_clean: false,
+        fof_overrides: None,
+        launched_by: None,
+        damage: SLUG_DAMAGE,
+    });
+    let mine_template = Projectile::Mine(MineProps {
+        id: TemplateId::Mine as i32,
+        spatial: SpatialProps {
+            position: Default::default(),
+            // only used to put the mine off the ship, it's dropped with zero velocity
+            velocity: Vec2f64 {
+                x: DEFAULT_PROJECTILE_SPEED,
+                y: 0.0,
+            },
+            angular_velocity: 0.0,
+            rotation_rad: 0.0,
+            radius: 1.0,
+        },
+        movement: Movement::ShipAccelerated {
+            max_linear_speed: DEFAULT_PROJECTILE_SPEED,
+            max_rotation_speed: DEFAULT_PROJECTILE_ROT_SPEED,
+            linear_drag: 0.0,
+            acc_linear: DEFAULT_PROJECTILE_SPEED * 2.0 / 1e6,
+            max_turn_speed: DEFAULT_PROJECTILE_ROT_SPEED,
+            acc_angular: DEFAULT_PROJECTILE_ROT_SPEED * 2.0 / 1e6,
+            brake_acc: DEFAULT_PROJECTILE_SPEED / 1e6,
+        },
+        properties: vec![ObjectProperty::Lifetime(ProcessProps::from(
+            MINE_LIFETIME_TICKS,
+        ))],
+        target: None,
+        explosion_props: ExplosionProps {
+            damage: 40.0,
+            radius: 7.5,
+            applied_force: 6e-12,
+            spread_speed: 1e-5,
+        },
+        markers: None,
+        health: Health::new(10.0),
+        to_clean: false,
+        fof_overrides: None,
+        launched_by: None,
+        trigger_radius: MINE_TRIGGER_RADIUS,
+        gas: 0.0,
+        turn: 0.0,
+        brake: 0.0,
+    });
+    let emp_template = Projectile::Emp(EmpProps {
+        id: TemplateId::Emp as i32,
+        spatial: SpatialProps {
+            position: Default::default(),
+            velocity: Vec2f64 {
+                x: DEFAULT_PROJECTILE_SPEED,
+                y: 0.0,
+            },
+            angular_velocity: 0.0,
+            rotation_rad: 0.0,
+            radius: 1.0,
+        },
+        movement: Movement::ShipAccelerated {
+            max_linear_speed: DEFAULT_PROJECTILE_SPEED,
+            max_rotation_speed: 0.0,
+            linear_drag: 0.0,
+            acc_linear: DEFAULT_PROJECTILE_SPEED * 2.0 / 1e6,
+            max_turn_speed: 0.0,
+            acc_angular: 0.0,
+            brake_acc: 0.0,
+        },
+        properties: vec![ObjectProperty::Lifetime(ProcessProps::from(
+            PROJECTILE_LIFETIME_TICKS,
+        ))],
+        health: Health::new(5.0),
+        to_clean: false,
+        fof_overrides: None,
+        launched_by: None,
+        burst_radius: EMP_BURST_RADIUS,
+        disable_ticks: EMP_DISABLE_TICKS,
+    });
+    let new_templates = vec![rocket_template, slug_template, mine_template, emp_template];
     if let Some(templates) = &mut state.projectile_templates {
-        templates.push(rocket_template);
+        templates.extend(new_templates);
     } else {
-        state.projectile_templates = Some(vec![rocket_template]);
+        state.projectile_templates = Some(new_templates);
     }
 }
 
@@ -683,6 +811,8 @@ pub fn make_sandbox_state(prng: &mut Pcg64Mcg, opts: Option<GenStateOpts>) -> Ga
         dialogue_states: Default::default(),
         breadcrumbs: None,
         projectile_templates: None,
+        combat_log: Some(CombatLog::new()),
+        pirate_waves: None,
     };
     add_default_templates(&mut state);
     state
@@ -748,6 +878,8 @@ fn gen_state(
         breadcrumbs: None,
         next_seed: None,
         projectile_templates: None,
+        combat_log: Some(CombatLog::new()),
+        pirate_waves: None,
     };
 
     let anchor_distances = index_state(&state).anchor_distances.clone();
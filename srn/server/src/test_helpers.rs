@@ -0,0 +1,48 @@
+use crate::indexing::GameStateCaches;
+use crate::new_id;
+use crate::system_gen::seed_state;
+use crate::vec2::Vec2f64;
+use crate::world::{add_player, spawn_ship, GameMode, GameState, ShipTemplate};
+use rand_pcg::Pcg64Mcg;
+use uuid::Uuid;
+
+// fixtures shared by the module tests, so every test file seeds its states the same way
+
+pub fn new_state(mode: &GameMode, seed: &str) -> GameState {
+    seed_state(mode, seed.to_string(), None, &mut GameStateCaches::new())
+}
+
+// returns (player_id, ship_id), the ship is put in the first location when there are explicit coordinates
+pub fn add_player_ship(
+    state: &mut GameState,
+    is_bot: bool,
+    name: Option<&str>,
+    at: Option<Vec2f64>,
+    prng: &mut Pcg64Mcg,
+) -> (Uuid, Uuid) {
+    add_player_with_ship(state, is_bot, name, ShipTemplate::player(at), prng)
+}
+
+// returns (player_id, ship_id), the ship is at a random planet of the location
+pub fn add_player_ship_in_location(
+    state: &mut GameState,
+    location_idx: usize,
+    prng: &mut Pcg64Mcg,
+) -> (Uuid, Uuid) {
+    let location_id = state.locations[location_idx].id;
+    let template = ShipTemplate::player(None).in_location(location_id);
+    add_player_with_ship(state, false, None, template, prng)
+}
+
+fn add_player_with_ship(
+    state: &mut GameState,
+    is_bot: bool,
+    name: Option<&str>,
+    template: ShipTemplate,
+    prng: &mut Pcg64Mcg,
+) -> (Uuid, Uuid) {
+    let player_id = new_id();
+    add_player(state, player_id, is_bot, name.map(|n| n.to_string()), prng);
+    let ship_id = spawn_ship(state, Some(player_id), template, prng).id;
+    (player_id, ship_id)
+}
@@ -6,9 +6,10 @@ use objekt_clonable::*;
 use objekt_clonable::*;
 use uuid::Uuid;
 
+use crate::fitting::ShipModule;
 use crate::vec2::Vec2f64;
 use crate::world::{Container, NatSpawnMineral, Player, PlayerId, Ship};
-use crate::{indexing, world};
+use crate::{fitting, indexing, world};
 
 pub fn update_ships_tractoring(
     ships: &Vec<Ship>,
@@ -27,7 +28,13 @@ pub fn update_ships_tractoring(
         .collect::<Vec<_>>()
 }
 
-const MAX_TRACTOR_DIST: f64 = 30.0;
+pub const MAX_TRACTOR_DIST: f64 = 30.0;
+
+pub fn get_tractor_range(ship: &Ship) -> f64 {
+    MAX_TRACTOR_DIST
+        + fitting::TRACTOR_AMPLIFIER_RANGE
+            * fitting::count_modules(ship, &ShipModule::TractorAmplifier) as f64
+}
 
 pub fn update_ship_tractor(
     t: Uuid,
@@ -37,7 +44,7 @@ pub fn update_ship_tractor(
 ) {
     if let Some(position) = find_tractorable_item_position(&minerals, &containers, t) {
         let dist = ship.spatial.position.euclidean_distance(&position);
-        if dist <= MAX_TRACTOR_DIST {
+        if dist <= get_tractor_range(ship) {
             ship.tractor_target = Some(t);
         } else {
             ship.tractor_target = None;
@@ -1,9 +1,11 @@
-use crate::indexing::{GameStateCaches, GameStateIndexes};
+use crate::autofocus::SpatialIndex;
+use crate::hp::STAR_FAR_RADIUS;
+use crate::indexing::{GameStateCaches, GameStateIndexes, ObjectIndexSpecifier, ObjectSpecifier};
 use crate::planet_movement::{
     project_body_relative_position, project_movement_relative_position, IBodyV2,
 };
 use crate::spatial_movement::Movement;
-use crate::world::{PlanetV2, SpatialProps};
+use crate::world::{Location, PlanetV2, SpatialProps};
 use crate::{planet_movement, spatial_movement, world, Vec2f64};
 use optimization_engine::panoc::{PANOCCache, PANOCOptimizer};
 use optimization_engine::{constraints, Optimizer, Problem, SolverError};
@@ -25,6 +27,7 @@ pub fn build_trajectory_to_point(
     to: &Vec2f64,
     for_movement: &Movement,
     update_every_ticks: u64,
+    obstacles: &Vec<TrajectoryObstacle>,
 ) -> Vec<Vec2f64> {
     let mut counter = 0;
     let current_target = to.clone();
@@ -46,8 +49,8 @@ pub fn build_trajectory_to_point(
                 if should_break {
                     break;
                 }
-                current_from =
-                    spatial_movement::move_ship_towards(&target_pos, &current_from, max_shift);
+                let next_point = avoid_obstacles(&current_from, &target_pos, obstacles)[0];
+                current_from = move_towards_clamped(&next_point, &current_from, max_shift);
                 result.push(current_from);
                 counter += 1;
             }
@@ -66,6 +69,7 @@ pub fn build_trajectory_to_planet(
     initial_ticks: u64,
     indexes: &GameStateIndexes,
     caches: &mut GameStateCaches,
+    obstacles: &Vec<TrajectoryObstacle>,
 ) -> Vec<Vec2f64> {
     let mut counter = 0;
     let mut current_from = current_pos;
@@ -94,14 +98,473 @@ pub fn build_trajectory_to_planet(
         if should_break {
             break;
         }
-        current_from =
-            spatial_movement::move_ship_towards(&new_absolute_position, &current_from, max_shift);
+        let next_point = avoid_obstacles(&current_from, &new_absolute_position, obstacles)[0];
+        current_from = move_towards_clamped(&next_point, &current_from, max_shift);
         result.push(current_from);
         counter += 1;
     }
     result
 }
 
+// do not jump over the detour points, otherwise the ship may cut the corner
+fn move_towards_clamped(target: &Vec2f64, from: &Vec2f64, max_shift: f64) -> Vec2f64 {
+    if target.euclidean_distance(from) <= max_shift {
+        target.clone()
+    } else {
+        spatial_movement::move_ship_towards(target, from, max_shift)
+    }
+}
+
+// a circle that the trajectory has to go around
+#[derive(Debug, Clone)]
+pub struct TrajectoryObstacle {
+    pub spec: ObjectSpecifier,
+    pub position: Vec2f64,
+    // already includes the avoidance margin
+    pub radius: f64,
+}
+
+pub const OBSTACLE_AVOIDANCE_MARGIN: f64 = 5.0;
+// the detour point is placed on the corner of a square around the obstacle, so both legs
+// going through it mostly clear the obstacle, and the rest is handled by the recursion
+const OBSTACLE_DETOUR_DISTANCE_COEFF: f64 = std::f64::consts::SQRT_2;
+const OBSTACLE_AVOIDANCE_MAX_DEPTH: u32 = 4;
+
+// Collects the star, planets and asteroids that may be on the way from one point to another.
+// Moving bodies are projected to the moment when the ship passes by them, assuming it flies
+// straight with max speed. The path is re-checked on every rebuild of the trajectory, so bodies
+// that come from far away are not a concern here
+pub fn find_trajectory_obstacles(
+    loc: &Location,
+    spatial_index: &SpatialIndex,
+    from: &Vec2f64,
+    to: &Vec2f64,
+    max_speed: f64,
+    exclude: Option<ObjectSpecifier>,
+    current_ticks: u64,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+) -> Vec<TrajectoryObstacle> {
+    let mut res = vec![];
+    let path_len = from.euclidean_distance(to);
+    if let Some(star) = &loc.star {
+        let radius = star.spatial.radius * STAR_FAR_RADIUS + OBSTACLE_AVOIDANCE_MARGIN;
+        if distance_to_segment(&star.spatial.position, from, to) < radius {
+            res.push(TrajectoryObstacle {
+                spec: ObjectSpecifier::Star { id: star.id },
+                position: star.spatial.position.clone(),
+                radius,
+            });
+        }
+    }
+    let max_body_radius = loc
+        .planets
+        .iter()
+        .map(|p| p.spatial.radius)
+        .fold(0.0, f64::max);
+    let search_center = from.add(&to.subtract(from).scalar_mul(0.5));
+    let search_radius = path_len / 2.0 + max_body_radius + OBSTACLE_AVOIDANCE_MARGIN;
+    for ois in spatial_index.rad_search(&search_center, search_radius) {
+        let found = match ois {
+            ObjectIndexSpecifier::Planet { idx } => loc.planets.get(idx).map(|p| {
+                (
+                    ObjectSpecifier::Planet { id: p.id },
+                    project_obstacle_position(
+                        p,
+                        from,
+                        to,
+                        max_speed,
+                        current_ticks,
+                        indexes,
+                        caches,
+                    ),
+                    p.spatial.radius,
+                )
+            }),
+            ObjectIndexSpecifier::Asteroid { idx } => loc.asteroids.get(idx).map(|a| {
+                (
+                    ObjectSpecifier::Asteroid { id: a.id },
+                    project_obstacle_position(
+                        a,
+                        from,
+                        to,
+                        max_speed,
+                        current_ticks,
+                        indexes,
+                        caches,
+                    ),
+                    a.spatial.radius,
+                )
+            }),
+            _ => None,
+        };
+        if let Some((spec, position, radius)) = found {
+            if exclude.as_ref().map_or(false, |ex| *ex == spec) {
+                continue;
+            }
+            let radius = radius + OBSTACLE_AVOIDANCE_MARGIN;
+            if distance_to_segment(&position, from, to) < radius {
+                res.push(TrajectoryObstacle {
+                    spec,
+                    position,
+                    radius,
+                });
+            }
+        }
+    }
+    res
+}
+
+fn project_obstacle_position<T: IBodyV2 + Clone>(
+    body: &T,
+    from: &Vec2f64,
+    to: &Vec2f64,
+    max_speed: f64,
+    current_ticks: u64,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+) -> Vec2f64 {
+    let current_position = body.get_spatial().position.clone();
+    if !matches!(body.get_movement(), Movement::RadialMonotonous { .. }) || max_speed <= 0.0 {
+        return current_position;
+    }
+    let anchor_position = match indexes
+        .bodies_by_id
+        .get(body.get_movement().get_anchor_spec())
+    {
+        Some(anchor) => anchor.get_spatial().position.clone(),
+        None => return current_position,
+    };
+    let passing_point = closest_point_on_segment(&current_position, from, to);
+    let ticks_to_pass = (from.euclidean_distance(&passing_point) / max_speed) as u64;
+    let mut projected = body.clone();
+    project_body_relative_position(
+        current_ticks + ticks_to_pass,
+        indexes,
+        caches,
+        Box::new(&mut projected),
+    );
+    projected
+        .get_movement()
+        .get_anchor_relative_position()
+        .map_or(current_position, |rel| anchor_position.add(&rel))
+}
+
+pub fn closest_point_on_segment(point: &Vec2f64, from: &Vec2f64, to: &Vec2f64) -> Vec2f64 {
+    let segment = to.subtract(from);
+    let len_sq = segment.scalar_multiply(&segment);
+    if len_sq < 1e-12 {
+        return from.clone();
+    }
+    let t = (point.subtract(from).scalar_multiply(&segment) / len_sq)
+        .max(0.0)
+        .min(1.0);
+    from.add(&segment.scalar_mul(t))
+}
+
+pub fn distance_to_segment(point: &Vec2f64, from: &Vec2f64, to: &Vec2f64) -> f64 {
+    closest_point_on_segment(point, from, to).euclidean_distance(point)
+}
+
+// Returns the points to fly through in order, the last one is always the target itself.
+// Obstacles containing the start or the target are ignored - the ship is either already
+// inside, or intentionally flies into them
+pub fn avoid_obstacles(
+    from: &Vec2f64,
+    to: &Vec2f64,
+    obstacles: &Vec<TrajectoryObstacle>,
+) -> Vec<Vec2f64> {
+    let mut res = find_detour_points(from, to, obstacles, OBSTACLE_AVOIDANCE_MAX_DEPTH);
+    res.push(to.clone());
+    res
+}
+
+fn find_detour_points(
+    from: &Vec2f64,
+    to: &Vec2f64,
+    obstacles: &Vec<TrajectoryObstacle>,
+    depth: u32,
+) -> Vec<Vec2f64> {
+    if depth == 0 {
+        return vec![];
+    }
+    let hit = obstacles
+        .iter()
+        .filter(|o| {
+            o.position.euclidean_distance(from) > o.radius
+                && o.position.euclidean_distance(to) > o.radius
+                && distance_to_segment(&o.position, from, to) < o.radius
+        })
+        .min_by(|a, b| {
+            let dist_a = from.euclidean_distance(&closest_point_on_segment(&a.position, from, to));
+            let dist_b = from.euclidean_distance(&closest_point_on_segment(&b.position, from, to));
+            dist_a
+                .partial_cmp(&dist_b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    let hit = match hit {
+        Some(hit) => hit,
+        None => return vec![],
+    };
+    // go around the side of the obstacle that the straight path is closer to
+    let closest = closest_point_on_segment(&hit.position, from, to);
+    let side = closest
+        .subtract(&hit.position)
+        .normalize()
+        .or_else(|| {
+            to.subtract(from)
+                .normalize()
+                .map(|dir| dir.rotate(PI / 2.0))
+        })
+        .unwrap_or(Vec2f64 { x: 0.0, y: 1.0 });
+    let detour = hit
+        .position
+        .add(&side.scalar_mul(hit.radius * OBSTACLE_DETOUR_DISTANCE_COEFF));
+    let mut res = find_detour_points(from, &detour, obstacles, depth - 1);
+    res.push(detour);
+    res.append(&mut find_detour_points(&detour, to, obstacles, depth - 1));
+    res
+}
+
+// absolute position of a body at the given tick, also projecting its anchor if it moves too
+// (e.g. for moons), using the same orbit phase tables as the real movement
+pub fn project_absolute_position(
+    spec: &ObjectSpecifier,
+    at_ticks: u64,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+) -> Option<Vec2f64> {
+    let body = indexes.bodies_by_id.get(spec)?;
+    let movement = body.get_movement();
+    if !matches!(movement, Movement::RadialMonotonous { .. }) {
+        return Some(body.get_spatial().position.clone());
+    }
+    let anchor_position =
+        project_absolute_position(movement.get_anchor_spec(), at_ticks, indexes, caches)?;
+    let mut projected_movement = movement.clone();
+    project_movement_relative_position(
+        at_ticks,
+        caches,
+        body.get_anchor_dist(indexes),
+        &mut projected_movement,
+        spec.clone(),
+    );
+    projected_movement
+        .get_anchor_relative_position()
+        .map(|rel| anchor_position.add(&rel))
+}
+
+fn get_orbit_period_ticks(spec: &ObjectSpecifier, indexes: &GameStateIndexes) -> Option<f64> {
+    match indexes.bodies_by_id.get(spec)?.get_movement() {
+        Movement::RadialMonotonous {
+            full_period_ticks, ..
+        } => Some(full_period_ticks.abs()),
+        _ => None,
+    }
+}
+
+pub const INTERCEPT_STEPS_PER_PERIOD: f64 = 32.0;
+pub const INTERCEPT_MAX_STEPS: u32 = 512;
+const INTERCEPT_REFINE_ITERS: u32 = 10;
+
+// Finds the earliest tick at which the ship, flying from a stop to a stop, can be where the target
+// body will be at that moment. Returns the tick and the meeting point.
+// The orbit is periodic, so the search steps are a fraction of the period, and the search
+// is limited by the time required to reach the farthest point of the orbit
+pub fn find_intercept(
+    from: &Vec2f64,
+    target: &ObjectSpecifier,
+    mov: &Movement,
+    current_ticks: u64,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+) -> Option<(u64, Vec2f64)> {
+    let acc = mov.get_current_linear_acceleration();
+    let max_speed = mov.get_max_speed();
+    let travel_ticks =
+        |to: &Vec2f64| acc_time_for_dist(acc, from.euclidean_distance(to), max_speed);
+    let current_position = project_absolute_position(target, current_ticks, indexes, caches)?;
+    let period = match get_orbit_period_ticks(target, indexes) {
+        Some(period) => period,
+        // not moving, so just fly to it
+        None => {
+            return Some((
+                current_ticks + travel_ticks(&current_position) as u64,
+                current_position,
+            ))
+        }
+    };
+    let orbit_radius = indexes.bodies_by_id.get(target)?.get_anchor_dist(indexes);
+    // doubled, since the anchor may be moving as well
+    let horizon = 2.0
+        * (travel_ticks(&current_position) + acc_time_for_dist(acc, 2.0 * orbit_radius, max_speed));
+    if !horizon.is_finite() {
+        return None;
+    }
+    let step = (period / INTERCEPT_STEPS_PER_PERIOD)
+        .min(horizon / 64.0)
+        .max(horizon / INTERCEPT_MAX_STEPS as f64)
+        .max(1.0);
+    // positive means the ship is late for the target being there
+    let lateness = |ticks_ahead: f64, caches: &mut GameStateCaches| {
+        let at = current_ticks + ticks_ahead as u64;
+        project_absolute_position(target, at, indexes, caches)
+            .map(|pos| (travel_ticks(&pos) - ticks_ahead, pos))
+    };
+    let mut prev_ahead = 0.0;
+    let mut ahead = 0.0;
+    loop {
+        let (late, _) = lateness(ahead, caches)?;
+        if late <= 0.0 {
+            break;
+        }
+        if ahead > horizon {
+            return None;
+        }
+        prev_ahead = ahead;
+        ahead += step;
+    }
+    // the moment of meeting is somewhere between the last two steps
+    let (mut early, mut late) = (ahead, prev_ahead);
+    for _i in 0..INTERCEPT_REFINE_ITERS {
+        let mid = (early + late) / 2.0;
+        if lateness(mid, caches)?.0 <= 0.0 {
+            early = mid;
+        } else {
+            late = mid;
+        }
+    }
+    let (_, position) = lateness(early, caches)?;
+    Some((current_ticks + early as u64, position))
+}
+
+// an accelerated flight to the point where the target body is going to be
+pub fn build_trajectory_accelerated_intercept(
+    target: &ObjectSpecifier,
+    mov: &Movement,
+    spatial: &SpatialProps,
+    current_ticks: u64,
+    indexes: &GameStateIndexes,
+    caches: &mut GameStateCaches,
+    obstacles: &Vec<TrajectoryObstacle>,
+) -> TrajectoryResult {
+    let (arrival_ticks, meeting_point) = match find_intercept(
+        &spatial.position,
+        target,
+        mov,
+        current_ticks,
+        indexes,
+        caches,
+    ) {
+        Some(intercept) => intercept,
+        None => return TrajectoryResult::Inaccessible,
+    };
+    match build_trajectory_accelerated_avoiding_obstacles(meeting_point, mov, spatial, obstacles) {
+        TrajectoryResult::Success(mut trajectory) => {
+            trajectory.arrival_ticks = Some(arrival_ticks);
+            TrajectoryResult::Success(trajectory)
+        }
+        other => other,
+    }
+}
+
+// keeps the speed through the detour points and only brakes for the final one - the same
+// accelerate-maintain-decelerate profile as in build_trajectory_accelerated, but along the whole path
+pub fn build_trajectory_accelerated_avoiding_obstacles(
+    to: Vec2f64,
+    mov: &Movement,
+    spatial: &SpatialProps,
+    obstacles: &Vec<TrajectoryObstacle>,
+) -> TrajectoryResult {
+    let waypoints = avoid_obstacles(&spatial.position, &to, obstacles);
+    if waypoints.len() <= 1 {
+        return build_trajectory_accelerated(
+            TrajectoryRequest::StartAndStopPoint { to },
+            mov,
+            spatial,
+        );
+    }
+    let acc = mov.get_current_linear_acceleration();
+    let max_speed = mov.get_max_speed();
+    if acc <= 0.0 || max_speed <= 0.0 {
+        return TrajectoryResult::Inaccessible;
+    }
+    let mut path = vec![spatial.position.clone()];
+    path.extend(waypoints);
+    // distance along the path to every path point
+    let mut path_dists = vec![0.0];
+    for i in 1..path.len() {
+        path_dists.push(path_dists[i - 1] + path[i - 1].euclidean_distance(&path[i]));
+    }
+    let total_dist = path_dists[path_dists.len() - 1];
+    let start_speed = spatial.velocity.euclidean_len();
+    // same kinematics as in build_trajectory_accelerated, getting to the speed v from zero takes v^2 / acc
+    let speed_at = |dist: f64| {
+        let accelerated = (start_speed * start_speed + acc * dist).sqrt();
+        let braking = (acc * (total_dist - dist).max(0.0)).sqrt();
+        accelerated.min(braking).min(max_speed)
+    };
+    let mut profile_dists = path_dists[1..path_dists.len() - 1].to_vec();
+    let maintain_dist = (max_speed * max_speed - start_speed * start_speed) / acc;
+    let decelerate_dist = total_dist - max_speed * max_speed / acc;
+    if maintain_dist < decelerate_dist {
+        profile_dists.push(maintain_dist);
+        profile_dists.push(decelerate_dist);
+    } else {
+        // peak speed
+        profile_dists.push((acc * total_dist - start_speed * start_speed) / 2.0 / acc);
+    }
+    profile_dists.retain(|dist| *dist > 0.0 && *dist < total_dist);
+    profile_dists.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut points = vec![TrajectoryItem {
+        ticks: 0,
+        is_reference_point: false,
+        precision_multiplier: 0.0,
+        spatial: spatial.clone(),
+    }];
+    let mut segment = 0;
+    for dist in profile_dists {
+        while segment < path.len() - 2 && path_dists[segment + 1] < dist {
+            segment += 1;
+        }
+        let dir = path[segment + 1]
+            .subtract(&path[segment])
+            .normalize()
+            .unwrap_or(Vec2f64::zero());
+        points.push(TrajectoryItem {
+            ticks: 0,
+            is_reference_point: true,
+            precision_multiplier: 1.0,
+            spatial: SpatialProps {
+                position: path[segment].add(&dir.scalar_mul(dist - path_dists[segment])),
+                velocity: dir.scalar_mul(speed_at(dist)),
+                angular_velocity: 0.0,
+                rotation_rad: 0.0,
+                radius: 0.0,
+            },
+        });
+    }
+    points.push(TrajectoryItem {
+        ticks: 0,
+        is_reference_point: true,
+        precision_multiplier: FINAL_POINT_PRECISION_MULTIPLIER,
+        spatial: SpatialProps {
+            position: to,
+            velocity: Vec2f64::zero(),
+            angular_velocity: 0.0,
+            rotation_rad: 0.0,
+            radius: 0.0,
+        },
+    });
+    interpolate_and_estimate_trajectory_points(&mut points);
+    TrajectoryResult::Success(Trajectory {
+        points: VecDeque::from(points),
+        total_ticks: 0,
+        arrival_ticks: None,
+    })
+}
+
 #[derive(Debug, Clone, TypescriptDefinition, TypeScriptify, Serialize, Deserialize)]
 #[serde(tag = "tag")]
 pub enum TrajectoryRequest {
@@ -275,12 +738,23 @@ pub struct TrajectoryItem {
 pub struct Trajectory {
     pub points: VecDeque<TrajectoryItem>,
     pub total_ticks: i32,
+    // for intercept trajectories, the tick when the ship is expected to meet the target
+    pub arrival_ticks: Option<u64>,
 }
 
-pub fn acc_time_for_dist(_acc: f64, _dist: f64, _vmax: f64) -> f64 {
-    // TODO: this is for a real physical movement without vmax, but I have to cap it somehow
-    // return (Math.abs(dist / acc)).sqrt();
-    return 0.0;
+// time to get from a stop to a stop, with the same acceleration and deceleration,
+// using the same distance model as solve_peak_speed (S = a * t * t for every half)
+pub fn acc_time_for_dist(acc: f64, dist: f64, vmax: f64) -> f64 {
+    if acc <= 0.0 || vmax <= 0.0 {
+        return f64::INFINITY;
+    }
+    let time_to_vmax = vmax / acc;
+    let dist_to_vmax = acc * time_to_vmax * time_to_vmax;
+    return if dist < 2.0 * dist_to_vmax {
+        2.0 * (dist / 2.0 / acc).sqrt()
+    } else {
+        2.0 * time_to_vmax + (dist - 2.0 * dist_to_vmax) / vmax
+    };
 }
 
 // dumb solution that ignores acceleration and speed
@@ -600,6 +1074,7 @@ pub fn build_trajectory_accelerated(
                     TrajectoryResult::Success(Trajectory {
                         points: VecDeque::from(points),
                         total_ticks: 0,
+                        arrival_ticks: None,
                     })
                 }
                 TrajectoryRequest::ImpactPoint { .. } => TrajectoryResult::Inaccessible,
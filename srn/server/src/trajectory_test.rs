@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod trajectory_test {
+    use crate::autofocus::build_spatial_index;
+    use crate::indexing::{index_state, GameStateCaches, ObjectSpecifier};
+    use crate::spatial_movement::Movement;
+    use crate::system_gen::seed_state;
+    use crate::trajectory::{
+        avoid_obstacles, build_trajectory_accelerated_avoiding_obstacles,
+        build_trajectory_to_point, distance_to_segment, find_trajectory_obstacles,
+        TrajectoryObstacle, TrajectoryResult,
+    };
+    use crate::vec2::Vec2f64;
+    use crate::world::{GameMode, GameState, SpatialProps};
+
+    fn obstacle_at(x: f64, y: f64, radius: f64) -> TrajectoryObstacle {
+        TrajectoryObstacle {
+            spec: ObjectSpecifier::Unknown,
+            position: Vec2f64 { x, y },
+            radius,
+        }
+    }
+
+    fn assert_path_clears(from: &Vec2f64, waypoints: &Vec<Vec2f64>, obstacle: &TrajectoryObstacle) {
+        let mut current = from.clone();
+        for point in waypoints.iter() {
+            assert!(
+                distance_to_segment(&obstacle.position, &current, point) >= obstacle.radius,
+                "leg {:?} -> {:?} goes through the obstacle",
+                current,
+                point
+            );
+            current = point.clone();
+        }
+    }
+
+    fn seeded() -> (GameState, Vec2f64, f64) {
+        let state = seed_state(
+            &GameMode::CargoRush,
+            "trajectory".to_string(),
+            None,
+            &mut GameStateCaches::new(),
+        );
+        let star = state.locations[0].star.clone().unwrap();
+        (state, star.spatial.position, star.spatial.radius)
+    }
+
+    #[test]
+    pub fn straight_path_has_no_detours() {
+        let from = Vec2f64 {
+            x: -200.0,
+            y: 100.0,
+        };
+        let to = Vec2f64 { x: 200.0, y: 100.0 };
+        let waypoints = avoid_obstacles(&from, &to, &vec![obstacle_at(0.0, 0.0, 50.0)]);
+        assert_eq!(waypoints, vec![to]);
+    }
+
+    #[test]
+    pub fn detour_goes_around_obstacles() {
+        let from = Vec2f64 { x: -200.0, y: 10.0 };
+        let to = Vec2f64 { x: 200.0, y: 10.0 };
+        let obstacles = vec![obstacle_at(0.0, 0.0, 50.0), obstacle_at(100.0, 60.0, 20.0)];
+        let waypoints = avoid_obstacles(&from, &to, &obstacles);
+        assert!(waypoints.len() > 1);
+        assert_eq!(waypoints.last(), Some(&to));
+        for obstacle in obstacles.iter() {
+            assert_path_clears(&from, &waypoints, obstacle);
+        }
+    }
+
+    #[test]
+    pub fn accelerated_detour_keeps_speed_through_detour_points() {
+        let from = Vec2f64 { x: -200.0, y: 10.0 };
+        let to = Vec2f64 { x: 200.0, y: 10.0 };
+        let obstacles = vec![obstacle_at(0.0, 0.0, 50.0)];
+        let waypoints = avoid_obstacles(&from, &to, &obstacles);
+        assert!(waypoints.len() > 1);
+        let mov = Movement::ShipAccelerated {
+            max_linear_speed: 1.0,
+            max_rotation_speed: 1.0,
+            linear_drag: 0.0,
+            acc_linear: 0.01,
+            max_turn_speed: 1.0,
+            acc_angular: 1.0,
+            brake_acc: 0.01,
+        };
+        let spatial = SpatialProps {
+            position: from,
+            velocity: Vec2f64::zero(),
+            angular_velocity: 0.0,
+            rotation_rad: 0.0,
+            radius: 1.0,
+        };
+        let trajectory = match build_trajectory_accelerated_avoiding_obstacles(
+            to.clone(),
+            &mov,
+            &spatial,
+            &obstacles,
+        ) {
+            TrajectoryResult::Success(trajectory) => trajectory,
+            _ => panic!("no trajectory"),
+        };
+        for waypoint in waypoints[0..waypoints.len() - 1].iter() {
+            let point = trajectory
+                .points
+                .iter()
+                .find(|p| p.spatial.position.euclidean_distance(waypoint) < 1e-6)
+                .expect("no trajectory point at the detour point");
+            assert!(point.spatial.velocity.euclidean_len() > 0.0);
+        }
+        let last = trajectory.points.back().unwrap();
+        assert_eq!(last.spatial.position, to);
+        assert_eq!(last.spatial.velocity, Vec2f64::zero());
+        let max_speed = trajectory
+            .points
+            .iter()
+            .map(|p| p.spatial.velocity.euclidean_len())
+            .fold(0.0, f64::max);
+        assert!(max_speed <= 1.0 + 1e-9);
+    }
+
+    #[test]
+    pub fn target_inside_obstacle_is_reachable() {
+        let from = Vec2f64 { x: -200.0, y: 0.0 };
+        let to = Vec2f64 { x: 0.0, y: 10.0 };
+        let waypoints = avoid_obstacles(&from, &to, &vec![obstacle_at(0.0, 0.0, 50.0)]);
+        assert_eq!(waypoints, vec![to]);
+    }
+
+    #[test]
+    pub fn finds_the_star_on_the_way() {
+        let (state, star_pos, star_radius) = seeded();
+        let loc = &state.locations[0];
+        let spatial_index = build_spatial_index(loc, 0);
+        let indexes = index_state(&state);
+        let mut caches = GameStateCaches::new();
+        let find = |from: Vec2f64, to: Vec2f64, caches: &mut GameStateCaches| {
+            find_trajectory_obstacles(
+                loc,
+                &spatial_index,
+                &from,
+                &to,
+                1.0,
+                None,
+                state.ticks,
+                &indexes,
+                caches,
+            )
+        };
+        let across = find(
+            star_pos.add(&Vec2f64 {
+                x: -3.0 * star_radius,
+                y: 0.0,
+            }),
+            star_pos.add(&Vec2f64 {
+                x: 3.0 * star_radius,
+                y: 0.0,
+            }),
+            &mut caches,
+        );
+        assert!(across
+            .iter()
+            .any(|o| matches!(o.spec, ObjectSpecifier::Star { .. })));
+        let aside = find(
+            star_pos.add(&Vec2f64 {
+                x: -3.0 * star_radius,
+                y: 3.0 * star_radius,
+            }),
+            star_pos.add(&Vec2f64 {
+                x: 3.0 * star_radius,
+                y: 3.0 * star_radius,
+            }),
+            &mut caches,
+        );
+        assert!(!aside
+            .iter()
+            .any(|o| matches!(o.spec, ObjectSpecifier::Star { .. })));
+    }
+
+    #[test]
+    pub fn monotonous_trajectory_avoids_the_star() {
+        let (_state, star_pos, star_radius) = seeded();
+        let obstacle = obstacle_at(star_pos.x, star_pos.y, star_radius);
+        let from = star_pos.add(&Vec2f64 {
+            x: -2.0 * star_radius,
+            y: 1.0,
+        });
+        let to = star_pos.add(&Vec2f64 {
+            x: 2.0 * star_radius,
+            y: 1.0,
+        });
+        let points = build_trajectory_to_point(
+            &SpatialProps {
+                position: from,
+                velocity: Vec2f64::zero(),
+                angular_velocity: 0.0,
+                rotation_rad: 0.0,
+                radius: 1.0,
+            },
+            &to,
+            &Movement::ShipMonotonous {
+                move_speed: star_radius / 5.0 / 20.0,
+                turn_speed: 0.0,
+            },
+            1,
+            &vec![obstacle.clone()],
+        );
+        assert!(points.len() > 0);
+        assert_path_clears(&from, &points, &obstacle);
+    }
+
+    #[test]
+    pub fn acc_time_caps_speed() {
+        // max speed is reached after 2 ticks and 4 units
+        assert_eq!(acc_time_for_dist(1.0, 8.0, 2.0), 4.0);
+        assert_eq!(acc_time_for_dist(1.0, 10.0, 2.0), 5.0);
+        assert_eq!(acc_time_for_dist(1.0, 2.0, 2.0), 2.0);
+        assert!(acc_time_for_dist(0.0, 2.0, 2.0).is_infinite());
+    }
+
+    #[test]
+    pub fn intercept_meets_moving_planets() {
+        let (state, star_pos, star_radius) = seeded();
+        let indexes = index_state(&state);
+        let mut caches = GameStateCaches::new();
+        let mov = ShipTemplate::defaul_ship_movement();
+        let from = star_pos.add(&Vec2f64 {
+            x: 0.0,
+            y: -2.0 * star_radius,
+        });
+        for planet in state.locations[0].planets.iter() {
+            let spec = ObjectSpecifier::Planet { id: planet.id };
+            let (arrival, point) =
+                find_intercept(&from, &spec, &mov, state.ticks, &indexes, &mut caches).unwrap();
+            assert!(arrival > state.ticks);
+            let planet_then =
+                project_absolute_position(&spec, arrival, &indexes, &mut caches).unwrap();
+            assert!(planet_then.euclidean_distance(&point) < 1e-6);
+            let travel = acc_time_for_dist(
+                mov.get_current_linear_acceleration(),
+                from.euclidean_distance(&point),
+                mov.get_max_speed(),
+            );
+            assert!(
+                travel <= (arrival - state.ticks) as f64 + 1.0,
+                "the ship is late for {}",
+                planet.name
+            );
+        }
+    }
+}
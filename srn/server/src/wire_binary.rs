@@ -0,0 +1,86 @@
+use crate::replay::{ReplayError, ValueDiff};
+use crate::replay_binary::{ByteReader, ByteWriter, StringTable};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Binary websocket framing, negotiated at the handshake via the protocol name, all the primitives are the same
+// as in the binary replays. Every frame is self-contained:
+//   opcode u8 - the same codes as in the text protocol, payload kind u8
+//   tag - length-prefixed, empty if there is none
+//   string table - every object key, string value and diff path of the frame is stored only once
+//   payload - nothing, a single value, or a diff batch
+pub const WIRE_BINARY_PROTOCOL: &str = "srn-binary-v1";
+
+const PAYLOAD_EMPTY: u8 = 0;
+const PAYLOAD_VALUE: u8 = 1;
+const PAYLOAD_DIFFS: u8 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", content = "value")]
+pub enum WirePayload {
+    Empty,
+    Value(Value),
+    Diffs(Vec<ValueDiff>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WireFrame {
+    pub opcode: u8,
+    pub tag: Option<String>,
+    pub payload: WirePayload,
+}
+
+pub fn encode_wire_frame(frame: &WireFrame) -> Vec<u8> {
+    let mut table = StringTable::default();
+    let mut payload = ByteWriter::new();
+    let kind = match &frame.payload {
+        WirePayload::Empty => PAYLOAD_EMPTY,
+        WirePayload::Value(v) => {
+            payload.value(v, &mut table);
+            PAYLOAD_VALUE
+        }
+        WirePayload::Diffs(diffs) => {
+            payload.diff_batch(diffs, &mut table);
+            PAYLOAD_DIFFS
+        }
+    };
+    let mut out = ByteWriter::new();
+    out.u8(frame.opcode);
+    out.u8(kind);
+    out.str(frame.tag.as_ref().map_or("", |t| t.as_str()));
+    out.varint(table.strings.len() as u64);
+    for str in table.strings.iter() {
+        out.str(str);
+    }
+    out.bytes.extend(payload.bytes);
+    out.bytes
+}
+
+pub fn decode_wire_frame(bytes: &[u8]) -> Result<WireFrame, ReplayError> {
+    let mut reader = ByteReader::new(bytes, 0);
+    let opcode = reader.u8()?;
+    let kind = reader.u8()?;
+    let tag = reader.str()?;
+    // the frames come from the clients, so the counts are bounded by the frame size, see ByteReader::count
+    let strings_count = reader.count()?;
+    let mut strings = Vec::with_capacity(strings_count);
+    for _ in 0..strings_count {
+        strings.push(reader.str()?);
+    }
+    let payload = match kind {
+        PAYLOAD_EMPTY => WirePayload::Empty,
+        PAYLOAD_VALUE => WirePayload::Value(reader.value(&strings)?),
+        PAYLOAD_DIFFS => WirePayload::Diffs(reader.diff_batch(&strings)?),
+        _ => {
+            return Err(ReplayError::BadFormat(format!(
+                "unknown payload kind {}",
+                kind
+            )));
+        }
+    };
+    Ok(WireFrame {
+        opcode,
+        tag: if tag.is_empty() { None } else { Some(tag) },
+        payload,
+    })
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod wire_binary_test {
+    use crate::replay::ValueDiff;
+    use crate::replay_binary::{ByteWriter, MAX_VALUE_DEPTH};
+    use crate::wire_binary::{decode_wire_frame, encode_wire_frame, WireFrame, WirePayload};
+    use serde_json::json;
+
+    const PAYLOAD_VALUE: u8 = 1;
+    const PAYLOAD_DIFFS: u8 = 2;
+    const VALUE_ARRAY: u8 = 7;
+
+    fn make_frame() -> WireFrame {
+        WireFrame {
+            opcode: 2,
+            tag: Some("tag".to_string()),
+            payload: WirePayload::Diffs(vec![
+                ValueDiff::Modified("/ticks".to_string(), json!(1000)),
+                ValueDiff::Added(
+                    "/players/0".to_string(),
+                    json!({"name": "test", "money": 10}),
+                ),
+                ValueDiff::Removed("/players/1".to_string()),
+            ]),
+        }
+    }
+
+    fn frame_header(kind: u8, strings_count: u64) -> ByteWriter {
+        let mut writer = ByteWriter::new();
+        writer.u8(1);
+        writer.u8(kind);
+        writer.str("");
+        writer.varint(strings_count);
+        writer
+    }
+
+    #[test]
+    fn decodes_encoded_frame() {
+        let bytes = encode_wire_frame(&make_frame());
+        let frame = decode_wire_frame(&bytes).unwrap();
+        assert_eq!(frame.opcode, 2);
+        assert_eq!(frame.tag, Some("tag".to_string()));
+        match frame.payload {
+            WirePayload::Diffs(diffs) => assert_eq!(diffs.len(), 3),
+            _ => panic!("not diffs"),
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let bytes = encode_wire_frame(&make_frame());
+        for len in 0..bytes.len() {
+            assert!(decode_wire_frame(&bytes[0..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_huge_counts() {
+        let writer = frame_header(PAYLOAD_VALUE, u64::MAX);
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+
+        let mut writer = frame_header(PAYLOAD_VALUE, 0);
+        writer.u8(VALUE_ARRAY);
+        writer.varint(1 << 40);
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+
+        let mut writer = frame_header(PAYLOAD_DIFFS, 0);
+        writer.varint(u64::MAX >> 1);
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+
+        let mut writer = ByteWriter::new();
+        writer.u8(1);
+        writer.u8(PAYLOAD_VALUE);
+        writer.varint(u64::MAX);
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_deeply_nested_frames() {
+        let mut writer = frame_header(PAYLOAD_VALUE, 0);
+        for _ in 0..MAX_VALUE_DEPTH + 1 {
+            writer.u8(VALUE_ARRAY);
+            writer.varint(1);
+        }
+        writer.u8(VALUE_ARRAY);
+        writer.varint(0);
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+
+        let mut writer = frame_header(PAYLOAD_VALUE, 0);
+        for _ in 0..1000 * 1000 {
+            writer.u8(VALUE_ARRAY);
+            writer.varint(1);
+        }
+        assert!(decode_wire_frame(&writer.bytes).is_err());
+    }
+}
@@ -7,7 +7,7 @@ use std::fmt::{Display, Formatter};
 use std::iter::FromIterator;
 
 use crate::abilities::{Ability, SHOOT_COOLDOWN_TICKS};
-use crate::api_struct::{new_bot, AiTrait, Bot, Room, RoomId};
+use crate::api_struct::{new_bot, AiTrait, Bot, BotDifficulty, Room, RoomId};
 use crate::autofocus::{build_spatial_index, object_index_into_object_id, SpatialIndex};
 use crate::bots::{do_bot_npcs_actions, do_bot_players_actions, BOT_ACTION_TIME_TICKS};
 use crate::cargo_rush::{CargoDeliveryQuestState, Quest};
@@ -15,10 +15,11 @@ use crate::combat::{
     acceleration_markers_to_string, guide_projectile, try_reacquire_target, update_explosions,
     Explosion, Health, Projectile, ShipTurret,
 };
+use crate::combat_log::CombatLog;
 use crate::dialogue::Dialogue;
 use crate::effects::{cleanup_effects, LocalEffect};
+use crate::fitting::ShipModule;
 use crate::fof::FofOverrides;
-use crate::hp::SHIP_REGEN_PER_SEC;
 use crate::indexing::{
     build_full_spatial_indexes, find_my_player, find_my_ship, find_planet,
     find_player_and_ship_mut, find_player_ship_index, find_spatial_ref_by_spec,
@@ -31,15 +32,16 @@ use crate::inventory::{
 use crate::long_actions::{
     cancel_all_long_actions_of_type, finish_long_act, finish_long_act_player, tick_long_act,
     tick_long_act_player, try_start_long_action_player_owned, try_start_long_action_ship_only,
-    LongAction, LongActionPlayer, LongActionStart, MIN_SHIP_DOCKING_RADIUS,
+    update_auto_jumps, LongAction, LongActionPlayer, LongActionStart, MIN_SHIP_DOCKING_RADIUS,
     SHIP_DOCKING_RADIUS_COEFF,
 };
 use crate::market::{init_all_planets_market, Market};
 use crate::notifications::{get_new_player_notifications, Notification, NotificationText};
 use crate::perf::{Sampler, SamplerMarks};
+use crate::pirate_defence::PirateWaves;
 use crate::planet_movement::IBodyV2;
 use crate::properties::{
-    cleanup_objects, update_properties_rules, MoneyOnKillProps, ObjectProperty, WRECK_DECAY_TICKS,
+    cleanup_objects, update_properties_rules, ObjectProperty, WRECK_DECAY_TICKS,
 };
 use crate::random_stuff::{
     gen_asteroid_radius, gen_asteroid_shift, gen_color, gen_mineral_props, gen_planet_count,
@@ -48,6 +50,7 @@ use crate::random_stuff::{
     gen_sat_radius, gen_star_name, gen_star_radius,
 };
 use crate::sandbox::ReferencableId;
+use crate::ship_classes::{get_ship_class, ShipClass, PIRATE_SHIP_CLASS, PLAYER_SHIP_CLASS};
 use crate::spatial_movement::{
     update_accelerated_movement, AccelerationMarkers, Movement, RotationMovement,
     EXTRA_PROJECTILE_TURN_DRAG,
@@ -63,11 +66,11 @@ use crate::world_actions::*;
 use crate::world_actions::{Action, ControlMarkers};
 use crate::world_events::{world_update_handle_event, GameEvent, ProcessedGameEvent};
 use crate::{
-    abilities, autofocus, cargo_rush, combat, hp, indexing, pirate_defence, prng_id, random_stuff,
-    spatial_movement, system_gen, trajectory, world_events,
+    abilities, autofocus, cargo_rush, combat, combat_log, hp, indexing, pirate_defence, prng_id,
+    random_stuff, spatial_movement, system_gen, trajectory, world_events,
 };
 use crate::{dialogue, vec2};
-use crate::{fire_event, market, notifications, planet_movement, tractoring};
+use crate::{economy, fire_event, market, notifications, planet_movement, tractoring};
 use crate::{get_prng, new_id, DEBUG_PHYSICS};
 use crate::{seed_prng, DialogueTable};
 use chrono::Utc;
@@ -279,16 +282,27 @@ pub struct Ship {
     pub markers: Option<String>,
     pub acceleration_markers: Option<AccelerationMarkers>,
     pub to_clean: bool,
+    // the ship class it was spawned from, see ship_classes
+    pub class_id: Option<String>,
+    // ships saved before the fitting have none
+    #[serde(default)]
+    pub modules: Vec<ShipModule>,
 }
 
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TypescriptDefinition, TypeScriptify,
+)]
 pub enum TemplateId {
     Unknown,
     Rocket,
+    Slug,
+    Mine,
+    Emp,
 }
 
 impl Ship {
     pub fn new(prng: &mut Pcg64Mcg, at: &mut Option<Vec2f64>) -> Ship {
-        let turrets = combat::gen_turrets(2, prng);
+        let turrets = combat::gen_turrets(2, &[TemplateId::Rocket], prng);
         Ship {
             id: prng_id(prng),
             color: gen_color(prng).to_string(),
@@ -325,6 +339,8 @@ impl Ship {
             markers: None,
             acceleration_markers: None,
             to_clean: false,
+            class_id: None,
+            modules: vec![],
         }
     }
 }
@@ -591,11 +607,15 @@ pub struct GameState {
     pub dialogue_states: Option<DialogueStates>,
     pub breadcrumbs: Option<Vec<Breadcrumb>>,
     pub projectile_templates: Option<Vec<Projectile>>,
+    pub combat_log: Option<CombatLog>,
+    pub pirate_waves: Option<PirateWaves>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, TypescriptDefinition, TypeScriptify)]
 pub struct GameOver {
     pub reason: String,
+    #[serde(default)]
+    pub victory: bool,
 }
 
 pub const GAME_STATE_VERSION: u32 = 4;
@@ -632,6 +652,8 @@ impl GameState {
             dialogue_states: Some(Default::default()),
             breadcrumbs: None,
             projectile_templates: None,
+            combat_log: Some(CombatLog::new()),
+            pirate_waves: None,
         }
     }
 }
@@ -878,19 +900,28 @@ fn update_world_iter(
                         .map(|m| m.time_before_next_shake -= elapsed);
                 } else {
                     let market_update_start = sampler.start(SamplerMarks::UpdateMarket as u32);
-                    let planets_clone = state.locations[0]
-                        .planets
+                    let planets_clone = state
+                        .locations
                         .iter()
-                        .map(|p| p.clone())
+                        .flat_map(|l| l.planets.iter().map(|p| p.clone()))
                         .collect::<Vec<_>>();
                     let new_market = if let Some(market) = &state.market {
                         let mut wares = market.wares.clone();
                         let mut prices = market.prices.clone();
-                        market::shake_market(planets_clone, &mut wares, &mut prices, prng);
+                        let mut price_modifiers = market.price_modifiers.clone();
+                        market::shake_market(
+                            planets_clone,
+                            &mut wares,
+                            &mut prices,
+                            &mut price_modifiers,
+                            prng,
+                        );
                         Some(Market {
                             wares,
                             prices,
                             time_before_next_shake: market::SHAKE_MARKET_EVERY_TICKS,
+                            time_before_next_economy_update: market.time_before_next_economy_update,
+                            price_modifiers,
                         })
                     } else {
                         None
@@ -898,6 +929,9 @@ fn update_world_iter(
                     state.market = new_market;
                     sampler.end(market_update_start);
                 }
+                let economy_mark = sampler.start(SamplerMarks::UpdateEconomy as u32);
+                economy::update_economy(&mut state, elapsed, prng);
+                sampler.end(economy_mark);
             }
         }
 
@@ -1034,6 +1068,10 @@ fn update_events(
             <= PROCESSED_EVENT_LIFETIME_TICKS
     });
     state.processed_events.append(&mut processed_events);
+    state
+        .combat_log
+        .get_or_insert_with(CombatLog::new)
+        .trim(current_state_ticks);
 }
 
 pub fn update_location(
@@ -1077,6 +1115,13 @@ pub fn update_location(
     spatial_movement::update_docked_ships_position(&mut state.locations[loc_idx], indexes);
     sampler.end(update_docked_ships_id);
     let update_ships_navigation_id = sampler.start(SamplerMarks::UpdateShipsNavigation as u32);
+    let navigation_obstacles = spatial_movement::find_navigation_obstacles(
+        &state.locations[loc_idx],
+        spatial_index,
+        indexes,
+        caches,
+        state.ticks,
+    );
     spatial_movement::update_ships_navigation(
         &mut state.locations[loc_idx].ships,
         elapsed,
@@ -1086,6 +1131,7 @@ pub fn update_location(
         state.update_every_ticks,
         caches,
         state.ticks,
+        &navigation_obstacles,
     );
     sampler.end(update_ships_navigation_id);
     if !client {
@@ -1118,6 +1164,7 @@ pub fn update_location(
     sampler.end(update_ship_tractoring_id);
     let cooldowns_id = sampler.start(SamplerMarks::UpdateAbilityCooldowns as u32);
     abilities::update_ships_ability_cooldowns(&mut state.locations[loc_idx].ships, elapsed);
+    combat::update_emp_disabled(&mut state.locations[loc_idx].ships, elapsed);
     sampler.end(cooldowns_id);
 
     let update_minerals_id = sampler.start(SamplerMarks::UpdateTractoredMinerals as u32);
@@ -1167,13 +1214,14 @@ pub fn update_location(
     let mut proj_target_mods = vec![];
 
     for (idx, proj) in state.locations[loc_idx].projectiles.iter().enumerate() {
-        if target_spatials[idx].is_none() {
+        if let (None, Some(radius)) = (&target_spatials[idx], proj.get_acquire_radius()) {
             if let Some(new_target_idx) = try_reacquire_target(
                 ObjectIndexSpecifier::Projectile { idx },
                 &proj.get_spatial().position,
                 spatial_index,
                 state,
                 loc_idx,
+                radius,
             ) {
                 proj_target_mods.push((
                     idx,
@@ -1192,10 +1240,12 @@ pub fn update_location(
         let movement_clone = proj.get_movement().clone();
         let (gas, turn, brake) = if let Some(target_spatial) = &target_spatials[idx] {
             let (gas, turn, brake) = guide_projectile(proj, target_spatial, elapsed);
-            *proj.get_markers_mut() = acceleration_markers_to_string(gas, turn, brake);
+            if let Some(markers) = proj.get_markers_mut() {
+                *markers = acceleration_markers_to_string(gas, turn, brake);
+            }
             (gas, turn, brake)
         } else {
-            (1.0, 0.0, 0.0)
+            proj.get_idle_controls()
         };
         update_accelerated_movement(
             elapsed,
@@ -1206,18 +1256,13 @@ pub fn update_location(
             brake,
             EXTRA_PROJECTILE_TURN_DRAG,
         );
-        match proj {
-            Projectile::Rocket(props) => {
-                props.gas = gas;
-                props.turn = turn;
-                props.brake = brake;
-            }
-        }
+        proj.set_controls(gas, turn, brake);
     }
     sampler.end(guidance_id);
 
     let collisions_id = sampler.start(SamplerMarks::UpdateProjectileCollisions as u32);
-    let projectile_hit_damages = combat::update_projectile_collisions(
+    combat::update_point_defence(state, loc_idx, spatial_index, indexes, current_tick, prng);
+    let projectile_hits_log = combat::update_projectile_collisions(
         &mut state.locations[loc_idx],
         update_options,
         spatial_index,
@@ -1226,6 +1271,7 @@ pub fn update_location(
         current_tick,
         prng,
     );
+    combat_log::record_damage(state, projectile_hits_log);
     sampler.end(collisions_id);
 
     let props_id = sampler.start(SamplerMarks::UpdatePropertiesRules as u32);
@@ -1246,7 +1292,6 @@ pub fn update_location(
         elapsed,
         prng,
         client,
-        projectile_hit_damages,
         spatial_index,
         indexes,
     );
@@ -1268,6 +1313,9 @@ pub fn update_location(
     sampler.end(autofocus_id);
 
     let long_act_ticks = sampler.start(SamplerMarks::UpdateTickLongActionsShips as u32);
+    if !client {
+        update_auto_jumps(&mut state, loc_idx, prng);
+    }
     let players_read = state.players.clone();
     let players_by_ship_id_read = index_players_by_ship_id(&players_read);
     let mut to_finish = vec![];
@@ -1301,7 +1349,7 @@ pub fn update_location(
     sampler.end(long_act_ticks);
 
     let exp_id = sampler.start(SamplerMarks::UpdateExplosions as u32);
-    update_explosions(
+    let explosions_log = update_explosions(
         &mut state.locations[loc_idx],
         elapsed as i32,
         spatial_index,
@@ -1309,6 +1357,7 @@ pub fn update_location(
         state.ticks,
         prng,
     );
+    combat_log::record_damage(state, explosions_log);
     sampler.end(exp_id);
 
     let clean = sampler.start(SamplerMarks::UpdateCleanup as u32);
@@ -1484,64 +1533,97 @@ pub struct ShipTemplate {
     health: Option<Health>,
     movement: Option<Movement>,
     properties: Option<Vec<ObjectProperty>>,
+    location_id: Option<Uuid>,
+    class_id: Option<String>,
+    turret_count: Option<usize>,
+    launchers: Vec<TemplateId>,
 }
 impl ShipTemplate {
     pub fn defaul_ship_movement() -> Movement {
-        let max_linear_speed = 20.0 / 1000.0 / 1000.0;
-        let max_angular_speed = PI / 1000.0 / 1000.0;
-        Movement::ShipAccelerated {
-            max_linear_speed,
-            max_rotation_speed: max_angular_speed,
-            linear_drag: max_linear_speed * 0.025 / 1e6, // 2.5% per second
-            acc_linear: max_linear_speed * 0.25 / 1e6,   // 25% per second
-            brake_acc: max_linear_speed * 0.5 / 1e6,     // 50% per second
-            max_turn_speed: max_angular_speed,
-            acc_angular: max_angular_speed / 6.0 / 1e5,
-        }
+        ShipTemplate::required_class(PLAYER_SHIP_CLASS)
+            .movement
+            .to_movement()
     }
-    pub fn pirate(at: Option<Vec2f64>) -> ShipTemplate {
-        let max_linear_speed = 20.0 / 1000.0 / 1000.0;
-        let max_angular_speed = PI / 2.0 / 1000.0 / 1000.0;
+
+    // the required classes are checked on startup, see ship_classes::validate_ship_classes
+    fn required_class(class_id: &str) -> &'static ShipClass {
+        get_ship_class(class_id).expect("required ship class is not defined")
+    }
+
+    pub fn from_class(class: &ShipClass, at: Option<Vec2f64>) -> ShipTemplate {
+        let movement = class.movement.to_movement();
+        let mut abilities = class.abilities.clone();
+        if class.can_toggle_legacy_movement {
+            abilities.push(Ability::ToggleMovement {
+                movements: vec![movement.clone(), class.movement.to_legacy_movement()],
+                current_idx: 0,
+            });
+        }
         ShipTemplate {
             at,
-            npc_traits: Some(vec![AiTrait::ImmediatePlanetLand]),
-            abilities: Some(vec![Ability::BlowUpOnLand]),
-            name: Some("Pirate".to_string()),
-            health: Some(Health::new(40.0)),
-            movement: Some(ShipTemplate::defaul_ship_movement()),
-            properties: Some(vec![
-                ObjectProperty::MoneyOnKill(MoneyOnKillProps { amount: 100 }),
-                ObjectProperty::PirateShip,
-            ]),
+            npc_traits: class.npc_traits.clone(),
+            abilities: Some(abilities),
+            name: class.ship_name.clone(),
+            health: Some(class.to_health()),
+            movement: Some(movement),
+            properties: Some(class.properties.clone()),
+            location_id: None,
+            class_id: Some(class.id.clone()),
+            turret_count: Some(class.turret_count),
+            launchers: class.launchers.clone(),
         }
     }
 
+    pub fn of_class(class_id: &str, at: Option<Vec2f64>) -> Option<ShipTemplate> {
+        get_ship_class(class_id).map(|class| ShipTemplate::from_class(class, at))
+    }
+
+    pub fn pirate(at: Option<Vec2f64>) -> ShipTemplate {
+        ShipTemplate::from_class(ShipTemplate::required_class(PIRATE_SHIP_CLASS), at)
+    }
+
     pub fn player(at: Option<Vec2f64>) -> ShipTemplate {
-        let max_linear_speed = 20.0 / 1000.0 / 1000.0;
-        let max_angular_speed = PI / 1000.0 / 1000.0;
-        let legacy_movement = Movement::ShipMonotonous {
-            move_speed: max_linear_speed,
-            turn_speed: max_angular_speed,
-        };
-        ShipTemplate {
-            at,
-            npc_traits: None,
-            abilities: Some(vec![Ability::ToggleMovement {
-                movements: vec![
-                    ShipTemplate::defaul_ship_movement(),
-                    legacy_movement.clone(),
-                ],
-                current_idx: 0,
-            }]),
-            name: None,
-            health: Some(Health::new_regen(
-                100.0,
-                SHIP_REGEN_PER_SEC / 1000.0 / 1000.0,
-            )),
-            movement: Some(ShipTemplate::defaul_ship_movement()),
-            properties: None,
+        ShipTemplate::from_class(ShipTemplate::required_class(PLAYER_SHIP_CLASS), at)
+    }
+
+    pub fn in_location(mut self, location_id: Uuid) -> ShipTemplate {
+        self.location_id = Some(location_id);
+        self
+    }
+
+    pub fn with_npc_traits(mut self, npc_traits: Vec<AiTrait>) -> ShipTemplate {
+        self.npc_traits = Some(npc_traits);
+        self
+    }
+}
+
+// explicit coordinates only make sense for a known location, so without it they are treated as the first location's ones,
+// otherwise the ship can appear in any star system that has planets to spawn near
+fn pick_spawn_location_idx(
+    state: &GameState,
+    template: &ShipTemplate,
+    prng: &mut Pcg64Mcg,
+) -> usize {
+    if let Some(location_id) = template.location_id {
+        if let Some(idx) = state.locations.iter().position(|l| l.id == location_id) {
+            return idx;
         }
+        warn!(format!("spawn location {} does not exist", location_id));
+    }
+    if template.at.is_some() || state.locations.len() <= 1 {
+        return 0;
     }
+    let with_planets = state
+        .locations
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.planets.len() > 0)
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    if with_planets.len() == 0 {
+        return 0;
+    }
+    with_planets[prng.gen_range(0, with_planets.len())]
 }
 
 pub fn spawn_ship<'a>(
@@ -1550,13 +1632,20 @@ pub fn spawn_ship<'a>(
     template: ShipTemplate,
     prng: &mut Pcg64Mcg,
 ) -> &'a Ship {
-    let rand_planet = get_random_planet(&state.locations[0].planets, None, prng);
+    let loc_idx = pick_spawn_location_idx(state, &template, prng);
+    let rand_planet = get_random_planet(&state.locations[loc_idx].planets, None, prng);
     let mut at = template.at;
     if rand_planet.is_some() && at.is_none() {
         let p = rand_planet.unwrap();
         at = Some(p.spatial.position.clone());
     }
     let mut ship = Ship::new(prng, &mut at);
+    if let Some(turret_count) = template.turret_count {
+        let turrets = combat::gen_turrets(turret_count, &template.launchers, prng);
+        ship.abilities = turrets.iter().map(|(a, _t)| a.clone()).collect();
+        ship.turrets = turrets.into_iter().map(|(_a, t)| t).collect();
+    }
+    ship.class_id = template.class_id;
     template
         .abilities
         .map(|abilities| ship.abilities.extend(abilities));
@@ -1593,8 +1682,8 @@ pub fn spawn_ship<'a>(
     if let Some(event) = event {
         world_events::fire_saved_event(state, event);
     }
-    state.locations[0].ships.push(ship);
-    &state.locations[0].ships[state.locations[0].ships.len() - 1]
+    state.locations[loc_idx].ships.push(ship);
+    &state.locations[loc_idx].ships[state.locations[loc_idx].ships.len() - 1]
 }
 
 #[derive(Clone)]
@@ -1651,7 +1740,9 @@ pub fn update_rule_specifics(
         GameMode::Tutorial => {}
         GameMode::Sandbox => {}
         GameMode::PirateDefence => {
-            pirate_defence::update_state_pirate_defence(state, prng);
+            if !client {
+                pirate_defence::update_state_pirate_defence(state, prng);
+            }
         }
     }
     mark_id.map(|mark_id| sampler.end(mark_id));
@@ -1734,6 +1825,7 @@ pub fn make_room(
     room_id: Uuid,
     prng: &mut Pcg64Mcg,
     bots_seed: Option<String>,
+    bot_difficulty: Option<BotDifficulty>,
     opts: Option<GenStateOpts>,
     external_caches: Option<&mut GameStateCaches>,
 ) -> (Uuid, Room) {
@@ -1765,7 +1857,7 @@ pub fn make_room(
     match mode {
         GameMode::Unknown => {}
         GameMode::CargoRush => {
-            cargo_rush::on_create_room(&mut room, prng);
+            cargo_rush::on_create_room(&mut room, bot_difficulty, prng);
         }
         GameMode::Tutorial => {}
         GameMode::Sandbox => {}
@@ -1842,6 +1934,7 @@ pub fn update_room(
         let npcs_mark = sampler.start(SamplerMarks::UpdateBotsNPCs as u32);
         do_bot_npcs_actions(
             room,
+            &d_table,
             bot_action_elapsed as i64,
             &spatial_indexes,
             &mut bot_prng,
@@ -1,12 +1,14 @@
 use crate::abilities::Ability;
 use crate::dialogue::{execute_dialog_option, DialogueTable, DialogueUpdate};
+use crate::fitting::FittingAction;
 use crate::indexing::ObjectSpecifier;
 use crate::indexing::{
     find_my_ship_mut, find_player_idx_by_ship_id, find_ship_index, find_ship_mut, GameStateIndexes,
 };
 use crate::inventory::InventoryAction;
 use crate::long_actions::{
-    try_start_long_action_player_owned, try_start_long_action_ship_only, LongActionStart,
+    cancel_auto_jump, try_start_long_action_player_owned, try_start_long_action_ship_only,
+    LongActionStart,
 };
 use crate::market::TradeAction;
 use crate::notifications::NotificationActionR;
@@ -17,7 +19,8 @@ use crate::trajectory::TrajectoryResult;
 use crate::world::{GameState, PlayerId, Ship, ShipWithTime};
 use crate::world_events::{fire_saved_event, GameEvent};
 use crate::{
-    fire_event, indexing, inventory, market, notifications, tractoring, trajectory, Vec2f64,
+    fire_event, fitting, indexing, inventory, market, notifications, tractoring, trajectory,
+    Vec2f64,
 };
 use rand::prelude::*;
 use rand_pcg::Pcg64Mcg;
@@ -59,6 +62,7 @@ pub fn world_update_handle_action(
                     forward: true,
                     last_tick: state_clone.millis,
                 });
+                cancel_auto_jump(ship);
                 ship.navigate_target = None;
                 ship.dock_target = None;
                 ship.trajectory = vec![];
@@ -95,6 +99,7 @@ pub fn world_update_handle_action(
                     forward: false,
                     last_tick: state_clone.millis,
                 });
+                cancel_auto_jump(ship);
                 ship.navigate_target = None;
                 ship.dock_target = None;
                 ship.trajectory = vec![];
@@ -138,6 +143,7 @@ pub fn world_update_handle_action(
                     prng,
                 );
                 let mut ship = &mut state.locations[idx.location_idx].ships[idx.ship_idx];
+                cancel_auto_jump(ship);
                 ship.dock_target = None;
                 ship.navigate_target = Some(target);
                 ship.movement_markers.gas = None;
@@ -171,6 +177,7 @@ pub fn world_update_handle_action(
                         let ship =
                             &mut state.locations[ship_idx.location_idx].ships[ship_idx.ship_idx];
 
+                        cancel_auto_jump(ship);
                         ship.navigate_target = None;
                         ship.dock_target = None;
                         ship.dock_target = Some(target);
@@ -242,6 +249,9 @@ pub fn world_update_handle_action(
         Action::Trade { player_id, action } => {
             market::attempt_trade(state, player_id, action, prng);
         }
+        Action::Fitting { player_id, action } => {
+            fitting::apply_fitting_action(state, player_id, action, prng);
+        }
         _ => {
             warn!(format!(
                 "action {:?} cannot be handled by world_update_handle_player_action",
@@ -273,6 +283,7 @@ pub fn is_world_update_action(act: &Action) -> bool {
             | Action::Notification { .. }
             | Action::SandboxCommand { .. }
             | Action::Trade { .. }
+            | Action::Fitting { .. }
     )
 }
 
@@ -356,6 +367,10 @@ pub enum Action {
         player_id: Uuid,
         action: TradeAction,
     },
+    Fitting {
+        player_id: Uuid,
+        action: FittingAction,
+    },
 }
 
 impl Action {
@@ -382,6 +397,7 @@ impl Action {
             Action::Notification { player_id, .. } => *player_id == my_player_id,
             Action::SandboxCommand { player_id, .. } => *player_id == my_player_id,
             Action::Trade { player_id, .. } => *player_id == my_player_id,
+            Action::Fitting { player_id, .. } => *player_id == my_player_id,
             Action::MoveAxis { ship_id, .. } => my_ship_id.map_or(false, |sid| sid == *ship_id),
             Action::StopMoveAxis { ship_id, .. } => my_ship_id.map_or(false, |sid| sid == *ship_id),
         }
@@ -1,3 +1,5 @@
+use crate::api_struct::{AiTrait, BotDifficulty};
+use crate::combat_log::KillRecord;
 use crate::dialogue::DialogueStates;
 use crate::indexing::ObjectSpecifier;
 use crate::pirate_defence;
@@ -26,8 +28,21 @@ pub fn world_update_handle_event(
     d_table: &DialogueTable,
 ) {
     match event {
-        GameEvent::PirateSpawn { at, .. } => {
-            pirate_defence::on_pirate_spawn(state, &at, prng);
+        GameEvent::PirateSpawn {
+            at,
+            class_id,
+            health_multiplier,
+            npc_traits,
+            ..
+        } => {
+            pirate_defence::on_pirate_spawn(
+                state,
+                &at,
+                &class_id,
+                health_multiplier,
+                npc_traits,
+                prng,
+            );
         }
         GameEvent::DialogueTriggerRequest {
             dialogue_name,
@@ -62,12 +77,19 @@ pub fn world_update_handle_event(
                 pirate_defence::on_ship_docked(state, ship, planet);
             }
         },
-        GameEvent::ShipDied { ship, .. } => match state.mode {
+        GameEvent::ShipDied { .. } => match state.mode {
             GameMode::Unknown => {}
             GameMode::CargoRush => {}
             GameMode::Tutorial => {}
             GameMode::Sandbox => {}
-            GameMode::PirateDefence => pirate_defence::on_ship_died(state, ship),
+            GameMode::PirateDefence => {}
+        },
+        GameEvent::ShipKilled { ship, kill, .. } => match state.mode {
+            GameMode::Unknown => {}
+            GameMode::CargoRush => {}
+            GameMode::Tutorial => {}
+            GameMode::Sandbox => {}
+            GameMode::PirateDefence => pirate_defence::on_ship_killed(state, ship, kill),
         },
 
         GameEvent::Unknown => {
@@ -109,6 +131,9 @@ pub fn world_update_handle_event(
             // only world-handled, not room for now
             sandbox::mutate_state(state, player_id, command)
         }
+        GameEvent::ServerShutdown { .. } => {
+            // sever-only, do nothing
+        }
     }
 }
 
@@ -145,6 +170,13 @@ pub enum GameEvent {
         ship: Ship,
         player_id: Option<Uuid>,
     },
+    // the kill feed, fired right after ShipDied with the credit from the combat log
+    ShipKilled {
+        state_id: Uuid,
+        ship: Ship,
+        kill: KillRecord,
+        text_representation: String,
+    },
     GameEnded {
         state_id: Uuid,
     },
@@ -167,11 +199,17 @@ pub enum GameEvent {
     PirateSpawn {
         at: Vec2f64,
         state_id: Uuid,
+        class_id: String,
+        // see pirate_defence::get_wave_health_multiplier
+        health_multiplier: f64,
+        // overrides the class ones, see pirate_defence::PirateWaveGroup
+        npc_traits: Option<Vec<AiTrait>>,
     },
     CreateRoomRequest {
         mode: GameMode,
         room_id: Uuid,
         bots_seed: Option<String>,
+        bot_difficulty: Option<BotDifficulty>,
     },
     QuitPlayerRequest {
         player_id: Uuid,
@@ -180,6 +218,12 @@ pub enum GameEvent {
         player_id: Uuid,
         command: SandboxCommand,
     },
+    // announced to every room before the server stops, see shutdown::announce_shutdown
+    ServerShutdown {
+        state_id: Uuid,
+        shutdown_in_ms: u32,
+        text_representation: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, TypeScriptify, TypescriptDefinition)]
@@ -218,6 +262,11 @@ pub enum ProcessedGameEvent {
         player_id: Option<Uuid>,
         processed_at_ticks: u64,
     },
+    ShipKilled {
+        kill: KillRecord,
+        text_representation: String,
+        processed_at_ticks: u64,
+    },
     GameEnded {
         processed_at_ticks: u64,
     },
@@ -249,6 +298,10 @@ pub enum ProcessedGameEvent {
         command: SandboxCommand,
         processed_at_ticks: u64,
     },
+    ServerShutdown {
+        text_representation: String,
+        processed_at_ticks: u64,
+    },
 }
 
 impl ProcessedGameEvent {
@@ -266,6 +319,7 @@ impl ProcessedGameEvent {
             }
             ProcessedGameEvent::RoomJoined { player_id, .. } => *player_id == my_player_id,
             ProcessedGameEvent::ShipDied { .. } => true,
+            ProcessedGameEvent::ShipKilled { .. } => true,
             ProcessedGameEvent::GameEnded { .. } => true,
             ProcessedGameEvent::GameStarted { .. } => true,
             ProcessedGameEvent::CargoQuestTriggerRequest { .. } => false,
@@ -273,6 +327,7 @@ impl ProcessedGameEvent {
             ProcessedGameEvent::DialogueTriggerRequest { .. } => false,
             ProcessedGameEvent::PirateSpawn { .. } => false,
             ProcessedGameEvent::SandboxCommandRequest { .. } => false,
+            ProcessedGameEvent::ServerShutdown { .. } => true,
         }
     }
 }
@@ -324,6 +379,15 @@ impl ProcessedGameEvent {
                 player_id,
                 processed_at_ticks: at_ticks,
             },
+            GameEvent::ShipKilled {
+                kill,
+                text_representation,
+                ..
+            } => ProcessedGameEvent::ShipKilled {
+                kill,
+                text_representation,
+                processed_at_ticks: at_ticks,
+            },
             GameEvent::GameEnded { .. } => ProcessedGameEvent::GameEnded {
                 processed_at_ticks: at_ticks,
             },
@@ -365,6 +429,13 @@ impl ProcessedGameEvent {
             GameEvent::QuitPlayerRequest { .. } => {
                 panic!("cannot convert non-world event to a processed event");
             }
+            GameEvent::ServerShutdown {
+                text_representation,
+                ..
+            } => ProcessedGameEvent::ServerShutdown {
+                text_representation,
+                processed_at_ticks: at_ticks,
+            },
             GameEvent::SandboxCommandRequest { player_id, command } => {
                 ProcessedGameEvent::SandboxCommandRequest {
                     player_id,
@@ -394,6 +465,9 @@ impl ProcessedGameEvent {
             ProcessedGameEvent::ShipDied {
                 processed_at_ticks, ..
             } => *processed_at_ticks,
+            ProcessedGameEvent::ShipKilled {
+                processed_at_ticks, ..
+            } => *processed_at_ticks,
             ProcessedGameEvent::GameEnded {
                 processed_at_ticks, ..
             } => *processed_at_ticks,
@@ -415,6 +489,9 @@ impl ProcessedGameEvent {
             ProcessedGameEvent::SandboxCommandRequest {
                 processed_at_ticks, ..
             } => *processed_at_ticks,
+            ProcessedGameEvent::ServerShutdown {
+                processed_at_ticks, ..
+            } => *processed_at_ticks,
         }
     }
 }
@@ -432,6 +509,7 @@ pub fn fire_saved_event(state: &mut GameState, event: GameEvent) {
         // something on them. typically, server just does retransmitting them to the client ahead of the normal update
         GameEvent::ShipSpawned { .. } => fire_event(event),
         GameEvent::ShipDied { .. } => fire_event(event),
+        GameEvent::ShipKilled { .. } => fire_event(event),
         GameEvent::SandboxCommandRequest { .. } => fire_event(event),
         _ => fire_event(event),
     }
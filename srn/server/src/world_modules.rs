@@ -0,0 +1,89 @@
+// the world simulation modules, shared by the server and the headless simulation binary (bin/sim.rs).
+// the explicit paths make them resolve the same way from both crate roots, and the crate root has to
+// provide what they use from it, like fire_event, new_id or get_prng
+
+#[path = "abilities.rs"]
+pub mod abilities;
+#[path = "api_struct.rs"]
+pub mod api_struct;
+#[path = "autofocus.rs"]
+pub mod autofocus;
+#[path = "behaviour_tree.rs"]
+pub mod behaviour_tree;
+#[path = "bots.rs"]
+pub mod bots;
+#[path = "cargo_rush.rs"]
+pub mod cargo_rush;
+#[path = "combat.rs"]
+pub mod combat;
+#[path = "combat_log.rs"]
+pub mod combat_log;
+#[path = "determinism.rs"]
+pub mod determinism;
+#[path = "dialogue.rs"]
+pub mod dialogue;
+#[path = "economy.rs"]
+pub mod economy;
+#[path = "effects.rs"]
+pub mod effects;
+#[path = "fitting.rs"]
+pub mod fitting;
+#[path = "fof.rs"]
+pub mod fof;
+#[path = "hp.rs"]
+pub mod hp;
+#[path = "indexing.rs"]
+pub mod indexing;
+#[path = "interpolation.rs"]
+pub mod interpolation;
+#[path = "inventory.rs"]
+pub mod inventory;
+#[path = "locations.rs"]
+pub mod locations;
+#[path = "long_actions.rs"]
+pub mod long_actions;
+#[path = "market.rs"]
+pub mod market;
+#[path = "notifications.rs"]
+pub mod notifications;
+#[path = "perf.rs"]
+pub mod perf;
+#[path = "pirate_defence.rs"]
+pub mod pirate_defence;
+#[path = "planet_movement.rs"]
+pub mod planet_movement;
+#[path = "properties/mod.rs"]
+pub mod properties;
+#[path = "random_stuff.rs"]
+pub mod random_stuff;
+#[path = "replay.rs"]
+pub mod replay;
+#[path = "replay_binary.rs"]
+pub mod replay_binary;
+#[path = "sandbox.rs"]
+pub mod sandbox;
+#[path = "self_inspect.rs"]
+pub mod self_inspect;
+#[path = "ship_classes.rs"]
+pub mod ship_classes;
+#[path = "spatial_movement.rs"]
+pub mod spatial_movement;
+#[path = "substitutions.rs"]
+pub mod substitutions;
+#[path = "system_gen.rs"]
+pub mod system_gen;
+#[path = "tractoring.rs"]
+pub mod tractoring;
+#[path = "trajectory.rs"]
+pub mod trajectory;
+#[path = "tutorial.rs"]
+pub mod tutorial;
+#[allow(dead_code)]
+#[path = "vec2.rs"]
+pub mod vec2;
+#[path = "world.rs"]
+pub mod world;
+#[path = "world_actions.rs"]
+pub mod world_actions;
+#[path = "world_events.rs"]
+pub mod world_events;
@@ -129,6 +129,59 @@ describe('update determinism', () => {
     }
   };
 
+  describe('replay check', () => {
+    const STEP_MS = 100;
+
+    const simulateStates = () => {
+      let state = wasm.seedWorld({
+        mode: 'CargoRush',
+        seed: 'replay check',
+        gen_state_opts: genStateOpts({ system_count: 1 }),
+      });
+      const states = [state];
+      for (let i = 0; i < 10; i++) {
+        state = updateWorld(state, STEP_MS);
+        states.push(state);
+      }
+      return states;
+    };
+
+    it('finds no divergence when re-simulating the same updates', () => {
+      const states = simulateStates();
+      const replay = wasm.packReplay(states, 'replay check', true);
+      const report = wasm.checkReplayDeterminism(replay, {
+        step_ticks: STEP_MS * 1000,
+        client: false,
+      });
+      expect(report.divergence).toBeFalsy();
+      expect(report.checked_frames).toEqual(10);
+      expect(report.last_checked_ticks).toEqual(states[10].ticks);
+    });
+
+    it('reports the first diverged frame with its diffs', () => {
+      const states = simulateStates();
+      states[5].milliseconds_remaining += 1;
+      const replay = wasm.packReplay(states, 'replay check', true);
+      const report = wasm.checkReplayDeterminism(replay, {
+        step_ticks: STEP_MS * 1000,
+        client: false,
+      });
+      expect(report.checked_frames).toEqual(5);
+      expect(report.divergence.ticks).toEqual(states[5].ticks);
+      expect(report.divergence.expected_hash).not.toEqual(
+        report.divergence.actual_hash
+      );
+      expect(report.divergence.diffs).toEqual([
+        {
+          Modified: [
+            '/milliseconds_remaining',
+            states[5].milliseconds_remaining - 1,
+          ],
+        },
+      ]);
+    });
+  });
+
   describe.each(['PirateDefence'])('room updates in %s mode', (mode) => {
     describe('room update', () => {
       xit('can make bots deterministic if necessary', async () => {
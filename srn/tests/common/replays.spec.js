@@ -185,6 +185,72 @@ describe('replay system', () => {
     }
   });
 
+  describe('keyframes', () => {
+    it('are produced while packing', () => {
+      const states = simulate(30);
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      expect(replayDiff.keyframes.length).toBeGreaterThan(0);
+    });
+
+    it('restore the same states as applying all the diffs', () => {
+      const states = simulate(30);
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      const withoutKeyframes = { ...replayDiff, keyframes: [] };
+      for (const tick of replayDiff.marks_ticks) {
+        expect(wasm.getDiffReplayStateAt(replayDiff, tick)).toEqual(
+          wasm.getDiffReplayStateAt(withoutKeyframes, tick)
+        );
+      }
+    });
+
+    it('are inserted into old replays on load', () => {
+      const states = simulate(30);
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      const lastTick =
+        replayDiff.marks_ticks[replayDiff.marks_ticks.length - 1];
+      const expected = wasm.getDiffReplayStateAt(replayDiff, lastTick);
+      wasm.loadReplay({ ...replayDiff, keyframes: [] });
+      expect(wasm.getPreloadedDiffReplayStateAt(lastTick)).toEqual(expected);
+    });
+  });
+
+  describe('binary format', () => {
+    it('is smaller than the json one', () => {
+      const states = simulate();
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      const binary = wasm.replayToBinary(replayDiff);
+      expect(binary.length).toBeLessThan(JSON.stringify(replayDiff).length);
+    });
+
+    it('restores the same states as the json one', () => {
+      // more states than the keyframe interval, so the seeking from a keyframe is covered too
+      const states = simulate(120);
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      const binary = wasm.replayToBinary(replayDiff);
+      for (const tick of [
+        0,
+        replayDiff.marks_ticks[1],
+        replayDiff.marks_ticks[50],
+        replayDiff.marks_ticks[51],
+        replayDiff.marks_ticks[replayDiff.marks_ticks.length - 1],
+      ]) {
+        expect(wasm.getBinaryReplayStateAt(binary, tick)).toEqual(
+          wasm.getDiffReplayStateAt(replayDiff, tick)
+        );
+      }
+    });
+
+    it('can be preloaded', () => {
+      const states = simulate();
+      const replayDiff = wasm.packReplay(states, 'test', true);
+      const lastTick =
+        replayDiff.marks_ticks[replayDiff.marks_ticks.length - 1];
+      const expected = wasm.getDiffReplayStateAt(replayDiff, lastTick);
+      wasm.loadReplay(wasm.replayToBinary(replayDiff));
+      expect(wasm.getPreloadedDiffReplayStateAt(lastTick)).toEqual(expected);
+    });
+  });
+
   it('can do sequential continuous restoration', () => {
     const states = simulate();
     const replayDiff = wasm.packReplay(states, 'test', true);
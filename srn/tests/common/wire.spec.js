@@ -0,0 +1,45 @@
+import { swapGlobals, wasm } from '../util';
+
+describe('binary wire protocol', () => {
+  beforeAll(swapGlobals);
+
+  it('can round-trip a state frame', () => {
+    const state = wasm.seedWorld({
+      mode: 'CargoRush',
+      seed: 'wire',
+    });
+    const frame = {
+      opcode: 8,
+      tag: null,
+      payload: { kind: 'Value', value: state },
+    };
+    const bytes = wasm.encodeWireFrame(frame);
+    expect(bytes.length).toBeLessThan(JSON.stringify(state).length);
+    expect(wasm.decodeWireFrame(bytes)).toEqual(frame);
+  });
+
+  it('can round-trip a diff frame', () => {
+    const frame = {
+      opcode: 11,
+      tag: null,
+      payload: {
+        kind: 'Diffs',
+        value: [
+          { Modified: ['/ticks', 100] },
+          { Added: ['/locations/0/ships/0', { id: 'abc' }] },
+          { Removed: '/locations/0/minerals/1' },
+        ],
+      },
+    };
+    expect(wasm.decodeWireFrame(wasm.encodeWireFrame(frame))).toEqual(frame);
+  });
+
+  it('keeps the tag of client frames', () => {
+    const frame = {
+      opcode: 13,
+      tag: 'some-tag',
+      payload: { kind: 'Value', value: { tag: 'Respawn' } },
+    };
+    expect(wasm.decodeWireFrame(wasm.encodeWireFrame(frame))).toEqual(frame);
+  });
+});
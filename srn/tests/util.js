@@ -132,6 +132,11 @@ export const loadWasm = timerify(async function loadWasm() {
     wasm.makeDialogueTable = wasmFunctions.make_dialogue_table;
     wasm.packReplay = wasmFunctions.pack_replay;
     wasm.loadReplay = wasmFunctions.load_replay;
+    wasm.replayToBinary = wasmFunctions.replay_to_binary;
+    wasm.getBinaryReplayStateAt = wasmFunctions.get_binary_replay_state_at;
+    wasm.checkReplayDeterminism = wasmFunctions.check_replay_determinism;
+    wasm.encodeWireFrame = wasmFunctions.encode_binary_wire_frame;
+    wasm.decodeWireFrame = wasmFunctions.decode_binary_wire_frame;
     wasm.generatePhaseTable = wasmFunctions.generate_phase_table;
     wasm.getDiffReplayStateAt = timerifySync(function getDiffReplayStateAt(
       ...args
@@ -144,6 +144,12 @@ mod substitutions;
 #[path = "../../server/src/combat.rs"]
 mod combat;
 
+#[path = "../../server/src/combat_log.rs"]
+mod combat_log;
+
+#[path = "../../server/src/determinism.rs"]
+mod determinism;
+
 #[path = "../../server/src/indexing.rs"]
 mod indexing;
 
@@ -168,12 +174,18 @@ mod properties;
 #[path = "../../server/src/tutorial.rs"]
 mod tutorial;
 
+#[path = "../../server/src/behaviour_tree.rs"]
+mod behaviour_tree;
+
 #[path = "../../server/src/bots.rs"]
 mod bots;
 
 #[path = "../../server/src/fof.rs"]
 mod fof;
 
+#[path = "../../server/src/fitting.rs"]
+mod fitting;
+
 #[path = "../../server/src/dialogue.rs"]
 mod dialogue;
 
@@ -186,6 +198,12 @@ mod world_actions;
 #[path = "../../server/src/replay.rs"]
 mod replay;
 
+#[path = "../../server/src/replay_binary.rs"]
+mod replay_binary;
+
+#[path = "../../server/src/wire_binary.rs"]
+mod wire_binary;
+
 #[path = "../../server/src/interpolation.rs"]
 mod interpolation;
 
@@ -198,12 +216,18 @@ mod sandbox;
 #[path = "../../server/src/self_inspect.rs"]
 mod self_inspect;
 
+#[path = "../../server/src/ship_classes.rs"]
+mod ship_classes;
+
 #[path = "../../server/src/spatial_movement.rs"]
 mod spatial_movement;
 
 #[path = "../../server/src/hp.rs"]
 mod hp;
 
+#[path = "../../server/src/economy.rs"]
+mod economy;
+
 #[path = "../../server/src/effects.rs"]
 mod effects;
 
@@ -309,7 +333,7 @@ pub fn parse_state(serialized_args: &str) -> String {
     return serde_json::to_string(&args).unwrap_or(DEFAULT_ERR.to_string());
 }
 
-use crate::api_struct::Room;
+use crate::api_struct::{BotDifficulty, Room};
 use crate::dialogue::{parse_dialogue_script_from_file, Dialogue, DialogueTable};
 use crate::indexing::{find_player_ship_index, GameStateCaches, ObjectSpecifier};
 use crate::perf::{ConsumeOptions, Sampler, SamplerMarks};
@@ -472,7 +496,7 @@ pub fn update_world_incremental(args: JsValue, elapsed_micro: i32) -> Result<JsV
 }
 
 fn execute_update_world(elapsed_micro: i32, mut args: UpdateWorldArgs) -> GameState {
-    let mut prng = get_continuous_state_prng(&mut args.state);
+    let mut prng = determinism::continuous_state_prng(&mut args.state);
     let (new_state, sampler, _) = world::update_world(
         args.state,
         elapsed_micro as i64,
@@ -551,9 +575,12 @@ struct SeedWorldArgs {
     gen_state_opts: Option<GenStateOpts>,
 }
 
+use crate::determinism::ResimulationOptions;
 use crate::interpolation::gen_rel_position_orbit_phase_table;
-use crate::replay::{ReplayDiffed, ReplayRaw, ValueDiff};
+use crate::replay::{ReplayDiffed, ReplayRaw, ValueDiff, DEFAULT_KEYFRAME_EVERY_TICKS};
+use crate::replay_binary::{write_replay_binary, CompactReplay};
 use crate::trajectory::TrajectoryRequest;
+use crate::wire_binary::{decode_wire_frame, encode_wire_frame, WireFrame};
 use combat::Projectile;
 use serde_wasm_bindgen::*;
 use spatial_movement::Movement;
@@ -583,6 +610,7 @@ struct CreateRoomArgs {
     mode: GameMode,
     seed: String,
     bots_seed: Option<String>,
+    bot_difficulty: Option<BotDifficulty>,
     gen_state_opts: Option<GenStateOpts>,
 }
 
@@ -595,6 +623,7 @@ pub fn create_room(args: JsValue) -> Result<JsValue, JsValue> {
         prng_id(&mut prng),
         &mut prng,
         args.bots_seed,
+        args.bot_difficulty,
         args.gen_state_opts,
         Some(&mut game_state_caches.write().unwrap()),
     );
@@ -632,17 +661,6 @@ fn get_continuous_room_prng(room: &mut Room) -> Mcg128Xsl64 {
     prng
 }
 
-fn get_continuous_state_prng(state: &mut GameState) -> Mcg128Xsl64 {
-    let mut prng = if let Some(next_seed) = state.next_seed.as_ref() {
-        Pcg64Mcg::seed_from_u64((*next_seed as u64))
-    } else {
-        let seed = state.seed.clone();
-        seed_prng(seed)
-    };
-    state.next_seed = Some(prng.next_u32());
-    prng
-}
-
 #[wasm_bindgen]
 pub fn update_room_full(
     room: JsValue,
@@ -777,8 +795,12 @@ pub fn get_preloaded_diff_replay_state_at(ticks: u32) -> Result<JsValue, JsValue
     let full_id = sampler
         .as_mut()
         .map(|s| s.start(SamplerMarks::GetDiffReplayStateAtPreloaded as u32));
-    let mut replay: ReplayDiffed = current_replay.read().unwrap().clone().unwrap();
-    let res = replay
+    // the replay carries keyframes, so cloning it on every call is too expensive
+    let res = current_replay
+        .read()
+        .unwrap()
+        .as_ref()
+        .unwrap()
         .get_state_at(ticks, &mut sampler)
         .map_err(|_| JsValue::from_str("failed to rewind"))?;
     sampler.as_mut().map(|s| {
@@ -817,8 +839,11 @@ pub fn get_preloaded_diff_replay_state_at_interpolated(
     let full_id = sampler
         .as_mut()
         .map(|s| s.start(SamplerMarks::GetDiffReplayStateAtPreloadedInterpolated as u32));
-    let mut replay: ReplayDiffed = current_replay.read().unwrap().clone().unwrap();
-    let (prev, next, curr) = replay
+    let (prev, next, curr) = current_replay
+        .read()
+        .unwrap()
+        .as_ref()
+        .unwrap()
         .get_state_at_interpolated(prev_ticks, next_ticks, value, &mut sampler, &mut caches)
         .map_err(|_| JsValue::from_str("failed to rewind"))?;
     sampler.as_mut().map(|s| {
@@ -845,12 +870,77 @@ pub fn get_preloaded_diff_replay_state_at_interpolated(
 
 #[wasm_bindgen]
 pub fn load_replay(replay: JsValue) -> Result<(), JsValue> {
-    let replay: ReplayDiffed = serde_wasm_bindgen::from_value(replay)?;
+    let replay: ReplayDiffed = if replay.is_instance_of::<js_sys::Uint8Array>() {
+        let bytes = js_sys::Uint8Array::new(&replay).to_vec();
+        CompactReplay::parse(&bytes)
+            .and_then(|r| r.to_replay_diffed())
+            .map_err(|e| {
+                JsValue::from_str(format!("failed to load binary replay: {:?}", e).as_str())
+            })?
+    } else {
+        let mut replay: ReplayDiffed = serde_wasm_bindgen::from_value(replay)?;
+        if replay.needs_keyframes_migration() {
+            replay
+                .migrate_keyframes(DEFAULT_KEYFRAME_EVERY_TICKS)
+                .map_err(|e| {
+                    JsValue::from_str(format!("failed to migrate replay: {:?}", e).as_str())
+                })?;
+        }
+        replay
+    };
     let mut r = current_replay.write().unwrap();
     *r = Some(replay);
     Ok(())
 }
 
+#[wasm_bindgen]
+pub fn replay_to_binary(replay: JsValue) -> Result<Vec<u8>, JsValue> {
+    let replay: ReplayDiffed = serde_wasm_bindgen::from_value(replay)?;
+    write_replay_binary(&replay)
+        .map_err(|e| JsValue::from_str(format!("failed to pack binary replay: {:?}", e).as_str()))
+}
+
+#[wasm_bindgen]
+pub fn get_binary_replay_state_at(replay: Vec<u8>, ticks: u32) -> Result<JsValue, JsValue> {
+    let replay = CompactReplay::parse(&replay).map_err(|e| {
+        JsValue::from_str(format!("failed to load binary replay: {:?}", e).as_str())
+    })?;
+    let res = replay
+        .get_state_at(ticks, &mut None)
+        .map_err(|_| JsValue::from_str("failed to rewind"))?;
+    Ok(custom_serialize(&res)?)
+}
+
+#[wasm_bindgen]
+pub fn decode_binary_wire_frame(bytes: Vec<u8>) -> Result<JsValue, JsValue> {
+    let frame = decode_wire_frame(&bytes)
+        .map_err(|e| JsValue::from_str(format!("failed to decode frame: {:?}", e).as_str()))?;
+    Ok(custom_serialize(&frame)?)
+}
+
+#[wasm_bindgen]
+pub fn encode_binary_wire_frame(frame: JsValue) -> Result<Vec<u8>, JsValue> {
+    let frame: WireFrame = custom_deserialize(frame)?;
+    Ok(encode_wire_frame(&frame))
+}
+
+#[wasm_bindgen]
+pub fn check_replay_determinism(replay: JsValue, opts: JsValue) -> Result<JsValue, JsValue> {
+    let replay: ReplayDiffed = serde_wasm_bindgen::from_value(replay)?;
+    let opts: ResimulationOptions = serde_wasm_bindgen::from_value(opts)?;
+    let frames = determinism::extract_replay_frames(&replay)
+        .map_err(|_| JsValue::from_str("failed to rewind"))?;
+    let log = determinism::collect_action_log(frames.iter());
+    let report = determinism::check_determinism(
+        &frames,
+        &log,
+        &opts,
+        &get_current_d_table(),
+        &mut game_state_caches.write().unwrap(),
+    );
+    Ok(custom_serialize(&report)?)
+}
+
 #[wasm_bindgen]
 pub fn load_d_table(d_table: JsValue) -> Result<(), JsValue> {
     let d_table: DialogueTable = custom_deserialize(d_table)?;